@@ -4,13 +4,125 @@
 //! filter out failed commands, and validate command sequences.
 
 use anyhow::{Result, anyhow};
+use regex::{Regex, RegexSet};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::process::{Command, Stdio};
+use std::sync::OnceLock;
 use std::time::Duration;
 use tokio::time::timeout;
 
 use crate::terminal::monitor::CommandEntry;
+use super::config::FilterConfig;
+use super::rules::ReplacementRules;
+
+/// Patterns of commands that destroy data or take a system down, for live warnings
+const DANGEROUS_COMMAND_PATTERNS: &[&str] = &[
+    r"\brm\s+(-\w*[rRf]|--recursive|--force)",
+    r"\bdd\s.*\bof=",
+    r"\bmkfs(\.\w+)?\b",
+    r"\b(shred|wipefs|fdisk|parted)\b",
+    r">\s*/dev/(sd|nvme|hd|disk)",
+    r"\bchmod\s+(-\w+\s+)*0?777\b",
+    r"\bch(mod|own)\s+-\w*R\w*\s+\S*\s*/(\s|$)",
+    r"\bgit\s+(push\s.*(--force\b|\s-f\b)|reset\s+--hard|clean\s+-\w*f)",
+    r"(?i)\b(drop|truncate)\s+(table|database|schema)\b",
+    r"\bkubectl\s+delete\b",
+    r"\bdocker\s+(system\s+prune|volume\s+(rm|prune))",
+    r"\bterraform\s+destroy\b",
+    r"\b(shutdown|reboot|halt|poweroff)\b",
+    r":\(\)\s*\{\s*:\|:&\s*\};:",
+];
+
+/// Characters that make a shell run more than the one program named, or write to files
+const SHELL_METACHARACTERS: &[char] = &[';', '|', '&', '<', '>', '$', '`', '\n'];
+
+/// `find` actions that change files or run other programs
+const UNSAFE_FIND_ACTIONS: &[&str] = &[
+    "-delete", "-exec", "-execdir", "-ok", "-okdir", "-fprint", "-fprint0", "-fprintf", "-fls",
+];
+
+/// Common secret formats collapsed in strict privacy mode; text matched by a `keep` group stays
+const SECRET_PATTERNS: &[&str] = &[
+    // AWS access key IDs, long-lived and temporary
+    r"\b(AKIA|ASIA)[0-9A-Z]{16}\b",
+    // GitHub personal, OAuth, user, server and refresh tokens
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    r"(?i)(?P<keep>\bbearer\s+)[A-Za-z0-9._~+/-]{8,}=*",
+    // A private key block, or everything after its header when the output was cut short
+    r"(?s)-----BEGIN [A-Z0-9 ]*PRIVATE KEY-----.*?(-----END [A-Z0-9 ]*PRIVATE KEY-----|\z)",
+];
+
+/// Text that replaces a detected secret
+const SECRET_REDACTED: &str = "[SECRET_REDACTED]";
+
+/// Parts of variable names that suggest the exported value is a secret
+const SECRET_VARIABLE_HINTS: &[&str] = &["TOKEN", "SECRET", "KEY", "PASS", "AUTH", "CREDENTIAL"];
+
+fn secret_regexes() -> &'static [Regex] {
+    static REGEXES: OnceLock<Vec<Regex>> = OnceLock::new();
+    REGEXES.get_or_init(|| {
+        SECRET_PATTERNS
+            .iter()
+            .map(|pattern| Regex::new(pattern).expect("secret patterns are valid regexes"))
+            .collect()
+    })
+}
+
+fn dangerous_command_set() -> &'static RegexSet {
+    static SET: OnceLock<RegexSet> = OnceLock::new();
+    SET.get_or_init(|| RegexSet::new(DANGEROUS_COMMAND_PATTERNS).expect("dangerous command patterns are valid regexes"))
+}
+
+fn export_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r#"\bexport\s+([A-Za-z_][A-Za-z0-9_]*)=("[^"]*"|'[^']*'|\S+)"#)
+            .expect("export pattern is a valid regex")
+    })
+}
+
+/// Whether an exported value looks like a secret, by the variable's name or the value's shape
+fn is_suspicious_assignment(name: &str, value: &str) -> bool {
+    let value = value.trim_matches(|c| c == '"' || c == '\'');
+    if value.is_empty() || value.starts_with('$') || value == SECRET_REDACTED {
+        return false;
+    }
+    let name = name.to_ascii_uppercase();
+    if SECRET_VARIABLE_HINTS.iter().any(|hint| name.contains(hint)) {
+        return true;
+    }
+    // Long random-looking strings mixing letters and digits, such as generated keys
+    value.len() >= 20
+        && !value.contains('/')
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || "_-+=.".contains(c))
+        && value.chars().any(|c| c.is_ascii_digit())
+        && value.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+/// Replace every detected secret in a piece of text
+fn collapse_secrets(text: &str) -> String {
+    let mut result = text.to_string();
+    for regex in secret_regexes() {
+        if regex.is_match(&result) {
+            result = regex
+                .replace_all(&result, |caps: &regex::Captures| {
+                    let keep = caps.name("keep").map_or("", |m| m.as_str());
+                    format!("{}{}", keep, SECRET_REDACTED)
+                })
+                .into_owned();
+        }
+    }
+    export_regex()
+        .replace_all(&result, |caps: &regex::Captures| {
+            if is_suspicious_assignment(&caps[1], &caps[2]) {
+                format!("export {}={}", &caps[1], SECRET_REDACTED)
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .into_owned()
+}
 
 /// Criteria for filtering commands
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +151,9 @@ pub struct FilterCriteria {
     pub privacy_mode: PrivacyMode,
     /// Custom sensitive patterns to filter
     pub custom_sensitive_patterns: Vec<String>,
+    /// Additional normalization rules for deduplication, applied after the built-in ones
+    #[serde(skip)]
+    pub custom_normalization_rules: ReplacementRules,
     /// Enable command sequence validation
     pub enable_sequence_validation: bool,
     /// Validate command dependencies and prerequisites
@@ -51,10 +166,13 @@ pub struct FilterCriteria {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PrivacyMode {
     /// Strict: Filter out all potentially sensitive information
+    #[serde(alias = "strict")]
     Strict,
     /// Moderate: Filter out obvious sensitive data but allow some context
+    #[serde(alias = "moderate")]
     Moderate,
     /// Lenient: Only filter out clearly sensitive patterns like passwords
+    #[serde(alias = "lenient")]
     Lenient,
 }
 
@@ -162,6 +280,7 @@ impl Default for FilterCriteria {
             enable_privacy_filtering: true,
             privacy_mode: PrivacyMode::default(),
             custom_sensitive_patterns: Vec::new(),
+            custom_normalization_rules: ReplacementRules::default(),
             enable_sequence_validation: true,
             validate_dependencies: true,
             suggest_fixes: true,
@@ -174,9 +293,28 @@ impl Default for FilterCriteria {
 pub struct FilterResult {
     pub should_include: bool,
     pub reason: String,
+    /// Which rule left the command out, `None` when it is included
+    pub exclusion: Option<ExclusionReason>,
     pub confidence: f32, // 0.0 to 1.0
 }
 
+/// The rule that left a command out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusionReason {
+    /// Exited non-zero while only successful commands are included
+    Unsuccessful,
+    /// Exited non-zero while failed commands are excluded
+    Failed,
+    /// Exited with a code from the exclusion list
+    ExcludedExitCode,
+    /// Matches one of the exclusion patterns
+    ExcludedPattern,
+    /// Its output reads like a failure
+    FailureIndicators,
+    /// Looks like a typo or a mistake
+    Suspicious,
+}
+
 /// Command dependency information
 #[derive(Debug, Clone)]
 pub struct CommandDependency {
@@ -245,11 +383,23 @@ impl CommandFilter {
         Self { criteria }
     }
 
+    /// Create a command filter with default criteria extended by the user's filter config
+    pub fn with_user_config() -> Result<Self> {
+        let mut criteria = FilterCriteria::default();
+        FilterConfig::load()?.apply_to(&mut criteria)?;
+        Ok(Self { criteria })
+    }
+
     /// Update filter criteria
     pub fn set_criteria(&mut self, criteria: FilterCriteria) {
         self.criteria = criteria;
     }
 
+    /// Whether secrets are collapsed before commands are published
+    pub fn is_strict(&self) -> bool {
+        matches!(self.criteria.privacy_mode, PrivacyMode::Strict)
+    }
+
     /// Get current filter criteria
     pub fn get_criteria(&self) -> &FilterCriteria {
         &self.criteria
@@ -264,6 +414,7 @@ impl CommandFilter {
                 return FilterResult {
                     should_include: false,
                     reason: "Only successful commands are included".to_string(),
+                    exclusion: Some(ExclusionReason::Unsuccessful),
                     confidence: 1.0,
                 };
             }
@@ -273,6 +424,7 @@ impl CommandFilter {
                 return FilterResult {
                     should_include: false,
                     reason: format!("Command failed with exit code {}", exit_code),
+                    exclusion: Some(ExclusionReason::Failed),
                     confidence: 1.0,
                 };
             }
@@ -281,6 +433,7 @@ impl CommandFilter {
                 return FilterResult {
                     should_include: false,
                     reason: format!("Exit code {} is in exclusion list", exit_code),
+                    exclusion: Some(ExclusionReason::ExcludedExitCode),
                     confidence: 1.0,
                 };
             }
@@ -293,6 +446,7 @@ impl CommandFilter {
                 return FilterResult {
                     should_include: false,
                     reason: format!("Command matches exclusion pattern: {}", pattern),
+                    exclusion: Some(ExclusionReason::ExcludedPattern),
                     confidence: 0.8,
                 };
             }
@@ -304,6 +458,7 @@ impl CommandFilter {
                 return FilterResult {
                     should_include: false,
                     reason: "Command output contains failure indicators".to_string(),
+                    exclusion: Some(ExclusionReason::FailureIndicators),
                     confidence: 0.7,
                 };
             }
@@ -315,6 +470,7 @@ impl CommandFilter {
                 return FilterResult {
                     should_include: false,
                     reason: "Command output contains failure indicators".to_string(),
+                    exclusion: Some(ExclusionReason::FailureIndicators),
                     confidence: 0.7,
                 };
             }
@@ -325,6 +481,7 @@ impl CommandFilter {
             return FilterResult {
                 should_include: false,
                 reason: "Command appears to be a typo or mistake".to_string(),
+                exclusion: Some(ExclusionReason::Suspicious),
                 confidence: 0.6,
             };
         }
@@ -332,6 +489,7 @@ impl CommandFilter {
         FilterResult {
             should_include: true,
             reason: "Command passed all filters".to_string(),
+            exclusion: None,
             confidence: 1.0,
         }
     }
@@ -342,6 +500,11 @@ impl CommandFilter {
             return command.clone();
         }
 
+        // Known secret formats are collapsed first, so the broader patterns below can't split them
+        let command = &match self.criteria.privacy_mode {
+            PrivacyMode::Strict => self.redact_secrets(command),
+            _ => command.clone(),
+        };
         let mut sanitized = command.clone();
         sanitized.command = self.redact_sensitive_data(&command.command);
         
@@ -352,10 +515,43 @@ impl CommandFilter {
         if let Some(error) = &command.error {
             sanitized.error = Some(self.redact_sensitive_data(error));
         }
+        if let Some(expanded_command) = &command.expanded_command {
+            sanitized.expanded_command = Some(self.redact_sensitive_data(expanded_command));
+        }
 
         sanitized
     }
 
+    /// Copy of a command with common secret formats collapsed to `[SECRET_REDACTED]`
+    ///
+    /// Covers AWS access keys, GitHub and bearer tokens, private key blocks and `export VAR=...`
+    /// assignments of values that look secret, in the command and everything it printed.
+    pub fn redact_secrets(&self, entry: &CommandEntry) -> CommandEntry {
+        let mut redacted = entry.clone();
+        redacted.command = collapse_secrets(&entry.command);
+        for text in [&mut redacted.output, &mut redacted.error, &mut redacted.expanded_command]
+            .into_iter()
+            .flatten()
+        {
+            *text = collapse_secrets(text);
+        }
+        redacted
+    }
+
+    /// Redact free-form text (e.g. annotations), returning `None` when nothing sensitive was found
+    pub fn redact_text_if_sensitive(&self, text: &str) -> Option<String> {
+        if !self.criteria.enable_privacy_filtering {
+            return None;
+        }
+
+        let redacted = self.redact_sensitive_data(text);
+        if redacted != text {
+            Some(redacted)
+        } else {
+            None
+        }
+    }
+
     /// Redact sensitive data from a text string
     fn redact_sensitive_data(&self, text: &str) -> String {
         let mut result = text.to_string();
@@ -407,7 +603,7 @@ impl CommandFilter {
         // Additional moderate patterns
         result = self.redact_api_keys(&result);
         result = self.redact_tokens(&result);
-        result = self.redact_secrets(&result);
+        result = self.redact_secret_assignments(&result);
         result = self.redact_credentials(&result);
         
         result
@@ -568,7 +764,7 @@ impl CommandFilter {
     }
 
     /// Redact secret patterns
-    fn redact_secrets(&self, text: &str) -> String {
+    fn redact_secret_assignments(&self, text: &str) -> String {
         use regex::Regex;
         
         let patterns = [
@@ -779,6 +975,28 @@ impl CommandFilter {
         failure_patterns.iter().any(|pattern| text_lower.contains(&pattern.to_lowercase()))
     }
 
+    /// Guess whether a command without a captured exit code succeeded, from its output
+    ///
+    /// Returns `None` when the exit code is known, the command is still running in the
+    /// background, or there is no output to judge by.
+    pub fn infer_success(&self, command: &CommandEntry) -> Option<bool> {
+        if command.exit_code.is_some() || command.backgrounded {
+            return None;
+        }
+
+        let texts: Vec<&str> = [&command.error, &command.output]
+            .into_iter()
+            .flatten()
+            .map(|text| text.as_str())
+            .filter(|text| !text.trim().is_empty())
+            .collect();
+        if texts.is_empty() {
+            return None;
+        }
+
+        Some(!texts.iter().any(|text| self.contains_failure_indicators(text)))
+    }
+
     /// Check if a command appears to be suspicious (typo, mistake, etc.)
     fn is_suspicious_command(&self, command: &str) -> bool {
         let cmd_parts: Vec<&str> = command.split_whitespace().collect();
@@ -802,18 +1020,37 @@ impl CommandFilter {
         false
     }
 
+    /// Check if a command looks destructive enough to warn about while it is captured
+    ///
+    /// Commands that are safe to re-execute (`echo`, `grep`, ...) never count, even when their
+    /// arguments mention something destructive, e.g. `grep "DROP TABLE" schema.sql`.
+    pub fn is_dangerous(&self, command: &str) -> bool {
+        if self.is_safe_to_test(command) {
+            return false;
+        }
+
+        dangerous_command_set().is_match(command)
+    }
+
     /// Check if a command is safe to re-execute for testing purposes
-    fn is_safe_to_test(&self, command: &str) -> bool {
+    ///
+    /// Only a single read-only program counts: anything a shell would treat as a second command,
+    /// a redirect or a substitution is rejected, as are `find` actions that delete or run things.
+    pub fn is_safe_to_test(&self, command: &str) -> bool {
         let safe_commands = [
             "ls", "pwd", "whoami", "date", "echo", "cat", "head", "tail",
             "grep", "find", "which", "type", "file", "stat", "wc",
         ];
 
+        if command.contains(SHELL_METACHARACTERS) {
+            return false;
+        }
+
         let cmd_parts: Vec<&str> = command.split_whitespace().collect();
-        if let Some(first_word) = cmd_parts.first() {
-            safe_commands.contains(first_word)
-        } else {
-            false
+        match cmd_parts.first() {
+            Some(&"find") => !cmd_parts.iter().any(|part| UNSAFE_FIND_ACTIONS.contains(part)),
+            Some(first_word) => safe_commands.contains(first_word),
+            None => false,
         }
     }
 
@@ -1066,14 +1303,11 @@ impl CommandFilter {
             } else {
                 stats.excluded_commands += 1;
                 
-                if result.reason.contains("failed") || result.reason.contains("exit code") {
-                    stats.failed_commands += 1;
-                } else if result.reason.contains("pattern") || result.reason.contains("typo") {
-                    stats.typo_commands += 1;
-                } else if result.reason.contains("suspicious") {
-                    stats.suspicious_commands += 1;
-                } else if result.reason.contains("failure indicators") {
-                    stats.error_output_commands += 1;
+                match result.exclusion {
+                    Some(ExclusionReason::Unsuccessful | ExclusionReason::Failed) => stats.failed_commands += 1,
+                    Some(ExclusionReason::ExcludedPattern | ExclusionReason::Suspicious) => stats.typo_commands += 1,
+                    Some(ExclusionReason::FailureIndicators) => stats.error_output_commands += 1,
+                    Some(ExclusionReason::ExcludedExitCode) | None => {}
                 }
             }
 
@@ -1169,7 +1403,7 @@ impl CommandFilter {
     }
 
     /// Normalize command for comparison (remove variable parts)
-    fn normalize_command(&self, command: &str) -> String {
+    pub fn normalize_command(&self, command: &str) -> String {
         let mut normalized = command.to_lowercase();
         
         // Remove common variable patterns
@@ -1177,6 +1411,7 @@ impl CommandFilter {
         normalized = self.remove_file_paths(&normalized);
         normalized = self.remove_process_ids(&normalized);
         normalized = self.remove_port_numbers(&normalized);
+        normalized = self.apply_custom_normalization_rules(&normalized);
         
         // Normalize whitespace
         normalized = normalized.split_whitespace().collect::<Vec<_>>().join(" ");
@@ -1267,6 +1502,11 @@ impl CommandFilter {
         result
     }
 
+    /// Apply user-defined normalization rules (e.g. build hashes, container IDs)
+    fn apply_custom_normalization_rules(&self, command: &str) -> String {
+        self.criteria.custom_normalization_rules.replace(command)
+    }
+
     /// Optimize workflow by identifying and consolidating patterns
     pub fn optimize_workflow(&self, commands: &[CommandEntry]) -> Vec<WorkflowOptimization> {
         if !self.criteria.enable_workflow_optimization {
@@ -1925,17 +2165,14 @@ impl Default for CommandFilter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
 
     fn create_test_command(command: &str, exit_code: Option<i32>) -> CommandEntry {
         CommandEntry {
             command: command.to_string(),
-            timestamp: Utc::now(),
             exit_code,
             working_directory: "/test".to_string(),
             shell: "bash".to_string(),
-            output: None,
-            error: None,
+            ..Default::default()
         }
     }
 
@@ -2016,6 +2253,12 @@ mod tests {
         let result = filter.filter_command(&cmd);
         assert!(!result.should_include);
         assert!(result.reason.contains("Only successful commands"));
+        assert_eq!(result.exclusion, Some(ExclusionReason::Unsuccessful));
+
+        // Counted as failed whatever the reason text says
+        let stats = filter.get_filtering_stats(&[cmd, create_test_command("ls", Some(0))]);
+        assert_eq!((stats.failed_commands, stats.included_commands), (1, 1));
+        assert_eq!(filter.filter_command(&create_test_command("ls", Some(0))).exclusion, None);
     }
 }
 
@@ -0,0 +1,137 @@
+//! User filter configuration
+//!
+//! Settings that extend the built-in filtering behaviour, loaded from
+//! `filter.json` in the DocPilot config directory.
+
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use super::command::{FilterCriteria, PrivacyMode};
+use super::rules::ReplacementRules;
+
+/// A regex → placeholder rule used to normalize commands before deduplication
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NormalizationRule {
+    /// Regular expression matched against the lowercased command
+    pub pattern: String,
+    /// Text that replaces every match
+    pub replacement: String,
+}
+
+impl NormalizationRule {
+    /// Create a rule, checking that the pattern compiles
+    pub fn new(pattern: &str, replacement: &str) -> Result<Self> {
+        Regex::new(pattern).map_err(|e| anyhow!("Invalid normalization pattern '{}': {}", pattern, e))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+        })
+    }
+}
+
+/// Compile regexes given on the command line, naming the first one that is invalid
+pub fn compile_exclude_patterns(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(|e| anyhow!("Invalid exclude pattern '{}': {}", pattern, e)))
+        .collect()
+}
+
+/// A regex → replacement rule that changes how commands are published, e.g. a cluster name to `<cluster>`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RewriteRule {
+    /// Regular expression matched against the command as typed
+    pub pattern: String,
+    /// Text that replaces every match; `$1` or `${name}` insert capture groups
+    pub replacement: String,
+}
+
+/// User-provided additions to the default filter criteria
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FilterConfig {
+    /// Extra normalization rules, applied after the built-in ones
+    #[serde(default)]
+    pub normalization_rules: Vec<NormalizationRule>,
+    /// Commands dropped at capture time, matching the command alone or followed by arguments
+    #[serde(default)]
+    pub ignore_commands: Vec<String>,
+    /// Environment variables recorded when a session starts; a trailing `*` matches a prefix,
+    /// and an empty list records `environment::DEFAULT_ENVIRONMENT_VARIABLES`
+    #[serde(default)]
+    pub environment_variables: Vec<String>,
+    /// Rewrites applied to commands when documentation is generated, in order
+    #[serde(default)]
+    pub rewrite_rules: Vec<RewriteRule>,
+    /// Regexes whose matches are replaced with `[REDACTED]` in generated documentation
+    #[serde(default)]
+    pub redact_patterns: Vec<String>,
+    /// Privacy mode used in place of the default; `strict` also collapses common secret formats
+    /// in generated documentation
+    #[serde(default)]
+    pub privacy_mode: Option<PrivacyMode>,
+}
+
+impl FilterConfig {
+    /// Load the filter configuration, falling back to defaults when no file exists
+    pub fn load() -> Result<Self> {
+        let config_path = Self::config_file_path()?;
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&config_path)?;
+        let config: FilterConfig = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid filter configuration {}: {}", config_path.display(), e))?;
+
+        for rule in &config.normalization_rules {
+            NormalizationRule::new(&rule.pattern, &rule.replacement)?;
+        }
+        super::rewrite::CommandRewriter::new(&config.rewrite_rules)?;
+        super::redact::Redactor::new(&config.redact_patterns)?;
+
+        Ok(config)
+    }
+
+    /// Write the filter configuration, creating the config directory if needed
+    pub fn save(&self) -> Result<()> {
+        let config_path = Self::config_file_path()?;
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&config_path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Add a redaction pattern after checking that it compiles
+    ///
+    /// Returns false when the pattern was already configured.
+    pub fn add_redact_pattern(&mut self, pattern: &str) -> Result<bool> {
+        super::redact::Redactor::new(&[pattern.to_string()])?;
+        if self.redact_patterns.iter().any(|existing| existing == pattern) {
+            return Ok(false);
+        }
+        self.redact_patterns.push(pattern.to_string());
+        Ok(true)
+    }
+
+    /// Get the filter configuration file path
+    pub fn config_file_path() -> Result<PathBuf> {
+        Ok(crate::settings::config_dir()?.join("filter.json"))
+    }
+
+    /// Add the user settings to a set of filter criteria, compiling the normalization rules
+    pub fn apply_to(&self, criteria: &mut FilterCriteria) -> Result<()> {
+        criteria.custom_normalization_rules = ReplacementRules::new(
+            "normalization",
+            self.normalization_rules.iter().map(|rule| (rule.pattern.as_str(), rule.replacement.as_str())),
+        )?;
+        criteria.custom_sensitive_patterns.extend(self.redact_patterns.iter().cloned());
+        if let Some(mode) = &self.privacy_mode {
+            criteria.privacy_mode = mode.clone();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+//! Environment snapshot recorded once when a session starts
+//!
+//! Only allowlisted variables are kept, and any named like a secret or whose value the privacy
+//! filter would redact are left out entirely, so the snapshot can be shown in documentation as it is.
+
+use std::collections::BTreeMap;
+
+use super::command::CommandFilter;
+
+/// Variables recorded when `filter.json` doesn't list any: the search path, locale and
+/// the settings that pick tool versions
+pub const DEFAULT_ENVIRONMENT_VARIABLES: &[&str] = &[
+    "PATH", "SHELL", "TERM", "LANG", "LC_ALL",
+    "JAVA_HOME", "GOPATH", "GOROOT", "CARGO_HOME", "RUSTUP_TOOLCHAIN",
+    "VIRTUAL_ENV", "CONDA_DEFAULT_ENV", "PYENV_VERSION", "NODE_ENV", "NVM_BIN",
+];
+
+/// Parts of variable names that hold secrets, whatever their value looks like
+const SECRET_NAME_PARTS: &[&str] = &["SECRET", "TOKEN", "PASSWORD", "PASSWD", "API_KEY", "APIKEY", "PRIVATE_KEY", "CREDENTIAL"];
+
+/// Check whether a variable name is allowed, where an entry ending in `*` matches a prefix
+fn is_allowed(name: &str, allowlist: &[String]) -> bool {
+    allowlist.iter().any(|allowed| match allowed.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == allowed,
+    })
+}
+
+/// The allowlisted variables of `vars`, without those that look like secrets
+///
+/// An empty allowlist uses `DEFAULT_ENVIRONMENT_VARIABLES`.
+pub fn environment_snapshot(allowlist: &[String], vars: impl IntoIterator<Item = (String, String)>) -> BTreeMap<String, String> {
+    let defaults: Vec<String>;
+    let allowlist = if allowlist.is_empty() {
+        defaults = DEFAULT_ENVIRONMENT_VARIABLES.iter().map(|name| name.to_string()).collect();
+        &defaults
+    } else {
+        allowlist
+    };
+
+    let filter = CommandFilter::new();
+    vars.into_iter()
+        .filter(|(name, _)| is_allowed(name, allowlist))
+        .filter(|(name, _)| !SECRET_NAME_PARTS.iter().any(|part| name.to_uppercase().contains(part)))
+        .filter(|(name, value)| filter.redact_text_if_sensitive(&format!("{}={}", name, value)).is_none())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environment_snapshot_keeps_allowlisted_non_secrets() {
+        let vars = || {
+            [
+                ("PATH", "/usr/local/bin:/usr/bin"),
+                ("HOME", "/home/user"),
+                ("NODE_ENV", "development"),
+                ("AWS_REGION", "eu-west-1"),
+                ("AWS_SECRET_ACCESS_KEY", "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY"),
+                ("DB_PASSWORD", "hunter2"),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        };
+
+        let defaults = environment_snapshot(&[], vars());
+        assert_eq!(defaults.keys().collect::<Vec<_>>(), vec!["NODE_ENV", "PATH"]);
+
+        let allowlist = vec!["AWS_*".to_string(), "DB_PASSWORD".to_string(), "HOME".to_string()];
+        let custom = environment_snapshot(&allowlist, vars());
+        assert_eq!(custom.keys().collect::<Vec<_>>(), vec!["AWS_REGION", "HOME"]);
+    }
+}
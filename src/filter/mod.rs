@@ -4,9 +4,22 @@
 //! including success/failure detection, command validation, and privacy filtering.
 
 pub mod command;
+pub mod config;
+pub mod environment;
+pub mod procedure;
+pub mod redact;
+pub mod replay;
+pub mod rewrite;
+pub mod rules;
 
 pub use command::{
-    CommandFilter, FilterCriteria, FilterResult, FilteringStats,
+    CommandFilter, ExclusionReason, FilterCriteria, FilterResult, FilteringStats,
     WorkflowOptimization, OptimizationType, ProcessedCommands, PrivacyMode,
     CommandDependency, ValidationResult, ValidationType, SequenceValidationError, ValidationErrorType
-};
\ No newline at end of file
+};
+pub use config::{FilterConfig, compile_exclude_patterns};
+pub use environment::environment_snapshot;
+pub use procedure::{Procedure, check_conformance};
+pub use redact::Redactor;
+pub use replay::{ReplayOutcome, replay_commands};
+pub use rewrite::CommandRewriter;
@@ -0,0 +1,195 @@
+//! Conformance checking against an approved procedure
+//!
+//! A procedure is an ordered list of expected commands. Captured commands are
+//! compared step by step using normalized commands, so differences in
+//! timestamps, temp paths, ports and similar variable parts do not count as
+//! deviations.
+
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::Path;
+
+use super::command::CommandFilter;
+use crate::terminal::monitor::CommandEntry;
+
+/// An ordered list of commands a session is expected to contain
+#[derive(Debug, Clone)]
+pub struct Procedure {
+    pub steps: Vec<String>,
+}
+
+impl Procedure {
+    /// Parse a procedure with one command per line; blank lines and `#` comments are ignored
+    pub fn parse(content: &str) -> Self {
+        let steps = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        Self { steps }
+    }
+
+    /// Load a procedure from a file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read procedure '{}': {}", path.display(), e))?;
+        let procedure = Self::parse(&content);
+        if procedure.steps.is_empty() {
+            return Err(anyhow!("Procedure '{}' does not contain any commands", path.display()));
+        }
+        Ok(procedure)
+    }
+}
+
+/// A procedure step that was run, but not where the procedure expects it
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutOfOrderStep {
+    /// 1-based position in the procedure
+    pub expected_position: usize,
+    /// 1-based position among the checked session commands
+    pub actual_position: usize,
+    pub command: String,
+}
+
+/// Result of comparing captured commands with a procedure
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    /// Steps that were matched in order
+    pub matched: usize,
+    /// Procedure steps (1-based position, command) that were never run
+    pub missing: Vec<(usize, String)>,
+    /// Captured commands (1-based position, command) that are not part of the procedure
+    pub extra: Vec<(usize, String)>,
+    /// Procedure steps that were run in a different order
+    pub out_of_order: Vec<OutOfOrderStep>,
+}
+
+impl ConformanceReport {
+    /// Whether the session conforms to the procedure
+    pub fn passed(&self, allow_extra: bool) -> bool {
+        self.missing.is_empty() && self.out_of_order.is_empty() && (allow_extra || self.extra.is_empty())
+    }
+}
+
+/// Compare commands with the procedure using the filter's command normalization
+pub fn check_conformance(procedure: &Procedure, commands: &[CommandEntry], filter: &CommandFilter) -> ConformanceReport {
+    let expected: Vec<String> = procedure.steps.iter().map(|step| filter.normalize_command(step)).collect();
+    let actual: Vec<String> = commands.iter().map(|cmd| filter.normalize_command(&cmd.command)).collect();
+
+    // The longest common subsequence is the largest set of steps that ran in order
+    let (n, m) = (expected.len(), actual.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut expected_matched = vec![false; n];
+    let mut actual_matched = vec![false; m];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if expected[i] == actual[j] {
+            expected_matched[i] = true;
+            actual_matched[j] = true;
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    let mut report = ConformanceReport {
+        matched: expected_matched.iter().filter(|matched| **matched).count(),
+        ..ConformanceReport::default()
+    };
+
+    // Unmatched steps that still appear elsewhere in the session ran out of order
+    for (step, key) in expected.iter().enumerate() {
+        if expected_matched[step] {
+            continue;
+        }
+        let elsewhere = (0..m).find(|&pos| !actual_matched[pos] && &actual[pos] == key);
+        match elsewhere {
+            Some(pos) => {
+                actual_matched[pos] = true;
+                report.out_of_order.push(OutOfOrderStep {
+                    expected_position: step + 1,
+                    actual_position: pos + 1,
+                    command: procedure.steps[step].clone(),
+                });
+            }
+            None => report.missing.push((step + 1, procedure.steps[step].clone())),
+        }
+    }
+
+    for (pos, matched) in actual_matched.iter().enumerate() {
+        if !matched {
+            report.extra.push((pos + 1, commands[pos].command.clone()));
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_commands(commands: &[&str]) -> Vec<CommandEntry> {
+        commands.iter().map(|command| CommandEntry {
+            command: command.to_string(),
+            exit_code: Some(0),
+            working_directory: "/srv/app".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        }).collect()
+    }
+
+    #[test]
+    fn test_procedure_parsing_skips_comments() {
+        let procedure = Procedure::parse("# Deploy\ngit pull\n\n  cargo build --release  \n# done\n");
+        assert_eq!(procedure.steps, vec!["git pull", "cargo build --release"]);
+    }
+
+    #[test]
+    fn test_matching_session_passes_with_fuzzy_arguments() {
+        let procedure = Procedure::parse("git pull\ncurl localhost:8080/health\nsystemctl restart app");
+        let commands = create_commands(&["git pull", "curl localhost:9090/health", "systemctl restart app"]);
+
+        let report = check_conformance(&procedure, &commands, &CommandFilter::new());
+        assert!(report.passed(false));
+        assert_eq!(report.matched, 3);
+    }
+
+    #[test]
+    fn test_reports_missing_extra_and_out_of_order_steps() {
+        let procedure = Procedure::parse("git pull\ncargo test\ncargo build --release\nsystemctl restart app");
+        let commands = create_commands(&["git pull", "cargo build --release", "cargo test", "vim notes.txt"]);
+
+        let report = check_conformance(&procedure, &commands, &CommandFilter::new());
+        assert!(!report.passed(true));
+        assert_eq!(report.missing, vec![(4, "systemctl restart app".to_string())]);
+        assert_eq!(report.extra, vec![(4, "vim notes.txt".to_string())]);
+        assert_eq!(report.out_of_order.len(), 1);
+        assert_eq!(report.out_of_order[0].expected_position, 2);
+        assert_eq!(report.out_of_order[0].actual_position, 3);
+    }
+
+    #[test]
+    fn test_allow_extra_only_ignores_additional_commands() {
+        let procedure = Procedure::parse("git pull\ncargo build");
+        let commands = create_commands(&["git pull", "ls -la", "cargo build"]);
+
+        let report = check_conformance(&procedure, &commands, &CommandFilter::new());
+        assert!(!report.passed(false));
+        assert!(report.passed(true));
+    }
+}
@@ -0,0 +1,93 @@
+//! Redacting user-defined patterns from generated documentation
+//!
+//! `redact_patterns` in `filter.json`, added with `docpilot config --redact`, are regexes whose
+//! matches are replaced with `[REDACTED]` in commands, their output and errors before a document
+//! is rendered. The stored session keeps the text as captured. Patterns are compiled once, and an
+//! invalid one is an error rather than being skipped, so nothing meant to be hidden slips through.
+
+use anyhow::Result;
+
+use super::config::FilterConfig;
+use super::rules::ReplacementRules;
+use crate::session::manager::Session;
+
+/// Text that replaces every match of a redaction pattern
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Compiled redaction patterns
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    patterns: ReplacementRules,
+}
+
+impl Redactor {
+    /// Compile patterns, naming the first one that is invalid
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let patterns = ReplacementRules::new("redaction", patterns.iter().map(|pattern| (pattern.as_str(), REDACTED)))?;
+        Ok(Self { patterns })
+    }
+
+    /// Patterns from the user's filter configuration
+    pub fn load() -> Result<Self> {
+        Self::new(&FilterConfig::load()?.redact_patterns)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Replace every match of every pattern
+    pub fn redact(&self, text: &str) -> String {
+        self.patterns.replace(text)
+    }
+
+    /// Copy of the session with its commands and what they printed redacted
+    pub fn apply(&self, session: &Session) -> Session {
+        let mut redacted = session.clone();
+        for command in &mut redacted.commands {
+            command.command = self.redact(&command.command);
+            for text in [&mut command.output, &mut command.error, &mut command.expanded_command]
+                .into_iter()
+                .flatten()
+            {
+                *text = self.redact(text);
+            }
+        }
+        redacted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::CommandEntry;
+
+    #[test]
+    fn test_redacts_commands_output_and_errors() {
+        let redactor = Redactor::new(&[r"(?i)password=\S+".to_string(), r"acct-\d{6}".to_string()]).unwrap();
+        assert_eq!(redactor.redact("login PASSWORD=hunter2 --account acct-123456"), "login [REDACTED] --account [REDACTED]");
+        assert_eq!(redactor.redact("make test"), "make test");
+
+        let mut session = Session::new("Billing".to_string(), None).unwrap();
+        session.commands.push(CommandEntry {
+            command: "billing-cli charge acct-123456".to_string(),
+            exit_code: Some(1),
+            working_directory: "/srv/billing".to_string(),
+            shell: "bash".to_string(),
+            output: Some("Charging acct-123456".to_string()),
+            error: Some("rejected: password=s3cret expired".to_string()),
+            ..Default::default()
+        });
+        let redacted = redactor.apply(&session);
+        assert_eq!(redacted.commands[0].command, "billing-cli charge [REDACTED]");
+        assert_eq!(redacted.commands[0].output.as_deref(), Some("Charging [REDACTED]"));
+        assert_eq!(redacted.commands[0].error.as_deref(), Some("rejected: [REDACTED] expired"));
+        assert_eq!(session.commands[0].command, "billing-cli charge acct-123456");
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_an_error() {
+        let error = Redactor::new(&["token=\\S+".to_string(), "(unclosed".to_string()]).unwrap_err();
+        assert!(error.to_string().contains("'(unclosed'"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Re-running a session's commands to check that documentation still works
+//!
+//! Commands are run in order, each in the working directory it was captured in,
+//! and their new exit codes are compared with the recorded ones. Only commands
+//! the filter considers safe to re-execute are run unless replay is forced; those
+//! are started directly with their arguments, never through a shell.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use super::command::CommandFilter;
+use crate::terminal::monitor::CommandEntry;
+
+/// How long a replayed command may run before it is killed, e.g. `tail -f`
+pub const REPLAY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How a replayed command's result compares with the recorded one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayOutcome {
+    /// Succeeded when recorded and again now
+    StillSucceeds,
+    /// Succeeded when recorded, fails now
+    NowFails,
+    /// Failed when recorded, succeeds now
+    NowSucceeds,
+    /// Failed when recorded and again now
+    StillFails,
+    /// No exit code was recorded, so only the new result is known
+    Unrecorded,
+    /// Not run because it is not safe to re-execute
+    Skipped,
+}
+
+/// One command of a replay
+#[derive(Debug, Clone)]
+pub struct ReplayStep {
+    /// 1-based position in the session
+    pub position: usize,
+    pub command: String,
+    pub recorded_exit_code: Option<i32>,
+    /// `None` when the command was skipped, could not be started or timed out
+    pub exit_code: Option<i32>,
+    /// Killed after running for `REPLAY_TIMEOUT`
+    pub timed_out: bool,
+    pub outcome: ReplayOutcome,
+}
+
+/// Results of replaying a session's commands
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    pub steps: Vec<ReplayStep>,
+}
+
+impl ReplayReport {
+    /// Steps with the given outcome
+    pub fn with_outcome(&self, outcome: ReplayOutcome) -> Vec<&ReplayStep> {
+        self.steps.iter().filter(|step| step.outcome == outcome).collect()
+    }
+
+    /// Whether no command that succeeded when recorded fails now
+    pub fn passed(&self) -> bool {
+        self.with_outcome(ReplayOutcome::NowFails).is_empty()
+    }
+}
+
+/// Compare a recorded exit code with the one from the replay
+pub fn compare_exit_codes(recorded: Option<i32>, replayed: Option<i32>) -> ReplayOutcome {
+    let now_ok = replayed == Some(0);
+    match recorded {
+        None => ReplayOutcome::Unrecorded,
+        Some(0) if now_ok => ReplayOutcome::StillSucceeds,
+        Some(0) => ReplayOutcome::NowFails,
+        Some(_) if now_ok => ReplayOutcome::NowSucceeds,
+        Some(_) => ReplayOutcome::StillFails,
+    }
+}
+
+/// Re-run commands in order, calling `on_step` after each one so progress can be shown
+///
+/// Commands that fail `CommandFilter::is_safe_to_test` are skipped unless `force` is set.
+/// Forced commands run through `sh -c`, so pipes and `&&` chains behave as they were typed.
+pub fn replay_commands(
+    commands: &[CommandEntry],
+    filter: &CommandFilter,
+    force: bool,
+    mut on_step: impl FnMut(&ReplayStep),
+) -> ReplayReport {
+    let mut report = ReplayReport::default();
+
+    for (index, entry) in commands.iter().enumerate() {
+        let command = if filter.is_safe_to_test(&entry.command) {
+            split_arguments(&entry.command).map(|args| direct_command(&args))
+        } else if force {
+            Some(shell_command(&entry.command))
+        } else {
+            None
+        };
+        let step = match command {
+            Some(command) => {
+                let (exit_code, timed_out) = run_command(command, entry, REPLAY_TIMEOUT);
+                ReplayStep {
+                    position: index + 1,
+                    command: entry.command.clone(),
+                    recorded_exit_code: entry.exit_code,
+                    exit_code,
+                    timed_out,
+                    outcome: compare_exit_codes(entry.exit_code, exit_code),
+                }
+            }
+            None => ReplayStep {
+                position: index + 1,
+                command: entry.command.clone(),
+                recorded_exit_code: entry.exit_code,
+                exit_code: None,
+                timed_out: false,
+                outcome: ReplayOutcome::Skipped,
+            },
+        };
+        on_step(&step);
+        report.steps.push(step);
+    }
+
+    report
+}
+
+/// Split a command line into arguments, honouring single and double quotes and backslashes
+///
+/// Returns `None` for an unterminated quote or an empty command.
+pub fn split_arguments(command: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => current.push(c),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => current.push(chars.next()?),
+                        c => current.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                current.push(chars.next()?);
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        args.push(current);
+    }
+    (!args.is_empty()).then_some(args)
+}
+
+/// A program started directly with its arguments
+fn direct_command(args: &[String]) -> Command {
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]);
+    command
+}
+
+/// A command line run by `sh -c`
+fn shell_command(command_line: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(command_line);
+    command
+}
+
+/// Run a command in its captured working directory, killing it after `limit`
+///
+/// Returns its exit code, `None` when it could not be started or timed out, and whether it timed out.
+fn run_command(mut command: Command, entry: &CommandEntry, limit: Duration) -> (Option<i32>, bool) {
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    // Fall back to the current directory when the captured one no longer exists
+    if Path::new(&entry.working_directory).is_dir() {
+        command.current_dir(&entry.working_directory);
+    }
+
+    let Ok(mut child) = command.spawn() else {
+        return (None, false);
+    };
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return (Some(status.code().unwrap_or(-1)), false),
+            Ok(None) if started.elapsed() < limit => std::thread::sleep(Duration::from_millis(20)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return (None, true);
+            }
+            Err(_) => return (None, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_command(command: &str, exit_code: Option<i32>) -> CommandEntry {
+        CommandEntry {
+            command: command.to_string(),
+            exit_code,
+            working_directory: "/nonexistent/docpilot".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compare_exit_codes() {
+        assert_eq!(compare_exit_codes(Some(0), Some(0)), ReplayOutcome::StillSucceeds);
+        assert_eq!(compare_exit_codes(Some(0), Some(2)), ReplayOutcome::NowFails);
+        assert_eq!(compare_exit_codes(Some(0), None), ReplayOutcome::NowFails);
+        assert_eq!(compare_exit_codes(Some(1), Some(0)), ReplayOutcome::NowSucceeds);
+        assert_eq!(compare_exit_codes(Some(1), Some(1)), ReplayOutcome::StillFails);
+        assert_eq!(compare_exit_codes(None, Some(0)), ReplayOutcome::Unrecorded);
+    }
+
+    #[test]
+    fn test_unsafe_commands_are_skipped_unless_forced() {
+        let commands = vec![
+            create_command("echo hello", Some(0)),
+            create_command("exit 3", Some(0)),
+        ];
+        let filter = CommandFilter::new();
+
+        let report = replay_commands(&commands, &filter, false, |_| {});
+        assert_eq!(report.steps[0].outcome, ReplayOutcome::StillSucceeds);
+        assert_eq!(report.steps[1].outcome, ReplayOutcome::Skipped);
+        assert!(report.passed());
+
+        let forced = replay_commands(&commands, &filter, true, |_| {});
+        assert_eq!(forced.steps[1].exit_code, Some(3));
+        assert_eq!(forced.steps[1].outcome, ReplayOutcome::NowFails);
+        assert!(!forced.passed());
+    }
+
+    #[test]
+    fn test_safe_commands_run_without_a_shell() {
+        assert_eq!(
+            split_arguments(r#"find . -name '*.rs' -path "src/my dir""#),
+            Some(vec!["find".into(), ".".into(), "-name".into(), "*.rs".into(), "-path".into(), "src/my dir".into()])
+        );
+        assert_eq!(split_arguments("echo 'unterminated"), None);
+
+        // The quoted pattern reaches grep as one argument
+        let commands = vec![create_command("grep -q 'no such text' /dev/null", Some(1))];
+        let report = replay_commands(&commands, &CommandFilter::new(), false, |_| {});
+        assert_eq!(report.steps[0].exit_code, Some(1));
+    }
+
+    #[test]
+    fn test_commands_are_killed_after_the_timeout() {
+        let entry = create_command("tail -f /dev/null", Some(0));
+        let args = split_arguments(&entry.command).unwrap();
+        let (exit_code, timed_out) = run_command(direct_command(&args), &entry, Duration::from_millis(200));
+        assert_eq!(exit_code, None);
+        assert!(timed_out);
+    }
+}
@@ -0,0 +1,91 @@
+//! Rewriting commands for publication
+//!
+//! `rewrite_rules` in `filter.json` replace text in commands when documentation is generated,
+//! e.g. a real cluster name with `<cluster>` or `/home/alice` with `~`. Unlike redaction this is
+//! about how the published commands read: the stored session keeps the commands as typed, and
+//! everything built from a document's commands (headings, table of contents, AI prompts) sees
+//! the rewritten form.
+
+use anyhow::Result;
+
+use super::config::{FilterConfig, RewriteRule};
+use super::rules::ReplacementRules;
+use crate::session::manager::Session;
+
+/// Compiled rewrite rules, applied in the order they are configured
+#[derive(Debug, Clone, Default)]
+pub struct CommandRewriter {
+    rules: ReplacementRules,
+}
+
+impl CommandRewriter {
+    /// Compile rules, naming the first pattern that is invalid
+    pub fn new(rules: &[RewriteRule]) -> Result<Self> {
+        let rules = ReplacementRules::new("rewrite", rules.iter().map(|rule| (rule.pattern.as_str(), rule.replacement.as_str())))?;
+        Ok(Self { rules })
+    }
+
+    /// Rules from the user's filter configuration
+    pub fn load() -> Result<Self> {
+        Self::new(&FilterConfig::load()?.rewrite_rules)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Apply every rule to a command line
+    pub fn rewrite(&self, command: &str) -> String {
+        self.rules.replace(command)
+    }
+
+    /// Copy of the session with its commands, and what their aliases expand to, rewritten
+    pub fn apply(&self, session: &Session) -> Session {
+        let mut rewritten = session.clone();
+        for command in &mut rewritten.commands {
+            command.command = self.rewrite(&command.command);
+            if let Some(expansion) = &command.expanded_command {
+                command.expanded_command = Some(self.rewrite(expansion));
+            }
+        }
+        rewritten
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::CommandEntry;
+
+    #[test]
+    fn test_rules_applied_in_order() {
+        let rule = |pattern: &str, replacement: &str| RewriteRule { pattern: pattern.to_string(), replacement: replacement.to_string() };
+        let rewriter = CommandRewriter::new(&[
+            rule(r"--context[= ]prod-eu-\w+", "--context <cluster>"),
+            rule(r"/home/\w+", "~"),
+            rule(r"~/src/(\w+)", "./$1"),
+        ])
+        .unwrap();
+        assert_eq!(
+            rewriter.rewrite("kubectl --context prod-eu-west3 apply -f /home/alice/src/app/deploy.yml"),
+            "kubectl --context <cluster> apply -f ./app/deploy.yml"
+        );
+        assert_eq!(rewriter.rewrite("make test"), "make test");
+
+        let mut session = Session::new("Deploy".to_string(), None).unwrap();
+        session.commands.push(CommandEntry {
+            command: "deploy /home/alice/app".to_string(),
+            exit_code: Some(0),
+            working_directory: "/home/alice".to_string(),
+            shell: "bash".to_string(),
+            expanded_command: Some("rsync -a /home/alice/app web:/srv".to_string()),
+            ..Default::default()
+        });
+        let rewritten = rewriter.apply(&session);
+        assert_eq!(rewritten.commands[0].command, "deploy ~/app");
+        assert_eq!(rewritten.commands[0].expanded_command.as_deref(), Some("rsync -a ~/app web:/srv"));
+        assert_eq!(session.commands[0].command, "deploy /home/alice/app");
+
+        assert!(CommandRewriter::new(&[rule("(unclosed", "")]).unwrap_err().to_string().contains("(unclosed"));
+    }
+}
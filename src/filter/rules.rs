@@ -0,0 +1,42 @@
+//! Compiled regex → replacement rules
+//!
+//! Normalization, rewriting and redaction all replace matches of user-configured regexes. They
+//! share this type so patterns are compiled once and an invalid one is always an error naming it,
+//! never skipped.
+
+use anyhow::{Result, anyhow};
+use regex::Regex;
+
+/// Regexes, each with the text that replaces its matches, applied in order
+#[derive(Debug, Clone, Default)]
+pub struct ReplacementRules {
+    rules: Vec<(Regex, String)>,
+}
+
+impl ReplacementRules {
+    /// Compile `(pattern, replacement)` pairs, naming the first pattern that is invalid
+    ///
+    /// `kind` says what the patterns are for in the error, e.g. `normalization` or `redaction`.
+    pub fn new<'a>(kind: &str, rules: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|(pattern, replacement)| {
+                Regex::new(pattern)
+                    .map(|regex| (regex, replacement.to_string()))
+                    .map_err(|e| anyhow!("Invalid {} pattern '{}': {}", kind, pattern, e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Apply every rule to `text`; `$1` or `${name}` in a replacement insert capture groups
+    pub fn replace(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |text, (pattern, replacement)| pattern.replace_all(&text, replacement.as_str()).into_owned())
+    }
+}
@@ -1,10 +1,12 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 
 use super::client::{LlmClient, LlmRequest};
 use super::prompt::{PromptEngine, PromptType, PromptContext};
 use super::config::LlmConfig;
+use super::cache::AnalysisCache;
 use crate::terminal::CommandEntry;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -111,6 +113,7 @@ pub struct AIAnalyzer {
     prompt_engine: PromptEngine,
     config: LlmConfig,
     analysis_cache: HashMap<String, AnalysisResult>,
+    disk_cache: Option<AnalysisCache>,
 }
 
 impl AIAnalyzer {
@@ -119,17 +122,52 @@ impl AIAnalyzer {
             prompt_engine: PromptEngine::new(),
             config,
             analysis_cache: HashMap::new(),
+            disk_cache: None,
         }
     }
 
+    /// Persist results in an on-disk cache so they survive between runs
+    pub fn with_disk_cache(mut self, cache: AnalysisCache) -> Self {
+        self.disk_cache = Some(cache);
+        self
+    }
+
+    /// Look up a result in memory first, then on disk
+    fn cached_result(&mut self, cache_key: &str) -> Option<AnalysisResult> {
+        if let Some(cached_result) = self.analysis_cache.get(cache_key) {
+            return Some(cached_result.clone());
+        }
+
+        let result = self.disk_cache.as_ref()?.get(cache_key)?;
+        self.analysis_cache.insert(cache_key.to_string(), result.clone());
+        Some(result)
+    }
+
+    /// Remember a result in memory and on disk
+    ///
+    /// Failed queries return an error before there is a result, so nothing here is a fallback.
+    fn store_result(&mut self, cache_key: String, result: &AnalysisResult) {
+        if let Some(disk_cache) = &self.disk_cache
+            && let Err(e) = disk_cache.put(&cache_key, result)
+        {
+            eprintln!("⚠️  Could not write analysis cache: {}", e);
+        }
+        self.analysis_cache.insert(cache_key, result.clone());
+    }
+
     /// Perform comprehensive AI analysis of a command
     pub async fn analyze_command(&mut self, entry: &CommandEntry, session_context: Option<&str>) -> Result<AnalysisResult> {
+        // Rule-based results are cheap, so they bypass the cache
+        if self.config.is_heuristic() {
+            return Ok(super::heuristic::analyze_command(entry));
+        }
+
         // Create cache key
         let cache_key = self.create_cache_key(entry, session_context);
         
         // Check cache first
-        if let Some(cached_result) = self.analysis_cache.get(&cache_key) {
-            return Ok(cached_result.clone());
+        if let Some(cached_result) = self.cached_result(&cache_key) {
+            return Ok(cached_result);
         }
 
         // Create prompt context
@@ -149,23 +187,99 @@ impl AIAnalyzer {
         };
 
         // Cache the result
-        self.analysis_cache.insert(cache_key, result.clone());
+        self.store_result(cache_key, &result);
 
         Ok(result)
     }
 
+    /// Produce a one-sentence explanation of a command without the heavier analysis fields
+    pub async fn explain_command(&mut self, entry: &CommandEntry, session_context: Option<&str>) -> Result<AnalysisResult> {
+        if self.config.is_heuristic() {
+            return Ok(super::heuristic::explain_command(entry));
+        }
+
+        let cache_key = format!("explain_{}", self.create_cache_key(entry, session_context));
+        if let Some(cached_result) = self.cached_result(&cache_key) {
+            return Ok(cached_result);
+        }
+
+        let mut context = PromptContext::from(entry);
+        context.session_description = session_context.map(|s| s.to_string());
+        context.platform = self.detect_platform();
+
+        let (system_prompt, user_prompt) = self.prompt_engine
+            .generate_prompt(PromptType::BriefExplanation, &context)?;
+        let llm_response = self.query_llm_with_limit(&system_prompt, &user_prompt, 120).await?;
+
+        let summary = if looks_structured(strip_code_fence(&llm_response)) {
+            self.parse_response(&llm_response, &entry.command).0
+        } else {
+            llm_response.split_whitespace().collect::<Vec<_>>().join(" ")
+        };
+
+        let result = AnalysisResult {
+            command: entry.command.clone(),
+            analysis_type: "Brief Explanation".to_string(),
+            summary,
+            detailed_explanation: String::new(),
+            issues: Vec::new(),
+            alternatives: Vec::new(),
+            context_insights: Vec::new(),
+            recommendations: Vec::new(),
+            confidence_score: 0.85, // Short factual answers are rarely off-target
+        };
+
+        self.store_result(cache_key, &result);
+
+        Ok(result)
+    }
+
+    /// Fix spelling and grammar in an annotation without changing what it says
+    ///
+    /// Replies that don't read as a corrected version of the note, such as the mock provider's
+    /// canned response or a rewrite of very different length, leave the text unchanged.
+    pub async fn polish_annotation(&self, text: &str) -> Result<String> {
+        // Rules can't proofread prose
+        if self.config.is_heuristic() {
+            return Ok(text.to_string());
+        }
+
+        let (system_prompt, user_prompt) = self.prompt_engine.generate_annotation_polish_prompt(text)?;
+        let max_tokens = (text.len() / 2 + 100) as u32;
+        let llm_response = self.query_llm_with_limit(&system_prompt, &user_prompt, max_tokens).await?;
+
+        let polished = strip_code_fence(&llm_response).trim().trim_matches('"').trim();
+        Ok(if is_plausible_polish(text, polished) { polished.to_string() } else { text.to_string() })
+    }
+
+    /// One-line definitions of command-line tools, from a single request for all of them
+    ///
+    /// Tools the reply doesn't define, or lines naming tools that weren't asked about, are left out.
+    pub async fn define_tools(&self, tools: &[String]) -> Result<HashMap<String, String>> {
+        // The glossary's built-in definitions are all the heuristic analyzer knows
+        if self.config.is_heuristic() {
+            return Ok(HashMap::new());
+        }
+
+        let (system_prompt, user_prompt) = self.prompt_engine.generate_tool_glossary_prompt(tools)?;
+        let max_tokens = (tools.len() * 30 + 100) as u32;
+        let llm_response = self.query_llm_with_limit(&system_prompt, &user_prompt, max_tokens).await?;
+        Ok(parse_tool_definitions(strip_code_fence(&llm_response), tools))
+    }
+
     /// Analyze command for errors and provide solutions
     async fn analyze_error(&self, context: &PromptContext) -> Result<AnalysisResult> {
         let (system_prompt, user_prompt) = self.prompt_engine
             .generate_prompt(PromptType::ErrorDiagnosis, context)?;
 
         let llm_response = self.query_llm(&system_prompt, &user_prompt).await?;
+        let (summary, detailed_explanation) = self.parse_response(&llm_response, &context.command);
         
         let mut result = AnalysisResult {
             command: context.command.clone(),
             analysis_type: "Error Diagnosis".to_string(),
-            summary: self.extract_summary(&llm_response),
-            detailed_explanation: llm_response.clone(),
+            summary,
+            detailed_explanation,
             issues: self.extract_issues(&llm_response, &context.command),
             alternatives: self.extract_alternatives(&llm_response),
             context_insights: self.extract_context_insights(&llm_response),
@@ -193,12 +307,13 @@ impl AIAnalyzer {
             .generate_prompt(PromptType::SecurityAnalysis, context)?;
 
         let llm_response = self.query_llm(&system_prompt, &user_prompt).await?;
+        let (summary, detailed_explanation) = self.parse_response(&llm_response, &context.command);
         
         let mut result = AnalysisResult {
             command: context.command.clone(),
             analysis_type: "Security Analysis".to_string(),
-            summary: self.extract_summary(&llm_response),
-            detailed_explanation: llm_response.clone(),
+            summary,
+            detailed_explanation,
             issues: self.extract_issues(&llm_response, &context.command),
             alternatives: self.extract_alternatives(&llm_response),
             context_insights: self.extract_context_insights(&llm_response),
@@ -219,12 +334,13 @@ impl AIAnalyzer {
             .generate_prompt(PromptType::WorkflowDocumentation, context)?;
 
         let llm_response = self.query_llm(&system_prompt, &user_prompt).await?;
+        let (summary, detailed_explanation) = self.parse_response(&llm_response, &context.command);
         
         let result = AnalysisResult {
             command: context.command.clone(),
             analysis_type: "Workflow Documentation".to_string(),
-            summary: self.extract_summary(&llm_response),
-            detailed_explanation: llm_response,
+            summary,
+            detailed_explanation,
             issues: self.extract_workflow_issues(&context.command),
             alternatives: self.extract_alternatives_from_context(context),
             context_insights: self.analyze_workflow_context(context),
@@ -241,12 +357,13 @@ impl AIAnalyzer {
             .generate_prompt(PromptType::CommandExplanation, context)?;
 
         let llm_response = self.query_llm(&system_prompt, &user_prompt).await?;
+        let (summary, detailed_explanation) = self.parse_response(&llm_response, &context.command);
         
         let result = AnalysisResult {
             command: context.command.clone(),
             analysis_type: "General Analysis".to_string(),
-            summary: self.extract_summary(&llm_response),
-            detailed_explanation: llm_response.clone(),
+            summary,
+            detailed_explanation,
             issues: self.extract_issues(&llm_response, &context.command),
             alternatives: self.extract_alternatives(&llm_response),
             context_insights: self.extract_context_insights(&llm_response),
@@ -259,6 +376,11 @@ impl AIAnalyzer {
 
     /// Query the configured LLM with error handling
     async fn query_llm(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
+        self.query_llm_with_limit(system_prompt, user_prompt, 2000).await
+    }
+
+    /// Query the configured LLM with a specific response token limit
+    async fn query_llm_with_limit(&self, system_prompt: &str, user_prompt: &str, max_tokens: u32) -> Result<String> {
         // Get default provider
         let provider_name = self.config.get_default_provider()
             .ok_or_else(|| anyhow!("No default LLM provider configured"))?;
@@ -274,35 +396,48 @@ impl AIAnalyzer {
         // Create request
         let request = LlmRequest {
             prompt: user_prompt.to_string(),
-            max_tokens: Some(2000),
+            max_tokens: Some(max_tokens),
             temperature: Some(0.3), // Lower temperature for more consistent analysis
             system_prompt: Some(system_prompt.to_string()),
         };
 
-        // Get response with error handling built into the client
-        match client.generate(request).await {
-            Ok(response) => Ok(response.content),
-            Err(e) => {
-                eprintln!("LLM query failed: {}", e);
-                // Return a fallback response instead of failing completely
-                Ok(format!("Analysis unavailable due to API error: {}", e))
-            }
+        // Retries are built into the client; a query that still fails is an error, never a reply
+        let response = client.generate(request).await.map_err(|e| anyhow!("LLM query failed: {}", e))?;
+        Ok(response.content)
+    }
+
+    /// Split an LLM response into a summary and a detailed explanation
+    ///
+    /// Models don't always answer in the requested prose format. JSON (valid or broken), code
+    /// fences and leading headings are tolerated so the analysis is kept instead of dropped.
+    fn parse_response(&self, response: &str, command: &str) -> (String, String) {
+        let text = strip_code_fence(response);
+        if !looks_structured(text) {
+            return (self.extract_summary(text), response.to_string());
         }
+
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(text)
+            && let Some(summary) = json_text_field(&value, &["summary", "explanation", "description", "analysis"])
+        {
+            let details = json_text_field(&value, &["detailed_explanation", "details", "explanation"])
+                .unwrap_or_else(|| summary.clone());
+            return (truncate_summary(&summary), details);
+        }
+
+        eprintln!("⚠️  AI analysis of `{}` was not in the expected format; using the raw response as its summary", command);
+        let raw = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (truncate_summary(&raw), text.to_string())
     }
 
     /// Extract summary from LLM response
     fn extract_summary(&self, response: &str) -> String {
-        // Simple extraction - take first paragraph or first 200 characters
-        let lines: Vec<&str> = response.lines().collect();
-        if let Some(first_line) = lines.first() {
-            if first_line.len() > 200 {
-                format!("{}...", &first_line[..200])
-            } else {
-                first_line.to_string()
-            }
-        } else {
-            "Analysis completed".to_string()
-        }
+        // Take the first line of prose, skipping blank lines and code fences
+        response
+            .lines()
+            .map(|line| line.trim().trim_start_matches('#').trim())
+            .find(|line| !line.is_empty() && !line.starts_with("```"))
+            .map(truncate_summary)
+            .unwrap_or_else(|| "Analysis completed".to_string())
     }
 
     /// Extract issues from LLM response
@@ -548,17 +683,24 @@ impl AIAnalyzer {
 
     /// Utility methods
     fn create_cache_key(&self, entry: &CommandEntry, session_context: Option<&str>) -> String {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        let mut hasher = DefaultHasher::new();
-        entry.command.hash(&mut hasher);
-        entry.working_directory.hash(&mut hasher);
-        entry.shell.hash(&mut hasher);
-        if let Some(context) = session_context {
-            context.hash(&mut hasher);
-        }
-        format!("analysis_{}", hasher.finish())
+        // Answers from another provider or model don't stand in for each other
+        let provider = self.config.get_default_provider();
+        let model = provider.and_then(|provider| self.config.get_model(provider));
+        // Keys name files in the on-disk cache, so they are hashed the same way by every build;
+        // a failed run is explained differently from a successful one
+        let fields = (
+            &entry.command,
+            &entry.working_directory,
+            &entry.shell,
+            entry.exit_code,
+            &entry.error,
+            session_context,
+            provider,
+            model,
+        );
+        let digest = Sha256::digest(serde_json::to_vec(&fields).unwrap_or_default());
+        let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("analysis_{}", hex)
     }
 
     fn detect_platform(&self) -> String {
@@ -857,10 +999,72 @@ impl AIAnalyzer {
     }
 }
 
+/// Remove a Markdown code fence wrapped around a whole response
+fn strip_code_fence(response: &str) -> &str {
+    let trimmed = response.trim();
+    match trimmed.strip_prefix("```") {
+        Some(fenced) => {
+            // Drop the fence's language tag, e.g. ```json
+            let body = fenced.split_once('\n').map_or("", |(_, body)| body);
+            body.trim_end().trim_end_matches("```").trim()
+        }
+        None => trimmed,
+    }
+}
+
+/// Read `tool: definition` lines, tolerating list markers and formatting around the tool name
+fn parse_tool_definitions(response: &str, tools: &[String]) -> HashMap<String, String> {
+    response
+        .lines()
+        .filter_map(|line| {
+            let (tool, definition) = line.trim().trim_start_matches(['-', '*', ' ']).split_once(':')?;
+            let tool = tool.trim().trim_matches(['`', '*']);
+            let definition = definition.trim().trim_end_matches('.');
+            (tools.iter().any(|wanted| wanted == tool) && !definition.is_empty())
+                .then(|| (tool.to_string(), definition.to_string()))
+        })
+        .collect()
+}
+
+/// Check whether a proofread note is a correction of `original` rather than something else
+fn is_plausible_polish(original: &str, polished: &str) -> bool {
+    let (original_len, polished_len) = (original.chars().count(), polished.chars().count());
+    !polished.is_empty()
+        && polished != super::client::MOCK_RESPONSE
+        && polished_len * 2 >= original_len
+        && polished_len <= original_len + original_len / 2 + 20
+}
+
+/// Check whether a response is (or tries to be) JSON rather than prose
+fn looks_structured(text: &str) -> bool {
+    text.starts_with('{') || text.starts_with('[')
+}
+
+/// First non-empty string among `keys` in a JSON object, or in the first object of an array
+fn json_text_field(value: &serde_json::Value, keys: &[&str]) -> Option<String> {
+    let object = match value {
+        serde_json::Value::Array(items) => items.first()?,
+        other => other,
+    };
+    keys.iter()
+        .filter_map(|key| object.get(key)?.as_str())
+        .map(str::trim)
+        .find(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// Shorten a summary to 200 characters
+fn truncate_summary(summary: &str) -> String {
+    if summary.chars().count() > 200 {
+        format!("{}...", summary.chars().take(200).collect::<String>())
+    } else {
+        summary.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
 
     fn create_test_config() -> LlmConfig {
         let mut config = LlmConfig::default();
@@ -872,12 +1076,11 @@ mod tests {
     fn create_test_command() -> CommandEntry {
         CommandEntry {
             command: "ls -la".to_string(),
-            timestamp: Utc::now(),
             exit_code: Some(0),
             working_directory: "/home/user".to_string(),
             shell: "bash".to_string(),
             output: Some("total 8\ndrwxr-xr-x 2 user user 4096 Jan 1 12:00 .".to_string()),
-            error: None,
+            ..Default::default()
         }
     }
 
@@ -914,6 +1117,20 @@ mod tests {
 
         assert_eq!(key1, key2);
         assert_ne!(key1, key3);
+        // Keys are file names in the on-disk cache, so they must not change between builds
+        assert_eq!(key1, "analysis_7d35bcef00c929828dc648e71a94173d18fdacb9342ff429485946ac4bcd2af2");
+
+        let failed = CommandEntry { exit_code: Some(2), error: Some("ls: cannot access".to_string()), ..entry.clone() };
+        assert_ne!(analyzer.create_cache_key(&failed, Some("test context")), key1);
+        let other_error = CommandEntry { error: Some("ls: permission denied".to_string()), ..failed.clone() };
+        assert_ne!(analyzer.create_cache_key(&other_error, Some("test context")), analyzer.create_cache_key(&failed, Some("test context")));
+
+        let mut config = create_test_config();
+        config.set_model("claude", "claude-3-haiku".to_string());
+        assert_ne!(AIAnalyzer::new(config.clone()).create_cache_key(&entry, Some("test context")), key1);
+        config.set_api_key("openai", "test-key".to_string()).unwrap();
+        config.set_default_provider("openai".to_string()).unwrap();
+        assert_ne!(AIAnalyzer::new(config).create_cache_key(&entry, Some("test context")), key1);
     }
 
     #[test]
@@ -937,6 +1154,28 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_response_tolerates_malformed_output() {
+        let analyzer = AIAnalyzer::new(create_test_config());
+
+        // Prose answers keep their first line as the summary
+        let (summary, details) = analyzer.parse_response("\n## Lists files\nShows hidden files too.", "ls -la");
+        assert_eq!(summary, "Lists files");
+        assert!(details.contains("hidden files"));
+
+        // Well-formed JSON, even inside a code fence, is read field by field
+        let fenced = "```json\n{\"summary\": \"Lists files\", \"details\": \"Long listing\"}\n```";
+        assert_eq!(analyzer.parse_response(fenced, "ls -la"), ("Lists files".to_string(), "Long listing".to_string()));
+
+        // Broken JSON is kept as raw text rather than dropped
+        let (summary, _) = analyzer.parse_response("{\"summary\": \"Lists files in long", "ls -la");
+        assert_eq!(summary, "{\"summary\": \"Lists files in long");
+
+        // Long summaries are cut on a character boundary
+        let (summary, _) = analyzer.parse_response(&"é".repeat(300), "ls -la");
+        assert_eq!(summary.chars().count(), 203);
+    }
+
     #[test]
     fn test_security_recommendations() {
         let config = create_test_config();
@@ -1155,22 +1394,19 @@ mod tests {
         
         let entry1 = CommandEntry {
             command: "ls -la".to_string(),
-            timestamp: Utc::now(),
             exit_code: Some(0),
             working_directory: "/home/user".to_string(),
             shell: "bash".to_string(),
-            output: None,
-            error: None,
+            ..Default::default()
         };
         
         let entry2 = CommandEntry {
             command: "ls -la".to_string(),
-            timestamp: Utc::now(),
             exit_code: Some(0),
-            working_directory: "/home/other".to_string(), // Different directory
+            working_directory: "/home/other".to_string(),
+            // Different directory
             shell: "bash".to_string(),
-            output: None,
-            error: None,
+            ..Default::default()
         };
         
         let key1 = analyzer.create_cache_key(&entry1, Some("context"));
@@ -1187,4 +1423,27 @@ mod tests {
         let key1_duplicate = analyzer.create_cache_key(&entry1, Some("context"));
         assert_eq!(key1, key1_duplicate);
     }
+
+    #[tokio::test]
+    async fn test_polish_annotation_keeps_text_for_implausible_replies() {
+        assert!(is_plausible_polish("restartd nginx becuase config changd", "Restarted nginx because the config changed."));
+        assert!(!is_plausible_polish("fixed it", ""));
+        assert!(!is_plausible_polish("fixed it", "I fixed the issue by carefully reviewing every configuration file and restarting the service."));
+        assert!(!is_plausible_polish("this note is long enough to be shortened a lot", "ok"));
+
+        // The mock provider's canned reply is not a correction
+        let analyzer = AIAnalyzer::new(LlmConfig::mock());
+        let note = "a note that is about as long as the canned mock response, give or take some words";
+        assert_eq!(analyzer.polish_annotation(note).await.unwrap(), note);
+    }
+
+    #[test]
+    fn test_tool_definitions_parsed_from_reply() {
+        let tools = vec!["kubectl".to_string(), "jq".to_string(), "acmectl".to_string()];
+        let reply = "- **kubectl**: Command-line client for Kubernetes.\n`jq`: JSON processor\nhelm: Kubernetes package manager\nNote: acmectl is unknown";
+        let definitions = parse_tool_definitions(reply, &tools);
+        assert_eq!(definitions.len(), 2);
+        assert_eq!(definitions["kubectl"], "Command-line client for Kubernetes");
+        assert_eq!(definitions["jq"], "JSON processor");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,126 @@
+//! Finding an LLM provider that answers before AI generation starts
+//!
+//! The default provider is tried first, then the other configured ones. When none of them answers,
+//! the reason each one failed is collected so `generate` can say why AI analysis was skipped.
+
+use anyhow::Result;
+use std::time::Duration;
+
+use super::client::{LlmClient, LlmProvider};
+use super::config::LlmConfig;
+use super::error_handler::RetryConfig;
+
+/// Longest failure reason kept when the error doesn't match a known kind
+const MAX_REASON_CHARS: usize = 80;
+
+/// Why a provider could not be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderFailure {
+    pub provider: String,
+    pub reason: String,
+}
+
+/// Providers to try, the default one first and the rest in alphabetical order
+pub fn provider_chain(config: &LlmConfig) -> Vec<String> {
+    let mut others: Vec<&str> = config.list_providers();
+    others.sort();
+    config
+        .get_default_provider()
+        .into_iter()
+        .chain(others.into_iter().filter(|provider| Some(*provider) != config.get_default_provider()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Name of the first provider that answers a short test request, or why each one failed
+pub async fn first_reachable_provider(config: &LlmConfig) -> std::result::Result<String, Vec<ProviderFailure>> {
+    let mut failures = Vec::new();
+    for provider_name in provider_chain(config) {
+        match check_provider(config, &provider_name).await {
+            Ok(()) => return Ok(provider_name),
+            Err(e) => failures.push(ProviderFailure { provider: provider_name, reason: short_reason(&e) }),
+        }
+    }
+    Err(failures)
+}
+
+/// Send a test request with the client AI analysis would use for the provider
+async fn check_provider(config: &LlmConfig, provider_name: &str) -> Result<()> {
+    let provider = LlmProvider::from_str(provider_name)?;
+    let api_key = config
+        .get_api_key_with_fallback(provider_name)
+        .or_else(|| (!provider.requires_api_key()).then(String::new))
+        .ok_or_else(|| anyhow::anyhow!("no API key"))?;
+    let retry_config = RetryConfig { max_retries: 1, base_delay: Duration::from_millis(500), ..RetryConfig::default() };
+    LlmClient::new(provider, api_key)?.with_retry_config(retry_config).test_connection().await
+}
+
+/// Short description of why a request failed, e.g. `auth failed` or `connection refused`
+pub fn short_reason(error: &anyhow::Error) -> String {
+    let message = format!("{:#}", error);
+    let lower = message.to_lowercase();
+    let known = [
+        (&["authentication error", "invalid api key", "invalid x-api-key", "401"][..], "auth failed"),
+        (&["connection refused"][..], "connection refused"),
+        (&["timed out", "timeout"][..], "timed out"),
+        (&["dns error", "failed to lookup", "name or service not known"][..], "host not found"),
+        (&["quota exceeded"][..], "quota exceeded"),
+        (&["rate limited"][..], "rate limited"),
+        (&["service unavailable"][..], "service unavailable"),
+        (&["no api key", "api key cannot be empty"][..], "no API key"),
+        (&["unknown provider", "unsupported provider"][..], "unknown provider"),
+    ];
+    if let Some((_, reason)) = known.iter().find(|(patterns, _)| patterns.iter().any(|pattern| lower.contains(pattern))) {
+        return reason.to_string();
+    }
+
+    let first_line = message.lines().next().unwrap_or_default().trim();
+    match first_line.char_indices().nth(MAX_REASON_CHARS) {
+        Some((end, _)) => format!("{}...", &first_line[..end]),
+        None => first_line.to_string(),
+    }
+}
+
+/// One line listing each provider with its failure, e.g. `claude: auth failed; ollama: connection refused`
+pub fn summarize_failures(failures: &[ProviderFailure]) -> String {
+    if failures.is_empty() {
+        return "no LLM provider configured".to_string();
+    }
+    failures
+        .iter()
+        .map(|failure| format!("{}: {}", failure.provider, failure.reason))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_failures_summarized_per_provider() {
+        let auth = anyhow!("LLM request failed: Authentication error with claude: invalid x-api-key");
+        let refused = anyhow!("LLM request failed: Max retries (1) exceeded for ollama: Network error with ollama (retryable: true): error sending request: tcp connect error: Connection refused (os error 111)");
+        let other = anyhow!("LLM request failed: API error from gemini: {}", "x".repeat(200));
+        assert_eq!(short_reason(&auth), "auth failed");
+        assert_eq!(short_reason(&refused), "connection refused");
+        assert_eq!(short_reason(&other).chars().count(), MAX_REASON_CHARS + 3);
+
+        let failures = vec![
+            ProviderFailure { provider: "claude".to_string(), reason: short_reason(&auth) },
+            ProviderFailure { provider: "ollama".to_string(), reason: short_reason(&refused) },
+        ];
+        assert_eq!(summarize_failures(&failures), "claude: auth failed; ollama: connection refused");
+    }
+
+    #[test]
+    fn test_default_provider_tried_first() {
+        let mut config = LlmConfig::default();
+        for provider in ["ollama", "chatgpt", "claude"] {
+            config.set_api_key(provider, "key".to_string()).unwrap();
+        }
+        config.set_default_provider("claude".to_string()).unwrap();
+        assert_eq!(provider_chain(&config), vec!["claude", "chatgpt", "ollama"]);
+    }
+}
@@ -0,0 +1,355 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::analyzer::AnalysisResult;
+
+const STATS_FILE: &str = "stats.json";
+
+/// An analysis result stored on disk together with when it was produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedAnalysis {
+    key: String,
+    created_at: DateTime<Utc>,
+    result: AnalysisResult,
+}
+
+/// Lookup counters persisted next to the cached entries
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups served from the cache, if any lookups were made
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            None
+        } else {
+            Some(self.hits as f64 / total as f64)
+        }
+    }
+}
+
+/// Summary of a single cache entry for listing
+#[derive(Debug, Clone)]
+pub struct CacheEntryInfo {
+    pub command: String,
+    pub analysis_type: String,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+/// On-disk cache of AI analysis results, one JSON file per entry
+#[derive(Debug, Clone)]
+pub struct AnalysisCache {
+    dir: PathBuf,
+}
+
+impl AnalysisCache {
+    /// Cache stored in the given directory
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Cache stored in `~/.docpilot/cache`
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(Self::default_directory()?))
+    }
+
+    /// Get the directory where cached analysis results are stored
+    pub fn default_directory() -> Result<PathBuf> {
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .map_err(|_| anyhow!("Cannot determine home directory"))?;
+
+        Ok(PathBuf::from(home).join(".docpilot").join("cache"))
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Look up a cached result, counting the lookup as a hit or miss
+    pub fn get(&self, key: &str) -> Option<AnalysisResult> {
+        let cached = fs::read_to_string(self.entry_path(key))
+            .ok()
+            .and_then(|content| serde_json::from_str::<CachedAnalysis>(&content).ok());
+
+        // Statistics are best effort and must never fail an analysis
+        let _ = self.record_lookup(cached.is_some());
+        cached.map(|entry| entry.result)
+    }
+
+    /// Store a result under the given key
+    pub fn put(&self, key: &str, result: &AnalysisResult) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = CachedAnalysis {
+            key: key.to_string(),
+            created_at: Utc::now(),
+            result: result.clone(),
+        };
+        fs::write(self.entry_path(key), serde_json::to_string_pretty(&entry)?)?;
+        Ok(())
+    }
+
+    /// List all readable entries, newest first
+    pub fn entries(&self) -> Result<Vec<CacheEntryInfo>> {
+        let mut entries = Vec::new();
+        if !self.dir.exists() {
+            return Ok(entries);
+        }
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !Self::is_entry_file(&path) {
+                continue;
+            }
+
+            let size_bytes = fs::metadata(&path)?.len();
+            let Ok(content) = fs::read_to_string(&path) else { continue };
+            let Ok(cached) = serde_json::from_str::<CachedAnalysis>(&content) else { continue };
+
+            entries.push(CacheEntryInfo {
+                command: cached.result.command,
+                analysis_type: cached.result.analysis_type,
+                created_at: cached.created_at,
+                size_bytes,
+            });
+        }
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
+        Ok(entries)
+    }
+
+    /// Remove entries, optionally only those older than `older_than`.
+    /// Returns the number of removed entries and the bytes freed.
+    pub fn clear(&self, older_than: Option<Duration>) -> Result<(usize, u64)> {
+        let mut removed = 0;
+        let mut freed = 0;
+        if !self.dir.exists() {
+            return Ok((removed, freed));
+        }
+
+        let cutoff = older_than.map(|age| Utc::now() - age);
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !Self::is_entry_file(&path) {
+                continue;
+            }
+
+            if let Some(cutoff) = cutoff {
+                // Entries that cannot be read are treated as stale
+                let created_at = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str::<CachedAnalysis>(&content).ok())
+                    .map(|cached| cached.created_at);
+                if matches!(created_at, Some(created_at) if created_at >= cutoff) {
+                    continue;
+                }
+            }
+
+            freed += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+
+        // A full clear also resets the hit/miss counters
+        if older_than.is_none() {
+            let stats_path = self.dir.join(STATS_FILE);
+            if stats_path.exists() {
+                fs::remove_file(stats_path)?;
+            }
+        }
+
+        Ok((removed, freed))
+    }
+
+    /// Get the recorded lookup statistics
+    pub fn stats(&self) -> Result<CacheStats> {
+        let stats_path = self.dir.join(STATS_FILE);
+        if !stats_path.exists() {
+            return Ok(CacheStats::default());
+        }
+
+        let content = fs::read_to_string(stats_path)?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    fn record_lookup(&self, hit: bool) -> Result<()> {
+        let mut stats = self.stats()?;
+        if hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(STATS_FILE), serde_json::to_string_pretty(&stats)?)?;
+        Ok(())
+    }
+
+    fn is_entry_file(path: &Path) -> bool {
+        path.extension().and_then(|s| s.to_str()) == Some("json")
+            && path.file_name().and_then(|s| s.to_str()) != Some(STATS_FILE)
+    }
+}
+
+/// Parse an age such as `30m`, `12h`, `7d` or `2w` (a bare number means days)
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+
+    let amount: i64 = amount.parse()
+        .map_err(|_| anyhow!("Invalid age: {} (expected e.g. 30m, 12h, 7d, 2w)", value))?;
+
+    match unit {
+        "s" => Ok(Duration::seconds(amount)),
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "" | "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        _ => Err(anyhow!("Invalid age unit: {} (expected s, m, h, d or w)", unit)),
+    }
+}
+
+/// Parse a size such as `512K`, `2M` or `1G` (a bare number means bytes)
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+
+    let amount: u64 = amount.parse()
+        .map_err(|_| anyhow!("Invalid size: {} (expected e.g. 512K, 2M, 1G)", value))?;
+
+    let multiplier: u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(anyhow!("Invalid size unit: {} (expected B, K, M or G)", unit)),
+    };
+    amount.checked_mul(multiplier).ok_or_else(|| anyhow!("Size too large: {}", value))
+}
+
+/// Format a byte count for display, e.g. `1.5 KB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Format how long ago a timestamp was, e.g. `3d` or `45m`
+pub fn format_age(timestamp: DateTime<Utc>) -> String {
+    let age = Utc::now() - timestamp;
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else if age.num_minutes() > 0 {
+        format!("{}m", age.num_minutes())
+    } else {
+        format!("{}s", age.num_seconds().max(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_result(command: &str) -> AnalysisResult {
+        AnalysisResult {
+            command: command.to_string(),
+            analysis_type: "General Analysis".to_string(),
+            summary: "Lists files".to_string(),
+            detailed_explanation: String::new(),
+            issues: Vec::new(),
+            alternatives: Vec::new(),
+            context_insights: Vec::new(),
+            recommendations: Vec::new(),
+            confidence_score: 0.8,
+        }
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30m").unwrap(), Duration::minutes(30));
+        assert_eq!(parse_age("7d").unwrap(), Duration::days(7));
+        assert_eq!(parse_age("3").unwrap(), Duration::days(3));
+        assert_eq!(parse_age("2w").unwrap(), Duration::weeks(2));
+        assert!(parse_age("soon").is_err());
+        assert!(parse_age("5y").is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("512K").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("2mb").unwrap(), 2 * 1024 * 1024);
+        assert!(parse_size("2 furlongs").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn test_cache_roundtrip_and_stats() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = AnalysisCache::new(temp_dir.path().join("cache"));
+
+        assert!(cache.get("analysis_1").is_none());
+        cache.put("analysis_1", &create_result("ls -la")).unwrap();
+        assert_eq!(cache.get("analysis_1").unwrap().command, "ls -la");
+
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.hit_rate(), Some(0.5));
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].command, "ls -la");
+        assert!(entries[0].size_bytes > 0);
+    }
+
+    #[test]
+    fn test_clear_older_than_keeps_recent_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = AnalysisCache::new(temp_dir.path().to_path_buf());
+        cache.put("analysis_1", &create_result("ls")).unwrap();
+
+        let (removed, _) = cache.clear(Some(Duration::days(1))).unwrap();
+        assert_eq!(removed, 0);
+        assert_eq!(cache.entries().unwrap().len(), 1);
+
+        let (removed, freed) = cache.clear(None).unwrap();
+        assert_eq!(removed, 1);
+        assert!(freed > 0);
+        assert!(cache.entries().unwrap().is_empty());
+    }
+}
@@ -1,8 +1,9 @@
 use anyhow::{Result, anyhow};
+use futures::stream::{self, BoxStream, StreamExt};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::time::Duration;
 use crate::llm::error_handler::{ErrorHandler, LlmError, RetryConfig};
 
@@ -12,8 +13,18 @@ pub enum LlmProvider {
     ChatGpt,
     Gemini,
     Ollama,
+    /// Offline provider returning canned responses, for demos and tests
+    Mock,
+    /// Offline rule-based analysis of commands; answers no free-form prompts
+    Heuristic,
 }
 
+/// How long a streamed response may take in total; longer than normal requests since it is shown as it arrives
+const STREAM_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Text returned by the mock provider, labeled so it is never mistaken for real analysis
+pub const MOCK_RESPONSE: &str = "🧪 Mock analysis: canned response from the offline mock provider, no LLM was called.";
+
 impl LlmProvider {
     pub fn from_str(s: &str) -> Result<Self> {
         match s.to_lowercase().as_str() {
@@ -21,6 +32,8 @@ impl LlmProvider {
             "chatgpt" | "openai" => Ok(LlmProvider::ChatGpt),
             "gemini" | "google" => Ok(LlmProvider::Gemini),
             "ollama" | "local" => Ok(LlmProvider::Ollama),
+            "mock" => Ok(LlmProvider::Mock),
+            "heuristic" | "offline" | "none" => Ok(LlmProvider::Heuristic),
             _ => Err(anyhow!("Unsupported LLM provider: {}", s)),
         }
     }
@@ -31,6 +44,8 @@ impl LlmProvider {
             LlmProvider::ChatGpt => "chatgpt",
             LlmProvider::Gemini => "gemini",
             LlmProvider::Ollama => "ollama",
+            LlmProvider::Mock => "mock",
+            LlmProvider::Heuristic => "heuristic",
         }
     }
 
@@ -40,6 +55,7 @@ impl LlmProvider {
             LlmProvider::ChatGpt => "https://api.openai.com/v1",
             LlmProvider::Gemini => "https://generativelanguage.googleapis.com/v1beta",
             LlmProvider::Ollama => "http://localhost:11434/api",
+            LlmProvider::Mock | LlmProvider::Heuristic => "",
         }
     }
 
@@ -49,8 +65,15 @@ impl LlmProvider {
             LlmProvider::ChatGpt => "gpt-4",
             LlmProvider::Gemini => "gemini-pro",
             LlmProvider::Ollama => "llama2", // Default Ollama model
+            LlmProvider::Mock => "mock",
+            LlmProvider::Heuristic => "rules",
         }
     }
+
+    /// Whether requests to this provider need an API key
+    pub fn requires_api_key(&self) -> bool {
+        !matches!(self, LlmProvider::Mock | LlmProvider::Heuristic)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,12 +104,13 @@ pub struct LlmClient {
     api_key: String,
     client: Client,
     model: String,
+    base_url: String,
     error_handler: std::sync::Mutex<ErrorHandler>,
 }
 
 impl LlmClient {
     pub fn new(provider: LlmProvider, api_key: String) -> Result<Self> {
-        if api_key.trim().is_empty() {
+        if provider.requires_api_key() && api_key.trim().is_empty() {
             return Err(anyhow!("API key cannot be empty"));
         }
 
@@ -95,6 +119,7 @@ impl LlmClient {
             .build()?;
 
         let model = provider.default_model().to_string();
+        let base_url = provider.api_base_url().to_string();
 
         let retry_config = RetryConfig {
             max_retries: 3,
@@ -111,6 +136,7 @@ impl LlmClient {
             api_key,
             client,
             model,
+            base_url,
             error_handler,
         })
     }
@@ -120,6 +146,20 @@ impl LlmClient {
         self
     }
 
+    /// Send requests to a different endpoint than the provider's default API
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Replace the retry policy used for failed requests
+    pub fn with_retry_config(self, retry_config: RetryConfig) -> Self {
+        Self {
+            error_handler: std::sync::Mutex::new(ErrorHandler::new(retry_config)),
+            ..self
+        }
+    }
+
     pub fn error_handler(&self) -> &std::sync::Mutex<ErrorHandler> {
         &self.error_handler
     }
@@ -139,37 +179,12 @@ impl LlmClient {
                 LlmProvider::ChatGpt => self.generate_chatgpt_internal(request.clone()).await,
                 LlmProvider::Gemini => self.generate_gemini_internal(request.clone()).await,
                 LlmProvider::Ollama => self.generate_ollama_internal(request.clone()).await,
+                LlmProvider::Mock => Ok(self.generate_mock_internal(&request)),
+                LlmProvider::Heuristic => Err(anyhow!("The heuristic provider only analyzes commands and does not answer prompts")),
             };
 
-            // Convert anyhow::Error to LlmError for error handler
-            result.map_err(|e| {
-                let error_str = e.to_string();
-                let provider_name = self.provider.name().to_string();
-                
-                if error_str.contains("rate limit") || error_str.contains("429") {
-                    LlmError::RateLimited {
-                        provider: provider_name,
-                        message: error_str,
-                        retry_after: Some(Duration::from_secs(60))
-                    }
-                } else if error_str.contains("network") || error_str.contains("connection") {
-                    LlmError::NetworkError {
-                        provider: provider_name,
-                        error: error_str,
-                        retryable: true,
-                    }
-                } else if error_str.contains("parse") || error_str.contains("json") {
-                    LlmError::ParseError {
-                        provider: provider_name,
-                        message: error_str,
-                    }
-                } else {
-                    LlmError::ApiError {
-                        provider: provider_name,
-                        message: error_str,
-                    }
-                }
-            })
+            // Convert anyhow::Error to LlmError so the error handler can decide whether to retry
+            result.map_err(|e| self.classify_error(e))
         };
 
         let mut handler = self.error_handler.lock().unwrap();
@@ -177,9 +192,92 @@ impl LlmClient {
             .map_err(|e| anyhow!("LLM request failed: {}", e))
     }
 
-    async fn generate_claude_internal(&self, request: LlmRequest) -> Result<LlmResponse> {
-        let url = format!("{}/messages", self.provider.api_base_url());
-        
+    /// Generate a response as a stream of text chunks, as the provider produces them
+    ///
+    /// Claude, ChatGPT and Ollama stream their answer; other providers are asked with
+    /// `generate` and the whole response is yielded as a single chunk, so callers have
+    /// one code path. Opening a stream is not retried, since chunks may already have
+    /// been shown when it fails.
+    pub async fn generate_streaming(&self, request: LlmRequest) -> Result<BoxStream<'static, Result<String>>> {
+        let request_builder = match self.provider {
+            LlmProvider::Claude => {
+                let mut payload = self.claude_payload(&request);
+                payload["stream"] = json!(true);
+                self.client
+                    .post(format!("{}/messages", self.base_url))
+                    .header("x-api-key", &self.api_key)
+                    .header("anthropic-version", "2023-06-01")
+                    .json(&payload)
+            }
+            LlmProvider::ChatGpt => {
+                let mut payload = self.chatgpt_payload(&request);
+                payload["stream"] = json!(true);
+                self.client
+                    .post(format!("{}/chat/completions", self.base_url))
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .json(&payload)
+            }
+            LlmProvider::Ollama => {
+                self.client
+                    .post(format!("{}/generate", self.base_url))
+                    .json(&self.ollama_payload(&request, true))
+            }
+            LlmProvider::Gemini | LlmProvider::Mock | LlmProvider::Heuristic => {
+                let response = self.generate(request).await?;
+                return Ok(stream::once(async move { Ok(response.content) }).boxed());
+            }
+        };
+
+        let response = request_builder
+            .header("Content-Type", "application/json")
+            .timeout(STREAM_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| anyhow!("LLM request failed: {}", self.classify_error(e.into())))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("LLM request failed: {}", self.status_error(response).await));
+        }
+
+        let state = StreamState {
+            provider: self.provider.clone(),
+            response,
+            buffer: Vec::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        };
+        Ok(stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.pending.pop_front() {
+                    return Some((item, state));
+                }
+                if state.finished {
+                    return None;
+                }
+                match state.response.chunk().await {
+                    Ok(Some(bytes)) => {
+                        state.buffer.extend_from_slice(&bytes);
+                        while let Some(end) = state.buffer.iter().position(|&b| b == b'\n') {
+                            let line: Vec<u8> = state.buffer.drain(..=end).collect();
+                            state.push_line(&String::from_utf8_lossy(&line));
+                        }
+                    }
+                    Ok(None) => {
+                        let rest = std::mem::take(&mut state.buffer);
+                        state.push_line(&String::from_utf8_lossy(&rest));
+                        state.finished = true;
+                    }
+                    Err(e) => {
+                        state.pending.push_back(Err(anyhow!("LLM stream interrupted: {}", e)));
+                        state.finished = true;
+                    }
+                }
+            }
+        }).boxed())
+    }
+
+    /// Request body for Claude's messages API
+    fn claude_payload(&self, request: &LlmRequest) -> Value {
         // Add user message (only user messages in the messages array for Claude)
         let messages = vec![json!({
             "role": "user",
@@ -198,6 +296,59 @@ impl LlmClient {
             payload["system"] = json!(system);
         }
 
+        payload
+    }
+
+    /// Request body for the chat completions API
+    fn chatgpt_payload(&self, request: &LlmRequest) -> Value {
+        let mut messages = Vec::new();
+        
+        // Add system message if provided
+        if let Some(system) = &request.system_prompt {
+            messages.push(json!({
+                "role": "system",
+                "content": system
+            }));
+        }
+        
+        // Add user message
+        messages.push(json!({
+            "role": "user",
+            "content": request.prompt
+        }));
+
+        json!({
+            "model": self.model,
+            "messages": messages,
+            "max_tokens": request.max_tokens.unwrap_or(1000),
+            "temperature": request.temperature.unwrap_or(0.7)
+        })
+    }
+
+    /// Request body for Ollama's generate API
+    fn ollama_payload(&self, request: &LlmRequest, stream: bool) -> Value {
+        // Combine system prompt and user prompt for Ollama
+        let prompt = if let Some(system) = &request.system_prompt {
+            format!("System: {}\n\nUser: {}", system, request.prompt)
+        } else {
+            request.prompt.clone()
+        };
+
+        json!({
+            "model": self.model,
+            "prompt": prompt,
+            "stream": stream,
+            "options": {
+                "num_predict": request.max_tokens.unwrap_or(1000),
+                "temperature": request.temperature.unwrap_or(0.7)
+            }
+        })
+    }
+
+    async fn generate_claude_internal(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let url = format!("{}/messages", self.base_url);
+        let payload = self.claude_payload(&request);
+
         let response = self.client
             .post(&url)
             .header("Content-Type", "application/json")
@@ -208,8 +359,7 @@ impl LlmClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Claude API error: {}", error_text));
+            return Err(self.status_error(response).await);
         }
 
         let response_json: Value = response.json().await?;
@@ -239,30 +389,8 @@ impl LlmClient {
     }
 
     async fn generate_chatgpt_internal(&self, request: LlmRequest) -> Result<LlmResponse> {
-        let url = format!("{}/chat/completions", self.provider.api_base_url());
-        
-        let mut messages = Vec::new();
-        
-        // Add system message if provided
-        if let Some(system) = &request.system_prompt {
-            messages.push(json!({
-                "role": "system",
-                "content": system
-            }));
-        }
-        
-        // Add user message
-        messages.push(json!({
-            "role": "user",
-            "content": request.prompt
-        }));
-
-        let payload = json!({
-            "model": self.model,
-            "messages": messages,
-            "max_tokens": request.max_tokens.unwrap_or(1000),
-            "temperature": request.temperature.unwrap_or(0.7)
-        });
+        let url = format!("{}/chat/completions", self.base_url);
+        let payload = self.chatgpt_payload(&request);
 
         let response = self.client
             .post(&url)
@@ -273,8 +401,7 @@ impl LlmClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("ChatGPT API error: {}", error_text));
+            return Err(self.status_error(response).await);
         }
 
         let response_json: Value = response.json().await?;
@@ -304,7 +431,7 @@ impl LlmClient {
 
     async fn generate_gemini_internal(&self, request: LlmRequest) -> Result<LlmResponse> {
         let url = format!("{}/models/{}:generateContent?key={}", 
-                         self.provider.api_base_url(), 
+                         self.base_url,
                          self.model, 
                          self.api_key);
         
@@ -339,8 +466,7 @@ impl LlmClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Gemini API error: {}", error_text));
+            return Err(self.status_error(response).await);
         }
 
         let response_json: Value = response.json().await?;
@@ -370,24 +496,8 @@ impl LlmClient {
     }
 
     async fn generate_ollama_internal(&self, request: LlmRequest) -> Result<LlmResponse> {
-        let url = format!("{}/generate", self.provider.api_base_url());
-        
-        // Combine system prompt and user prompt for Ollama
-        let prompt = if let Some(system) = &request.system_prompt {
-            format!("System: {}\n\nUser: {}", system, request.prompt)
-        } else {
-            request.prompt.clone()
-        };
-
-        let payload = json!({
-            "model": self.model,
-            "prompt": prompt,
-            "stream": false,
-            "options": {
-                "num_predict": request.max_tokens.unwrap_or(1000),
-                "temperature": request.temperature.unwrap_or(0.7)
-            }
-        });
+        let url = format!("{}/generate", self.base_url);
+        let payload = self.ollama_payload(&request, false);
 
         let response = self.client
             .post(&url)
@@ -397,8 +507,7 @@ impl LlmClient {
             .await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Ollama API error: {}", error_text));
+            return Err(self.status_error(response).await);
         }
 
         let response_json: Value = response.json().await?;
@@ -430,6 +539,98 @@ impl LlmClient {
         })
     }
 
+    /// Answer with the canned mock response, without any network access
+    fn generate_mock_internal(&self, request: &LlmRequest) -> LlmResponse {
+        let prompt_tokens = request.system_prompt.iter().chain(std::iter::once(&request.prompt))
+            .map(|text| text.split_whitespace().count() as u32)
+            .sum();
+        let completion_tokens = MOCK_RESPONSE.split_whitespace().count() as u32;
+
+        LlmResponse {
+            content: MOCK_RESPONSE.to_string(),
+            usage: Some(Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            }),
+            model: self.model.clone(),
+            provider: self.provider.name().to_string(),
+        }
+    }
+
+    /// Classify a failed request as retryable (network, 5xx, 429) or fatal (auth, bad request)
+    fn classify_error(&self, error: anyhow::Error) -> LlmError {
+        let provider = self.provider.name().to_string();
+
+        let error = match error.downcast::<LlmError>() {
+            Ok(llm_error) => return llm_error,
+            Err(error) => error,
+        };
+
+        if let Some(request_error) = error.downcast_ref::<reqwest::Error>() {
+            if request_error.is_decode() {
+                return LlmError::ParseError {
+                    provider,
+                    message: request_error.to_string(),
+                };
+            }
+
+            // Connection failures and timeouts usually clear up on their own
+            let retryable = request_error.is_timeout() || request_error.is_connect() || request_error.is_request();
+            return LlmError::NetworkError {
+                provider,
+                error: request_error.to_string(),
+                retryable,
+            };
+        }
+
+        let error_str = error.to_string();
+        if error_str.contains("parse") || error_str.contains("json") || error_str.contains("response format") {
+            LlmError::ParseError {
+                provider,
+                message: error_str,
+            }
+        } else {
+            LlmError::ApiError {
+                provider,
+                message: error_str,
+            }
+        }
+    }
+
+    /// Build an error from a non-success HTTP response based on its status code
+    async fn status_error(&self, response: reqwest::Response) -> anyhow::Error {
+        let status = response.status();
+        let retry_after = response.headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let body = response.text().await.unwrap_or_default();
+
+        let provider = self.provider.name().to_string();
+        let message = format!("{} API error ({}): {}", provider, status, body.trim());
+
+        let error = match status.as_u16() {
+            429 => LlmError::RateLimited {
+                provider,
+                message,
+                retry_after,
+            },
+            401 | 403 => LlmError::AuthenticationError { provider, message },
+            408 => LlmError::NetworkError {
+                provider,
+                error: message,
+                retryable: true,
+            },
+            500..=599 => LlmError::ServiceUnavailable { provider, retry_after },
+            400..=499 => LlmError::InvalidRequest { provider, message },
+            _ => LlmError::ApiError { provider, message },
+        };
+
+        anyhow::Error::new(error)
+    }
+
     /// Test the connection to the LLM provider
     pub async fn test_connection(&self) -> Result<()> {
         let test_request = LlmRequest {
@@ -450,6 +651,63 @@ impl LlmClient {
 
 }
 
+/// Reading position of a streamed response: bytes of an unfinished line and parsed chunks not yet yielded
+struct StreamState {
+    provider: LlmProvider,
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    pending: VecDeque<Result<String>>,
+    finished: bool,
+}
+
+impl StreamState {
+    /// Queue the text of one line of the response, if it carries any
+    fn push_line(&mut self, line: &str) {
+        match stream_line_text(&self.provider, line) {
+            Ok(Some(text)) if !text.is_empty() => self.pending.push_back(Ok(text)),
+            Ok(_) => {}
+            Err(e) => {
+                self.pending.push_back(Err(e));
+                self.finished = true;
+            }
+        }
+    }
+}
+
+/// Text carried by one line of a streamed response
+///
+/// Claude and ChatGPT send server-sent events (`data: {...}` lines); Ollama sends
+/// one JSON object per line. Lines without text, such as event names, keep-alives
+/// and the final `[DONE]`, give `None`.
+fn stream_line_text(provider: &LlmProvider, line: &str) -> Result<Option<String>> {
+    let line = line.trim();
+    let data = match provider {
+        LlmProvider::Ollama => line,
+        _ => match line.strip_prefix("data:") {
+            Some(data) => data.trim_start(),
+            None => return Ok(None),
+        },
+    };
+    if data.is_empty() || data == "[DONE]" {
+        return Ok(None);
+    }
+
+    let event: Value = serde_json::from_str(data)
+        .map_err(|e| anyhow!("Invalid {} stream event: {}", provider.name(), e))?;
+    if let Some(error) = event.get("error") {
+        let message = error["message"].as_str().or(error.as_str()).unwrap_or("unknown error");
+        return Err(anyhow!("{} API error: {}", provider.name(), message));
+    }
+
+    let text = match provider {
+        LlmProvider::Claude => event["delta"]["text"].as_str(),
+        LlmProvider::ChatGpt => event["choices"][0]["delta"]["content"].as_str(),
+        LlmProvider::Ollama => event["response"].as_str(),
+        LlmProvider::Gemini | LlmProvider::Mock | LlmProvider::Heuristic => None,
+    };
+    Ok(text.map(str::to_string))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -599,4 +857,140 @@ mod tests {
         assert_eq!(LlmProvider::from_str("google").unwrap(), LlmProvider::Gemini);
         assert_eq!(LlmProvider::from_str("local").unwrap(), LlmProvider::Ollama);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_mock_provider_answers_offline() {
+        assert_eq!(LlmProvider::from_str("mock").unwrap(), LlmProvider::Mock);
+        assert!(!LlmProvider::Mock.requires_api_key());
+
+        let client = LlmClient::new(LlmProvider::Mock, String::new()).unwrap();
+        let first = client.generate(test_request()).await.unwrap();
+        let second = client.generate(test_request()).await.unwrap();
+        assert_eq!(first.content, MOCK_RESPONSE);
+        assert_eq!(first.content, second.content);
+        assert_eq!(first.provider, "mock");
+        assert!(first.content.contains("Mock"));
+    }
+
+    fn mock_client(server: &wiremock::MockServer) -> LlmClient {
+        LlmClient::new(LlmProvider::Claude, "test-key".to_string())
+            .unwrap()
+            .with_base_url(server.uri())
+            .with_retry_config(RetryConfig {
+                max_retries: 2,
+                base_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(50),
+                backoff_multiplier: 2.0,
+                jitter: false,
+            })
+    }
+
+    fn test_request() -> LlmRequest {
+        LlmRequest {
+            prompt: "Explain ls".to_string(),
+            max_tokens: Some(10),
+            temperature: None,
+            system_prompt: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_retries_service_unavailable() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/messages"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("overloaded"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "content": [{ "type": "text", "text": "Lists files" }],
+                "usage": { "input_tokens": 5, "output_tokens": 2 }
+            })))
+            .mount(&server)
+            .await;
+
+        let response = mock_client(&server).generate(test_request()).await
+            .expect("Request should succeed after retrying the 503");
+
+        assert_eq!(response.content, "Lists files");
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_fails_fast_on_auth_error() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("invalid x-api-key"))
+            .mount(&server)
+            .await;
+
+        let error = mock_client(&server).generate(test_request()).await.unwrap_err();
+
+        assert!(error.to_string().contains("Authentication error"));
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_generate_streaming_yields_claude_deltas() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let events = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"Lists \"}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"files\"}}\n\n",
+            "event: message_stop\n",
+            "data: {\"type\":\"message_stop\"}\n\n",
+        );
+        Mock::given(method("POST"))
+            .and(path("/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(events, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let chunks: Vec<String> = mock_client(&server).generate_streaming(test_request()).await.unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(chunks, vec!["Lists ", "files"]);
+    }
+
+    #[test]
+    fn test_stream_line_text_per_provider() {
+        let chatgpt = r#"data: {"choices":[{"delta":{"content":"Hi"}}]}"#;
+        assert_eq!(stream_line_text(&LlmProvider::ChatGpt, chatgpt).unwrap().as_deref(), Some("Hi"));
+        assert_eq!(stream_line_text(&LlmProvider::ChatGpt, "data: [DONE]").unwrap(), None);
+
+        let ollama = r#"{"model":"llama3","response":"Hi","done":false}"#;
+        assert_eq!(stream_line_text(&LlmProvider::Ollama, ollama).unwrap().as_deref(), Some("Hi"));
+        assert_eq!(stream_line_text(&LlmProvider::Ollama, "").unwrap(), None);
+
+        let error = r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        assert!(stream_line_text(&LlmProvider::Claude, error).unwrap_err().to_string().contains("Overloaded"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_streaming_falls_back_to_one_chunk() {
+        let client = LlmClient::new(LlmProvider::Mock, String::new()).unwrap();
+        let chunks: Vec<String> = client.generate_streaming(test_request()).await.unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(chunks, vec![MOCK_RESPONSE]);
+    }
+}
@@ -3,17 +3,30 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use base64::{Engine as _, engine::general_purpose};
 
 use super::client::LlmProvider;
+use super::keychain::{KeyStore, OsKeychain};
+
+/// Layout version of the config file written by this build
+///
+/// Files written before the version field was added count as version 0.
+pub const CONFIG_VERSION: u32 = 1;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmConfig {
+    /// Layout version of the file the configuration was read from, see [`CONFIG_VERSION`]
+    #[serde(default)]
+    pub version: u32,
     pub default_provider: Option<String>,
     pub providers: HashMap<String, ProviderConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub encryption_key: Option<String>,
+    /// Keep API keys in the operating system's keychain rather than in this file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub use_keychain: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,29 +38,64 @@ pub struct ProviderConfig {
     pub temperature: Option<f32>,
     #[serde(default)]
     pub encrypted: bool,
+    /// Keychain account holding the API key; `api_key` is empty in the file when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keychain_account: Option<String>,
 }
 
 impl Default for LlmConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             default_provider: None,
             providers: HashMap::new(),
             encryption_key: None,
+            use_keychain: false,
         }
     }
 }
 
 impl LlmConfig {
+    /// Configuration using only the offline mock provider, which needs no API key
+    pub fn mock() -> Self {
+        Self {
+            default_provider: Some(LlmProvider::Mock.name().to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Configuration using only the offline heuristic analyzer, which needs no API key or network
+    pub fn heuristic() -> Self {
+        Self {
+            default_provider: Some(LlmProvider::Heuristic.name().to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Check whether the default provider is the offline mock provider
+    pub fn is_mock(&self) -> bool {
+        self.default_provider.as_deref() == Some(LlmProvider::Mock.name())
+    }
+
+    /// Check whether the default provider is the offline heuristic analyzer
+    pub fn is_heuristic(&self) -> bool {
+        self.default_provider.as_deref() == Some(LlmProvider::Heuristic.name())
+    }
+
     /// Load configuration from file or create default
     pub fn load() -> Result<Self> {
         let config_path = Self::config_file_path()?;
         
         if config_path.exists() {
             let content = fs::read_to_string(&config_path)?;
-            let mut config: LlmConfig = serde_json::from_str(&content)?;
+            // Older layouts are upgraded in memory; the file itself changes on the next save
+            let mut value: serde_json::Value = serde_json::from_str(&content)?;
+            migrate_config_value(&mut value)?;
+            let mut config: LlmConfig = serde_json::from_value(value)?;
             
             // Decrypt API keys if they are encrypted
             config.decrypt_api_keys()?;
+            config.read_keychain_keys(&OsKeychain);
             
             Ok(config)
         } else {
@@ -58,6 +106,14 @@ impl LlmConfig {
         }
     }
 
+    /// Upgrade the config file to the current layout, keeping the old file as a backup
+    ///
+    /// Only the layout changes: every key in the file, including ones this build doesn't know,
+    /// is written back as it was.
+    pub fn migrate() -> Result<ConfigMigration> {
+        migrate_config_file(&Self::config_file_path()?)
+    }
+
     /// Save configuration to file
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_file_path()?;
@@ -69,34 +125,20 @@ impl LlmConfig {
 
         // Clone config and encrypt API keys before saving
         let mut config_to_save = self.clone();
+        if self.use_keychain {
+            config_to_save.move_keys_to_keychain(&OsKeychain);
+        } else {
+            config_to_save.move_keys_out_of_keychain(&OsKeychain);
+        }
         config_to_save.encrypt_api_keys()?;
 
         let content = serde_json::to_string_pretty(&config_to_save)?;
-        fs::write(&config_path, content)?;
-        
-        // Set restrictive permissions on the config file (Unix only)
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&config_path)?.permissions();
-            perms.set_mode(0o600); // Read/write for owner only
-            fs::set_permissions(&config_path, perms)?;
-        }
-
-        Ok(())
+        write_private_file(&config_path, |file| Ok(file.write_all(content.as_bytes())?))
     }
 
     /// Get the configuration file path
     fn config_file_path() -> Result<PathBuf> {
-        let config_dir = if let Ok(xdg_config) = env::var("XDG_CONFIG_HOME") {
-            PathBuf::from(xdg_config)
-        } else if let Ok(home) = env::var("HOME") {
-            PathBuf::from(home).join(".config")
-        } else {
-            return Err(anyhow!("Cannot determine config directory"));
-        };
-
-        Ok(config_dir.join("docpilot").join("config.json"))
+        Ok(crate::settings::config_dir()?.join("config.json"))
     }
 
     /// Set API key for a provider
@@ -109,6 +151,7 @@ impl LlmConfig {
                 max_tokens: None,
                 temperature: None,
                 encrypted: false,
+                keychain_account: None,
             }
         });
 
@@ -146,6 +189,7 @@ impl LlmConfig {
                 max_tokens: None,
                 temperature: None,
                 encrypted: false,
+                keychain_account: None,
             }
         });
 
@@ -167,6 +211,7 @@ impl LlmConfig {
                 max_tokens: None,
                 temperature: None,
                 encrypted: false,
+                keychain_account: None,
             }
         });
 
@@ -216,6 +261,60 @@ impl LlmConfig {
         }
     }
 
+    /// Fill in the API keys kept in the keychain, warning about any that can't be read
+    fn read_keychain_keys(&mut self, store: &dyn KeyStore) {
+        for (provider, provider_config) in &mut self.providers {
+            let Some(account) = &provider_config.keychain_account else {
+                continue;
+            };
+            match store.get(account) {
+                Ok(Some(key)) => provider_config.api_key = key,
+                Ok(None) => eprintln!("⚠️  The API key for {} is missing from the keychain; set it again with 'docpilot config'", provider),
+                Err(e) => eprintln!("⚠️  Cannot read the API key for {} from the keychain: {}", provider, e),
+            }
+        }
+    }
+
+    /// Store API keys in the keychain, leaving only the account in the configuration
+    ///
+    /// Without a usable keychain the keys stay in the configuration and a warning is printed.
+    fn move_keys_to_keychain(&mut self, store: &dyn KeyStore) {
+        for (provider, provider_config) in &mut self.providers {
+            if provider_config.api_key.is_empty() {
+                continue;
+            }
+            match store.set(provider, &provider_config.api_key) {
+                Ok(()) => {
+                    provider_config.api_key.clear();
+                    provider_config.encrypted = false;
+                    provider_config.keychain_account = Some(provider.clone());
+                }
+                Err(e) => {
+                    eprintln!("⚠️  No usable keychain ({}); API keys are stored in the config file instead", e);
+                    for provider_config in self.providers.values_mut() {
+                        if !provider_config.api_key.is_empty() {
+                            provider_config.keychain_account = None;
+                        }
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Put API keys back into the configuration and remove them from the keychain
+    fn move_keys_out_of_keychain(&mut self, store: &dyn KeyStore) {
+        for provider_config in self.providers.values_mut() {
+            // A key that couldn't be read stays where it is rather than being lost
+            if let Some(account) = &provider_config.keychain_account
+                && !provider_config.api_key.is_empty()
+            {
+                let _ = store.delete(account);
+                provider_config.keychain_account = None;
+            }
+        }
+    }
+
     /// Encrypt API keys in the configuration
     fn encrypt_api_keys(&mut self) -> Result<()> {
         let key = self.get_encryption_key()?;
@@ -285,6 +384,7 @@ impl LlmConfig {
             "chatgpt" | "openai" => "OPENAI_API_KEY",
             "gemini" | "google" => "GOOGLE_API_KEY",
             "ollama" => "OLLAMA_API_KEY", // Optional for Ollama
+            "mock" | "heuristic" => return Some(String::new()), // Offline providers need no key
             _ => return None,
         };
 
@@ -314,7 +414,7 @@ impl LlmConfig {
 
         // Check default provider
         if let Some(default) = &self.default_provider {
-            if !self.providers.contains_key(default) {
+            if !self.providers.contains_key(default) && !self.is_mock() && !self.is_heuristic() {
                 warnings.push(format!("Default provider '{}' is not configured", default));
             }
         }
@@ -326,7 +426,7 @@ impl LlmConfig {
     pub fn is_configured(&self) -> bool {
         // Check if we have a default provider that's properly configured
         if let Some(default_provider) = &self.default_provider {
-            if self.has_provider(default_provider) {
+            if self.has_provider(default_provider) || self.is_mock() || self.is_heuristic() {
                 return true;
             }
         }
@@ -336,11 +436,201 @@ impl LlmConfig {
     }
 }
 
+/// Mask an API key for display, keeping a short prefix and the last four characters, e.g. `sk-...abcd`
+pub fn mask_api_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    // Short keys would be mostly revealed by the prefix and suffix
+    if chars.len() < 12 {
+        return "*".repeat(chars.len().max(4));
+    }
+
+    let prefix: String = match key.find('-') {
+        Some(dash) if dash <= 4 => key[..=dash].to_string(),
+        _ => String::new(),
+    };
+    let suffix: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", prefix, suffix)
+}
+
+/// Result of upgrading the config file with `config --migrate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigMigration {
+    /// There is no config file yet
+    Missing,
+    /// The file already has this version, the current one or a newer one
+    UpToDate(u32),
+    /// The file was upgraded from version `from`; the old file was copied to `backup`
+    Migrated { from: u32, backup: PathBuf },
+}
+
+/// Upgrade the config file at `path`, see [`LlmConfig::migrate`]
+fn migrate_config_file(path: &Path) -> Result<ConfigMigration> {
+    if !path.exists() {
+        return Ok(ConfigMigration::Missing);
+    }
+    let content = fs::read_to_string(path)?;
+    let mut value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("Config file {} is not valid JSON: {}", path.display(), e))?;
+    let from = migrate_config_value(&mut value)?;
+    if from >= CONFIG_VERSION {
+        return Ok(ConfigMigration::UpToDate(from));
+    }
+
+    let backup = path.with_file_name(format!("config.v{}.json.bak", from));
+    write_private_file(&backup, |file| Ok(file.write_all(content.as_bytes())?))?;
+    let migrated = serde_json::to_string_pretty(&value)?;
+    write_private_file(path, |file| Ok(file.write_all(migrated.as_bytes())?))?;
+    Ok(ConfigMigration::Migrated { from, backup })
+}
+
+/// Upgrade the JSON of a config file to the current layout, returning the version it had
+///
+/// Files from a newer build are left alone, so their settings survive a downgrade.
+fn migrate_config_value(value: &mut serde_json::Value) -> Result<u32> {
+    let config = value.as_object_mut().ok_or_else(|| anyhow!("Config file does not contain a JSON object"))?;
+    let from = config.get("version").and_then(serde_json::Value::as_u64).unwrap_or(0) as u32;
+    if from >= CONFIG_VERSION {
+        return Ok(from);
+    }
+
+    // Version 0: the providers table and a provider's key could be missing or null
+    let providers = config.entry("providers").or_insert(serde_json::Value::Null);
+    if providers.is_null() {
+        *providers = serde_json::json!({});
+    }
+    for provider in providers.as_object_mut().into_iter().flat_map(|providers| providers.values_mut()) {
+        if let Some(provider) = provider.as_object_mut() {
+            let api_key = provider.entry("api_key").or_insert(serde_json::Value::Null);
+            if api_key.is_null() {
+                *api_key = serde_json::json!("");
+            }
+        }
+    }
+
+    config.insert("version".to_string(), CONFIG_VERSION.into());
+    Ok(from)
+}
+
+/// Replace `path` with what `write` puts in a file that only the owner can read (Unix)
+///
+/// The content goes to a temporary file next to `path` that is renamed over it once complete,
+/// so an interrupted write or a concurrent `config` run never leaves a half-written file and
+/// the API keys in the previous version survive a failed save.
+fn write_private_file(path: &Path, write: impl FnOnce(&mut fs::File) -> Result<()>) -> Result<()> {
+    let file_name = path.file_name().ok_or_else(|| anyhow!("Invalid config path: {}", path.display()))?;
+    // One temporary file per process, so concurrent saves don't write into each other's
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name.to_string_lossy(), std::process::id()));
+
+    // Left over from an interrupted save; removed so it is created fresh with the right mode
+    let _ = fs::remove_file(&temp_path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600); // Read/write for owner only
+    }
+
+    let result = options
+        .open(&temp_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()?;
+            Ok(fs::rename(&temp_path, path)?)
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    /// Keychain kept in memory, or one that is never available
+    struct MemoryKeyStore {
+        secrets: std::cell::RefCell<HashMap<String, String>>,
+        available: bool,
+    }
+
+    impl KeyStore for MemoryKeyStore {
+        fn get(&self, account: &str) -> Result<Option<String>> {
+            Ok(self.secrets.borrow().get(account).cloned())
+        }
+
+        fn set(&self, account: &str, secret: &str) -> Result<()> {
+            if !self.available {
+                return Err(anyhow!("no secret service provider found"));
+            }
+            self.secrets.borrow_mut().insert(account.to_string(), secret.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, account: &str) -> Result<()> {
+            self.secrets.borrow_mut().remove(account);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_api_keys_in_keychain() {
+        let store = MemoryKeyStore { secrets: Default::default(), available: true };
+        let mut config = LlmConfig::default();
+        config.set_api_key("claude", "sk-ant-secret".to_string()).unwrap();
+        config.set_base_url("ollama", "http://localhost:11434".to_string());
+
+        // The saved form only names the keychain account
+        let mut saved = config.clone();
+        saved.move_keys_to_keychain(&store);
+        assert_eq!(saved.providers["claude"].api_key, "");
+        assert_eq!(saved.providers["claude"].keychain_account.as_deref(), Some("claude"));
+        assert_eq!(saved.providers["ollama"].keychain_account, None);
+        assert!(!serde_json::to_string(&saved).unwrap().contains("sk-ant-secret"));
+
+        let mut loaded: LlmConfig = serde_json::from_str(&serde_json::to_string(&saved).unwrap()).unwrap();
+        loaded.read_keychain_keys(&store);
+        assert_eq!(loaded.get_api_key("claude"), Some("sk-ant-secret"));
+
+        // Turning the keychain off puts the key back in the file
+        loaded.move_keys_out_of_keychain(&store);
+        assert_eq!(loaded.providers["claude"].keychain_account, None);
+        assert_eq!(loaded.get_api_key("claude"), Some("sk-ant-secret"));
+        assert!(store.secrets.borrow().is_empty());
+
+        // Without a keychain the keys stay in the configuration
+        let unavailable = MemoryKeyStore { secrets: Default::default(), available: false };
+        let mut saved = config.clone();
+        saved.move_keys_to_keychain(&unavailable);
+        assert_eq!(saved.get_api_key("claude"), Some("sk-ant-secret"));
+        assert_eq!(saved.providers["claude"].keychain_account, None);
+    }
+
+    #[test]
+    fn test_failed_save_keeps_previous_config() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, r#"{"default_provider":"claude"}"#).unwrap();
+
+        let result = write_private_file(&path, |file| {
+            file.write_all(b"{\"default_pro")?;
+            Err(anyhow!("disk full"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"default_provider":"claude"}"#);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1, "temporary file was left behind");
+
+        write_private_file(&path, |file| Ok(file.write_all(b"{}")?)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+
     #[test]
     fn test_config_creation() {
         let config = LlmConfig::default();
@@ -348,6 +638,13 @@ mod tests {
         assert!(config.default_provider.is_none());
     }
 
+    #[test]
+    fn test_mask_api_key() {
+        assert_eq!(mask_api_key("sk-ant-REDACTED"), "sk-...mnop");
+        assert_eq!(mask_api_key("AIzaSyA1234567890wxyz"), "...wxyz");
+        assert_eq!(mask_api_key("short-key"), "*********");
+    }
+
     #[test]
     fn test_api_key_management() {
         let mut config = LlmConfig::default();
@@ -408,6 +705,34 @@ mod tests {
         assert_eq!(config.get_model("nonexistent"), None);
     }
 
+    #[test]
+    fn test_migrate_unversioned_config_keeps_settings() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let old = r#"{
+  "default_provider": "ollama",
+  "providers": {"ollama": {"model": "llama3", "base_url": "http://gpu-box:11434", "max_tokens": null, "temperature": null}},
+  "custom_headers": {"X-Team": "infra"}
+}"#;
+        fs::write(&path, old).unwrap();
+
+        let migration = migrate_config_file(&path).unwrap();
+        let backup = dir.path().join("config.v0.json.bak");
+        assert_eq!(migration, ConfigMigration::Migrated { from: 0, backup: backup.clone() });
+        assert_eq!(fs::read_to_string(&backup).unwrap(), old);
+
+        let migrated: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(migrated["version"], CONFIG_VERSION);
+        assert_eq!(migrated["custom_headers"]["X-Team"], "infra");
+        let config: LlmConfig = serde_json::from_value(migrated).unwrap();
+        assert_eq!(config.get_default_provider(), Some("ollama"));
+        assert_eq!(config.get_base_url("ollama"), Some("http://gpu-box:11434"));
+        assert_eq!(config.get_api_key("ollama"), Some(""));
+
+        assert_eq!(migrate_config_file(&path).unwrap(), ConfigMigration::UpToDate(CONFIG_VERSION));
+        assert_eq!(migrate_config_file(&dir.path().join("missing.json")).unwrap(), ConfigMigration::Missing);
+    }
+
     #[test]
     fn test_base_url_management() {
         let mut config = LlmConfig::default();
@@ -438,6 +763,7 @@ mod tests {
             max_tokens: None,
             temperature: None,
             encrypted: false,
+            keychain_account: None,
         });
         
         let warnings = config.validate().unwrap();
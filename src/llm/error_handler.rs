@@ -178,9 +178,13 @@ impl ErrorHandler {
                         }
                     }
 
-                    // Check if error is retryable
-                    if !self.is_retryable(&error) || attempts > self.config.max_retries {
-                        // Update circuit breaker on non-retryable errors
+                    // Fatal errors are returned as-is without spending retries
+                    if !self.is_retryable(&error) {
+                        return Err(error);
+                    }
+
+                    if attempts > self.config.max_retries {
+                        // Update circuit breaker when a provider stays unavailable
                         if matches!(error, LlmError::ServiceUnavailable { .. }) {
                             self.circuit_breaker = Some(CircuitBreakerState {
                                 failure_count: 1,
@@ -0,0 +1,222 @@
+//! Offline, rule-based command analysis
+//!
+//! Used instead of an LLM when the heuristic provider is selected, so the AI templates
+//! work without configuration or network access. Commands are classified with
+//! `CommandType`, explained from the built-in tool definitions and checked for a few
+//! risky patterns. Results carry a low confidence score to show they are guesses.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::analyzer::{
+    AnalysisResult, Issue, IssueCategory, IssueSeverity, Recommendation, RecommendationCategory,
+    RecommendationPriority,
+};
+use crate::filter::CommandFilter;
+use crate::output::glossary::{static_definition, tool_name};
+use crate::output::markdown::CommandType;
+use crate::terminal::CommandEntry;
+
+/// Confidence of heuristic results, well below what LLM analyses report
+pub const HEURISTIC_CONFIDENCE: f32 = 0.4;
+
+/// A risky command pattern and what to say about it
+struct RiskPattern {
+    pattern: &'static str,
+    severity: IssueSeverity,
+    category: IssueCategory,
+    description: &'static str,
+    impact: &'static str,
+    solution: &'static str,
+}
+
+const RISK_PATTERNS: &[RiskPattern] = &[
+    RiskPattern {
+        pattern: r"\b(curl|wget)\b[^|]*\|\s*(sudo\s+)?(ba|z|da|k)?sh\b",
+        severity: IssueSeverity::Critical,
+        category: IssueCategory::Security,
+        description: "Downloaded script is piped straight into a shell",
+        impact: "Whatever the server sends runs unreviewed with your permissions",
+        solution: "Download the script to a file, review it, then run it",
+    },
+    RiskPattern {
+        pattern: r"^\s*sudo\b",
+        severity: IssueSeverity::Low,
+        category: IssueCategory::Security,
+        description: "Runs with elevated privileges",
+        impact: "Mistakes can affect the whole system",
+        solution: "Make sure root access is really needed for this step",
+    },
+];
+
+fn risk_regexes() -> &'static [Regex] {
+    static REGEXES: OnceLock<Vec<Regex>> = OnceLock::new();
+    REGEXES.get_or_init(|| {
+        RISK_PATTERNS
+            .iter()
+            .map(|risk| Regex::new(risk.pattern).expect("risk patterns are valid regexes"))
+            .collect()
+    })
+}
+
+/// Analyze a command without an LLM
+pub fn analyze_command(entry: &CommandEntry) -> AnalysisResult {
+    let command_type = CommandType::classify_command(&entry.command);
+    let summary = explanation(&entry.command, &command_type);
+
+    let mut detailed_explanation = format!(
+        "{} It is classified as a {} command: {}.",
+        summary,
+        command_type.name(),
+        command_type.description().to_lowercase()
+    );
+    if let Some(code) = entry.exit_code.filter(|code| *code != 0) {
+        detailed_explanation.push_str(&format!(" It exited with status {}.", code));
+    }
+
+    let mut issues = security_issues(&entry.command);
+    if let Some(code) = entry.exit_code.filter(|code| *code != 0) {
+        issues.push(Issue {
+            severity: IssueSeverity::Medium,
+            category: IssueCategory::Safety,
+            description: format!("Command failed with exit code {}", code),
+            impact: "Later steps may depend on what it should have done".to_string(),
+            solution: "Check the error output before continuing".to_string(),
+        });
+    }
+    let recommendations = issues
+        .iter()
+        .filter(|issue| issue.severity >= IssueSeverity::High)
+        .map(|issue| Recommendation {
+            priority: if issue.severity == IssueSeverity::Critical {
+                RecommendationPriority::Urgent
+            } else {
+                RecommendationPriority::High
+            },
+            category: RecommendationCategory::Security,
+            title: issue.description.clone(),
+            description: issue.impact.clone(),
+            implementation: issue.solution.clone(),
+        })
+        .collect();
+
+    AnalysisResult {
+        command: entry.command.clone(),
+        analysis_type: "Heuristic Analysis".to_string(),
+        summary,
+        detailed_explanation,
+        issues,
+        alternatives: Vec::new(),
+        context_insights: Vec::new(),
+        recommendations,
+        confidence_score: HEURISTIC_CONFIDENCE,
+    }
+}
+
+/// One-sentence explanation of a command without an LLM
+pub fn explain_command(entry: &CommandEntry) -> AnalysisResult {
+    let command_type = CommandType::classify_command(&entry.command);
+    AnalysisResult {
+        command: entry.command.clone(),
+        analysis_type: "Brief Explanation".to_string(),
+        summary: explanation(&entry.command, &command_type),
+        detailed_explanation: String::new(),
+        issues: Vec::new(),
+        alternatives: Vec::new(),
+        context_insights: Vec::new(),
+        recommendations: Vec::new(),
+        confidence_score: HEURISTIC_CONFIDENCE,
+    }
+}
+
+/// Templated explanation from the tool's built-in definition, or its command type
+fn explanation(command: &str, command_type: &CommandType) -> String {
+    let Some(tool) = tool_name(command) else {
+        return "Empty command.".to_string();
+    };
+    match static_definition(tool) {
+        Some(definition) => format!("`{}`: {}.", tool, definition),
+        None => format!("Runs `{}` ({}).", tool, command_type.description().to_lowercase()),
+    }
+}
+
+/// Issues for the risky patterns a command matches
+///
+/// Destructive commands are recognised by the same patterns as the live warnings of
+/// `start --warn-dangerous`, so both agree on what is dangerous.
+fn security_issues(command: &str) -> Vec<Issue> {
+    let mut issues: Vec<Issue> = RISK_PATTERNS
+        .iter()
+        .zip(risk_regexes())
+        .filter(|(_, regex)| regex.is_match(command))
+        .map(|(risk, _)| Issue {
+            severity: risk.severity.clone(),
+            category: risk.category.clone(),
+            description: risk.description.to_string(),
+            impact: risk.impact.to_string(),
+            solution: risk.solution.to_string(),
+        })
+        .collect();
+    if CommandFilter::new().is_dangerous(command) {
+        issues.push(Issue {
+            severity: IssueSeverity::High,
+            category: IssueCategory::Safety,
+            description: "Destructive command".to_string(),
+            impact: "It can delete data, open up permissions or take a system down, and is hard to undo".to_string(),
+            solution: "Double-check the target, or try it somewhere disposable first".to_string(),
+        });
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_command(command: &str, exit_code: Option<i32>) -> CommandEntry {
+        CommandEntry {
+            command: command.to_string(),
+            exit_code,
+            working_directory: "/srv/app".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_explains_known_and_unknown_tools() {
+        let known = analyze_command(&create_command("git status", Some(0)));
+        assert_eq!(known.summary, "`git`: Distributed version control system.");
+        assert!(known.detailed_explanation.contains("VersionControl"));
+        assert!(known.issues.is_empty());
+        assert_eq!(known.confidence_score, HEURISTIC_CONFIDENCE);
+
+        let unknown = explain_command(&create_command("./deploy.sh --prod", Some(0)));
+        assert!(unknown.summary.starts_with("Runs `deploy.sh`"));
+    }
+
+    #[test]
+    fn test_flags_risky_patterns() {
+        let descriptions = |command: &str| -> Vec<String> {
+            analyze_command(&create_command(command, Some(0))).issues.into_iter().map(|issue| issue.description).collect()
+        };
+
+        assert_eq!(descriptions("curl -fsSL https://get.example.com | sudo bash"), vec!["Downloaded script is piped straight into a shell"]);
+        assert_eq!(descriptions("rm -rf build/"), vec!["Destructive command"]);
+        assert_eq!(descriptions("rm -r -f build/"), vec!["Destructive command"]);
+        assert_eq!(descriptions("sudo chmod -R 777 /var/www"), vec!["Runs with elevated privileges", "Destructive command"]);
+        assert!(descriptions("grep 'DROP TABLE' schema.sql").is_empty());
+        assert!(descriptions("curl -o install.sh https://get.example.com").is_empty());
+        assert!(descriptions("chmod 755 script.sh").is_empty());
+
+        let piped = analyze_command(&create_command("wget -qO- https://x.example | sh", Some(0)));
+        assert!(matches!(piped.recommendations[0].priority, RecommendationPriority::Urgent));
+    }
+
+    #[test]
+    fn test_reports_failed_commands() {
+        let result = analyze_command(&create_command("cargo build", Some(101)));
+        assert!(result.detailed_explanation.ends_with("It exited with status 101."));
+        assert_eq!(result.issues[0].description, "Command failed with exit code 101");
+    }
+}
@@ -0,0 +1,50 @@
+//! API keys kept in the operating system's keychain instead of the config file
+//!
+//! Uses the macOS Keychain, the Secret Service (GNOME Keyring, KWallet) on Linux or the Windows
+//! Credential Manager through the `keyring` crate. Entries belong to the `docpilot` service,
+//! with the provider name as the account; the config file only records that account.
+
+use anyhow::Result;
+
+/// Service name of DocPilot's keychain entries
+pub const KEYCHAIN_SERVICE: &str = "docpilot";
+
+/// Somewhere secrets can be kept outside the config file
+pub trait KeyStore {
+    /// The secret stored for `account`, or `None` if there is none
+    fn get(&self, account: &str) -> Result<Option<String>>;
+    /// Store `secret` for `account`, replacing any previous one
+    fn set(&self, account: &str, secret: &str) -> Result<()>;
+    /// Remove the secret for `account`; removing a missing secret is not an error
+    fn delete(&self, account: &str) -> Result<()>;
+}
+
+/// The operating system's keychain
+pub struct OsKeychain;
+
+impl OsKeychain {
+    fn entry(account: &str) -> Result<keyring::Entry> {
+        Ok(keyring::Entry::new(KEYCHAIN_SERVICE, account)?)
+    }
+}
+
+impl KeyStore for OsKeychain {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        match Self::entry(account)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<()> {
+        Ok(Self::entry(account)?.set_password(secret)?)
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        match Self::entry(account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
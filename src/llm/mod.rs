@@ -2,7 +2,11 @@ pub mod client;
 pub mod config;
 pub mod prompt;
 pub mod analyzer;
+pub mod availability;
+pub mod cache;
 pub mod error_handler;
+pub mod heuristic;
+pub mod keychain;
 
 #[cfg(test)]
 pub mod integration_tests;
@@ -11,4 +15,5 @@ pub use client::{LlmClient, LlmProvider, LlmRequest, LlmResponse, Usage};
 pub use config::{LlmConfig, ProviderConfig};
 pub use prompt::{PromptEngine, PromptType, PromptContext, PromptTemplate};
 pub use analyzer::{AIAnalyzer, AnalysisResult, Issue, Alternative, ContextInsight, Recommendation};
+pub use cache::AnalysisCache;
 pub use error_handler::{ErrorHandler, LlmError, RetryConfig, RateLimitInfo};
\ No newline at end of file
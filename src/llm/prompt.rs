@@ -71,6 +71,9 @@ pub enum PromptType {
     WorkflowDocumentation,
     MarkdownPostProcessing,
     DocumentationEnhancement,
+    BriefExplanation,
+    AnnotationPolish,
+    ToolGlossary,
 }
 
 impl PromptEngine {
@@ -468,6 +471,48 @@ Format as professional markdown documentation."#.to_string(),
             },
         );
 
+        // Brief Explanation Template (lightweight, one sentence per command)
+        templates.insert(
+            PromptType::BriefExplanation,
+            PromptTemplate {
+                system_prompt: r#"You explain terminal commands to readers of step-by-step documentation. Reply with exactly one plain-English sentence describing what the command does in its context. Do not use markdown, lists, headings, or code blocks, and do not suggest alternatives."#.to_string(),
+                user_prompt_template: r#"Explain in one sentence: `{{command}}`
+**Working Directory**: {{working_directory}}
+{{#if session_description}}**Session Context**: {{session_description}}{{/if}}
+{{#if exit_code}}**Exit Code**: {{exit_code}}{{/if}}"#.to_string(),
+                context_variables: vec![
+                    "command".to_string(),
+                    "working_directory".to_string(),
+                    "session_description".to_string(),
+                    "exit_code".to_string(),
+                ],
+            },
+        );
+
+        // Annotation Polish Template (spelling and grammar only)
+        templates.insert(
+            PromptType::AnnotationPolish,
+            PromptTemplate {
+                system_prompt: r#"You proofread notes that an engineer typed quickly while working in a terminal. Fix spelling, grammar and punctuation only. Keep the meaning, tone and length, and leave commands, paths, code, names and technical terms exactly as written. Reply with the corrected note only, without quotes, markdown or comments."#.to_string(),
+                user_prompt_template: r#"{{annotation}}"#.to_string(),
+                context_variables: vec![
+                    "annotation".to_string(),
+                ],
+            },
+        );
+
+        // Tool Glossary Template (one definition per tool, all tools in one request)
+        templates.insert(
+            PromptType::ToolGlossary,
+            PromptTemplate {
+                system_prompt: r#"You write glossaries for technical documentation. For each command-line tool listed, give a short definition of what the tool is, in one line of at most 12 words, for a reader who has never used it. Reply with one line per tool in the form `tool: definition`, using the tool names exactly as listed, and nothing else. Leave out tools you don't recognize."#.to_string(),
+                user_prompt_template: r#"{{tools}}"#.to_string(),
+                context_variables: vec![
+                    "tools".to_string(),
+                ],
+            },
+        );
+
         Self { templates }
     }
 
@@ -599,6 +644,34 @@ Format as professional markdown documentation."#.to_string(),
         Ok((system_prompt, user_prompt))
     }
 
+    /// Generate prompt for proofreading an annotation
+    pub fn generate_annotation_polish_prompt(&self, annotation: &str) -> Result<(String, String)> {
+        let template = self.templates.get(&PromptType::AnnotationPolish)
+            .ok_or_else(|| anyhow::anyhow!("Annotation polish template not found"))?;
+
+        let mut variables = std::collections::HashMap::new();
+        variables.insert("annotation".to_string(), annotation.to_string());
+
+        let system_prompt = template.system_prompt.clone();
+        let user_prompt = self.render_template_with_vars(&template.user_prompt_template, &variables)?;
+
+        Ok((system_prompt, user_prompt))
+    }
+
+    /// Generate prompt for defining the tools of a session in one request
+    pub fn generate_tool_glossary_prompt(&self, tools: &[String]) -> Result<(String, String)> {
+        let template = self.templates.get(&PromptType::ToolGlossary)
+            .ok_or_else(|| anyhow::anyhow!("Tool glossary template not found"))?;
+
+        let mut variables = std::collections::HashMap::new();
+        variables.insert("tools".to_string(), tools.join("\n"));
+
+        let system_prompt = template.system_prompt.clone();
+        let user_prompt = self.render_template_with_vars(&template.user_prompt_template, &variables)?;
+
+        Ok((system_prompt, user_prompt))
+    }
+
     /// Get available prompt types
     pub fn available_prompt_types(&self) -> Vec<PromptType> {
         self.templates.keys().cloned().collect()
@@ -651,7 +724,6 @@ impl Default for PromptEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
 
     #[test]
     fn test_prompt_engine_creation() {
@@ -776,12 +848,11 @@ mod tests {
     fn test_context_from_command_entry() {
         let entry = CommandEntry {
             command: "git commit -m 'test'".to_string(),
-            timestamp: Utc::now(),
             exit_code: Some(0),
             working_directory: "/project".to_string(),
             shell: "bash".to_string(),
             output: Some("[main abc123] test".to_string()),
-            error: None,
+            ..Default::default()
         };
 
         let context = PromptContext::from(&entry);
@@ -845,6 +916,7 @@ mod tests {
             PromptType::PerformanceAnalysis,
             PromptType::AlternativeSuggestion,
             PromptType::WorkflowDocumentation,
+            PromptType::BriefExplanation,
         ];
         
         let context = PromptContext {
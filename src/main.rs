@@ -8,10 +8,17 @@ mod llm;
 mod session;
 mod output;
 mod filter;
+mod settings;
 
-use terminal::TerminalMonitor;
+use terminal::{CommandEntry, TerminalMonitor};
+use terminal::registry::{DEFAULT_MAX_MONITORS, MonitorRecord, MonitorRegistry, describe_session};
 use llm::{LlmClient, LlmProvider, LlmConfig};
-use session::{SessionManager, AnnotationType};
+use llm::config::{CONFIG_VERSION, ConfigMigration};
+use session::{SessionManager, Session, SessionError, SessionState, AnnotationType};
+use session::manager::{CAPTURE_DELAY_SETTING, DAILY_LOG_SETTING, EXPAND_ALIASES_SETTING, ONLY_DIR_SETTING, TERMINAL_COLUMNS_SETTING, TERMINAL_LINES_SETTING, TRACK_NESTING_SETTING, WARN_DANGEROUS_SETTING};
+use session::files::{FileTracking, MAX_TRACKED_FILES};
+use output::profile::{DEFAULT_TEMPLATE, GenerateProfile};
+use settings::Settings;
 
 #[derive(Parser)]
 #[command(name = "docpilot")]
@@ -62,7 +69,18 @@ By default, DocPilot runs in background mode, allowing you to continue using you
 EXAMPLES:
     docpilot start \"Setting up development environment\"                    # Runs in background (default)
     docpilot start \"Database migration process\" --output migration-guide.md  # Background with custom output
-    docpilot start \"API testing workflow\" --foreground                      # Runs in foreground for debugging")]
+    docpilot start \"API testing workflow\" --foreground                      # Runs in foreground for debugging
+    docpilot start \"Release checklist\" --expand-aliases                     # Also record what aliases/functions run
+    docpilot start \"Document the API service\" --only-dir ~/src/api         # Ignore commands run outside ~/src/api
+    docpilot start \"Server cleanup\" --warn-dangerous --annotate-dangerous  # Flag rm -rf, DROP TABLE, ... as they run
+    docpilot start \"Nightly run\" --on-conflict stop-and-generate          # Scripted: finish any running session first
+    docpilot start \"Nightly run\" --resume-or-new                          # Scripted: keep using a running session if there is one
+    docpilot start \"Nightly run\" --reattach                               # Continue a crashed session whose monitor died
+    docpilot start \"Morning maintenance\" --daily-log                      # Append to ~/docpilot-logs/YYYY-MM-DD.md when stopped
+    docpilot start \"Configure nginx\" --track-files '/etc/nginx/**/*.conf'  # Also document which config files changed
+    docpilot start \"Deploy to staging\" --capture-delay 30                 # Skip commands run in the first 30 seconds
+    docpilot start \"Debug the web container\" --track-nesting              # Note commands run in subshells or docker exec
+    docpilot start \"Upgrade Postgres\" --tag billing --tag database       # Tag the session to find it with 'docpilot list --tag'")]
     Start {
         /// Brief description of what you're documenting
         #[arg(help = "Describe what workflow you're documenting")]
@@ -75,6 +93,54 @@ EXAMPLES:
         /// Run in foreground instead of background (for debugging)
         #[arg(long, help = "Run in foreground instead of background (default: background)")]
         foreground: bool,
+        
+        /// Record what aliases and shell functions expand to
+        #[arg(long, help = "Resolve aliases and shell functions when capturing, so docs show what actually ran (adds work to every prompt)")]
+        expand_aliases: bool,
+        
+        /// Only capture commands run inside this directory
+        #[arg(long, value_name = "PATH", help = "Only capture commands whose working directory is inside PATH")]
+        only_dir: Option<String>,
+        
+        /// Warn when a destructive command is captured
+        #[arg(long, help = "Print a warning in this terminal when a destructive command (rm -rf, dd, DROP TABLE, git push --force, ...) is captured")]
+        warn_dangerous: bool,
+        
+        /// Also record those warnings as annotations
+        #[arg(long, help = "Add a warning annotation for each destructive command (implies --warn-dangerous)")]
+        annotate_dangerous: bool,
+        
+        /// What to do with an already running session, without prompting
+        #[arg(long, value_name = "ACTION", help = "If a session is already running: stop-and-generate, stop, or cancel (skips the prompt; required when stdin is not a terminal)")]
+        on_conflict: Option<String>,
+
+        /// Keep using a running session instead of starting another
+        #[arg(long, conflicts_with = "on_conflict", help = "If a session is already running, resume it (when paused) instead of starting a new one; safe to run repeatedly")]
+        resume_or_new: bool,
+
+        /// Record which matching files change during the session (repeatable)
+        #[arg(long, value_name = "GLOB", help = "Document files matching GLOB (relative to this directory) that are created, modified or deleted before the session stops (repeatable)")]
+        track_files: Vec<String>,
+
+        /// Attach a new monitor to an interrupted session whose monitor died
+        #[arg(long, help = "If an interrupted session is found and its monitor is no longer running, attach a new monitor and keep capturing into it without asking")]
+        reattach: bool,
+
+        /// Append the documentation to a file per day instead of writing one per session
+        #[arg(long, value_name = "DIR", num_args = 0..=1, conflicts_with = "output", help = "When the session stops, append its documentation to DIR/YYYY-MM-DD.md (default ~/docpilot-logs), one file per day with a separator between sessions")]
+        daily_log: Option<Option<PathBuf>>,
+
+        /// Drop commands run in the first seconds of the session
+        #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..=86400), help = "Don't capture commands run within SECONDS of the session start (at most a day), e.g. while you get the terminal ready")]
+        capture_delay: Option<u64>,
+
+        /// Record which commands ran in a nested shell
+        #[arg(long, help = "Record when a command runs in a nested shell (a subshell, docker exec, sudo -s, ...) and what started that shell (adds work to every prompt; show it with 'generate --show-nesting')")]
+        track_nesting: bool,
+
+        /// Tags for organizing sessions (repeatable)
+        #[arg(long = "tag", value_name = "TAG", help = "Tag the session, e.g. with the project it belongs to (repeatable; see 'docpilot list --tag')")]
+        tags: Vec<String>,
     },
     
     /// 🛑 Stop the current documentation session
@@ -85,8 +151,13 @@ This command stops monitoring, saves all captured data, and provides a summary o
 
 EXAMPLES:
     docpilot stop
-    docpilot end")]
-    Stop,
+    docpilot end
+    docpilot stop --monitor 48213                    # Stop one of several background monitors")]
+    Stop {
+        /// Monitor to stop, by PID or session ID
+        #[arg(long, value_name = "PID|SESSION", help = "Stop the background monitor with this PID or session ID and its session (see 'docpilot status --monitors')")]
+        monitor: Option<String>,
+    },
     
     /// ⏸️ Pause the current documentation session
     #[command(alias = "hold")]
@@ -116,31 +187,51 @@ EXAMPLES:
     
 Annotations help explain what you're doing, provide warnings, mark milestones, or add explanations that commands alone can't capture.
 
+Refer to another annotation with [[ann:N]] (the Nth annotation) or [[warning:N]], [[note:N]], [[explanation:N]], [[milestone:N]] (the Nth of that type); generated documentation turns these into links.
+
 EXAMPLES:
     docpilot annotate \"Now configuring the database connection\"
+    docpilot annotate \"Rolled back because of [[warning:1]]\" -a explanation
     docpilot add \"This step requires admin privileges\" --annotation-type warning
-    docpilot comment \"Deployment completed successfully\" -a milestone")]
+    docpilot comment \"Deployment completed successfully\" -a milestone
+    docpilot annotate --edit <annotation-id> \"Fixed the typo\"    # Replace an annotation's text
+    docpilot annotate --delete <annotation-id>                  # Remove an annotation
+
+Annotation IDs are shown by 'docpilot annotations'.")]
     Annotate {
-        /// The annotation text to add
-        #[arg(help = "Text content of your annotation")]
-        text: String,
+        /// The annotation text to add, or the new text with --edit
+        #[arg(required_unless_present = "delete", conflicts_with = "delete",
+              help = "Text content of your annotation")]
+        text: Option<String>,
         /// Type of annotation (note, explanation, warning, milestone)
         #[arg(short = 'a', long, default_value = "note",
               help = "Annotation type: note, explanation, warning, milestone")]
         annotation_type: String,
+        /// Save the text as-is even if it looks like it contains secrets
+        #[arg(long, help = "Skip the secret check and save the text as-is")]
+        force: bool,
+        /// Replace the text of an existing annotation instead of adding one
+        #[arg(long, value_name = "ANNOTATION_ID", conflicts_with = "delete",
+              help = "Replace the text of the annotation with this ID")]
+        edit: Option<String>,
+        /// Remove an existing annotation
+        #[arg(long, value_name = "ANNOTATION_ID", help = "Remove the annotation with this ID")]
+        delete: Option<String>,
     },
     
     /// 📋 List all annotations in the current session
-    #[command(alias = "list", alias = "show")]
+    #[command(alias = "show")]
     #[command(long_about = "View and filter annotations from your current session.
     
 Display all annotations with timestamps, types, and content. Filter by type or limit to recent entries.
 
 EXAMPLES:
     docpilot annotations                    # Show all annotations
-    docpilot list --recent 5               # Show last 5 annotations
+    docpilot annotations --recent 5        # Show last 5 annotations
     docpilot show --filter-type warning    # Show only warnings
-    docpilot annotations -r 3 -f milestone # Last 3 milestones")]
+    docpilot annotations -r 3 -f milestone # Last 3 milestones
+    docpilot annotations export -o notes.md            # Only the annotations, as markdown
+    docpilot annotations export --format json -f warning  # Warnings as JSON on stdout")]
     Annotations {
         /// Show only recent annotations (last N)
         #[arg(short, long, help = "Limit to N most recent annotations")]
@@ -148,6 +239,8 @@ EXAMPLES:
         /// Filter by annotation type
         #[arg(short = 'f', long, help = "Filter by type: note, explanation, warning, milestone")]
         filter_type: Option<String>,
+        #[command(subcommand)]
+        action: Option<AnnotationsAction>,
     },
     
     /// 📝 Quick note annotation
@@ -163,6 +256,9 @@ EXAMPLES:
         /// The note text to add
         #[arg(help = "Your note content")]
         text: String,
+        /// Save the text as-is even if it looks like it contains secrets
+        #[arg(long, help = "Skip the secret check and save the text as-is")]
+        force: bool,
     },
     
     /// 💡 Quick explanation annotation
@@ -178,6 +274,9 @@ EXAMPLES:
         /// The explanation text to add
         #[arg(help = "Your explanation content")]
         text: String,
+        /// Save the text as-is even if it looks like it contains secrets
+        #[arg(long, help = "Skip the secret check and save the text as-is")]
+        force: bool,
     },
     
     /// ⚠️ Quick warning annotation
@@ -193,6 +292,9 @@ EXAMPLES:
         /// The warning text to add
         #[arg(help = "Your warning content")]
         text: String,
+        /// Save the text as-is even if it looks like it contains secrets
+        #[arg(long, help = "Skip the secret check and save the text as-is")]
+        force: bool,
     },
     
     /// 🎯 Quick milestone annotation
@@ -208,6 +310,68 @@ EXAMPLES:
         /// The milestone text to add
         #[arg(help = "Your milestone content")]
         text: String,
+        /// Save the text as-is even if it looks like it contains secrets
+        #[arg(long, help = "Skip the secret check and save the text as-is")]
+        force: bool,
+    },
+
+    /// 🏷️ Tag a captured command for grouping and filtering
+    #[command(long_about = "Tag a captured command to put it in a group of your own.
+
+Commands are numbered from 1 in the order they were captured, the step numbers shown by 'docpilot generate --step-numbers'. Tags are shown with the command and can be used by 'docpilot generate --group-by tag' and 'docpilot generate --tag <TAG>', for when the automatic command types don't group the commands the way you want.
+
+EXAMPLES:
+    docpilot tag-command 3 database               # Tag the third command of the current session
+    docpilot tag-command 7 deploy --session abc123
+    docpilot tag-command 3 database --remove      # Take the tag off again")]
+    TagCommand {
+        /// Number of the command, counting captured commands from 1
+        #[arg(help = "Command number (1 = first captured command)")]
+        number: usize,
+        /// Tag to add
+        #[arg(help = "Tag to add, e.g. database")]
+        tag: String,
+        /// Session the command belongs to
+        #[arg(short, long, help = "Session ID (defaults to the current or most recent session)")]
+        session: Option<String>,
+        /// Remove the tag instead of adding it
+        #[arg(long, help = "Remove the tag instead of adding it")]
+        remove: bool,
+    },
+
+    /// 🏷️ Tag a session to organize and find it later
+    #[command(long_about = "Add or remove tags on a session, e.g. the project or system it is about.
+
+Tags are shown by 'docpilot status' and in the metadata of generated documentation, and 'docpilot list --tag <TAG>' finds every session with a tag. To tag a single command instead, use 'docpilot tag-command'.
+
+EXAMPLES:
+    docpilot tag add billing                      # Tag the current session
+    docpilot tag add database --session abc123    # Tag another session
+    docpilot tag remove billing                   # Take the tag off again")]
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+
+    /// 📌 Mark a session as one to keep
+    #[command(long_about = "Pin a session to mark it as one to keep.
+
+Pinned sessions are marked with 📌 in session lists. Use it for reference procedures you want to keep generating documentation from.
+
+EXAMPLES:
+    docpilot pin abc123                           # Mark session abc123 as one to keep
+    docpilot unpin abc123                         # Clear the mark again")]
+    Pin {
+        /// Session to pin
+        #[arg(help = "Session ID")]
+        session_id: String,
+    },
+
+    /// Clear the pinned mark from a session
+    Unpin {
+        /// Session to unpin
+        #[arg(help = "Session ID")]
+        session_id: String,
     },
     
     /// ⚙️ Configure LLM settings
@@ -217,13 +381,22 @@ EXAMPLES:
 Set up API keys and providers for AI-powered command analysis, explanations, and insights.
 
 EXAMPLES:
-    docpilot config                                    # Show current configuration
+    docpilot config                                    # Show current configuration (keys masked)
+    docpilot config --show-keys                        # Reveal full API keys when debugging
     docpilot cfg --provider claude --api-key sk-...   # Set Claude as provider
     docpilot setup -p chatgpt -a your-api-key         # Set ChatGPT as provider
-    docpilot config --provider ollama --base-url http://localhost:11434  # Set Ollama")]
+    docpilot config --provider ollama --base-url http://localhost:11434  # Set Ollama
+    docpilot config --provider ollama --model llama3.1  # Use a specific model
+    docpilot config --provider heuristic              # Offline rule-based analysis, no API or network
+    docpilot config --always-overwrite true           # Never ask before replacing generated docs
+    docpilot config --max-monitors 3                  # Record in up to three terminals at once
+    docpilot config --keychain true                   # Move API keys out of the config file into the OS keychain
+    docpilot config --spill-output-over 1M            # Keep output over 1 MB in linked sidecar log files
+    docpilot config --redact '(?i)password=\\S+'       # Replace matches with [REDACTED] in generated docs (repeatable)
+    docpilot config --migrate                         # Upgrade an older config file, keeping a backup")]
     Config {
-        /// LLM provider (claude, chatgpt, gemini, ollama)
-        #[arg(short, long, help = "AI provider: claude, chatgpt, gemini, ollama")]
+        /// LLM provider (claude, chatgpt, gemini, ollama, mock, heuristic)
+        #[arg(short, long, help = "AI provider: claude, chatgpt, gemini, ollama, mock (offline canned responses), heuristic (offline rule-based analysis)")]
         provider: Option<String>,
         
         /// API key for the LLM provider
@@ -233,6 +406,119 @@ EXAMPLES:
         /// Base URL for the LLM provider (useful for Ollama or custom endpoints)
         #[arg(short, long, help = "Base URL for the provider (e.g., http://localhost:11434 for Ollama)")]
         base_url: Option<String>,
+        
+        /// Model for the LLM provider
+        #[arg(short, long, help = "Model for the selected provider (e.g., llama3.1 for Ollama)")]
+        model: Option<String>,
+        
+        /// Show full API keys instead of masked ones
+        #[arg(long, help = "Reveal full API keys when showing the configuration (keys are masked by default)")]
+        show_keys: bool,
+        
+        /// Overwrite existing documentation files without asking
+        #[arg(long, value_name = "BOOL", help = "Always overwrite existing output files without asking: true or false")]
+        always_overwrite: Option<bool>,
+        
+        /// Maximum number of background monitors running at once
+        #[arg(long, value_name = "N", help = "Allow up to N background monitors at once (default 1); never more than one per terminal")]
+        max_monitors: Option<usize>,
+        
+        /// Keep API keys in the operating system's keychain
+        #[arg(long, value_name = "BOOL", help = "Keep API keys in the OS keychain (macOS Keychain, Secret Service, Windows Credential Manager) instead of the config file: true or false")]
+        keychain: Option<bool>,
+
+        /// Move captured output above this size to sidecar files
+        #[arg(long, value_name = "SIZE", help = "Keep captured output larger than SIZE (e.g. 512K, 2M) in sidecar log files linked from the docs instead of the session; 0 turns this off")]
+        spill_output_over: Option<String>,
+
+        /// Directory for the sidecar log files
+        #[arg(long, value_name = "DIR", help = "Write sidecar log files of large output to DIR (default ~/.docpilot/logs)")]
+        spill_dir: Option<PathBuf>,
+
+        /// Upgrade the config file to the current format
+        #[arg(long, help = "Upgrade an older config file to the current format, keeping a copy of the old file next to it")]
+        migrate: bool,
+
+        /// Regexes to redact from generated documentation (repeatable)
+        #[arg(long, value_name = "REGEX", help = "Replace text matching REGEX with [REDACTED] in commands, output and errors of generated docs (repeatable; saved in filter.json)")]
+        redact: Vec<String>,
+    },
+    
+    /// ✅ Verify a session against an approved procedure
+    #[command(long_about = "Check that a session followed an approved procedure.
+    
+The procedure file lists the expected commands in order, one per line (blank lines and lines starting with # are ignored). Successful session commands are compared using normalized commands, so variable parts such as timestamps, temp paths and ports do not cause a mismatch. Missing, extra and out-of-order steps are reported, and the command exits with status 1 if the session does not conform.
+
+EXAMPLES:
+    docpilot verify --against procedure.txt                 # Check the current/last session
+    docpilot verify --against deploy.txt --session abc123   # Check a specific session
+    docpilot verify --against deploy.txt --allow-extra      # Only require the listed steps, in order")]
+    Verify {
+        /// File with the expected commands, one per line
+        #[arg(long, value_name = "FILE", help = "Procedure file with the expected commands in order")]
+        against: String,
+        
+        /// Specific session ID to verify (defaults to current/last session)
+        #[arg(short, long, help = "Session ID to verify")]
+        session: Option<String>,
+        
+        /// Do not fail when the session contains commands that are not in the procedure
+        #[arg(long, help = "Allow commands that are not part of the procedure")]
+        allow_extra: bool,
+    },
+    
+    /// 🔁 Re-run a session's commands to check the documentation still works
+    #[command(long_about = "Re-run the commands of a session in order and compare their exit codes with the recorded ones.
+
+Each command runs with sh in the working directory it was captured in, without its output being shown. Only commands that are safe to re-execute (read-only ones such as ls, cat, grep and find) are run; others are skipped with a notice unless --force is given. The report lists commands that still succeed, commands that now fail and commands that failed before but succeed now. The command exits with status 1 if a command that succeeded when recorded fails now.
+
+EXAMPLES:
+    docpilot replay                         # Replay the current or most recent session
+    docpilot replay --session abc123        # Replay a specific session
+    docpilot replay --session abc123 --force  # Also run commands that change things")]
+    Replay {
+        /// Session to replay (defaults to the current or most recent session)
+        #[arg(short, long, help = "Session ID (defaults to the current or most recent session)")]
+        session: Option<String>,
+        
+        /// Run commands that are not known to be safe to re-execute
+        #[arg(long, help = "Also run commands that are not known to be safe to re-execute")]
+        force: bool,
+    },
+
+    /// 🧩 Combine several sessions into one
+    #[command(long_about = "Merge two or more sessions into a new session, e.g. the fragments of a process documented over several days.
+
+Commands and annotations from all sessions are interleaved by timestamp and the commands are numbered again. The merged session gets a new ID and a description joining the originals, spans from the earliest start to the latest stop, and adds up the sessions' statistics; time covered by overlapping sessions only counts once in its duration. The original sessions are left as they are.
+
+The merged session is saved with your other sessions, so it can be documented with 'docpilot generate --session <id>'. With --output a copy of its JSON is also written to the given file.
+
+EXAMPLES:
+    docpilot merge abc123 def456                        # Merge two sessions
+    docpilot merge abc123 def456 ghi789 -o combined.json  # Also write the merged session to a file")]
+    Merge {
+        /// IDs of the sessions to merge, in any order
+        #[arg(required = true, num_args = 2.., value_name = "SESSION", help = "Session IDs to merge (at least two)")]
+        sessions: Vec<String>,
+
+        /// File to write the merged session's JSON to
+        #[arg(short, long, help = "Also write the merged session to this JSON file")]
+        output: Option<String>,
+    },
+
+    /// 🗄️ Manage the cache of AI analysis results
+    #[command(long_about = "Inspect and prune the on-disk cache of AI analysis results.
+    
+Analysis results are cached in ~/.docpilot/cache/ so regenerating documentation does not query the AI provider again for the same commands.
+
+EXAMPLES:
+    docpilot cache list                     # Show cached entries with sizes and ages
+    docpilot cache stats                    # Show entry count, disk usage and hit rate
+    docpilot cache clear                    # Remove all cached results
+    docpilot cache clear --older-than 7d    # Remove results older than a week")]
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
     },
     
     /// 📄 Generate documentation from a session
@@ -256,7 +542,37 @@ EXAMPLES:
     docpilot generate --output my-guide.md          # Generate from current/last session
     docpilot gen --session session-id -o guide.md  # Generate from specific session
     docpilot doc --template comprehensive           # Use specific template
-    docpilot generate --template ai-enhanced        # Generate with AI analysis")]
+    docpilot generate --template ai-enhanced        # Generate with AI analysis
+    docpilot generate -o guide.md --split-by phase  # One file per workflow phase plus guide.md index
+    docpilot generate -o docs/ --split-by type      # docs/index.md plus one linked page per command type
+    docpilot generate --ai-mode explain             # One-sentence AI explanation per command
+    docpilot generate --ai-min-significance 0       # Analyze every command, even `ls` and `cd`
+    docpilot generate --context \"Kubernetes production deploy runbook\"
+    docpilot generate --context-file docs/architecture.md  # Longer project context from a file
+    docpilot generate -o guide.md --manifest        # Also write guide.docpilot.json for traceability
+    docpilot generate --language de                 # German headings and labels
+    docpilot generate --focus-failures              # Post-mortem: failures in full, successes collapsed
+    docpilot generate --split-chains                # List the steps of `make && make install` lines
+    docpilot generate --collapse-repeats            # `kubectl get pods` polled 8 times becomes one entry
+    docpilot generate -o guide.md --force           # Replace guide.md without asking
+    docpilot generate -o content/posts/deploy.md --front-matter  # Drop-in page for Hugo or Jekyll
+    docpilot generate --exclude-pattern '^git (status|diff)' --exclude-pattern '^ls( |$)'  # Drop noise for this doc
+    docpilot generate --group-by tag --tag database --tag deploy  # Only tagged commands, one section per tag
+    docpilot generate --toc-depth 4                 # Table of contents down to individual commands
+    docpilot generate --gap-threshold 15            # Only note pauses of 15 minutes or more as manual steps
+    docpilot generate --baseline 20240601-release   # Show how each command's output changed since that session
+    docpilot generate --step-numbers                # \"Step 12\" headings and #step-12 links that stay valid when regenerating
+    docpilot generate --prerequisites               # Start with a checklist of what to install first
+    docpilot generate --glossary                    # End with one-line definitions of kubectl, jq, ...
+    docpilot generate --show-nesting                # Mark commands run inside docker exec, subshells, ...
+    docpilot generate --resolve-scripts             # Show what `npm run build` actually runs
+    docpilot generate --raw-output                  # Keep colour codes and progress redraws as captured
+    docpilot generate --polish-annotations          # Fix typos in rushed notes (needs an LLM provider)
+    docpilot generate --timings                     # Show where generation time goes
+    docpilot generate --fail-on-errors --max-failures 2  # CI gate: exit 1 after writing the doc if more than 2 commands failed
+    docpilot generate --incremental -o part-2.md    # Only what was added since the last generate
+    docpilot generate --profile deploy-docs         # Options saved with 'docpilot profile save deploy-docs ...'
+    docpilot generate --template ai-enhanced --mock-llm  # Try AI templates offline with canned responses")]
     Generate {
         /// Output file name for the generated documentation
         #[arg(short, long, help = "Output markdown file (e.g., guide.md)")]
@@ -265,10 +581,120 @@ EXAMPLES:
         /// Specific session ID to generate from (defaults to current/last session)
         #[arg(short, long, help = "Session ID to generate documentation from")]
         session: Option<String>,
+
+        /// Start from options saved with 'docpilot profile save'
+        #[arg(long, value_name = "NAME", help = "Use the options saved in profile NAME; options given here override the profile's")]
+        profile: Option<String>,
+
+        /// Compare command output with an earlier session
+        #[arg(long, value_name = "SESSION_ID", help = "Show how each command's output changed since the matching command (same normalized form) in session SESSION_ID")]
+        baseline: Option<String>,
+
+        #[command(flatten)]
+        options: Box<GenerateProfile>,
+        
+        /// Report how long each generation stage took
+        #[arg(long, help = "Print how long loading, filtering, structuring, AI analysis, markdown writing and file IO took")]
+        timings: bool,
+        
+        /// Overwrite the output file without asking
+        #[arg(long, visible_alias = "yes", help = "Overwrite an existing output file without asking")]
+        force: bool,
+        
+        /// Only document what was added since the last generate
+        #[arg(long, help = "Only document commands and annotations added since the session was last generated")]
+        incremental: bool,
+
+        /// Exit non-zero when too many captured commands failed
+        #[arg(long, help = "Exit with status 1 after writing the documentation if more commands failed than --max-failures allows (for CI); counts every captured command, including ones the document leaves out")]
+        fail_on_errors: bool,
+
+        /// Failed commands tolerated by --fail-on-errors
+        #[arg(long, value_name = "N", default_value_t = 0, requires = "fail_on_errors", help = "Number of failed commands --fail-on-errors tolerates (default 0)")]
+        max_failures: usize,
+    },
+
+    /// 🎛️ Save and manage named sets of generate options
+    #[command(long_about = "Save a combination of generate options under a name and reuse it with 'generate --profile NAME'.
+
+Profiles are stored in the config file. Options given to generate on the command line override the profile's; a switch the profile turns on is turned off with --SWITCH=false.
+
+EXAMPLES:
+    docpilot profile save deploy-docs --template technical --front-matter --context \"Production deploy runbook\"
+    docpilot generate --profile deploy-docs -o deploy.md         # Use the saved options
+    docpilot generate --profile deploy-docs --template minimal   # Override one of them
+    docpilot generate --profile deploy-docs --front-matter=false # Turn off one of its switches
+    docpilot profile list                                        # Show saved profiles and their options
+    docpilot profile delete deploy-docs                          # Remove a profile")]
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    
+    /// 🔍 Render a session with several templates to compare them
+    #[command(long_about = "Render the same session with each of the given templates to help choose one.
+
+Each rendering is written to preview-<template>.md (replacing earlier previews), and a summary compares their length and number of sections. Open the files side by side or diff them.
+
+Templates: standard, minimal, comprehensive, hierarchical, professional, compact, rich, technical, github, ai-enhanced
+
+EXAMPLES:
+    docpilot preview --templates technical,minimal          # preview-technical.md and preview-minimal.md
+    docpilot preview --templates rich,github --dir /tmp     # Write the previews to /tmp
+    docpilot preview --templates ai-enhanced,standard --mock-llm  # Compare AI templates offline")]
+    Preview {
+        /// Templates to render
+        #[arg(long, value_name = "LIST", value_delimiter = ',', required = true, help = "Comma-separated templates to render (e.g., technical,minimal)")]
+        templates: Vec<String>,
+        
+        /// Session to preview (defaults to the current or most recent session)
+        #[arg(short, long, help = "Session ID (defaults to the current or most recent session)")]
+        session: Option<String>,
+        
+        /// Directory for the preview files
+        #[arg(long, value_name = "DIR", default_value = ".", help = "Directory to write the preview-<template>.md files to")]
+        dir: PathBuf,
+        
+        /// Use canned AI responses instead of calling an LLM
+        #[arg(long, help = "Use the offline mock LLM provider for templates that would call an LLM")]
+        mock_llm: bool,
+    },
+    
+    /// 📤 Export a session's documentation in another format
+    #[command(long_about = "Render a session with a template and write it as a standalone HTML page or as markdown, or write the session itself as JSON.
+
+HTML pages are self-contained: the markdown is converted and styled with the template's theme, so the file can be opened in a browser or shared as is. Table of contents links keep working.
+
+JSON contains the whole session (commands, annotations, events, metadata, statistics) with RFC 3339 timestamps, for other tools. Asciicast writes an asciinema v2 recording that types each command at its captured time and shows its output, for replaying in a web player; the terminal size recorded at start is used, 80x24 otherwise. The template doesn't apply to either.
+
+Formats: html (default), markdown, json, asciicast
+
+EXAMPLES:
+    docpilot export --format html --output guide.html       # Standalone page for the current session
+    docpilot export --session abc123 --template technical   # Technical theme, named after the session
+    docpilot export --format markdown -o guide.md           # Same as generate, for scripts
+    docpilot export --format json -o - | jq '.commands[]'   # Feed the session to other tools
+    docpilot export --format asciicast -o demo.cast         # Replay with 'asciinema play demo.cast'")]
+    Export {
+        /// Session to export (defaults to the current or most recent session)
+        #[arg(short, long, help = "Session ID (defaults to the current or most recent session)")]
+        session: Option<String>,
+        
+        /// Format to write
+        #[arg(long, value_name = "FORMAT", default_value = "html", help = "Output format: html (standalone page), markdown, json (the session's data), or asciicast (asciinema recording)")]
+        format: String,
         
-        /// Template style for documentation
-        #[arg(short, long, default_value = "standard", help = "Template: standard (ai-enhanced if configured), comprehensive, minimal, ai-enhanced")]
+        /// Output file
+        #[arg(short, long, help = "Output file (defaults to the session description with the format's extension; - writes json or asciicast to stdout)")]
+        output: Option<PathBuf>,
+        
+        /// Template style, which also picks the page's theme
+        #[arg(short, long, default_value = "standard", help = "Template: standard, minimal, comprehensive, hierarchical, professional, compact, rich, technical, github, ai-enhanced")]
         template: String,
+        
+        /// Use canned AI responses instead of calling an LLM
+        #[arg(long, help = "Use the offline mock LLM provider (canned, clearly labeled responses) to try AI templates without API keys")]
+        mock_llm: bool,
     },
     
     /// � Show current session status
@@ -279,8 +705,52 @@ Shows session details, statistics, recent commands, annotations, and metadata.
 
 EXAMPLES:
     docpilot status
-    docpilot info")]
-    Status,
+    docpilot info
+    docpilot status --brief                         # One line for PS1, e.g. \"● rec 2m 14cmds\"
+    docpilot status --monitors                      # List running background monitors
+    docpilot status --monitor 48213                 # Status of the session a monitor captures
+    docpilot info --storage                         # Disk usage of sessions, backups and the analysis cache")]
+    Status {
+        /// Print a single compact line for shell prompts
+        #[arg(long, help = "Print one compact line (e.g. \"● rec 2m 14cmds\") or nothing when no session is active; for shell prompts")]
+        brief: bool,
+        /// List running background monitors
+        #[arg(long, help = "List running background monitors with their sessions and terminals")]
+        monitors: bool,
+        /// Show the session of a specific monitor
+        #[arg(long, value_name = "PID|SESSION", help = "Show the session captured by the monitor with this PID or session ID")]
+        monitor: Option<String>,
+        /// Show how much disk space DocPilot's data takes
+        #[arg(long, help = "Show the number and size of stored sessions, backups and cached analyses, and the oldest and newest session")]
+        storage: bool,
+    },
+
+    /// 🗂️ List stored sessions
+    #[command(alias = "sessions")]
+    #[command(long_about = "List stored sessions in a table, newest first.
+
+Each session is shown with its ID, description, state, number of captured commands, creation date and tags; pinned sessions are marked with 📌. Sessions are sorted by when they were created, or with --sort modified by when their file was last written, which puts sessions you recently stopped, edited or tagged first.
+
+EXAMPLES:
+    docpilot list                                   # All sessions
+    docpilot list --limit 10                        # The 10 newest sessions
+    docpilot list --state active                    # Sessions that are still running
+    docpilot list --state completed --sort modified # Finished sessions, most recently changed first
+    docpilot list --tag billing                     # Only sessions tagged 'billing'")]
+    List {
+        /// Only list sessions in this state
+        #[arg(long, value_name = "STATE", help = "Only list sessions that are active, paused, completed (stopped) or error")]
+        state: Option<String>,
+        /// Show at most this many sessions
+        #[arg(short = 'n', long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), help = "Show at most N sessions")]
+        limit: Option<usize>,
+        /// Only list sessions with this tag
+        #[arg(long, value_name = "TAG", help = "Only list sessions tagged TAG (see 'docpilot tag')")]
+        tag: Option<String>,
+        /// Sort order
+        #[arg(long, value_name = "ORDER", default_value = "created", help = "Sort by creation time (created, default) or by when the session file was last written (modified), newest first")]
+        sort: String,
+    },
     
     /// Hidden command for background monitoring
     #[command(hide = true)]
@@ -296,6 +766,56 @@ EXAMPLES:
         session_id: String,
     },
     
+    /// 🌐 Open an SSH session whose commands are captured too
+    #[command(long_about = "Connect to a remote host with ssh and add the commands you run there to the active session.
+
+DocPilot copies its bash hook to the remote host and starts an interactive bash that sources your ~/.bashrc and then the hook. When you log out, the remote command log is copied back, recorded in the session with working directories like 'web1:/var/www', and the remote files are removed. Nothing is installed permanently.
+
+All arguments are passed to ssh. The connection is shared between the setup steps and your shell, so you only authenticate once. The remote host needs bash; commands run there appear in the session when the SSH session ends.
+
+EXAMPLES:
+    docpilot ssh web1                           # Capture a maintenance session on web1
+    docpilot ssh -p 2222 deploy@db.internal     # Any ssh options work
+    docpilot ssh -i ~/.ssh/ops ops@10.0.0.12")]
+    Ssh {
+        /// Arguments for ssh, ending with the destination
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true, value_name = "SSH_ARGS")]
+        ssh_args: Vec<String>,
+    },
+    
+    /// ✏️ Edit a session's JSON in your editor
+    #[command(long_about = "Open a session's JSON in $VISUAL or $EDITOR and store it again after checking it.
+
+You edit a copy; the session file is only replaced when the result is valid JSON in the session format (see 'docpilot schema'), keeps the same session ID and has consistent timestamps. Otherwise you can reopen the editor to fix it, or give up and keep the stored session unchanged. The previous version is kept in ~/.docpilot/backups/ and the statistics are recounted from the edited commands and annotations.
+
+A session that a monitor is still recording cannot be edited, because the monitor would overwrite the changes on its next save. Stop it first.
+
+EXAMPLES:
+    docpilot edit                         # Edit the current or most recent session
+    docpilot edit --session abc123        # Edit a specific session
+    EDITOR=nano docpilot edit")]
+    Edit {
+        /// Session to edit (defaults to the current or most recent session)
+        #[arg(short, long, help = "Session ID (defaults to the current or most recent session)")]
+        session: Option<String>,
+    },
+    
+    /// 📐 Print the JSON Schema of a DocPilot file format
+    #[command(long_about = "Print a JSON Schema document describing a DocPilot file format.
+
+Use it to validate session files, JSON exports and manifests or to generate code for tools that read them. The schema is derived from the same types DocPilot reads and writes, so it matches this version's format.
+
+EXAMPLES:
+    docpilot schema                          # Schema for session files (~/.docpilot/sessions/*.json)
+    docpilot schema --for manifest           # Schema for .docpilot.json manifests
+    docpilot schema --for export             # Schema for 'docpilot export --format json' output
+    docpilot schema > docpilot-session.schema.json")]
+    Schema {
+        /// Which format to describe
+        #[arg(long = "for", value_name = "FORMAT", default_value = "session", help = "Format to describe: session (default), manifest or export")]
+        target: String,
+    },
+    
     /// 🧪 Simulate commands for testing (hidden)
     #[command(hide = true)]
     Simulate {
@@ -304,6 +824,106 @@ EXAMPLES:
     },
 }
 
+#[derive(Subcommand)]
+enum AnnotationsAction {
+    /// Export only the annotations, as markdown or JSON
+    Export {
+        /// Session to export (defaults to the current or most recent session)
+        #[arg(short, long, help = "Session ID (defaults to the current or most recent session)")]
+        session: Option<String>,
+        /// Output format
+        #[arg(long, value_name = "FORMAT", default_value = "markdown", help = "Output format: markdown (default) or json")]
+        format: String,
+        /// Group markdown output by annotation type
+        #[arg(long, help = "Group annotations by type instead of listing them chronologically (markdown only)")]
+        by_type: bool,
+        /// Filter by annotation type
+        #[arg(short = 'f', long, help = "Filter by type: note, explanation, warning, milestone")]
+        filter_type: Option<String>,
+        /// File to write instead of stdout
+        #[arg(short, long, value_name = "FILE", help = "Write to FILE instead of printing to stdout")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+    /// Add a tag to a session
+    Add {
+        /// Tag to add
+        #[arg(help = "Tag to add, e.g. billing")]
+        tag: String,
+        /// Session to tag
+        #[arg(short, long, help = "Session ID (defaults to the current or most recent session)")]
+        session: Option<String>,
+    },
+    /// Remove a tag from a session
+    Remove {
+        /// Tag to remove
+        #[arg(help = "Tag to remove")]
+        tag: String,
+        /// Session to untag
+        #[arg(short, long, help = "Session ID (defaults to the current or most recent session)")]
+        session: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cached analysis results
+    List,
+    /// Remove cached analysis results
+    Clear {
+        /// Only remove entries older than this age
+        #[arg(long, value_name = "AGE", help = "Only remove entries older than AGE (e.g., 30m, 12h, 7d, 2w)")]
+        older_than: Option<String>,
+    },
+    /// Show cache size and hit rate
+    Stats,
+}
+
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// Save generate options under a name, replacing a profile of the same name
+    Save {
+        /// Name to use with 'generate --profile'
+        #[arg(help = "Profile name")]
+        name: String,
+
+        #[command(flatten)]
+        options: Box<GenerateProfile>,
+    },
+    /// List saved profiles and their options
+    List,
+    /// Remove a saved profile
+    Delete {
+        /// Profile to remove
+        #[arg(help = "Profile name")]
+        name: String,
+    },
+}
+
+/// How `start` handles a session that is already running
+enum ConflictAction {
+    /// Stop the running session and generate its documentation
+    StopAndGenerate,
+    /// Stop the running session without generating documentation
+    Stop,
+    /// Keep the running session and don't start a new one
+    Cancel,
+}
+
+impl ConflictAction {
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "stop-and-generate" | "generate" => Ok(ConflictAction::StopAndGenerate),
+            "stop" => Ok(ConflictAction::Stop),
+            "cancel" => Ok(ConflictAction::Cancel),
+            _ => Err(anyhow::anyhow!("Invalid --on-conflict action: {} (expected stop-and-generate, stop, or cancel)", s)),
+        }
+    }
+}
+
 /// Check if we're running in a test environment
 fn is_test_environment() -> bool {
     std::env::var("PWD")
@@ -320,18 +940,94 @@ fn is_test_environment() -> bool {
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+
+    // The prompt indicator runs on every prompt, so it skips the usual setup and never reports errors
+    if let Commands::Status { brief: true, .. } = cli.command {
+        print_brief_status();
+        return Ok(());
+    }
+
     let mut session_manager = SessionManager::new()?;
 
     // Session recovery is now handled per-command as needed
     // No global session recovery to prevent conflicts
 
     match cli.command {
-        Commands::Start { description, output, foreground } => {
+        Commands::Start { description, output, foreground, expand_aliases, only_dir, warn_dangerous, annotate_dangerous, on_conflict, resume_or_new, track_files, reattach, daily_log, capture_delay, track_nesting, tags } => {
+            // Resolve the directory up front so a typo doesn't silently capture nothing
+            let only_dir = match only_dir.map(std::fs::canonicalize).transpose() {
+                Ok(Some(dir)) if !dir.is_dir() => {
+                    eprintln!("❌ --only-dir must be a directory: {}", dir.display());
+                    std::process::exit(1);
+                }
+                Ok(dir) => dir,
+                Err(e) => {
+                    eprintln!("❌ Cannot use --only-dir: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            
+            if let Some(pattern) = track_files.iter().find(|pattern| glob::Pattern::new(pattern).is_err()) {
+                eprintln!("❌ Invalid --track-files pattern: {}", pattern);
+                std::process::exit(1);
+            }
+            if tags.iter().any(|tag| tag.trim().is_empty()) {
+                eprintln!("❌ --tag cannot be empty");
+                std::process::exit(1);
+            }
+            session_manager.set_output_spill(Settings::load().ok().and_then(|settings| settings.output_spill()));
+            let daily_log = match daily_log {
+                Some(dir) => match dir.map(Ok).unwrap_or_else(crate::output::default_daily_log_directory)
+                    .and_then(|dir| fs::create_dir_all(&dir).map(|_| dir.clone()).map_err(|e| anyhow::anyhow!("{}: {}", dir.display(), e)))
+                {
+                    Ok(dir) => Some(std::path::absolute(&dir).unwrap_or(dir)),
+                    Err(e) => {
+                        eprintln!("❌ Cannot use --daily-log directory: {}", e);
+                        std::process::exit(1);
+                    }
+                },
+                None => None,
+            };
+
             // Try to recover any interrupted sessions first
             if let Ok(Some(recovered_session_id)) = session_manager.recover_session() {
                 println!("🔄 Found interrupted session: {}", recovered_session_id);
+
+                // The crash may have taken its monitor with it, leaving nothing to capture commands
+                if let Some(session) = session_manager.get_current_session()
+                    && !has_live_monitor(session)
+                {
+                    println!("⚠️  The monitor recording '{}' is no longer running", session.description);
+                    if reattach || resume_or_new || prompt_reattach() {
+                        if !reattach_monitor(&recovered_session_id) {
+                            std::process::exit(1);
+                        }
+                        if !resume_or_new {
+                            return Ok(());
+                        }
+                    }
+                }
                 println!();
             }
+
+            // Reuse whatever is running, so scripts can call start every time
+            if resume_or_new && let Some(current_session) = session_manager.get_current_session() {
+                let (session_id, current_description) = (current_session.id.clone(), current_session.description.clone());
+                if current_session.state.is_paused() {
+                    if let Err(e) = session_manager.resume_session() {
+                        eprintln!("❌ Failed to resume session: {}", e);
+                        std::process::exit(1);
+                    }
+                    println!("▶️  Resumed existing session: {}", current_description);
+                } else {
+                    println!("✅ Session already running: {}", current_description);
+                }
+                println!("   Session ID: {}", session_id);
+                if current_description != description {
+                    println!("   (asked for \"{}\"; the running session keeps its description)", description);
+                }
+                return Ok(());
+            }
             
             // Check if there's already an active session (including recovered ones)
             if let Some(current_session) = session_manager.get_current_session() {
@@ -342,49 +1038,42 @@ async fn main() -> Result<()> {
                 println!("   Commands captured: {}", current_session.stats.total_commands);
                 println!();
                 
-                // Interactive prompt for handling the existing session
-                println!("DocPilot only supports one active session at a time to prevent shell hook conflicts.");
-                println!("What would you like to do with the existing session?");
-                println!();
-                println!("1. Stop and generate documentation from current session, then start new one");
-                println!("2. Stop current session without generating docs, then start new one");
-                println!("3. Cancel - keep current session running");
-                println!();
-                print!("Choose option (1/2/3): ");
-                
-                use std::io::{self, Write};
-                io::stdout().flush().unwrap();
+                let action = match on_conflict.as_deref().map(ConflictAction::from_str) {
+                    Some(Ok(action)) => action,
+                    Some(Err(e)) => {
+                        eprintln!("❌ {}", e);
+                        std::process::exit(1);
+                    }
+                    None => prompt_conflict_action(),
+                };
                 
-                let mut input = String::new();
-                match io::stdin().read_line(&mut input) {
-                    Ok(_) => {
-                        let choice = input.trim();
-                        match choice {
-                            "1" => {
-                                println!();
-                                println!("🛑 Stopping current session and generating documentation...");
-                                
-                                // Stop current session
-                                match session_manager.stop_session() {
-                                    Ok(Some(session)) => {
-                                        println!("✅ Session '{}' stopped successfully!", session.description);
+                match action {
+                    ConflictAction::StopAndGenerate => {
+                        println!();
+                        println!("🛑 Stopping current session and generating documentation...");
+                        
+                        // Stop current session
+                        match session_manager.stop_session() {
+                            Ok(Some(session)) => {
+                                println!("✅ Session '{}' stopped successfully!", session.description);
+                                stop_session_monitors(&session.id);
+                                append_daily_log(&session).await;
                                         
-                                        // Generate documentation from the stopped session
-                                        let output_file = if let Some(ref session_output) = session.output_file {
-                                            session_output.clone()
-                                        } else {
-                                            // Generate filename from session description
-                                            let sanitized_desc = session.description
-                                                .chars()
-                                                .map(|c| if c.is_alphanumeric() || c == ' ' { c } else { ' ' })
-                                                .collect::<String>()
-                                                .split_whitespace()
-                                                .collect::<Vec<_>>()
-                                                .join("-")
-                                                .to_lowercase();
-                                            std::path::PathBuf::from(format!("{}.md", sanitized_desc))
-                                        };
+                                // Generate documentation from the stopped session
+                                let output_file = match session.output_file {
+                                    Some(ref session_output) => match crate::output::check_writable(session_output) {
+                                        Ok(()) => session_output.clone(),
+                                        Err(e) => {
+                                            println!("⚠️  {}", e);
+                                            session_output_fallback(&session)
+                                        }
+                                    },
+                                    // Generate filename from session description
+                                    None => PathBuf::from(crate::output::default_output_filename(&session)),
+                                };
                                         
+                                match confirm_output_path(output_file, false) {
+                                    Some(output_file) => {
                                         println!("📄 Generating documentation to: {}", output_file.display());
                                         match crate::output::generate_documentation(&session, &output_file, "standard").await {
                                             Ok(_) => {
@@ -396,69 +1085,69 @@ async fn main() -> Result<()> {
                                                 eprintln!("   You can generate it later with: docpilot generate --session {}", session.id);
                                             }
                                         }
-                                        
-                                        // Ensure current session is cleared for new session start
-                                        session_manager.clear_current_session();
-                                    }
-                                    Ok(None) => {
-                                        println!("ℹ️  No session was active (unexpected state)");
                                     }
-                                    Err(e) => {
-                                        eprintln!("❌ Failed to stop current session: {}", e);
-                                        eprintln!("   Please run 'docpilot stop' manually first");
-                                        std::process::exit(1);
+                                    None => {
+                                        println!("⏭️  Skipped generating documentation");
+                                        println!("   You can generate it later with: docpilot generate --session {}", session.id);
                                     }
                                 }
-                                
-                                println!();
-                                println!("🚀 Now starting new session: {}", description);
+                                        
+                                // Ensure current session is cleared for new session start
+                                session_manager.clear_current_session();
                             }
-                            "2" => {
-                                println!();
-                                println!("🛑 Stopping current session without generating documentation...");
-                                
-                                match session_manager.stop_session() {
-                                    Ok(Some(session)) => {
-                                        println!("✅ Session '{}' stopped successfully!", session.description);
-                                        println!("💡 You can generate documentation later with: docpilot generate --session {}", session.id);
+                            Ok(None) => {
+                                println!("ℹ️  No session was active (unexpected state)");
+                            }
+                            Err(e) => {
+                                eprintln!("❌ Failed to stop current session: {}", e);
+                                eprintln!("   Please run 'docpilot stop' manually first");
+                                std::process::exit(1);
+                            }
+                        }
+                        
+                        println!();
+                        println!("🚀 Now starting new session: {}", description);
+                    }
+                    ConflictAction::Stop => {
+                        println!();
+                        println!("🛑 Stopping current session without generating documentation...");
+                        
+                        match session_manager.stop_session() {
+                            Ok(Some(session)) => {
+                                println!("✅ Session '{}' stopped successfully!", session.description);
+                                stop_session_monitors(&session.id);
+                                append_daily_log(&session).await;
+                                println!("💡 You can generate documentation later with: docpilot generate --session {}", session.id);
                                         
-                                        // Ensure current session is cleared for new session start
-                                        session_manager.clear_current_session();
-                                    }
-                                    Ok(None) => {
-                                        println!("ℹ️  No session was active (unexpected state)");
-                                    }
-                                    Err(e) => {
-                                        eprintln!("❌ Failed to stop current session: {}", e);
-                                        eprintln!("   Please run 'docpilot stop' manually first");
-                                        std::process::exit(1);
-                                    }
-                                }
-                                
-                                println!();
-                                println!("🚀 Now starting new session: {}", description);
+                                // Ensure current session is cleared for new session start
+                                session_manager.clear_current_session();
                             }
-                            "3" | "" => {
-                                println!();
-                                println!("❌ Cancelled. Keeping current session active.");
-                                println!("   Use 'docpilot stop' to end it manually");
-                                println!("   Use 'docpilot status' to see session details");
-                                std::process::exit(0);
+                            Ok(None) => {
+                                println!("ℹ️  No session was active (unexpected state)");
                             }
-                            _ => {
-                                println!();
-                                eprintln!("❌ Invalid choice. Please run the command again and choose 1, 2, or 3.");
+                            Err(e) => {
+                                eprintln!("❌ Failed to stop current session: {}", e);
+                                eprintln!("   Please run 'docpilot stop' manually first");
                                 std::process::exit(1);
                             }
                         }
+                        
+                        println!();
+                        println!("🚀 Now starting new session: {}", description);
                     }
-                    Err(e) => {
-                        eprintln!("❌ Failed to read input: {}", e);
-                        std::process::exit(1);
+                    ConflictAction::Cancel => {
+                        println!();
+                        println!("❌ Cancelled. Keeping current session active.");
+                        println!("   Use 'docpilot stop' to end it manually");
+                        println!("   Use 'docpilot status' to see session details");
+                        std::process::exit(0);
                     }
                 }
             }
 
+            let tty = crate::terminal::PlatformUtils::controlling_tty();
+            ensure_monitor_can_start(tty.as_deref());
+
             println!("🚀 Starting documentation session: {}", description);
             
             let output_path = output.map(|s| std::path::PathBuf::from(s));
@@ -483,6 +1172,15 @@ async fn main() -> Result<()> {
                     println!("   Working directory: {}", std::env::current_dir()
                         .map(|p| p.display().to_string())
                         .unwrap_or_else(|_| "unknown".to_string()));
+                    if let Some(dir) = &only_dir {
+                        println!("   Capturing only commands inside: {}", dir.display());
+                    }
+                    if let Some(dir) = &daily_log {
+                        println!("   Appending to the daily log in: {}", dir.display());
+                    }
+                    if let Some(delay) = capture_delay.filter(|delay| *delay > 0) {
+                        println!("   Commands run in the next {} seconds will not be captured", delay);
+                    }
                     
                     // Create and start terminal monitor
                     let mut monitor = match TerminalMonitor::new(session_id.clone()) {
@@ -501,9 +1199,61 @@ async fn main() -> Result<()> {
                         }
                     };
                     
+                    monitor.set_expand_aliases(expand_aliases);
+                    monitor.set_only_dir(only_dir.clone());
+                    monitor.set_capture_delay(capture_delay.unwrap_or_default());
+                    let nesting_base = track_nesting.then(current_shell_level);
+                    monitor.set_nesting_base(nesting_base);
+                    
+                    let snapshot_file = session_manager.get_current_session().map(|session| session_manager.file_snapshot_path(&session.id));
                     // Update session with monitor information
                     let (shell_type, platform) = if let Some(session) = session_manager.get_current_session_mut() {
                         session.update_from_monitor(&monitor);
+                        if expand_aliases {
+                            session.metadata.settings.insert(EXPAND_ALIASES_SETTING.to_string(), "true".to_string());
+                        }
+                        if let Some(dir) = &only_dir {
+                            session.metadata.settings.insert(ONLY_DIR_SETTING.to_string(), dir.display().to_string());
+                        }
+                        if let Some(dir) = &daily_log {
+                            session.metadata.settings.insert(DAILY_LOG_SETTING.to_string(), dir.display().to_string());
+                        }
+                        if let Some(delay) = capture_delay.filter(|delay| *delay > 0) {
+                            session.metadata.settings.insert(CAPTURE_DELAY_SETTING.to_string(), delay.to_string());
+                        }
+                        if let Some(base) = nesting_base {
+                            session.metadata.settings.insert(TRACK_NESTING_SETTING.to_string(), base.to_string());
+                        }
+                        for tag in &tags {
+                            let _ = session.set_tag(tag, true);
+                        }
+                        // Shells don't usually export these, so they are only there when the user did
+                        for (variable, setting) in [("COLUMNS", TERMINAL_COLUMNS_SETTING), ("LINES", TERMINAL_LINES_SETTING)] {
+                            if let Some(value) = std::env::var(variable).ok().filter(|value| value.parse::<u32>().is_ok_and(|size| size > 0)) {
+                                session.metadata.settings.insert(setting.to_string(), value);
+                            }
+                        }
+                        if let Some(snapshot_file) = snapshot_file.filter(|_| !track_files.is_empty()) {
+                            match FileTracking::start(track_files.clone(), &session.metadata.working_directory, &snapshot_file) {
+                                Ok((tracking, snapshot)) => {
+                                    println!("   Tracking {} files matching: {}", snapshot.files.len(), track_files.join(", "));
+                                    if snapshot.partial {
+                                        println!("   ⚠️  Only the first {} files are tracked; narrow the patterns to track all", MAX_TRACKED_FILES);
+                                    }
+                                    session.metadata.file_tracking = Some(tracking);
+                                }
+                                Err(e) => eprintln!("⚠️  Not tracking files: {}", e),
+                            }
+                        }
+                        if annotate_dangerous {
+                            session.metadata.settings.insert(WARN_DANGEROUS_SETTING.to_string(), "annotate".to_string());
+                        } else if warn_dangerous {
+                            session.metadata.settings.insert(WARN_DANGEROUS_SETTING.to_string(), "warn".to_string());
+                        }
+                        // A background monitor records its own PID once it has started
+                        if foreground {
+                            session.metadata.monitor_pid = Some(std::process::id());
+                        }
                         let session_clone = session.clone();
                         let shell = session.metadata.shell_type.clone();
                         let plat = session.metadata.platform.clone();
@@ -515,6 +1265,9 @@ async fn main() -> Result<()> {
                     
                     println!("   Shell: {}", shell_type);
                     println!("   Platform: {}", platform);
+                    if !tags.is_empty() {
+                        println!("   Tags: {}", tags.iter().map(|tag| tag.trim()).collect::<Vec<_>>().join(", "));
+                    }
                     
                     match monitor.start_monitoring() {
                         Ok(_) => {
@@ -535,8 +1288,12 @@ async fn main() -> Result<()> {
                                 println!("   docpilot status   - Show session status");
                                 println!();
                                 
+                                register_monitor(std::process::id(), &session_id, tty.clone());
+                                
                                 // Monitor commands and add them to session
-                                if let Err(e) = monitor_with_session(&mut monitor, &mut session_manager).await {
+                                let result = monitor_with_session(&mut monitor, &mut session_manager).await;
+                                unregister_monitor(std::process::id());
+                                if let Err(e) = result {
                                     eprintln!("❌ Error during terminal monitoring: {}", e);
                                     if let Some(session) = session_manager.get_current_session_mut() {
                                         session.set_error(format!("Monitoring error: {}", e));
@@ -557,16 +1314,6 @@ async fn main() -> Result<()> {
                                 println!("   docpilot stop     - Stop monitoring and save session");
                                 println!();
                                 
-                                // Create PID file for background process tracking
-                                let home_dir = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-                                let docpilot_dir = PathBuf::from(home_dir).join(".docpilot");
-                                let pid_file = docpilot_dir.join("monitor.pid");
-                                
-                                // Ensure directory exists
-                                if let Err(e) = fs::create_dir_all(&docpilot_dir) {
-                                    eprintln!("⚠️  Warning: Could not create .docpilot directory: {}", e);
-                                }
-                                
                                 println!("✅ DocPilot is now running in the background!");
                                 println!("   Your terminal is free to use normally.");
                                 println!("   All commands will be captured automatically.");
@@ -574,23 +1321,11 @@ async fn main() -> Result<()> {
                                 // Fork the process to run in background
                                 #[cfg(unix)]
                                 {
-                                    use std::process::Command;
-                                    
-                                    // Spawn a new background process
-                                    let mut cmd = Command::new(std::env::current_exe().unwrap_or_else(|_| "docpilot".into()));
-                                    cmd.arg("background-monitor")
-                                        .arg(&session_id)
-                                        .stdin(std::process::Stdio::null())
-                                        .stdout(std::process::Stdio::null())
-                                        .stderr(std::process::Stdio::null());
-                                    
-                                    match cmd.spawn() {
-                                        Ok(child) => {
-                                            let pid = child.id();
-                                            if let Err(e) = fs::write(&pid_file, pid.to_string()) {
-                                                eprintln!("⚠️  Warning: Could not write PID file: {}", e);
-                                            } else {
-                                                println!("📝 Background process PID: {} (saved to {})", pid, pid_file.display());
+                                    match spawn_background_monitor(&session_id) {
+                                        Ok(pid) => {
+                                            if register_monitor(pid, &session_id, tty.clone()) {
+                                                println!("📝 Background process PID: {}{}", pid,
+                                                    tty.as_deref().map(|tty| format!(" (terminal {})", tty)).unwrap_or_default());
                                             }
                                         }
                                         Err(e) => {
@@ -608,14 +1343,12 @@ async fn main() -> Result<()> {
                                     // On non-Unix systems, fall back to foreground mode
                                     eprintln!("⚠️  Background mode not supported on this platform");
                                     eprintln!("   Running in foreground mode instead");
-                                    let pid = process::id();
-                                    if let Err(e) = fs::write(&pid_file, pid.to_string()) {
-                                        eprintln!("⚠️  Warning: Could not write PID file: {}", e);
-                                    }
+                                    let pid = std::process::id();
+                                    register_monitor(pid, &session_id, tty.clone());
                                     if let Err(e) = monitor_with_session(&mut monitor, &mut session_manager).await {
                                         eprintln!("❌ Error during monitoring: {}", e);
                                     }
-                                    let _ = fs::remove_file(&pid_file);
+                                    unregister_monitor(pid);
                                 }
                             }
                         }
@@ -657,46 +1390,68 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::Stop => {
+        Commands::Stop { monitor } => {
             // Try to recover any interrupted sessions first
             if let Ok(Some(recovered_session_id)) = session_manager.recover_session() {
                 println!("🔄 Recovered interrupted session: {}", recovered_session_id);
             }
             
-            // Check for and stop background monitoring process
-            let home_dir = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-            let docpilot_dir = PathBuf::from(home_dir).join(".docpilot");
-            let pid_file = docpilot_dir.join("monitor.pid");
-            
-            if pid_file.exists() {
-                if let Ok(pid_str) = fs::read_to_string(&pid_file) {
-                    if let Ok(pid) = pid_str.trim().parse::<u32>() {
-                        println!("🛑 Stopping background monitoring process (PID: {})...", pid);
-                        
-                        // Try to terminate the background process
-                        #[cfg(unix)]
-                        {
-                            use std::process::Command;
-                            let _ = Command::new("kill")
-                                .arg(pid.to_string())
-                                .output();
-                        }
-                        
-                        #[cfg(windows)]
-                        {
-                            use std::process::Command;
-                            let _ = Command::new("taskkill")
-                                .args(&["/PID", &pid.to_string(), "/F"])
-                                .output();
-                        }
-                        
-                        // Remove PID file
-                        let _ = fs::remove_file(&pid_file);
+            // Check for and stop background monitoring processes
+            let mut registry = match MonitorRegistry::open_default() {
+                Ok(registry) => Some(registry),
+                Err(e) => {
+                    eprintln!("⚠️  Warning: Could not read the monitor registry: {}", e);
+                    None
+                }
+            };
+            if let Some(ref mut registry) = registry {
+                registry.prune();
+            }
+
+            if let Some(target) = monitor {
+                let Some(record) = registry.as_ref().and_then(|registry| registry.find(&target)).cloned() else {
+                    eprintln!("❌ No running monitor matches '{}'", target);
+                    eprintln!("   See running monitors with 'docpilot status --monitors'");
+                    std::process::exit(1);
+                };
+                // Stop the chosen monitor's session rather than whichever was recovered
+                if !record.session_id.is_empty() {
+                    match session_manager.load_session(&record.session_id) {
+                        Ok(session) => session_manager.set_current_session(session),
+                        Err(e) => eprintln!("⚠️  Could not load session {}: {}", record.session_id, e),
                     }
                 }
+                if let Some(ref mut registry) = registry {
+                    stop_monitors(registry, &[record]);
+                }
+            } else if let Some(ref mut registry) = registry {
+                // Monitors imported from an old monitor.pid have no session and belong to whichever is running
+                let monitors: Vec<MonitorRecord> = match session_manager.get_current_session() {
+                    Some(session) => registry.monitors().iter()
+                        .filter(|m| m.session_id == session.id || m.session_id.is_empty())
+                        .cloned()
+                        .collect(),
+                    None => registry.monitors().to_vec(),
+                };
+                stop_monitors(registry, &monitors);
+            }
+
+            // Pick up what the stopped monitors saved on their way out
+            if let Err(e) = session_manager.reload_current_session() {
+                eprintln!("⚠️  Could not reload the session after stopping its monitors: {}", e);
             }
+
+            // A monitor stops its session itself when it exits
+            let stopped = match session_manager.get_current_session() {
+                Some(session) if session.state.is_stopped() => {
+                    let session = session.clone();
+                    session_manager.clear_current_session();
+                    Ok(Some(session))
+                }
+                _ => session_manager.stop_session(),
+            };
             
-            match session_manager.stop_session() {
+            match stopped {
                 Ok(Some(session)) => {
                     println!("🛑 Documentation session stopped successfully!");
                     println!();
@@ -720,13 +1475,23 @@ async fn main() -> Result<()> {
                     println!("   Commands captured: {}", session.stats.total_commands);
                     println!("   Successful commands: {}", session.stats.successful_commands);
                     println!("   Failed commands: {}", session.stats.failed_commands);
+                    let (inferred_successful, inferred_failed) = session.inferred_outcome_counts();
+                    if inferred_successful + inferred_failed > 0 {
+                        println!("   No exit code, inferred from output: {} likely successful, {} likely failed", inferred_successful, inferred_failed);
+                    }
                     println!("   Annotations added: {}", session.stats.total_annotations);
                     if session.stats.pause_resume_count > 0 {
                         println!("   Pause/Resume cycles: {}", session.stats.pause_resume_count);
                     }
                     println!();
-                    if let Some(output_file) = session.output_file {
+                    if let Some(ref output_file) = session.output_file {
                         println!("📄 Output file: {}", output_file.display());
+                        if let Err(e) = crate::output::check_writable(output_file) {
+                            println!("   ⚠️  {}", e);
+                            println!("   'docpilot generate' will write to {} instead", session_output_fallback(&session).display());
+                        }
+                    } else if session.daily_log_dir().is_some() {
+                        append_daily_log(&session).await;
                     } else {
                         println!("📄 No output file specified (use --output next time)");
                     }
@@ -753,8 +1518,12 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     eprintln!("❌ Failed to stop session: {}", e);
-                    eprintln!("   The session may be in an inconsistent state");
-                    eprintln!("   Use 'docpilot status' to check session state");
+                    if let Some(SessionError::InvalidTransition { .. }) = e.downcast_ref::<SessionError>() {
+                        eprintln!("   Start a new session with 'docpilot start \"description\"'");
+                    } else {
+                        eprintln!("   The session may be in an inconsistent state");
+                        eprintln!("   Use 'docpilot status' to check session state");
+                    }
                 }
             }
         }
@@ -777,10 +1546,16 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     eprintln!("❌ Failed to pause session: {}", e);
-                    if e.to_string().contains("No active session") {
-                        eprintln!("   Start a session first with 'docpilot start \"description\"'");
-                    } else {
-                        eprintln!("   Use 'docpilot status' to check the current session state");
+                    match e.downcast_ref::<SessionError>() {
+                        Some(SessionError::NoActiveSession) => {
+                            eprintln!("   Start a session first with 'docpilot start \"description\"'");
+                        }
+                        Some(SessionError::InvalidTransition { from: SessionState::Paused, .. }) => {
+                            eprintln!("   Use 'docpilot resume' to continue monitoring");
+                        }
+                        _ => {
+                            eprintln!("   Use 'docpilot status' to check the current session state");
+                        }
                     }
                     std::process::exit(1);
                 }
@@ -806,17 +1581,59 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     eprintln!("❌ Failed to resume session: {}", e);
-                    if e.to_string().contains("No active session") {
-                        eprintln!("   Start a session first with 'docpilot start \"description\"'");
-                    } else if e.to_string().contains("Cannot resume") {
-                        eprintln!("   The session may not be in a paused state");
-                        eprintln!("   Use 'docpilot status' to check the current session state");
+                    match e.downcast_ref::<SessionError>() {
+                        Some(SessionError::NoActiveSession) => {
+                            eprintln!("   Start a session first with 'docpilot start \"description\"'");
+                        }
+                        Some(SessionError::InvalidTransition { from: SessionState::Active, .. }) => {
+                            eprintln!("   The session is already capturing commands");
+                        }
+                        Some(SessionError::InvalidTransition { .. }) => {
+                            eprintln!("   Start a new session with 'docpilot start \"description\"'");
+                        }
+                        _ => {}
+                    }
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Annotate { delete: Some(annotation_id), .. } => {
+            if let Some(session) = session_manager.get_current_session() {
+                exit_if_recording(session, "delete the annotation");
+            }
+            match session_manager.remove_annotation(&annotation_id) {
+                Ok(annotation) => {
+                    println!("🗑️  Annotation deleted: \"{}\"", annotation.text);
+                    if let Some(session) = session_manager.get_current_session() {
+                        println!("   Total annotations: {}", session.stats.total_annotations);
                     }
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to delete annotation: {}", e);
+                    print_annotation_change_hint(&e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Annotate { text: Some(text), edit: Some(annotation_id), force, .. } => {
+            if let Some(session) = session_manager.get_current_session() {
+                exit_if_recording(session, "edit the annotation");
+            }
+            let text = review_annotation_for_secrets(text, force);
+            match session_manager.update_annotation(&annotation_id, text.clone()) {
+                Ok(()) => {
+                    println!("✏️  Annotation updated");
+                    println!("   ID: {}", annotation_id);
+                    println!("   Text: \"{}\"", text);
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to edit annotation: {}", e);
+                    print_annotation_change_hint(&e);
                     std::process::exit(1);
                 }
             }
         }
-        Commands::Annotate { text, annotation_type } => {
+        Commands::Annotate { text: Some(text), annotation_type, force, .. } => {
             // Parse annotation type
             let parsed_type = match annotation_type.to_lowercase().as_str() {
                 "note" | "n" => AnnotationType::Note,
@@ -831,6 +1648,8 @@ async fn main() -> Result<()> {
                 }
             };
 
+            let text = review_annotation_for_secrets(text, force);
+
             match session_manager.add_annotation(text.clone(), parsed_type.clone()) {
                 Ok(annotation_id) => {
                     if let Some(session) = session_manager.get_current_session() {
@@ -870,7 +1689,7 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     eprintln!("❌ Failed to add annotation: {}", e);
-                    if e.to_string().contains("No active session") {
+                    if let Some(SessionError::NoActiveSession) = e.downcast_ref::<SessionError>() {
                         eprintln!("   Start a session first with 'docpilot start \"description\"'");
                         eprintln!("   Annotations can only be added to active sessions");
                     } else {
@@ -880,7 +1699,56 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::Annotations { recent, filter_type } => {
+        Commands::Annotate { .. } => unreachable!("clap requires the text unless --delete is given"),
+        Commands::Annotations { action: Some(AnnotationsAction::Export { session, format, by_type, filter_type, output }), .. } => {
+            let format = match crate::output::annotations::AnnotationExportFormat::from_str(&format) {
+                Ok(format) => format,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let filter = match filter_type.as_deref().map(parse_annotation_filter) {
+                Some(Err(e)) => {
+                    eprintln!("❌ {}", e);
+                    eprintln!("   Valid types: note, explanation, warning, milestone");
+                    std::process::exit(1);
+                }
+                Some(Ok(filter)) => Some(filter),
+                None => None,
+            };
+
+            let session = match session {
+                Some(session_id) => session_manager.load_session(&session_id).ok(),
+                None => current_or_latest_session(&mut session_manager),
+            };
+            let Some(mut session) = session else {
+                eprintln!("❌ No session found to export annotations from");
+                std::process::exit(1);
+            };
+            if let Some(filter) = &filter {
+                session.annotations.retain(|a| std::mem::discriminant(&a.annotation_type) == std::mem::discriminant(filter));
+            }
+
+            let grouping = if by_type {
+                crate::output::markdown::AnnotationGrouping::ByType
+            } else {
+                crate::output::markdown::AnnotationGrouping::Chronological
+            };
+            let exported = crate::output::annotations::export_annotations(&session, format, grouping)?;
+
+            match output {
+                Some(path) => {
+                    if let Err(e) = fs::write(&path, &exported) {
+                        eprintln!("❌ Failed to write {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    }
+                    println!("✅ Exported {} annotations to {}", session.annotations.len(), path.display());
+                }
+                None => print!("{}", exported),
+            }
+        }
+        Commands::Annotations { recent, filter_type, .. } => {
             if let Some(session) = session_manager.get_current_session() {
                 if session.annotations.is_empty() {
                     println!("📝 No annotations found in current session");
@@ -893,21 +1761,15 @@ async fn main() -> Result<()> {
                 
                 // Filter by type if specified
                 if let Some(ref filter) = filter_type {
-                    let filter_type = match filter.to_lowercase().as_str() {
-                        "note" | "n" => Some(AnnotationType::Note),
-                        "explanation" | "explain" | "e" => Some(AnnotationType::Explanation),
-                        "warning" | "warn" | "w" => Some(AnnotationType::Warning),
-                        "milestone" | "mile" | "m" => Some(AnnotationType::Milestone),
-                        _ => {
-                            eprintln!("❌ Invalid filter type: {}", filter);
+                    let ft = match parse_annotation_filter(filter) {
+                        Ok(ft) => ft,
+                        Err(e) => {
+                            eprintln!("❌ {}", e);
                             eprintln!("   Valid types: note, explanation, warning, milestone");
                             return Ok(());
                         }
                     };
-                    
-                    if let Some(ft) = filter_type {
-                        annotations.retain(|a| std::mem::discriminant(&a.annotation_type) == std::mem::discriminant(&ft));
-                    }
+                    annotations.retain(|a| std::mem::discriminant(&a.annotation_type) == std::mem::discriminant(&ft));
                 }
 
                 // Sort by timestamp (newest first)
@@ -953,19 +1815,201 @@ async fn main() -> Result<()> {
                 println!("   Then add annotations with 'docpilot annotate \"your text\"'");
             }
         }
-        Commands::Note { text } => {
+        Commands::Note { text, force } => {
+            let text = review_annotation_for_secrets(text, force);
             handle_quick_annotation(&mut session_manager, text, AnnotationType::Note, "📝", "Note").await;
         }
-        Commands::Explain { text } => {
+        Commands::Explain { text, force } => {
+            let text = review_annotation_for_secrets(text, force);
             handle_quick_annotation(&mut session_manager, text, AnnotationType::Explanation, "💡", "Explanation").await;
         }
-        Commands::Warn { text } => {
+        Commands::Warn { text, force } => {
+            let text = review_annotation_for_secrets(text, force);
             handle_quick_annotation(&mut session_manager, text, AnnotationType::Warning, "⚠️", "Warning").await;
         }
-        Commands::Milestone { text } => {
+        Commands::Milestone { text, force } => {
+            let text = review_annotation_for_secrets(text, force);
             handle_quick_annotation(&mut session_manager, text, AnnotationType::Milestone, "🎯", "Milestone").await;
         }
-        Commands::Config { provider, api_key, base_url } => {
+        Commands::TagCommand { number, tag, session, remove } => {
+            let session = match session {
+                Some(session_id) => match session_manager.load_session(&session_id) {
+                    Ok(session) => Some(session),
+                    Err(e) => {
+                        eprintln!("❌ Failed to load session '{}': {}", session_id, e);
+                        std::process::exit(1);
+                    }
+                },
+                None => current_or_latest_session(&mut session_manager),
+            };
+            let Some(session) = session else {
+                eprintln!("❌ No session found");
+                eprintln!("   Start a session first with 'docpilot start \"description\"'");
+                std::process::exit(1);
+            };
+            exit_if_recording(&session, "tag the command");
+
+            match session_manager.set_command_tag(&session.id, number, &tag, !remove) {
+                Ok(changed) => {
+                    let command = &session.commands[number - 1].command;
+                    match (remove, changed) {
+                        (false, true) => println!("🏷️  Tagged command #{} `{}` as '{}'", number, command, tag.trim()),
+                        (false, false) => println!("ℹ️  Command #{} is already tagged '{}'", number, tag.trim()),
+                        (true, true) => println!("🏷️  Removed tag '{}' from command #{} `{}`", tag.trim(), number, command),
+                        (true, false) => println!("ℹ️  Command #{} has no tag '{}'", number, tag.trim()),
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to tag command: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Tag { action } => {
+            let (tag, session, tagged) = match action {
+                TagAction::Add { tag, session } => (tag, session, true),
+                TagAction::Remove { tag, session } => (tag, session, false),
+            };
+            let session = match session {
+                Some(session_id) => match session_manager.load_session(&session_id) {
+                    Ok(session) => Some(session),
+                    Err(e) => {
+                        eprintln!("❌ Failed to load session '{}': {}", session_id, e);
+                        std::process::exit(1);
+                    }
+                },
+                None => current_or_latest_session(&mut session_manager),
+            };
+            let Some(session) = session else {
+                eprintln!("❌ No session found");
+                eprintln!("   Start a session first with 'docpilot start \"description\"'");
+                std::process::exit(1);
+            };
+            exit_if_recording(&session, "tag it");
+
+            match session_manager.set_session_tag(&session.id, &tag, tagged) {
+                Ok(changed) => match (tagged, changed) {
+                    (true, true) => println!("🏷️  Tagged session '{}' as '{}'", session.description, tag.trim()),
+                    (true, false) => println!("ℹ️  Session '{}' is already tagged '{}'", session.description, tag.trim()),
+                    (false, true) => println!("🏷️  Removed tag '{}' from session '{}'", tag.trim(), session.description),
+                    (false, false) => println!("ℹ️  Session '{}' has no tag '{}'", session.description, tag.trim()),
+                },
+                Err(e) => {
+                    eprintln!("❌ Failed to tag session: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Pin { session_id } => set_session_pinned(&mut session_manager, &session_id, true),
+        Commands::Unpin { session_id } => set_session_pinned(&mut session_manager, &session_id, false),
+        Commands::Config { provider, api_key, base_url, model, show_keys, always_overwrite, max_monitors, keychain, spill_output_over, spill_dir, migrate, redact } => {
+            if migrate {
+                match LlmConfig::migrate() {
+                    Ok(ConfigMigration::Missing) => println!("No config file yet; one in the current format is created when you first configure DocPilot"),
+                    Ok(ConfigMigration::UpToDate(version)) => println!("✅ Config file is already in the current format (version {})", version),
+                    Ok(ConfigMigration::Migrated { from, backup }) => {
+                        println!("✅ Config file upgraded from version {} to {}", from, CONFIG_VERSION);
+                        println!("   Old file kept at: {}", backup.display());
+                    }
+                    Err(e) => {
+                        eprintln!("❌ Failed to migrate configuration: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
+
+            if !redact.is_empty() {
+                let mut filter_config = match crate::filter::FilterConfig::load() {
+                    Ok(filter_config) => filter_config,
+                    Err(e) => {
+                        eprintln!("❌ {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                let mut added = Vec::new();
+                for pattern in &redact {
+                    match filter_config.add_redact_pattern(pattern) {
+                        Ok(true) => added.push(pattern),
+                        Ok(false) => println!("ℹ️  Already redacting '{}'", pattern),
+                        Err(e) => {
+                            eprintln!("❌ {}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                if !added.is_empty() {
+                    if let Err(e) = filter_config.save() {
+                        eprintln!("❌ Failed to save filter configuration: {}", e);
+                        std::process::exit(1);
+                    }
+                    for pattern in added {
+                        println!("🔒 Text matching '{}' will be replaced with [REDACTED] in generated documentation", pattern);
+                    }
+                }
+                if provider.is_none() && api_key.is_none() && base_url.is_none() && model.is_none() && always_overwrite.is_none() && max_monitors.is_none()
+                    && keychain.is_none() && spill_output_over.is_none() && spill_dir.is_none()
+                {
+                    return Ok(());
+                }
+            }
+
+            if always_overwrite.is_some() || max_monitors.is_some() || spill_output_over.is_some() || spill_dir.is_some() {
+                if max_monitors == Some(0) {
+                    eprintln!("❌ --max-monitors must be at least 1");
+                    std::process::exit(1);
+                }
+                let mut settings = match Settings::load() {
+                    Ok(settings) => settings,
+                    Err(e) => {
+                        eprintln!("❌ {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                if let Some(always_overwrite) = always_overwrite {
+                    settings.always_overwrite = always_overwrite;
+                }
+                if let Some(max_monitors) = max_monitors {
+                    settings.max_monitors = Some(max_monitors);
+                }
+                if let Some(size) = &spill_output_over {
+                    match crate::llm::cache::parse_size(size) {
+                        Ok(0) => settings.spill_output_over = None,
+                        Ok(bytes) => settings.spill_output_over = Some(bytes),
+                        Err(e) => {
+                            eprintln!("❌ {}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                if let Some(dir) = spill_dir.clone() {
+                    settings.spill_dir = Some(std::path::absolute(&dir).unwrap_or(dir));
+                }
+                if let Err(e) = settings.save() {
+                    eprintln!("❌ Failed to save settings: {}", e);
+                    std::process::exit(1);
+                }
+
+                match always_overwrite {
+                    Some(true) => println!("Existing output files will be overwritten without asking"),
+                    Some(false) => println!("You will be asked before existing output files are overwritten"),
+                    None => {}
+                }
+                if let Some(max_monitors) = max_monitors {
+                    println!("Up to {} background monitor(s) may run at once", max_monitors);
+                }
+                if spill_output_over.is_some() || spill_dir.is_some() {
+                    match settings.output_spill() {
+                        Some(spill) => println!("Captured output over {} is kept in sidecar files in {}",
+                                                crate::llm::cache::format_size(spill.threshold), spill.directory.display()),
+                        None => println!("Captured output is kept in the session however large it is"),
+                    }
+                }
+                if provider.is_none() && api_key.is_none() && base_url.is_none() && model.is_none() && keychain.is_none() {
+                    return Ok(());
+                }
+            }
+
             let mut config = match LlmConfig::load() {
                 Ok(c) => c,
                 Err(e) => {
@@ -974,6 +2018,47 @@ async fn main() -> Result<()> {
                 }
             };
 
+            if let Some(keychain) = keychain {
+                config.use_keychain = keychain;
+                if let Err(e) = config.save() {
+                    eprintln!("Failed to save configuration: {}", e);
+                    return Ok(());
+                }
+                // Saving falls back to the config file when there is no keychain, which it has warned about
+                let in_keychain = LlmConfig::load()
+                    .map(|saved| saved.providers.values().all(|p| p.keychain_account.is_some() || p.api_key.is_empty()))
+                    .unwrap_or(false);
+                if !keychain {
+                    println!("API keys are stored in the config file");
+                } else if in_keychain {
+                    println!("API keys are stored in the OS keychain");
+                }
+                if provider.is_none() && api_key.is_none() && base_url.is_none() && model.is_none() {
+                    return Ok(());
+                }
+            }
+
+            if let Some(model) = model {
+                // The model belongs to the provider being selected, or to the default one
+                let Some(model_provider) = provider.clone().or_else(|| config.get_default_provider().map(|s| s.to_string())) else {
+                    eprintln!("No default provider set. Please specify a provider with --provider");
+                    std::process::exit(1);
+                };
+                if let Err(e) = LlmProvider::from_str(&model_provider) {
+                    eprintln!("Invalid provider: {}", e);
+                    std::process::exit(1);
+                }
+                config.set_model(&model_provider, model.clone());
+                if let Err(e) = config.save() {
+                    eprintln!("Failed to save configuration: {}", e);
+                    return Ok(());
+                }
+                println!("Updated model for {} to {}", model_provider, model);
+                if provider.is_none() && api_key.is_none() && base_url.is_none() {
+                    return Ok(());
+                }
+            }
+
             match (&provider, &api_key, &base_url) {
                 (Some(p), Some(key), Some(url)) => {
                     // Set provider, API key, and base URL
@@ -1109,6 +2194,10 @@ async fn main() -> Result<()> {
                 }
                 (None, None, None) => {
                     // Show current configuration
+                    if show_keys {
+                        println!("⚠️  Showing full API keys. Make sure nobody can see your screen and this output isn't logged.");
+                        println!();
+                    }
                     println!("Current LLM Configuration:");
                     println!("========================");
                     
@@ -1124,13 +2213,20 @@ async fn main() -> Result<()> {
                         println!("  None");
                     } else {
                         for provider in providers {
-                            let has_key = config.get_api_key(provider).map_or(false, |k| !k.is_empty());
+                            let mut key_display = match config.get_api_key(provider) {
+                                Some(key) if !key.is_empty() && show_keys => format!("✓ ({})", key),
+                                Some(key) if !key.is_empty() => format!("✓ ({})", crate::llm::config::mask_api_key(key)),
+                                _ => "✗".to_string(),
+                            };
+                            if config.providers.get(provider).is_some_and(|p| p.keychain_account.is_some()) {
+                                key_display.push_str(" in keychain");
+                            }
                             let model = config.get_model(provider).unwrap_or("default");
                             let base_url = config.get_base_url(provider);
                             
                             print!("  {} - API Key: {} - Model: {}",
                                    provider,
-                                   if has_key { "✓" } else { "✗" },
+                                   key_display,
                                    model);
                             
                             if let Some(url) = base_url {
@@ -1140,6 +2236,32 @@ async fn main() -> Result<()> {
                         }
                     }
                     
+                    match Settings::load() {
+                        Ok(settings) => {
+                            println!("\nAlways overwrite output files: {}", if settings.always_overwrite { "yes" } else { "no (ask first)" });
+                            println!("Maximum background monitors: {}", settings.max_monitors());
+                            match settings.output_spill() {
+                                Some(spill) => println!("Large output: over {} goes to sidecar files in {}",
+                                                        crate::llm::cache::format_size(spill.threshold), spill.directory.display()),
+                                None => println!("Large output: kept in the session"),
+                            }
+                        }
+                        Err(e) => eprintln!("\nSettings: {}", e),
+                    }
+                    println!("API key storage: {}", if config.use_keychain { "OS keychain" } else { "config file" });
+                    match crate::filter::FilterConfig::load() {
+                        Ok(filter_config) => {
+                            if !filter_config.redact_patterns.is_empty() {
+                                println!("Redacted from generated docs: {}", filter_config.redact_patterns.iter()
+                                    .map(|pattern| format!("'{}'", pattern)).collect::<Vec<_>>().join(", "));
+                            }
+                            if matches!(filter_config.privacy_mode, Some(crate::filter::PrivacyMode::Strict)) {
+                                println!("Secrets in generated docs: collapsed (strict privacy mode)");
+                            }
+                        }
+                        Err(e) => eprintln!("Filter configuration: {}", e),
+                    }
+
                     // Show validation warnings
                     match config.validate() {
                         Ok(warnings) => {
@@ -1155,108 +2277,610 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::Generate { output, session, template } => {
-            // Handle the generate command
-            let session_to_use = if let Some(session_id) = session {
-                // Load specific session
-                match session_manager.load_session(&session_id) {
+        Commands::Verify { against, session, allow_extra } => {
+            let procedure = match crate::filter::Procedure::load(std::path::Path::new(&against)) {
+                Ok(procedure) => procedure,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let session_to_use = match session {
+                Some(session_id) => match session_manager.load_session(&session_id) {
                     Ok(session) => Some(session),
                     Err(e) => {
                         eprintln!("❌ Failed to load session '{}': {}", session_id, e);
-                        eprintln!("   Use 'docpilot status' to see available sessions");
-                        return Ok(());
+                        std::process::exit(1);
                     }
-                }
-            } else {
-                // Use current session or most recent completed session
-                session_manager.get_current_session().cloned()
-                    .or_else(|| {
-                        // Try to get the most recent completed session by modification time
-                        session_manager.list_sessions()
-                            .ok()
-                            .and_then(|sessions| {
-                                // Sort sessions by modification time (most recent first)
-                                let mut session_with_times: Vec<_> = sessions.into_iter()
-                                    .filter_map(|session_id| {
-                                        let session_file = SessionManager::get_sessions_directory()
-                                            .ok()?
-                                            .join(format!("{}.json", session_id));
-                                        let metadata = std::fs::metadata(&session_file).ok()?;
-                                        let modified = metadata.modified().ok()?;
-                                        Some((session_id, modified))
-                                    })
-                                    .collect();
-                                
-                                session_with_times.sort_by(|a, b| b.1.cmp(&a.1));
-                                session_with_times.first().map(|(id, _)| id.clone())
-                            })
-                            .and_then(|session_id| session_manager.load_session(&session_id).ok())
-                    })
+                },
+                None => current_or_latest_session(&mut session_manager),
             };
-
-            let session = match session_to_use {
-                Some(s) => s,
-                None => {
-                    eprintln!("❌ No session found to generate documentation from");
-                    eprintln!("   Start a session with 'docpilot start \"description\"'");
-                    eprintln!("   Or specify a session ID with --session");
-                    return Ok(());
-                }
+            let Some(session) = session_to_use else {
+                eprintln!("❌ No session found to verify");
+                eprintln!("   Specify a session ID with --session");
+                std::process::exit(1);
             };
 
-            // Determine output file
-            let output_file = if let Some(output_path) = output {
-                let path = std::path::PathBuf::from(output_path);
-                // If we're in a test environment and path is relative, make it relative to HOME
-                if path.is_relative() && is_test_environment() {
-                    if let Ok(home) = std::env::var("HOME") {
-                        std::path::PathBuf::from(home).join(path)
-                    } else {
-                        path
-                    }
-                } else {
-                    path
-                }
-            } else if let Some(ref session_output) = session.output_file {
-                session_output.clone()
-            } else {
-                // Generate a default filename based on session description
-                let sanitized_desc = session.description
-                    .chars()
-                    .map(|c| if c.is_alphanumeric() || c == ' ' { c } else { ' ' })
-                    .collect::<String>()
-                    .split_whitespace()
-                    .collect::<Vec<_>>()
-                    .join("-")
-                    .to_lowercase();
-                let filename = format!("{}.md", sanitized_desc);
-                // If we're in a test environment, write to HOME directory
-                if is_test_environment() {
-                    if let Ok(home) = std::env::var("HOME") {
-                        std::path::PathBuf::from(home).join(filename)
-                    } else {
-                        std::path::PathBuf::from(filename)
-                    }
-                } else {
-                    std::path::PathBuf::from(filename)
+            let filter = match crate::filter::CommandFilter::with_user_config() {
+                Ok(filter) => filter,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
                 }
             };
+            let commands = filter.get_filtered_commands(&session.commands);
+            let report = crate::filter::check_conformance(&procedure, &commands, &filter);
 
-            println!("📄 Generating documentation from session: {}", session.description);
-            println!("   Session ID: {}", session.id);
-            println!("   Template: {}", template);
-            println!("   Output file: {}", output_file.display());
+            println!("🔍 Verifying '{}' against {}", session.description, against);
+            println!("   Procedure steps: {}, checked commands: {}", procedure.steps.len(), commands.len());
             println!();
 
-            // Generate the documentation using the output module
-            match crate::output::generate_documentation(&session, &output_file, &template).await {
-                Ok(_) => {
-                    println!("✅ Documentation generated successfully!");
-                    println!("📊 Session Statistics:");
-                    println!("   Commands captured: {}", session.stats.total_commands);
-                    println!("   Annotations added: {}", session.stats.total_annotations);
-                    if let Some(duration) = session.get_duration_seconds() {
-                        let hours = duration / 3600;
+            if !report.missing.is_empty() {
+                println!("❌ Missing steps:");
+                for (position, command) in &report.missing {
+                    println!("   {}. {}", position, command);
+                }
+            }
+            if !report.out_of_order.is_empty() {
+                println!("🔀 Out-of-order steps:");
+                for step in &report.out_of_order {
+                    println!("   {}. {} (ran as command {})", step.expected_position, step.command, step.actual_position);
+                }
+            }
+            if !report.extra.is_empty() {
+                let icon = if allow_extra { "ℹ️ " } else { "➕" };
+                println!("{} Extra commands not in the procedure:", icon);
+                for (position, command) in &report.extra {
+                    println!("   {}. {}", position, command);
+                }
+            }
+
+            if report.passed(allow_extra) {
+                println!("✅ Session conforms to the procedure ({}/{} steps matched)", report.matched, procedure.steps.len());
+            } else {
+                println!();
+                println!("❌ Session does not conform to the procedure ({}/{} steps matched in order)", report.matched, procedure.steps.len());
+                std::process::exit(1);
+            }
+        }
+        Commands::Replay { session, force } => {
+            let session_to_use = match session {
+                Some(session_id) => match session_manager.load_session(&session_id) {
+                    Ok(session) => Some(session),
+                    Err(e) => {
+                        eprintln!("❌ Failed to load session '{}': {}", session_id, e);
+                        std::process::exit(1);
+                    }
+                },
+                None => current_or_latest_session(&mut session_manager),
+            };
+            let Some(session) = session_to_use else {
+                eprintln!("❌ No session found to replay");
+                eprintln!("   Specify a session ID with --session");
+                std::process::exit(1);
+            };
+
+            let filter = match crate::filter::CommandFilter::with_user_config() {
+                Ok(filter) => filter,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            println!("🔁 Replaying {} commands of '{}'", session.commands.len(), session.description);
+            if force {
+                println!("⚠️  --force runs every command, including ones that change files or systems");
+            }
+            println!();
+
+            let report = crate::filter::replay_commands(&session.commands, &filter, force, |step| {
+                let exit_code = |code: Option<i32>| code.map_or("?".to_string(), |code| code.to_string());
+                match step.outcome {
+                    crate::filter::ReplayOutcome::Skipped => {
+                        println!("   ⏭️  {}. {} (skipped: not safe to re-execute, use --force to run it)", step.position, step.command);
+                    }
+                    _ if step.timed_out => {
+                        println!("   ⏱️  {}. {} (killed after {}s, recorded {})", step.position, step.command,
+                                 crate::filter::replay::REPLAY_TIMEOUT.as_secs(), exit_code(step.recorded_exit_code));
+                    }
+                    _ => {
+                        println!("   ▶️  {}. {} (exit {}, recorded {})", step.position, step.command, exit_code(step.exit_code), exit_code(step.recorded_exit_code));
+                    }
+                }
+            });
+
+            println!();
+            println!("📋 Replay report:");
+            let sections = [
+                (crate::filter::ReplayOutcome::StillSucceeds, "✅ Still succeed"),
+                (crate::filter::ReplayOutcome::NowFails, "❌ Now fail"),
+                (crate::filter::ReplayOutcome::NowSucceeds, "🔧 Failed when recorded, succeed now"),
+                (crate::filter::ReplayOutcome::StillFails, "➖ Failed when recorded and still fail"),
+                (crate::filter::ReplayOutcome::Unrecorded, "❔ No recorded exit code"),
+            ];
+            for (outcome, label) in sections {
+                let steps = report.with_outcome(outcome);
+                if steps.is_empty() {
+                    continue;
+                }
+                println!("{} ({}):", label, steps.len());
+                for step in steps {
+                    println!("   {}. {}", step.position, step.command);
+                }
+            }
+            let skipped = report.with_outcome(crate::filter::ReplayOutcome::Skipped).len();
+            if skipped > 0 {
+                println!("⏭️  Skipped {} commands that are not safe to re-execute", skipped);
+            }
+
+            if !report.passed() {
+                println!();
+                println!("❌ Some commands that succeeded when recorded fail now");
+                std::process::exit(1);
+            }
+        }
+        Commands::Merge { sessions, output } => {
+            let mut loaded = Vec::new();
+            for session_id in &sessions {
+                match session_manager.load_session(session_id) {
+                    Ok(session) => loaded.push(session),
+                    Err(e) => {
+                        eprintln!("❌ Failed to load session '{}': {}", session_id, e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            let merged = match Session::merge(&loaded) {
+                Ok(merged) => merged,
+                Err(e) => {
+                    eprintln!("❌ Failed to merge sessions: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if let Err(e) = session_manager.save_session(&merged) {
+                eprintln!("❌ Failed to save merged session: {}", e);
+                std::process::exit(1);
+            }
+
+            println!("🧩 Merged {} sessions into '{}'", loaded.len(), merged.description);
+            println!("   Session ID: {}", merged.id);
+            println!("   Commands: {}, annotations: {}", merged.stats.total_commands, merged.stats.total_annotations);
+            if let Some(duration) = merged.stats.duration_seconds {
+                println!("   Duration: {}", format_elapsed(duration as i64));
+            }
+
+            if let Some(output) = output {
+                if let Err(e) = session_manager.export_session(&merged.id, std::path::Path::new(&output)) {
+                    eprintln!("❌ Failed to write merged session to {}: {}", output, e);
+                    std::process::exit(1);
+                }
+                println!("💾 Saved merged session to {}", output);
+            }
+            println!("   Generate documentation with: docpilot generate --session {}", merged.id);
+        }
+        Commands::Profile { action } => {
+            let mut settings = match Settings::load() {
+                Ok(settings) => settings,
+                Err(e) => {
+                    eprintln!("❌ Failed to load settings: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            match action {
+                ProfileAction::Save { name, options } => {
+                    if name.trim().is_empty() {
+                        eprintln!("❌ Profile name cannot be empty");
+                        std::process::exit(1);
+                    }
+                    if let Err(e) = options.validate() {
+                        eprintln!("❌ {}", e);
+                        std::process::exit(1);
+                    }
+                    let args = options.to_args();
+                    let replaced = settings.profiles.insert(name.clone(), *options).is_some();
+                    if let Err(e) = settings.save() {
+                        eprintln!("❌ Failed to save settings: {}", e);
+                        std::process::exit(1);
+                    }
+                    println!("✅ {} profile '{}'", if replaced { "Updated" } else { "Saved" }, name);
+                    if args.is_empty() {
+                        println!("   It sets no options; generate will use its defaults");
+                    } else {
+                        println!("   Options: {}", args.join(" "));
+                    }
+                    println!("💡 Use it with: docpilot generate --profile {}", name);
+                }
+                ProfileAction::List => {
+                    if settings.profiles.is_empty() {
+                        println!("🎛️  No saved profiles");
+                        println!("   Save one with: docpilot profile save <NAME> --template technical --front-matter ...");
+                        return Ok(());
+                    }
+
+                    println!("🎛️  Saved Profiles");
+                    println!("=================");
+                    for (name, options) in &settings.profiles {
+                        let args = options.to_args();
+                        println!("  {:<20}  {}", name, if args.is_empty() { "(no options)".to_string() } else { args.join(" ") });
+                    }
+                }
+                ProfileAction::Delete { name } => {
+                    if settings.profiles.remove(&name).is_none() {
+                        eprintln!("❌ No profile named '{}'", name);
+                        eprintln!("   See saved profiles with 'docpilot profile list'");
+                        std::process::exit(1);
+                    }
+                    if let Err(e) = settings.save() {
+                        eprintln!("❌ Failed to save settings: {}", e);
+                        std::process::exit(1);
+                    }
+                    println!("🗑️  Deleted profile '{}'", name);
+                }
+            }
+        }
+        Commands::Cache { action } => {
+            use crate::llm::cache::{AnalysisCache, format_age, format_size, parse_age};
+
+            let cache = match AnalysisCache::open_default() {
+                Ok(cache) => cache,
+                Err(e) => {
+                    eprintln!("❌ Failed to locate analysis cache: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            match action {
+                CacheAction::List => {
+                    let entries = cache.entries()?;
+                    if entries.is_empty() {
+                        println!("🗄️  Analysis cache is empty ({})", cache.directory().display());
+                        return Ok(());
+                    }
+
+                    println!("🗄️  Cached Analysis Results");
+                    println!("==========================");
+                    for entry in &entries {
+                        let command = if entry.command.chars().count() > 50 {
+                            format!("{}...", entry.command.chars().take(47).collect::<String>())
+                        } else {
+                            entry.command.clone()
+                        };
+                        println!("  {:<50}  {:<18}  {:>9}  {:>4} old",
+                                command, entry.analysis_type, format_size(entry.size_bytes), format_age(entry.created_at));
+                    }
+                    println!();
+                    println!("Total: {} entries", entries.len());
+                }
+                CacheAction::Clear { older_than } => {
+                    let older_than = match older_than.as_deref().map(parse_age) {
+                        Some(Ok(age)) => Some(age),
+                        Some(Err(e)) => {
+                            eprintln!("❌ {}", e);
+                            std::process::exit(1);
+                        }
+                        None => None,
+                    };
+
+                    let (removed, freed) = cache.clear(older_than)?;
+                    if removed == 0 {
+                        println!("✅ Nothing to remove from the analysis cache");
+                    } else {
+                        println!("🗑️  Removed {} cached {} ({} freed)",
+                                removed, if removed == 1 { "entry" } else { "entries" }, format_size(freed));
+                    }
+                }
+                CacheAction::Stats => {
+                    let entries = cache.entries()?;
+                    let stats = cache.stats()?;
+                    let total_size: u64 = entries.iter().map(|entry| entry.size_bytes).sum();
+
+                    println!("🗄️  Analysis Cache");
+                    println!("=================");
+                    println!("Location: {}", cache.directory().display());
+                    println!("Entries: {}", entries.len());
+                    println!("Disk usage: {}", format_size(total_size));
+                    if let Some(oldest) = entries.last() {
+                        println!("Oldest entry: {} old", format_age(oldest.created_at));
+                    }
+                    match stats.hit_rate() {
+                        Some(rate) => println!("Hit rate: {:.0}% ({} hits, {} misses)", rate * 100.0, stats.hits, stats.misses),
+                        None => println!("Hit rate: no lookups recorded yet"),
+                    }
+                }
+            }
+        }
+        Commands::Generate { output, session, profile, baseline, options, timings, force, incremental, fail_on_errors, max_failures } => {
+            let options = match profile {
+                None => *options,
+                Some(name) => match Settings::load().ok().and_then(|mut settings| settings.profiles.remove(&name)) {
+                    Some(saved) => {
+                        println!("🎛️  Using profile '{}'", name);
+                        options.over(&saved)
+                    }
+                    None => {
+                        eprintln!("❌ No profile named '{}'", name);
+                        eprintln!("   See saved profiles with 'docpilot profile list'");
+                        std::process::exit(1);
+                    }
+                },
+            };
+            let GenerateProfile { template, split_by, ai_mode, ai_min_significance, context, context_file, manifest, language, focus_failures, split_chains, mock_llm, max_inline_command, no_filter, only_successful, exclude_patterns, prerequisites, no_prerequisites, number_annotations_per_type, no_dir_changes, front_matter, collapse_repeats, resolve_scripts, raw_output, polish_annotations, group_by, tags, step_numbers, gap_threshold, glossary, show_nesting, toc_depth } = options;
+            let template = template.unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+            let prerequisites = prerequisites.or(if no_prerequisites == Some(true) { Some(false) } else { None });
+            let [manifest, focus_failures, split_chains, mock_llm, no_filter, only_successful, number_annotations_per_type, no_dir_changes, front_matter, collapse_repeats, resolve_scripts, raw_output, polish_annotations, step_numbers, glossary, show_nesting] =
+                [manifest, focus_failures, split_chains, mock_llm, no_filter, only_successful, number_annotations_per_type, no_dir_changes, front_matter, collapse_repeats, resolve_scripts, raw_output, polish_annotations, step_numbers, glossary, show_nesting].map(Option::unwrap_or_default);
+            let exclude_patterns = match crate::filter::compile_exclude_patterns(&exclude_patterns) {
+                Ok(patterns) => patterns,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let split_by = match split_by.as_deref().map(crate::output::SplitBy::from_str) {
+                Some(Ok(mode)) => Some(mode),
+                Some(Err(e)) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+                None => None,
+            };
+            let group_by_tag = match group_by.as_deref().map(str::to_lowercase).as_deref() {
+                Some("tag" | "tags") => true,
+                Some(other) => {
+                    eprintln!("❌ Unknown grouping: {} (expected tag)", other);
+                    std::process::exit(1);
+                }
+                None => false,
+            };
+            if toc_depth == Some(0) {
+                eprintln!("❌ --toc-depth must be at least 1 (the document's sections)");
+                std::process::exit(1);
+            }
+            let mut generate_options = crate::output::GenerateOptions {
+                manifest,
+                focus_failures,
+                split_chains,
+                mock_llm,
+                max_inline_command_length: max_inline_command,
+                collapse_repeats,
+                front_matter,
+                hide_directory_changes: no_dir_changes,
+                timings: timings.then(crate::output::StageTimings::new),
+                number_annotations_per_type,
+                prerequisites,
+                resolve_scripts,
+                raw_output,
+                polish_annotations,
+                group_by_tag,
+                toc_depth,
+                step_numbers,
+                ai_min_significance,
+                gap_threshold_minutes: gap_threshold,
+                glossary,
+                show_nesting,
+                ..Default::default()
+            };
+            match ai_mode.as_deref().map(crate::output::AIAnalysisMode::from_str) {
+                Some(Ok(mode)) => generate_options.ai_mode = Some(mode),
+                Some(Err(e)) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+                None => {}
+            }
+            match language.as_deref().map(crate::output::i18n::Locale::from_str) {
+                Some(Ok(locale)) => generate_options.locale = Some(locale),
+                Some(Err(e)) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+                None => {}
+            }
+            generate_options.context = match load_generate_context(context, context_file.as_deref()) {
+                Ok(context) => context,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            // Handle the generate command
+            let load_start = std::time::Instant::now();
+            let session_to_use = if let Some(session_id) = session {
+                // Load specific session
+                match session_manager.load_session(&session_id) {
+                    Ok(session) => Some(session),
+                    Err(e) => {
+                        eprintln!("❌ Failed to load session '{}': {}", session_id, e);
+                        eprintln!("   Use 'docpilot status' to see available sessions");
+                        return Ok(());
+                    }
+                }
+            } else {
+                current_or_latest_session(&mut session_manager)
+            };
+
+            let mut session = match session_to_use {
+                Some(s) => s,
+                None => {
+                    eprintln!("❌ No session found to generate documentation from");
+                    eprintln!("   Start a session with 'docpilot start \"description\"'");
+                    eprintln!("   Or specify a session ID with --session");
+                    return Ok(());
+                }
+            };
+
+            if let Some(baseline_id) = baseline {
+                match session_manager.load_session(&baseline_id) {
+                    Ok(baseline) => {
+                        println!("🔁 Comparing command output with session {}", baseline.id);
+                        generate_options.baseline = Some(crate::output::baseline::OutputBaseline::new(baseline));
+                    }
+                    Err(e) => {
+                        eprintln!("❌ Failed to load baseline session '{}': {}", baseline_id, e);
+                        eprintln!("   Use 'docpilot status' to see available sessions");
+                        return Ok(());
+                    }
+                }
+            }
+
+            if let Some(timings) = &generate_options.timings {
+                timings.add_since(crate::output::Stage::LoadSession, load_start);
+            }
+
+            // Taken before any commands are left out, so the next incremental run starts after all of them
+            let generation_marker = session.generation_marker();
+            if incremental {
+                let last = match session_manager.last_generation(&session.id) {
+                    Ok(last) => last,
+                    Err(e) => {
+                        eprintln!("❌ Could not read what was last documented: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                if let Some(last) = &last {
+                    println!("🧩 Documenting what was added since {}", last.generated_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"));
+                }
+                session = session.since_last_generate(last.as_ref());
+                if session.commands.is_empty() && session.annotations.is_empty() {
+                    println!("✨ Nothing new since the last generate; no documentation written");
+                    return Ok(());
+                }
+            }
+
+            // The CI gate counts every captured command, whatever the filters below leave out of the document
+            let gated_commands = fail_on_errors.then(|| session.commands.clone());
+
+            // Ad-hoc exclusions only shape this document; the stored session keeps every command
+            let filter_start = std::time::Instant::now();
+            if !exclude_patterns.is_empty() {
+                let before = session.commands.len();
+                session.commands.retain(|cmd| !exclude_patterns.iter().any(|pattern| pattern.is_match(&cmd.command)));
+                session.recount_command_stats();
+                println!("🚫 Excluded {} commands matching --exclude-pattern", before - session.commands.len());
+            }
+            if !tags.is_empty() {
+                let before = session.commands.len();
+                session.commands.retain(|cmd| cmd.tags.iter().any(|tag| tags.iter().any(|wanted| wanted.trim() == tag)));
+                session.recount_command_stats();
+                println!("🏷️  Kept {} of {} commands tagged {}", session.commands.len(), before, tags.join(", "));
+            }
+            if !no_filter {
+                let mut filter = match crate::filter::CommandFilter::with_user_config() {
+                    Ok(filter) => filter,
+                    Err(e) => {
+                        eprintln!("❌ {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                let mut criteria = filter.get_criteria().clone();
+                criteria.only_successful = only_successful;
+                // This documents failures, so failed commands stay unless only successful ones were asked for
+                if focus_failures {
+                    criteria.exclude_failed = false;
+                }
+                // Repeats are shown with their run count instead
+                if collapse_repeats {
+                    criteria.enable_deduplication = false;
+                }
+                filter.set_criteria(criteria);
+
+                let processed = filter.process_commands(&session.commands);
+                if processed.filtered_commands.len() < processed.original_count {
+                    eprintln!("{}", filter_summary(&processed));
+                    session.commands = processed.filtered_commands;
+                    session.recount_command_stats();
+                }
+            }
+            if group_by_tag && !session.commands.iter().any(|cmd| !cmd.tags.is_empty()) {
+                println!("⚠️  No commands are tagged, so --group-by tag puts them all in one group");
+                println!("   Tag commands with 'docpilot tag-command <NUMBER> <TAG>'");
+            }
+            if let Some(timings) = &generate_options.timings {
+                timings.add_since(crate::output::Stage::Filtering, filter_start);
+            }
+
+            // Determine output file
+            let output_file = if let Some(output_path) = output {
+                let path = std::path::PathBuf::from(output_path);
+                // If we're in a test environment and path is relative, make it relative to HOME
+                if path.is_relative() && is_test_environment() {
+                    if let Ok(home) = std::env::var("HOME") {
+                        std::path::PathBuf::from(home).join(path)
+                    } else {
+                        path
+                    }
+                } else {
+                    path
+                }
+            } else if let Some(ref session_output) = session.output_file {
+                // The recorded path may be gone or read-only, e.g. after moving machines
+                match crate::output::check_writable(session_output) {
+                    Ok(()) => session_output.clone(),
+                    Err(e) => {
+                        let fallback = session_output_fallback(&session);
+                        println!("⚠️  {}", e);
+                        println!("   Writing to {} instead", fallback.display());
+                        fallback
+                    }
+                }
+            } else {
+                // Generate a default filename based on session description
+                let filename = crate::output::default_output_filename(&session);
+                // If we're in a test environment, write to HOME directory
+                if is_test_environment() {
+                    if let Ok(home) = std::env::var("HOME") {
+                        std::path::PathBuf::from(home).join(filename)
+                    } else {
+                        std::path::PathBuf::from(filename)
+                    }
+                } else {
+                    std::path::PathBuf::from(filename)
+                }
+            };
+
+            // Split documentation written into a directory gets an index.md there
+            let output_file = if split_by.is_some() && output_file.is_dir() {
+                output_file.join(crate::output::INDEX_FILE_NAME)
+            } else {
+                output_file
+            };
+
+            let Some(output_file) = confirm_output_path(output_file.clone(), force) else {
+                println!("❌ Generation cancelled; {} was left unchanged", output_file.display());
+                return Ok(());
+            };
+
+            println!("📄 Generating documentation from session: {}", session.description);
+            println!("   Session ID: {}", session.id);
+            println!("   Template: {}", template);
+            println!("   Output file: {}", output_file.display());
+            if let Some(mode) = split_by {
+                println!("   Split by: {}", mode.name());
+            }
+            println!();
+
+            // Generate the documentation using the output module
+            let result = match split_by {
+                Some(mode) => crate::output::generate_split_documentation(&session, &output_file, &template, mode, &generate_options)
+                    .await
+                    .map(|_| ()),
+                None => crate::output::generate_documentation_with_options(&session, &output_file, &template, &generate_options).await,
+            };
+
+            let generated = result.is_ok();
+            match result {
+                Ok(_) => {
+                    println!("✅ Documentation generated successfully!");
+                    println!("📊 Session Statistics:");
+                    println!("   Commands captured: {}", session.stats.total_commands);
+                    println!("   Annotations added: {}", session.stats.total_annotations);
+                    if let Some(duration) = session.get_duration_seconds() {
+                        let hours = duration / 3600;
                         let minutes = (duration % 3600) / 60;
                         let seconds = duration % 60;
                         if hours > 0 {
@@ -1267,9 +2891,14 @@ async fn main() -> Result<()> {
                             println!("   Session duration: {}s", seconds);
                         }
                     }
+                    print_annotation_coverage(&session);
                     println!();
                     println!("📄 Documentation saved to: {}", output_file.display());
                     println!("💡 You can now view, edit, or share your documentation!");
+
+                    if let Err(e) = session_manager.record_generation(&session.id, generation_marker) {
+                        eprintln!("⚠️  Could not record what was documented for --incremental: {}", e);
+                    }
                 }
                 Err(e) => {
                     eprintln!("❌ Failed to generate documentation: {}", e);
@@ -1277,9 +2906,238 @@ async fn main() -> Result<()> {
                     eprintln!("   Use 'docpilot status' to verify session details");
                 }
             }
+
+            if let Some(timings) = &generate_options.timings {
+                println!();
+                print!("{}", timings.report());
+            }
+
+            // The document is written either way, so a failing pipeline still has it to look at
+            if let Some(commands) = gated_commands {
+                if !generated {
+                    std::process::exit(1);
+                }
+                let failures: Vec<(usize, &CommandEntry)> = commands.iter().enumerate()
+                    .filter(|(_, cmd)| cmd.exit_code.is_some_and(|code| code != 0))
+                    .map(|(index, cmd)| (cmd.step.unwrap_or(index + 1), cmd))
+                    .collect();
+                let failed = failures.len();
+                if failed > max_failures {
+                    eprintln!();
+                    eprintln!("❌ {} of {} captured commands failed; --max-failures allows {}",
+                            failed, commands.len(), max_failures);
+                    for (number, cmd) in failures.iter().take(10) {
+                        eprintln!("   {}. {} (exit code {})", number, cmd.command, cmd.exit_code.unwrap_or_default());
+                    }
+                    if failures.len() > 10 {
+                        eprintln!("   ... and {} more", failures.len() - 10);
+                    }
+                    std::process::exit(1);
+                }
+                println!("✅ {} failed {} within the allowed {}",
+                        failed, if failed == 1 { "command is" } else { "commands are" }, max_failures);
+            }
         }
-        Commands::Status => {
-            if let Some(session) = session_manager.get_current_session() {
+        Commands::Export { session, format, output, template, mock_llm } => {
+            let format = match crate::output::ExportFormat::from_str(&format) {
+                Ok(format) => format,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let template = template.trim().to_lowercase();
+            if !crate::output::TEMPLATES.contains(&template.as_str()) {
+                eprintln!("❌ Unknown template: {}", template);
+                eprintln!("   Valid templates: {}", crate::output::TEMPLATES.join(", "));
+                std::process::exit(1);
+            }
+
+            let session = match session {
+                Some(session_id) => session_manager.load_session(&session_id).ok(),
+                None => current_or_latest_session(&mut session_manager),
+            };
+            let Some(session) = session else {
+                eprintln!("❌ No session found to export");
+                eprintln!("   Start a session with 'docpilot start \"description\"' or specify one with --session");
+                std::process::exit(1);
+            };
+
+            // Only session data goes to stdout: rendering a document prints progress there
+            if output.as_deref() == Some(std::path::Path::new("-")) {
+                if format.is_document() {
+                    eprintln!("❌ Only --format json or asciicast can be written to stdout");
+                    std::process::exit(1);
+                }
+                match crate::output::session_data(&session, format) {
+                    Ok(data) => print!("{}", data),
+                    Err(e) => {
+                        eprintln!("❌ Failed to export: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return Ok(());
+            }
+
+            let output = output.unwrap_or_else(|| {
+                PathBuf::from(crate::output::default_output_filename(&session)).with_extension(format.extension())
+            });
+            if !format.is_document() {
+                println!("📤 Exporting \"{}\" as {}", session.description, format.extension());
+            } else {
+                println!("📤 Exporting \"{}\" as {} with the {} template", session.description, format.extension(), template);
+            }
+            let options = crate::output::GenerateOptions { mock_llm, ..Default::default() };
+            match crate::output::export_document(&session, &output, &template, format, &options).await {
+                Ok(()) => println!("✅ Exported to {}", output.display()),
+                Err(e) => {
+                    eprintln!("❌ Failed to export: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Preview { templates, session, dir, mock_llm } => {
+            let templates: Vec<String> = templates.iter().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect();
+            if let Some(unknown) = templates.iter().find(|t| !crate::output::TEMPLATES.contains(&t.as_str())) {
+                eprintln!("❌ Unknown template: {}", unknown);
+                eprintln!("   Valid templates: {}", crate::output::TEMPLATES.join(", "));
+                std::process::exit(1);
+            }
+            if !dir.is_dir() {
+                eprintln!("❌ Directory {} does not exist", dir.display());
+                std::process::exit(1);
+            }
+
+            let session = match session {
+                Some(session_id) => match session_manager.load_session(&session_id) {
+                    Ok(session) => Some(session),
+                    Err(e) => {
+                        eprintln!("❌ Failed to load session '{}': {}", session_id, e);
+                        std::process::exit(1);
+                    }
+                },
+                None => current_or_latest_session(&mut session_manager),
+            };
+            let Some(session) = session else {
+                eprintln!("❌ No session found to preview");
+                eprintln!("   Start a session with 'docpilot start \"description\"' or specify one with --session");
+                std::process::exit(1);
+            };
+
+            println!("🔍 Previewing \"{}\" with {} templates", session.description, templates.len());
+            println!();
+            let options = crate::output::GenerateOptions { mock_llm, ..Default::default() };
+            match crate::output::write_template_previews(&session, &templates, &dir, &options).await {
+                Ok(previews) => {
+                    println!();
+                    println!("📊 Template comparison:");
+                    let width = previews.iter().map(|p| p.template.len()).max().unwrap_or(0);
+                    for preview in &previews {
+                        println!(
+                            "   {:<width$}  {:>5} lines  {:>3} sections  {:>8}  → {}",
+                            preview.template,
+                            preview.content.lines().count(),
+                            preview.section_count(),
+                            format!("{:.1} KB", preview.content.len() as f64 / 1024.0),
+                            preview.path.display(),
+                            width = width,
+                        );
+                    }
+                    if let [first, second, ..] = previews.as_slice() {
+                        println!();
+                        println!("💡 Compare them with: diff {} {}", first.path.display(), second.path.display());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to render previews: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Status { monitors: true, .. } => {
+            let mut registry = MonitorRegistry::open_default()?;
+            if registry.prune() > 0 {
+                let _ = registry.save();
+            }
+            if registry.monitors().is_empty() {
+                println!("No background monitors are running.");
+                return Ok(());
+            }
+            println!("Background Monitors");
+            println!("===================");
+            for record in registry.monitors() {
+                let description = session_manager.load_session(&record.session_id)
+                    .map(|session| session.description)
+                    .unwrap_or_default();
+                println!("  PID {} - session {} - {} - since {}{}",
+                       record.pid,
+                       describe_session(record),
+                       record.tty.as_deref().unwrap_or("no terminal"),
+                       record.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                       if description.is_empty() { String::new() } else { format!(" - {}", description) });
+            }
+        }
+        Commands::Status { storage: true, .. } => {
+            use crate::llm::cache::{AnalysisCache, format_size};
+
+            let stats = session_manager.get_storage_stats()?;
+            let cache_entries = AnalysisCache::open_default().and_then(|cache| cache.entries()).unwrap_or_default();
+            let cache_size: u64 = cache_entries.iter().map(|entry| entry.size_bytes).sum();
+            let data_dir = SessionManager::get_sessions_directory()?
+                .parent()
+                .map(std::path::Path::to_path_buf)
+                .unwrap_or_default();
+            let data_size = directory_size(&data_dir);
+
+            println!("🗄️  DocPilot Storage");
+            println!("===================");
+            println!("Location: {}", data_dir.display());
+            println!("Sessions: {} ({})", stats.session_count, format_size(stats.session_size));
+            println!("Backups: {} ({})", stats.backup_count, format_size(stats.backup_size));
+            println!("Analysis cache: {} {} ({})",
+                   cache_entries.len(), if cache_entries.len() == 1 { "entry" } else { "entries" }, format_size(cache_size));
+            let other_size = data_size.saturating_sub(stats.total_size + cache_size);
+            if other_size > 0 {
+                println!("Other files: {} (hooks, monitor registry, logs)", format_size(other_size));
+            }
+            println!("Total: {}", format_size(data_size));
+
+            if let Some((id, created_at)) = &stats.oldest_session {
+                println!();
+                println!("Oldest session: {} ({})", id, created_at.format("%Y-%m-%d %H:%M:%S UTC"));
+            }
+            if let Some((id, created_at)) = &stats.newest_session {
+                println!("Newest session: {} ({})", id, created_at.format("%Y-%m-%d %H:%M:%S UTC"));
+            }
+            if let Some((id, size)) = &stats.largest_session
+                && stats.session_count > 1
+            {
+                println!("Largest session: {} ({})", id, format_size(*size));
+            }
+
+            if cache_size > 0 {
+                println!();
+                println!("💡 Free cache space with 'docpilot cache clear --older-than 30d'");
+            }
+        }
+        Commands::Status { monitor, .. } => {
+            // Each invocation is a new process, so look for the session the monitor is running
+            let current_session = match monitor {
+                Some(target) => {
+                    let record = MonitorRegistry::open_default()?.find(&target).cloned();
+                    match record.filter(|record| !record.session_id.is_empty()) {
+                        Some(record) => Some(session_manager.load_session(&record.session_id)?),
+                        None => {
+                            eprintln!("❌ No running monitor with a known session matches '{}'", target);
+                            eprintln!("   See running monitors with 'docpilot status --monitors'");
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                None => session_manager.get_current_session().cloned()
+                    .or_else(|| session_manager.find_active_session()),
+            };
+            if let Some(session) = current_session.as_ref() {
                 println!("Current Session Status");
                 println!("=====================");
                 println!("Session ID: {}", session.id);
@@ -1297,6 +3155,13 @@ async fn main() -> Result<()> {
                 println!("  Commands captured: {}", session.stats.total_commands);
                 println!("  Successful commands: {}", session.stats.successful_commands);
                 println!("  Failed commands: {}", session.stats.failed_commands);
+                let (inferred_successful, inferred_failed) = session.inferred_outcome_counts();
+                if inferred_successful + inferred_failed > 0 {
+                    println!("  No exit code, inferred from output: {} likely successful, {} likely failed", inferred_successful, inferred_failed);
+                }
+                if session.stats.terminated_commands > 0 {
+                    println!("  Terminated by signal: {}", session.stats.terminated_commands);
+                }
                 println!("  Annotations: {}", session.stats.total_annotations);
                 println!("  Pause/Resume count: {}", session.stats.pause_resume_count);
                 println!();
@@ -1308,6 +3173,19 @@ async fn main() -> Result<()> {
                 if let Some(ref user) = session.metadata.user {
                     println!("  User: {}", user);
                 }
+                if let Some(ref tty) = session.metadata.tty {
+                    println!("  TTY: {}", tty);
+                }
+                if !session.metadata.tags.is_empty() {
+                    println!("  Tags: {}", session.metadata.tags.join(", "));
+                }
+                if let Some(pid) = session.metadata.monitor_pid {
+                    match crate::terminal::PlatformUtils::is_docpilot_process(pid) {
+                        Some(true) => println!("  Monitor PID: {} (running)", pid),
+                        Some(false) => println!("  Monitor PID: {} (not running)", pid),
+                        None => println!("  Monitor PID: {}", pid),
+                    }
+                }
                 if let Some(ref output_file) = session.output_file {
                     println!("  Output file: {}", output_file.display());
                 }
@@ -1348,14 +3226,16 @@ async fn main() -> Result<()> {
                             println!("Available sessions:");
                             for session_id in sessions.iter().take(5) {
                                 if let Ok(session) = session_manager.load_session(session_id) {
-                                    println!("  {} - {} ({:?})",
+                                    println!("  {}{} - {} ({:?}){}",
+                                           if session.metadata.pinned { "📌 " } else { "" },
                                            session_id,
                                            session.description,
-                                           session.state);
+                                           session.state,
+                                           format_session_tags(&session));
                                 }
                             }
                             if sessions.len() > 5 {
-                                println!("  ... and {} more", sessions.len() - 5);
+                                println!("  ... and {} more (see 'docpilot list')", sessions.len() - 5);
                             }
                         }
                     }
@@ -1363,12 +3243,87 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        Commands::List { state, limit, tag, sort } => {
+            let state_filter = match state.as_deref().map(parse_session_state_filter) {
+                Some(Err(e)) => {
+                    eprintln!("❌ {}", e);
+                    eprintln!("   Valid states: active, paused, completed, error");
+                    std::process::exit(1);
+                }
+                Some(Ok(filter)) => Some(filter),
+                None => None,
+            };
+            let by_modification = match sort.to_lowercase().as_str() {
+                "created" => false,
+                "modified" => true,
+                _ => {
+                    eprintln!("❌ Invalid sort order: {}", sort);
+                    eprintln!("   Valid orders: created, modified");
+                    std::process::exit(1);
+                }
+            };
+
+            let session_ids = match session_manager.list_sessions_sorted() {
+                Ok(session_ids) => session_ids,
+                Err(e) => {
+                    eprintln!("❌ Failed to list sessions: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let mut sessions: Vec<Session> = session_ids.iter()
+                .filter_map(|session_id| session_manager.load_session(session_id).ok())
+                .filter(|session| state_filter.is_none_or(|matches| matches(&session.state)))
+                .filter(|session| tag.as_deref().is_none_or(|tag| session.has_tag(tag)))
+                .collect();
+            if !by_modification {
+                sessions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+            }
+            let total = sessions.len();
+            sessions.truncate(limit.unwrap_or(total));
+
+            if sessions.is_empty() {
+                if state.is_some() || tag.is_some() {
+                    println!("No sessions match the filters");
+                } else {
+                    println!("No sessions found.");
+                    println!("Start a new session with: docpilot start \"description\"");
+                }
+                return Ok(());
+            }
+
+            // The pin marker gets its own column so emoji width doesn't shift the others
+            println!("   {:<36}  {:<40}  {:<9}  {:>8}  {:<16}  TAGS", "ID", "DESCRIPTION", "STATE", "COMMANDS", "CREATED");
+            for session in &sessions {
+                let description = if session.description.chars().count() > 40 {
+                    format!("{}...", session.description.chars().take(37).collect::<String>())
+                } else {
+                    session.description.clone()
+                };
+                println!("{}{:<36}  {:<40}  {:<9}  {:>8}  {:<16}  {}",
+                       if session.metadata.pinned { "📌 " } else { "   " },
+                       session.id,
+                       description,
+                       session_state_name(&session.state),
+                       session.stats.total_commands,
+                       session.created_at.format("%Y-%m-%d %H:%M"),
+                       session.metadata.tags.join(", "));
+            }
+            if total > sessions.len() {
+                println!();
+                println!("Showing {} of {} sessions", sessions.len(), total);
+            }
+        }
         Commands::BackgroundMonitor { session_id } => {
             // This is the hidden command used for background monitoring
             let mut session_manager = SessionManager::new()?;
+            session_manager.set_output_spill(Settings::load().ok().and_then(|settings| settings.output_spill()));
             
             // Load the session and set it as current
-            if let Ok(session) = session_manager.load_session(&session_id) {
+            if let Ok(mut session) = session_manager.load_session(&session_id) {
+                // Record this process as the session's monitor so a stuck one can be found
+                session.metadata.monitor_pid = Some(std::process::id());
+                let _ = session_manager.save_session(&session);
+
                 // Set the loaded session as current
                 session_manager.set_current_session(session.clone());
                 
@@ -1378,6 +3333,10 @@ async fn main() -> Result<()> {
                     if let Some(started_at) = session.started_at {
                         monitor.set_session_start_time(started_at);
                     }
+                    monitor.set_expand_aliases(session.expands_aliases());
+                    monitor.set_only_dir(session.only_dir());
+                    monitor.set_capture_delay(session.capture_delay().unwrap_or_default());
+                    monitor.set_nesting_base(session.nesting_base());
                     
                     if monitor.start_monitoring_background().is_ok() {
                         println!("Background monitoring started - direct terminal monitoring");
@@ -1385,28 +3344,139 @@ async fn main() -> Result<()> {
                         
                         // Run the monitoring loop with real-time capture
                         let _ = monitor_with_session(&mut monitor, &mut session_manager).await;
+                        unregister_monitor(std::process::id());
                     }
                 }
-            }
-        }
-        Commands::Hooks { session_id } => {
-            // This outputs the shell hooks content directly for evaluation
-            // Create a temporary monitor to generate hooks
-            if let Ok(monitor) = TerminalMonitor::new(session_id.clone()) {
-                match monitor.get_shell_hooks_content() {
-                    Ok(hooks_content) => {
-                        // Output the hooks content directly - no other output
-                        print!("{}", hooks_content);
+            }
+        }
+        Commands::Hooks { session_id } => {
+            // This outputs the shell hooks content directly for evaluation
+            // Create a temporary monitor to generate hooks
+            if let Ok(mut monitor) = TerminalMonitor::new(session_id.clone()) {
+                if let Ok(session) = session_manager.load_session(&session_id) {
+                    monitor.set_expand_aliases(session.expands_aliases());
+                }
+                match monitor.get_shell_hooks_content() {
+                    Ok(hooks_content) => {
+                        // Output the hooks content directly - no other output
+                        print!("{}", hooks_content);
+                    }
+                    Err(e) => {
+                        eprintln!("Error generating hooks: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                eprintln!("Error creating monitor for hooks");
+                std::process::exit(1);
+            }
+        }
+        Commands::Ssh { ssh_args } => {
+            let session = match session_manager.get_current_session() {
+                Some(session) if session.state.is_active() => session,
+                Some(_) => {
+                    eprintln!("❌ The current session is paused; remote commands would not be captured");
+                    eprintln!("   Use 'docpilot resume' first");
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("❌ No active session to add remote commands to");
+                    eprintln!("   Start a session first with 'docpilot start \"description\"'");
+                    std::process::exit(1);
+                }
+            };
+
+            let capture = match crate::terminal::remote::SshCapture::new(&session.id, ssh_args) {
+                Ok(capture) => capture,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            println!("🌐 Connecting to {}; commands run there are added to '{}'", capture.host(), session.description);
+            match capture.run() {
+                Ok((status, relayed)) => {
+                    println!();
+                    if relayed > 0 {
+                        println!("📥 Added {} commands from {} to the session", relayed, capture.host());
+                    } else {
+                        println!("ℹ️  No commands were captured on {}", capture.host());
+                    }
+                    if !status.success() {
+                        std::process::exit(status.code().unwrap_or(1));
+                    }
+                }
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Edit { session } => {
+            let session = match session {
+                Some(session_id) => match session_manager.load_session(&session_id) {
+                    Ok(session) => Some(session),
+                    Err(e) => {
+                        eprintln!("❌ Failed to load session '{}': {}", session_id, e);
+                        std::process::exit(1);
+                    }
+                },
+                None => current_or_latest_session(&mut session_manager),
+            };
+            let Some(session) = session else {
+                eprintln!("❌ No session found to edit");
+                std::process::exit(1);
+            };
+
+            exit_if_recording(&session, "edit it");
+
+            let original = serde_json::to_string_pretty(&session)?;
+            let edit_dir = SessionManager::get_sessions_directory()?.with_file_name("edit");
+            fs::create_dir_all(&edit_dir)?;
+            let edit_path = edit_dir.join(format!("{}.json", session.id));
+            fs::write(&edit_path, &original)?;
+
+            loop {
+                if let Err(e) = open_in_editor(&edit_path) {
+                    eprintln!("❌ {}", e);
+                    eprintln!("   Set $EDITOR to the editor you want to use");
+                    break;
+                }
+
+                let edited = fs::read_to_string(&edit_path)?;
+                if edited.trim() == original.trim() {
+                    println!("ℹ️  No changes made");
+                    break;
+                }
+
+                match session_manager.save_edited_session(&session.id, &edited) {
+                    Ok(saved) => {
+                        println!("✅ Session '{}' updated", saved.description);
+                        println!("   Commands: {}, Annotations: {}", saved.stats.total_commands, saved.stats.total_annotations);
+                        println!("   The previous version was backed up to ~/.docpilot/backups/");
+                        break;
                     }
                     Err(e) => {
-                        eprintln!("Error generating hooks: {}", e);
-                        std::process::exit(1);
+                        eprintln!("❌ {}", e);
+                        if !ask_to_retry_edit() {
+                            eprintln!("   The stored session was left unchanged");
+                            break;
+                        }
                     }
                 }
-            } else {
-                eprintln!("Error creating monitor for hooks");
-                std::process::exit(1);
             }
+            let _ = fs::remove_file(&edit_path);
+        }
+        Commands::Schema { target } => {
+            let target = match crate::session::schema::SchemaTarget::from_str(&target) {
+                Ok(target) => target,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            };
+            println!("{}", serde_json::to_string_pretty(&crate::session::schema::json_schema(target))?);
         }
         Commands::Simulate { commands } => {
             // This is a hidden testing command to simulate user commands
@@ -1432,6 +3502,14 @@ async fn main() -> Result<()> {
                             shell: "zsh".to_string(),
                             output: None,
                             error: None,
+                            expanded_command: None,
+                            backgrounded: false,
+                            tags: Vec::new(),
+                            step: None,
+                            output_size: None,
+                            output_log: None,
+                            nesting: None,
+                            duration_ms: None,
                         };
                         
                         // Add to session
@@ -1455,6 +3533,595 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Parse the annotation type given to `--filter-type`
+fn parse_annotation_filter(filter: &str) -> Result<AnnotationType> {
+    match filter.to_lowercase().as_str() {
+        "note" | "n" => Ok(AnnotationType::Note),
+        "explanation" | "explain" | "e" => Ok(AnnotationType::Explanation),
+        "warning" | "warn" | "w" => Ok(AnnotationType::Warning),
+        "milestone" | "mile" | "m" => Ok(AnnotationType::Milestone),
+        _ => Err(anyhow::anyhow!("Invalid filter type: {}", filter)),
+    }
+}
+
+/// Use the current session or, if none is active, the most recently modified one
+fn current_or_latest_session(session_manager: &mut SessionManager) -> Option<Session> {
+    session_manager.get_current_session().cloned()
+        .or_else(|| {
+            // Try to get the most recent completed session by modification time
+            session_manager.list_sessions_sorted()
+                .ok()
+                .and_then(|sessions| sessions.into_iter().next())
+                .and_then(|session_id| session_manager.load_session(&session_id).ok())
+        })
+}
+
+/// Print a one-line session indicator for shell prompts, or nothing when no session is active
+///
+/// This runs on every prompt, so only the session of a running monitor is read, preferring the
+/// one started from this terminal, rather than every session file.
+fn print_brief_status() {
+    let Ok(mut registry) = MonitorRegistry::open_default() else {
+        return;
+    };
+    registry.prune();
+    let tty = crate::terminal::PlatformUtils::controlling_tty();
+    let Some(monitor) = registry.monitors().iter()
+        .filter(|monitor| !monitor.session_id.is_empty())
+        .max_by_key(|monitor| (tty.is_some() && monitor.tty == tty, monitor.started_at))
+    else {
+        return;
+    };
+    let Some(session) = SessionManager::new().ok()
+        .and_then(|mut manager| manager.load_session(&monitor.session_id).ok())
+        .filter(|session| session.state.is_active() || session.state.is_paused())
+    else {
+        return;
+    };
+
+    let indicator = if session.state.is_paused() { "⏸ paused" } else { "● rec" };
+    let elapsed = format_elapsed(session.get_duration_seconds().unwrap_or(0) as i64).replace(' ', "");
+    println!("{} {} {}cmds", indicator, elapsed, session.stats.total_commands);
+}
+
+/// Combined size of the files in a directory and its subdirectories; unreadable entries count as empty
+fn directory_size(path: &std::path::Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => directory_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Path used instead of a session's recorded output file when that file cannot be written
+fn session_output_fallback(session: &Session) -> PathBuf {
+    crate::output::fallback_output_directory().join(crate::output::default_output_filename(session))
+}
+
+/// Append a stopped session to its daily log, if it was started with `--daily-log`
+async fn append_daily_log(session: &Session) {
+    let Some(dir) = session.daily_log_dir() else {
+        return;
+    };
+    match crate::output::append_to_daily_log(session, &dir, "standard").await {
+        Ok(path) => println!("📓 Appended to daily log: {}", path.display()),
+        Err(e) => {
+            eprintln!("⚠️  Warning: Failed to append to the daily log: {}", e);
+            eprintln!("   You can generate it later with: docpilot generate --session {}", session.id);
+        }
+    }
+}
+
+/// Longest stretches without annotations that `generate` suggests explaining
+const MAX_COVERAGE_SUGGESTIONS: usize = 3;
+
+/// One line saying how many commands `generate` left out of the document and why
+fn filter_summary(processed: &crate::filter::ProcessedCommands) -> String {
+    let stats = &processed.stats;
+    let repeats = stats.included_commands.saturating_sub(processed.filtered_commands.len());
+    let categorized = stats.failed_commands + stats.typo_commands + stats.suspicious_commands + stats.error_output_commands;
+    let reasons: Vec<String> = [
+        (stats.failed_commands, "failed"),
+        (stats.typo_commands + stats.suspicious_commands, "likely typos"),
+        (stats.error_output_commands, "with error output"),
+        (stats.excluded_commands.saturating_sub(categorized), "excluded by filter.json"),
+        (repeats, "quick repeats"),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, reason)| format!("{} {}", count, reason))
+    .collect();
+    format!(
+        "🧹 Left out {} of {} commands ({}); use --no-filter to keep them",
+        processed.original_count - processed.filtered_commands.len(),
+        processed.original_count,
+        reasons.join(", "),
+    )
+}
+
+/// Tell how many of the documented commands have an annotation nearby, and where notes are missing
+fn print_annotation_coverage(session: &Session) {
+    let coverage = crate::output::coverage::annotation_coverage(session);
+    if coverage.total == 0 {
+        return;
+    }
+    println!("   Annotation coverage: {} of {} commands annotated ({}%)", coverage.annotated, coverage.total, coverage.percent());
+    for run in coverage.unnarrated_runs.iter().take(MAX_COVERAGE_SUGGESTIONS) {
+        let suggestion = match &run.phase {
+            Some(phase) => format!("consider explaining the {} phase", phase_name(phase)),
+            None => "consider adding a note there".to_string(),
+        };
+        println!("   📝 Commands {}–{} have no annotation nearby; {}", run.first, run.last, suggestion);
+    }
+}
+
+/// Lowercase name of a workflow phase for use in a sentence, e.g. `build`
+fn phase_name(phase: &crate::output::markdown::WorkflowPhase) -> String {
+    format!("{:?}", phase).to_lowercase()
+}
+
+/// Shell level (`$SHLVL`) of the shell docpilot was run from, 1 when unknown
+fn current_shell_level() -> u32 {
+    std::env::var("SHLVL").ok().and_then(|level| level.parse().ok()).unwrap_or(1)
+}
+
+/// Combine `--context` and `--context-file` into the project context passed to AI analysis
+fn load_generate_context(context: Option<String>, context_file: Option<&str>) -> Result<Option<String>> {
+    let mut parts = Vec::new();
+
+    if let Some(context) = context {
+        parts.push(context.trim().to_string());
+    }
+
+    if let Some(path) = context_file {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read context file '{}': {}", path, e))?;
+        parts.push(content.trim().to_string());
+    }
+
+    parts.retain(|part| !part.is_empty());
+    if parts.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(parts.join("\n\n")))
+    }
+}
+
+/// Ask what to do with the running session, or cancel when there is no terminal to ask
+fn prompt_conflict_action() -> ConflictAction {
+    use std::io::{self, IsTerminal, Write};
+
+    // Waiting on stdin would hang scripts and CI forever
+    if !io::stdin().is_terminal() {
+        eprintln!("⚠️  A session is already active and there is no terminal to ask what to do with it, so the new session is cancelled.");
+        eprintln!("   Pass --on-conflict stop-and-generate or stop to replace it without a prompt");
+        return ConflictAction::Cancel;
+    }
+
+    // Interactive prompt for handling the existing session
+    println!("DocPilot only supports one active session at a time to prevent shell hook conflicts.");
+    println!("What would you like to do with the existing session?");
+    println!();
+    println!("1. Stop and generate documentation from current session, then start new one");
+    println!("2. Stop current session without generating docs, then start new one");
+    println!("3. Cancel - keep current session running");
+    println!();
+    print!("Choose option (1/2/3): ");
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    if let Err(e) = io::stdin().read_line(&mut input) {
+        eprintln!("❌ Failed to read input: {}", e);
+        std::process::exit(1);
+    }
+
+    match input.trim() {
+        "1" => ConflictAction::StopAndGenerate,
+        "2" => ConflictAction::Stop,
+        "3" | "" => ConflictAction::Cancel,
+        _ => {
+            println!();
+            eprintln!("❌ Invalid choice. Please run the command again and choose 1, 2, or 3.");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Exit with an explanation when another monitor can't be started on this terminal
+fn ensure_monitor_can_start(tty: Option<&str>) {
+    // Two monitors on one terminal install competing hooks and corrupt each other's captures
+    let max_monitors = Settings::load().map(|settings| settings.max_monitors()).unwrap_or(DEFAULT_MAX_MONITORS);
+    match MonitorRegistry::open_default() {
+        Ok(mut registry) => {
+            if registry.prune() > 0 {
+                let _ = registry.save();
+            }
+            if let Err(e) = registry.check_can_start(tty, max_monitors) {
+                eprintln!("❌ Cannot start another monitor: {}", e);
+                eprintln!("   See running monitors with 'docpilot status --monitors'");
+                eprintln!("   Stop one with 'docpilot stop --monitor <PID>', or allow more with 'docpilot config --max-monitors <N>'");
+                std::process::exit(1);
+            }
+        }
+        Err(e) => eprintln!("⚠️  Warning: Could not read the monitor registry: {}", e),
+    }
+}
+
+/// Start the hidden background-monitor process for a session and return its PID
+fn spawn_background_monitor(session_id: &str) -> std::io::Result<u32> {
+    let child = std::process::Command::new(std::env::current_exe().unwrap_or_else(|_| "docpilot".into()))
+        .arg("background-monitor")
+        .arg(session_id)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(child.id())
+}
+
+/// Check whether some DocPilot monitor is still capturing into a session
+///
+/// PIDs are checked against the program running them, so a PID reused by another
+/// process after a crash doesn't count as a live monitor.
+fn has_live_monitor(session: &Session) -> bool {
+    let registered = MonitorRegistry::open_default()
+        .map(|mut registry| {
+            registry.prune();
+            !registry.for_session(&session.id).is_empty()
+        })
+        .unwrap_or(false);
+    registered || session.metadata.monitor_pid.is_some_and(|pid| crate::terminal::PlatformUtils::is_docpilot_process(pid) == Some(true))
+}
+
+/// Find the monitor still recording into a session, if any
+///
+/// A running monitor keeps its own copy of the session and overwrites the file when it
+/// flushes, so changes written by other commands in the meantime would be lost.
+fn recording_monitor_pid(session: &Session) -> Option<u32> {
+    MonitorRegistry::open_default().ok()
+        .and_then(|mut registry| {
+            registry.prune();
+            registry.for_session(&session.id).first().map(|monitor| monitor.pid)
+        })
+        .or_else(|| session.metadata.monitor_pid.filter(|pid| {
+            (session.state.is_active() || session.state.is_paused())
+                && crate::terminal::PlatformUtils::is_docpilot_process(*pid) == Some(true)
+        }))
+}
+
+/// Refuse to change a session that a monitor is still recording into
+fn exit_if_recording(session: &Session, action: &str) {
+    if let Some(pid) = recording_monitor_pid(session) {
+        eprintln!("❌ Session '{}' is still being recorded by monitor PID {}", session.description, pid);
+        eprintln!("   Stop it first with 'docpilot stop --monitor {}', then {}", pid, action);
+        std::process::exit(1);
+    }
+}
+
+/// Ask whether to attach a new monitor to a recovered session, or decline when there is no terminal to ask
+fn prompt_reattach() -> bool {
+    use std::io::{self, IsTerminal, Write};
+
+    if !io::stdin().is_terminal() {
+        println!("   Pass --reattach to keep capturing into it without a prompt");
+        return false;
+    }
+
+    print!("Attach a new monitor and keep capturing into this session? [Y/n]: ");
+    io::stdout().flush().unwrap();
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).is_err() {
+        return false;
+    }
+    matches!(input.trim().to_lowercase().as_str(), "" | "y" | "yes")
+}
+
+/// Attach a new background monitor to an existing session so capture continues where it stopped
+fn reattach_monitor(session_id: &str) -> bool {
+    let tty = crate::terminal::PlatformUtils::controlling_tty();
+    ensure_monitor_can_start(tty.as_deref());
+
+    match spawn_background_monitor(session_id) {
+        Ok(pid) => {
+            register_monitor(pid, session_id, tty.clone());
+            println!("🔁 Attached a new monitor to session {}", session_id);
+            println!("📝 Background process PID: {}{}", pid,
+                tty.as_deref().map(|tty| format!(" (terminal {})", tty)).unwrap_or_default());
+            println!("   Commands will be captured into it again; stop it with 'docpilot stop'");
+            true
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to start background process: {}", e);
+            false
+        }
+    }
+}
+
+/// Pin or unpin a session for `docpilot pin` and `docpilot unpin`
+fn set_session_pinned(session_manager: &mut SessionManager, session_id: &str, pin: bool) {
+    if let Ok(session) = session_manager.load_session(session_id) {
+        exit_if_recording(&session, if pin { "pin it" } else { "unpin it" });
+    }
+
+    match session_manager.set_pinned(session_id, pin) {
+        Ok(changed) => match (pin, changed) {
+            (true, true) => println!("📌 Pinned session {}", session_id),
+            (true, false) => println!("ℹ️  Session {} is already pinned", session_id),
+            (false, true) => println!("📌 Unpinned session {}", session_id),
+            (false, false) => println!("ℹ️  Session {} is not pinned", session_id),
+        },
+        Err(e) => {
+            eprintln!("❌ Failed to {} session '{}': {}", if pin { "pin" } else { "unpin" }, session_id, e);
+            eprintln!("   Use 'docpilot status' to see available sessions");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Record a started monitor in the registry, warning if that fails
+fn register_monitor(pid: u32, session_id: &str, tty: Option<String>) -> bool {
+    let result = MonitorRegistry::open_default().and_then(|mut registry| {
+        registry.register(MonitorRecord { pid, session_id: session_id.to_string(), tty, started_at: chrono::Utc::now() });
+        registry.save()
+    });
+    if let Err(e) = &result {
+        eprintln!("⚠️  Warning: Could not record the monitor in the registry: {}", e);
+    }
+    result.is_ok()
+}
+
+/// Remove a monitor that is exiting from the registry
+fn unregister_monitor(pid: u32) {
+    if let Ok(mut registry) = MonitorRegistry::open_default()
+        && registry.unregister(pid).is_some()
+    {
+        let _ = registry.save();
+    }
+}
+
+/// How long `stop` waits for a monitor to save the session and exit
+const MONITOR_EXIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Terminate monitor processes and remove them from the registry
+fn stop_monitors(registry: &mut MonitorRegistry, monitors: &[MonitorRecord]) {
+    if monitors.is_empty() {
+        return;
+    }
+
+    for monitor in monitors {
+        println!("🛑 Stopping background monitoring process (PID: {})...", monitor.pid);
+
+        #[cfg(unix)]
+        {
+            use std::process::Command;
+            let _ = Command::new("kill")
+                .arg(monitor.pid.to_string())
+                .output();
+        }
+
+        #[cfg(windows)]
+        {
+            use std::process::Command;
+            let _ = Command::new("taskkill")
+                .args(&["/PID", &monitor.pid.to_string(), "/F"])
+                .output();
+        }
+
+        registry.unregister(monitor.pid);
+    }
+
+    if let Err(e) = registry.save() {
+        eprintln!("⚠️  Warning: Could not update the monitor registry: {}", e);
+    }
+
+    // Each monitor saves the session as it exits; changing the session before then would race it
+    let deadline = std::time::Instant::now() + MONITOR_EXIT_TIMEOUT;
+    let mut running: Vec<u32> = monitors.iter().map(|monitor| monitor.pid).collect();
+    while !running.is_empty() && std::time::Instant::now() < deadline {
+        running.retain(|pid| crate::terminal::PlatformUtils::is_process_running(*pid) == Some(true));
+        if !running.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+    }
+    for pid in running {
+        eprintln!("⚠️  Monitor PID {} did not exit within {}s; commands it captured last may be missing",
+                 pid, MONITOR_EXIT_TIMEOUT.as_secs());
+    }
+}
+
+/// Stop the background monitors capturing into a session
+fn stop_session_monitors(session_id: &str) {
+    if let Ok(mut registry) = MonitorRegistry::open_default() {
+        let monitors = registry.for_session(session_id);
+        stop_monitors(&mut registry, &monitors);
+    }
+}
+
+/// Open a file in the user's editor and wait for it to close
+fn open_in_editor(path: &std::path::Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+
+    // Run through the shell so editors configured with arguments, like "code --wait", work
+    #[cfg(unix)]
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("docpilot-edit")
+        .arg(path)
+        .status();
+    #[cfg(not(unix))]
+    let status = std::process::Command::new(&editor).arg(path).status();
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(anyhow::anyhow!("Editor '{}' exited with {}", editor, status)),
+        Err(e) => Err(anyhow::anyhow!("Could not run editor '{}': {}", editor, e)),
+    }
+}
+
+/// Ask whether to reopen the editor after an invalid edit; never asks without a terminal
+fn ask_to_retry_edit() -> bool {
+    use std::io::{self, IsTerminal, Write};
+
+    if !io::stdin().is_terminal() {
+        return false;
+    }
+
+    print!("Reopen the editor to fix it? (Y/n): ");
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).is_err() {
+        return false;
+    }
+    !matches!(input.trim().to_lowercase().as_str(), "n" | "no")
+}
+
+/// Ask before replacing an existing documentation file, offering a numbered variant instead
+///
+/// Returns the path to write to, or `None` if the user cancelled. Without a terminal to ask,
+/// or with `force` or the `always_overwrite` setting, the file is overwritten.
+fn confirm_output_path(path: PathBuf, force: bool) -> Option<PathBuf> {
+    use std::io::{self, IsTerminal, Write};
+
+    if force || !path.exists() || Settings::load().is_ok_and(|settings| settings.always_overwrite) {
+        return Some(path);
+    }
+
+    if !io::stdin().is_terminal() {
+        println!("⚠️  Overwriting existing {}", path.display());
+        return Some(path);
+    }
+
+    let metadata = fs::metadata(&path).ok();
+    let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+    let modified = metadata
+        .and_then(|m| m.modified().ok())
+        .map(|time| chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let numbered = crate::output::numbered_output_path(&path);
+
+    println!("⚠️  {} already exists ({} bytes, last modified {}).", path.display(), size, modified);
+    println!("   Any edits made to it since it was generated would be lost.");
+    println!("   (Skip this question with --force, or 'docpilot config --always-overwrite true')");
+    println!();
+    println!("1. Overwrite it");
+    println!("2. Write to {} instead", numbered.display());
+    println!("3. Cancel");
+    println!();
+    print!("Choose option (1/2/3): ");
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).is_err() {
+        return None;
+    }
+
+    match input.trim() {
+        "1" => Some(path),
+        "2" => Some(numbered),
+        _ => None,
+    }
+}
+
+/// Lowercase name of a session state for tables
+fn session_state_name(state: &SessionState) -> &'static str {
+    match state {
+        SessionState::Active => "active",
+        SessionState::Paused => "paused",
+        SessionState::Stopped => "completed",
+        SessionState::Error(_) => "error",
+    }
+}
+
+/// Predicate for `list --state`, accepting "stopped" as another name for "completed"
+fn parse_session_state_filter(state: &str) -> Result<fn(&SessionState) -> bool> {
+    match state.to_lowercase().as_str() {
+        "active" => Ok(SessionState::is_active),
+        "paused" => Ok(SessionState::is_paused),
+        "completed" | "stopped" => Ok(SessionState::is_stopped),
+        "error" | "failed" => Ok(SessionState::is_error),
+        _ => Err(anyhow::anyhow!("Invalid session state: {}", state)),
+    }
+}
+
+/// Tags of a session for session lists, e.g. " [billing, database]", or nothing when it has none
+fn format_session_tags(session: &Session) -> String {
+    if session.metadata.tags.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", session.metadata.tags.join(", "))
+    }
+}
+
+/// Suggest how to recover from a failed `annotate --edit` or `--delete`
+fn print_annotation_change_hint(error: &anyhow::Error) {
+    match error.downcast_ref::<SessionError>() {
+        Some(SessionError::NoActiveSession) => {
+            eprintln!("   Annotations can only be changed in the active session");
+        }
+        Some(SessionError::AnnotationNotFound(_)) => {
+            eprintln!("   List annotation IDs with 'docpilot annotations'");
+        }
+        _ => {}
+    }
+}
+
+/// Warn about secrets in annotation text and let the user decide whether to redact them
+fn review_annotation_for_secrets(text: String, force: bool) -> String {
+    use std::io::{self, IsTerminal, Write};
+
+    if force {
+        return text;
+    }
+
+    let filter = crate::filter::CommandFilter::new();
+    let Some(redacted) = filter.redact_text_if_sensitive(&text) else {
+        return text;
+    };
+
+    println!("⚠️  This annotation looks like it contains sensitive data (tokens, passwords, keys).");
+    println!("   Redacted version: \"{}\"", redacted);
+
+    // Without a terminal to ask, err on the side of not leaking the secret
+    if !io::stdin().is_terminal() {
+        println!("🔒 Saving the redacted version (use --force to keep the original text)");
+        return redacted;
+    }
+
+    println!();
+    println!("1. Save the redacted version (recommended)");
+    println!("2. Save the original text as-is");
+    println!("3. Cancel");
+    println!();
+    print!("Choose option (1/2/3): ");
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).is_err() {
+        return redacted;
+    }
+
+    match input.trim() {
+        "2" => text,
+        "3" => {
+            println!("❌ Annotation cancelled");
+            std::process::exit(0);
+        }
+        _ => redacted,
+    }
+}
+
 /// Helper function for quick annotation commands
 async fn handle_quick_annotation(
     session_manager: &mut SessionManager,
@@ -1495,7 +4162,7 @@ async fn handle_quick_annotation(
         }
         Err(e) => {
             eprintln!("❌ Failed to add {}: {}", type_name.to_lowercase(), e);
-            if e.to_string().contains("No active session") {
+            if let Some(SessionError::NoActiveSession) = e.downcast_ref::<SessionError>() {
                 eprintln!("   Start a session first with 'docpilot start \"description\"'");
                 eprintln!("   Then add annotations to document your workflow");
             } else {
@@ -1506,6 +4173,90 @@ async fn handle_quick_annotation(
     }
 }
 
+/// How often to report a command that is still running
+const HEARTBEAT_INTERVAL_SECS: u64 = 30;
+
+/// Format elapsed seconds as a short duration, e.g. `45s`, `2m` or `1h 5m`
+fn format_elapsed(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    if seconds >= 3600 {
+        format!("{}h {}m", seconds / 3600, (seconds % 3600) / 60)
+    } else if seconds >= 60 {
+        format!("{}m", seconds / 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Live warnings about destructive commands, enabled with `start --warn-dangerous`
+struct DangerousCommandWarnings {
+    filter: crate::filter::CommandFilter,
+    annotate: bool,
+    /// Terminal of the session, where a background monitor shows its warnings
+    tty: Option<String>,
+    /// Running command already warned about, so it isn't reported again when it completes
+    warned_running: Option<CommandEntry>,
+}
+
+impl DangerousCommandWarnings {
+    /// Warnings configured for the current session, if it asked for them
+    fn for_session(session_manager: &SessionManager) -> Option<Self> {
+        let session = session_manager.get_current_session()?;
+        session.warns_dangerous().then(|| Self {
+            filter: crate::filter::CommandFilter::new(),
+            annotate: session.annotates_dangerous(),
+            tty: session.metadata.tty.clone(),
+            warned_running: None,
+        })
+    }
+
+    /// Warn about a command that is running (`started`) or was just captured, once per run
+    fn check(&mut self, command: &CommandEntry, started: bool, session_manager: &mut SessionManager) {
+        if started {
+            let already_warned = self.warned_running.as_ref().is_some_and(|warned| {
+                warned.command == command.command && warned.timestamp == command.timestamp
+            });
+            if already_warned || !self.filter.is_dangerous(&command.command) {
+                return;
+            }
+            self.warned_running = Some(command.clone());
+        } else {
+            let warned_when_started = self.warned_running.as_ref().is_some_and(|warned| {
+                warned.command == command.command && warned.timestamp <= command.timestamp
+            });
+            if warned_when_started {
+                self.warned_running = None;
+                return;
+            }
+            if !self.filter.is_dangerous(&command.command) {
+                return;
+            }
+        }
+
+        self.show(&format!("⚠️  Destructive command: {}", command.command));
+        if self.annotate {
+            let text = format!("Destructive command run in {}: `{}`", command.working_directory, command.command);
+            if let Err(e) = session_manager.add_annotation(text, AnnotationType::Warning) {
+                eprintln!("⚠️  Failed to add warning annotation: {}", e);
+            }
+        }
+    }
+
+    /// Print the warning, on the session's terminal when this monitor has no terminal of its own
+    fn show(&self, message: &str) {
+        use std::io::{IsTerminal, Write};
+
+        if !std::io::stdout().is_terminal()
+            && let Some(tty) = &self.tty
+            && let Ok(mut terminal) = fs::OpenOptions::new().write(true).open(tty)
+        {
+            let _ = write!(terminal, "\r\n{}\r\n", message);
+            return;
+        }
+        println!("{}", message);
+    }
+}
+
 /// Monitor terminal commands and integrate with session management
 async fn monitor_with_session(
     monitor: &mut TerminalMonitor,
@@ -1514,52 +4265,27 @@ async fn monitor_with_session(
     use tokio::signal;
     use tokio::time::{interval, Duration};
     
-    // Set up Ctrl+C handler
+    // Set up Ctrl+C handler, and SIGTERM for `docpilot stop` and `kill`
     let ctrl_c = signal::ctrl_c();
     tokio::pin!(ctrl_c);
+    let mut terminate = TerminateSignal::new();
     
     // Set up periodic status updates and command checking
     let mut status_interval = interval(Duration::from_secs(30));
     let mut command_check_interval = interval(Duration::from_millis(1000));
+    let mut heartbeat_interval = interval(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
     
     // Track the last number of commands we've seen
     let mut last_command_count = 0;
     
+    let mut dangerous_warnings = DangerousCommandWarnings::for_session(session_manager);
+    
     println!("🔄 Starting continuous monitoring loop...");
     
-    loop {
+    let received = loop {
         tokio::select! {
-            _ = &mut ctrl_c => {
-                println!();
-                println!("🛑 Received Ctrl+C, stopping session gracefully...");
-                
-                if let Err(e) = monitor.stop_monitoring() {
-                    eprintln!("⚠️  Error stopping monitor: {}", e);
-                }
-                
-                // Stop the session
-                match session_manager.stop_session() {
-                    Ok(Some(session)) => {
-                        println!("✅ Session stopped successfully!");
-                        println!("📊 Final statistics:");
-                        println!("   Commands captured: {}", session.stats.total_commands);
-                        println!("   Annotations added: {}", session.stats.total_annotations);
-                        if let Some(duration) = session.get_duration_seconds() {
-                            let minutes = duration / 60;
-                            let seconds = duration % 60;
-                            if minutes > 0 {
-                                println!("   Session duration: {}m {}s", minutes, seconds);
-                            } else {
-                                println!("   Session duration: {}s", seconds);
-                            }
-                        }
-                        println!("💾 Session saved to: ~/.docpilot/sessions/{}.json", session.id);
-                    }
-                    Ok(None) => println!("ℹ️  No session was active."),
-                    Err(e) => eprintln!("❌ Error stopping session: {}", e),
-                }
-                break;
-            }
+            _ = &mut ctrl_c => break "Ctrl+C",
+            _ = terminate.recv() => break "SIGTERM",
             _ = status_interval.tick() => {
                 // Periodic status update
                 if let Some(session) = session_manager.get_current_session() {
@@ -1570,11 +4296,29 @@ async fn monitor_with_session(
                     }
                 }
             }
+            _ = heartbeat_interval.tick() => {
+                // Reassure the user during slow steps that the capture is still alive
+                if let Some(running) = monitor.running_command() {
+                    let elapsed = (chrono::Utc::now() - running.timestamp).num_seconds();
+                    if elapsed >= HEARTBEAT_INTERVAL_SECS as i64 {
+                        println!("⏳ running: {} ({})", running.command, format_elapsed(elapsed));
+                    }
+                }
+            }
             _ = command_check_interval.tick() => {
                 // Check for new commands using direct terminal monitoring
                 if monitor.is_monitoring() {
                     match monitor.check_for_new_commands().await {
                         Ok(new_commands) => {
+                            // zsh reports commands as they start, so the warning can come before they finish
+                            if let Some(warnings) = &mut dangerous_warnings {
+                                for command in &new_commands {
+                                    warnings.check(command, false, session_manager);
+                                }
+                                if let Some(running) = monitor.running_command() {
+                                    warnings.check(running, true, session_manager);
+                                }
+                            }
                             for command in new_commands {
                                 if let Err(e) = session_manager.add_command(command.clone()) {
                                     eprintln!("⚠️  Failed to add command to session: {}", e);
@@ -1582,16 +4326,95 @@ async fn monitor_with_session(
                                     println!("📝 Captured: {}", command.command);
                                 }
                             }
+                            for job in monitor.take_completed_jobs() {
+                                let exit_code = job.exit_code.unwrap_or_default();
+                                match session_manager.complete_background_command(&job.command, exit_code) {
+                                    Ok(true) => println!("📝 Background job finished: {} (exit code {})", job.command, exit_code),
+                                    Ok(false) => {}
+                                    Err(e) => eprintln!("⚠️  Failed to record background job: {}", e),
+                                }
+                            }
                         }
                         Err(e) => {
                             eprintln!("📡 Terminal monitoring error: {}", e);
                         }
                     }
                 }
+                
+                // Persist commands held back by save throttling once the interval elapses
+                if let Err(e) = session_manager.flush_if_due() {
+                    eprintln!("⚠️  Failed to save session: {}", e);
+                }
             }
         }
+    };
+
+    println!();
+    println!("🛑 Received {}, stopping session gracefully...", received);
+
+    // Make sure throttled command saves reach disk before anything else can fail
+    if let Err(e) = session_manager.flush() {
+        eprintln!("⚠️  Error saving pending commands: {}", e);
     }
-    
-    
+
+    if let Err(e) = monitor.stop_monitoring() {
+        eprintln!("⚠️  Error stopping monitor: {}", e);
+    }
+
+    // Stop the session
+    match session_manager.stop_session() {
+        Ok(Some(session)) => {
+            println!("✅ Session stopped successfully!");
+            println!("📊 Final statistics:");
+            println!("   Commands captured: {}", session.stats.total_commands);
+            println!("   Annotations added: {}", session.stats.total_annotations);
+            if let Some(duration) = session.get_duration_seconds() {
+                let minutes = duration / 60;
+                let seconds = duration % 60;
+                if minutes > 0 {
+                    println!("   Session duration: {}m {}s", minutes, seconds);
+                } else {
+                    println!("   Session duration: {}s", seconds);
+                }
+            }
+            println!("💾 Session saved to: ~/.docpilot/sessions/{}.json", session.id);
+        }
+        Ok(None) => println!("ℹ️  No session was active."),
+        Err(e) => eprintln!("❌ Error stopping session: {}", e),
+    }
+
     Ok(())
 }
+
+/// SIGTERM, sent by `docpilot stop` and a plain `kill`; never arrives where there is no such signal
+struct TerminateSignal {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl TerminateSignal {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            let signal = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate());
+            if let Err(e) = &signal {
+                eprintln!("⚠️  Could not listen for SIGTERM, only Ctrl+C stops the session cleanly: {}", e);
+            }
+            Self { signal: signal.ok() }
+        }
+        #[cfg(not(unix))]
+        {
+            Self {}
+        }
+    }
+
+    /// Wait for the signal
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = &mut self.signal {
+            signal.recv().await;
+            return;
+        }
+        std::future::pending::<()>().await
+    }
+}
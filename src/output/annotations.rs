@@ -0,0 +1,85 @@
+use anyhow::{Result, anyhow};
+use serde_json::json;
+
+use crate::session::manager::Session;
+use super::markdown::{AnnotationGrouping, MarkdownConfig, MarkdownTemplate};
+
+/// File format for exported annotations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnnotationExportFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+impl AnnotationExportFormat {
+    /// Parse an export format from its command line name
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(AnnotationExportFormat::Markdown),
+            "json" => Ok(AnnotationExportFormat::Json),
+            _ => Err(anyhow!("Unknown export format: {} (expected markdown or json)", s)),
+        }
+    }
+}
+
+/// Render only a session's annotations, without the command log
+///
+/// Markdown uses the same layout as the annotations section of generated documentation.
+pub fn export_annotations(session: &Session, format: AnnotationExportFormat, grouping: AnnotationGrouping) -> Result<String> {
+    match format {
+        AnnotationExportFormat::Markdown => {
+            let mut config = MarkdownConfig::default();
+            config.template_options.annotation_grouping = grouping;
+            MarkdownTemplate::with_config(config).generate_annotations(session)
+        }
+        AnnotationExportFormat::Json => {
+            let export = json!({
+                "session_id": session.id,
+                "description": session.description,
+                "annotations": session.annotations,
+            });
+            Ok(serde_json::to_string_pretty(&export)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::manager::AnnotationType;
+
+    fn session_with_annotations() -> Session {
+        let mut session = Session::new("Deploy runbook".to_string(), None).unwrap();
+        session.add_annotation("Check the replica count first".to_string(), AnnotationType::Warning);
+        session.add_annotation("Rollout finished".to_string(), AnnotationType::Milestone);
+        session
+    }
+
+    #[test]
+    fn test_export_annotations_as_markdown() {
+        let session = session_with_annotations();
+
+        let chronological = export_annotations(&session, AnnotationExportFormat::Markdown, AnnotationGrouping::Chronological).unwrap();
+        assert!(chronological.starts_with("# Deploy runbook"));
+        assert!(chronological.contains("### ⚠️ Annotation 1"));
+        assert!(chronological.contains("Rollout finished"));
+        assert!(!chronological.contains("## Commands"));
+
+        let grouped = export_annotations(&session, AnnotationExportFormat::Markdown, AnnotationGrouping::ByType).unwrap();
+        assert!(grouped.contains("### 🎯 Milestones"));
+        assert!(grouped.contains("#### 🎯 Annotation 2"));
+    }
+
+    #[test]
+    fn test_export_annotations_as_json() {
+        let session = session_with_annotations();
+        let exported = export_annotations(&session, AnnotationExportFormat::Json, AnnotationGrouping::default()).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&exported).unwrap();
+        assert_eq!(value["session_id"], session.id.as_str());
+        assert_eq!(value["annotations"].as_array().unwrap().len(), 2);
+        assert_eq!(value["annotations"][1]["text"], "Rollout finished");
+        assert!(AnnotationExportFormat::from_str("yaml").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! asciinema recordings of sessions
+//!
+//! `docpilot export --format asciicast` writes an asciicast v2 file: a JSON header line followed by
+//! one `[seconds, "o", text]` line per output event. Each command is typed at the offset its
+//! timestamp has from the session start, with its output and errors right after it, so a
+//! documented workflow can be replayed in the asciinema player.
+
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use std::path::Path;
+
+use crate::session::manager::Session;
+
+/// Terminal width used when the session didn't record one
+pub const DEFAULT_WIDTH: u32 = 80;
+
+/// Terminal height used when the session didn't record one
+pub const DEFAULT_HEIGHT: u32 = 24;
+
+/// Pause between a command being typed and its output appearing
+const OUTPUT_DELAY_SECONDS: f64 = 0.2;
+
+/// The session as an asciicast v2 recording
+pub fn session_asciicast(session: &Session) -> Result<String> {
+    let start = session.started_at.unwrap_or(session.created_at);
+    let mut header = json!({
+        "version": 2,
+        "width": session.terminal_columns().unwrap_or(DEFAULT_WIDTH),
+        "height": session.terminal_lines().unwrap_or(DEFAULT_HEIGHT),
+        "timestamp": start.timestamp(),
+        "title": session.description,
+    });
+    if let Some(shell) = session.commands.first().map(|command| &command.shell) {
+        header["env"] = json!({ "SHELL": shell });
+    }
+
+    let mut cast = serde_json::to_string(&header)? + "\n";
+    // Players expect event times that never go backwards
+    let mut last_time = 0.0_f64;
+    let mut event = |time: f64, text: String| -> Result<()> {
+        last_time = time.max(last_time);
+        cast.push_str(&serde_json::to_string(&json!([(last_time * 1000.0).round() / 1000.0, "o", text]))?);
+        cast.push('\n');
+        Ok(())
+    };
+    for command in &session.commands {
+        let offset = (command.timestamp - start).num_milliseconds().max(0) as f64 / 1000.0;
+        event(offset, format!("$ {}\r\n", command.command))?;
+        for text in [&command.output, &command.error].into_iter().flatten().filter(|text| !text.is_empty()) {
+            event(offset + OUTPUT_DELAY_SECONDS, terminal_lines(text))?;
+        }
+    }
+    Ok(cast)
+}
+
+/// Write the session as an asciicast v2 recording to `path`
+pub fn generate_asciicast(session: &Session, path: &Path) -> Result<()> {
+    std::fs::write(path, session_asciicast(session)?).map_err(|e| anyhow!("Cannot write {}: {}", path.display(), e))
+}
+
+/// Text with the line endings a terminal expects, ending in a newline
+fn terminal_lines(text: &str) -> String {
+    let mut lines = text.replace("\r\n", "\n").replace('\n', "\r\n");
+    if !lines.ends_with("\r\n") {
+        lines.push_str("\r\n");
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::manager::TERMINAL_COLUMNS_SETTING;
+    use crate::terminal::CommandEntry;
+    use chrono::{DateTime, Duration};
+
+    #[test]
+    fn test_commands_become_timed_output_events() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut session = Session::new("Build".to_string(), None).unwrap();
+        session.started_at = Some(start);
+        session.metadata.settings.insert(TERMINAL_COLUMNS_SETTING.to_string(), "120".to_string());
+        for (seconds, command, output, error) in [(3, "cargo build", Some("Compiling app\nFinished"), None), (10, "ls missing", None, Some("ls: missing: No such file"))] {
+            session.commands.push(CommandEntry {
+                command: command.to_string(),
+                timestamp: start + Duration::seconds(seconds),
+                exit_code: Some(0),
+                working_directory: "/srv".to_string(),
+                shell: "bash".to_string(),
+                output: output.map(str::to_string),
+                error: error.map(str::to_string),
+                ..Default::default()
+            });
+        }
+
+        let cast = session_asciicast(&session).unwrap();
+        let lines: Vec<serde_json::Value> = cast.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!((lines[0]["version"].as_u64(), lines[0]["width"].as_u64(), lines[0]["height"].as_u64()), (Some(2), Some(120), Some(24)));
+        assert_eq!(lines[0]["timestamp"], 1_700_000_000);
+        assert_eq!(lines[1], json!([3.0, "o", "$ cargo build\r\n"]));
+        assert_eq!(lines[2], json!([3.2, "o", "Compiling app\r\nFinished\r\n"]));
+        assert_eq!(lines[3], json!([10.0, "o", "$ ls missing\r\n"]));
+        assert_eq!(lines[4], json!([10.2, "o", "ls: missing: No such file\r\n"]));
+    }
+}
@@ -0,0 +1,217 @@
+//! Command output compared with the same commands in an earlier session
+//!
+//! `generate --baseline ID` matches each command to a command of the baseline session with the
+//! same normalized form, the first run to the first run and so on, and shows how its output
+//! changed. Commands without a match in the baseline are documented as usual.
+
+use std::collections::HashMap;
+
+use crate::filter::CommandFilter;
+use crate::session::manager::Session;
+use crate::terminal::CommandEntry;
+
+/// Output lines kept around each change when rendering a diff
+pub const DIFF_CONTEXT_LINES: usize = 2;
+
+/// Largest number of line pairs compared exactly; beyond it changed regions are shown as replaced
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// A session whose command output later documents are compared with
+#[derive(Debug, Clone)]
+pub struct OutputBaseline {
+    session: Session,
+}
+
+impl OutputBaseline {
+    pub fn new(session: Session) -> Self {
+        Self { session }
+    }
+
+    /// ID of the baseline session
+    pub fn session_id(&self) -> &str {
+        &self.session.id
+    }
+
+    /// The baseline session
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Baseline output of each command that has a match, keyed by the command's position in `commands`
+    ///
+    /// The nth run of a command is compared with the nth run in the baseline, or its last run when
+    /// the baseline ran it fewer times. Commands where neither run has output are left out.
+    pub fn match_outputs(&self, commands: &[CommandEntry]) -> HashMap<usize, String> {
+        let filter = CommandFilter::new();
+        let mut baseline_runs: HashMap<String, Vec<&CommandEntry>> = HashMap::new();
+        for command in &self.session.commands {
+            baseline_runs.entry(filter.normalize_command(&command.command)).or_default().push(command);
+        }
+
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut matches = HashMap::new();
+        for (index, command) in commands.iter().enumerate() {
+            let normalized = filter.normalize_command(&command.command);
+            let run = seen.entry(normalized.clone()).or_default();
+            let baseline = baseline_runs.get(&normalized).and_then(|runs| runs.get(*run).or(runs.last()));
+            *run += 1;
+
+            if let Some(baseline) = baseline
+                && (baseline.output.is_some() || command.output.is_some())
+            {
+                matches.insert(index, baseline.output.clone().unwrap_or_default());
+            }
+        }
+        matches
+    }
+}
+
+/// A line of a line-by-line comparison
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Compare two outputs line by line
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut diff: Vec<DiffLine> = old[..prefix].iter().map(|line| DiffLine::Same(line)).collect();
+    if old_middle.len() * new_middle.len() > MAX_DIFF_CELLS {
+        diff.extend(old_middle.iter().map(|line| DiffLine::Removed(line)));
+        diff.extend(new_middle.iter().map(|line| DiffLine::Added(line)));
+    } else {
+        diff.extend(diff_middle(old_middle, new_middle));
+    }
+    diff.extend(old[old.len() - suffix..].iter().map(|line| DiffLine::Same(line)));
+    diff
+}
+
+/// Longest-common-subsequence diff of the lines between the common prefix and suffix
+fn diff_middle<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    diff.extend(old[i..].iter().map(|line| DiffLine::Removed(line)));
+    diff.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+    diff
+}
+
+/// Render the changed lines of a diff with `context` unchanged lines around them, in `diff` syntax
+///
+/// Returns `None` when nothing changed. Skipped unchanged lines are marked with `@@`.
+pub fn render_diff(diff: &[DiffLine], context: usize) -> Option<String> {
+    let changed: Vec<usize> = diff.iter().enumerate().filter(|(_, line)| !matches!(line, DiffLine::Same(_))).map(|(index, _)| index).collect();
+    if changed.is_empty() {
+        return None;
+    }
+
+    let shown = |index: usize| changed.iter().any(|&change| change.abs_diff(index) <= context);
+    let mut rendered = Vec::new();
+    let mut skipped = false;
+    for (index, line) in diff.iter().enumerate() {
+        if !shown(index) {
+            skipped = true;
+            continue;
+        }
+        if skipped {
+            rendered.push("@@".to_string());
+            skipped = false;
+        }
+        rendered.push(match line {
+            DiffLine::Same(text) => format!(" {}", text),
+            DiffLine::Removed(text) => format!("-{}", text),
+            DiffLine::Added(text) => format!("+{}", text),
+        });
+    }
+    if skipped {
+        rendered.push("@@".to_string());
+    }
+    Some(rendered.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    fn command(command: &str, output: Option<&str>, seconds: i64) -> CommandEntry {
+        CommandEntry {
+            command: command.to_string(),
+            timestamp: DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap(),
+            exit_code: Some(0),
+            working_directory: "/srv/app".to_string(),
+            shell: "bash".to_string(),
+            output: output.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_outputs_matched_by_normalized_command() {
+        let mut baseline = Session::new("Last release".to_string(), None).unwrap();
+        baseline.commands = vec![
+            command("curl localhost:8080/health", Some("ok"), 0),
+            command("kubectl get pods", Some("web-1 Running"), 1),
+            command("kubectl get pods", Some("web-1 Running\nweb-2 Running"), 2),
+        ];
+        let commands = vec![
+            command("curl localhost:9090/health", Some("degraded"), 10),
+            // Runs within the same second are still told apart
+            command("kubectl get pods", Some("web-1 Running"), 11),
+            command("kubectl get pods", Some("web-1 Running"), 11),
+            command("kubectl get pods", Some("web-1 Running"), 11),
+            command("make deploy", Some("done"), 14),
+        ];
+
+        let matches = OutputBaseline::new(baseline).match_outputs(&commands);
+        assert_eq!(matches.len(), 4);
+        assert_eq!(matches[&0], "ok");
+        assert_eq!(matches[&1], "web-1 Running");
+        assert_eq!(matches[&2], "web-1 Running\nweb-2 Running");
+        assert_eq!(matches[&3], "web-1 Running\nweb-2 Running");
+    }
+
+    #[test]
+    fn test_diff_shows_changes_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh";
+        let new = "a\nb\nc\nD\ne\nf\ng\nh\ni";
+        let diff = diff_lines(old, new);
+        assert_eq!(render_diff(&diff, 1).unwrap(), "@@\n c\n-d\n+D\n e\n@@\n h\n+i");
+        assert_eq!(render_diff(&diff_lines(old, old), 1), None);
+
+        assert_eq!(diff_lines("x\ny", "y\nz"), vec![DiffLine::Removed("x"), DiffLine::Same("y"), DiffLine::Added("z")]);
+    }
+}
@@ -0,0 +1,127 @@
+//! User-defined command categories
+//!
+//! Categories for tools the built-in command types don't know, loaded from
+//! `categories.json` in the DocPilot config directory, e.g.
+//!
+//! ```json
+//! { "categories": [ { "name": "Platform", "icon": "🛰️", "prefixes": ["acmectl", "make deploy"] } ] }
+//! ```
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Icon for categories that don't set one
+const DEFAULT_ICON: &str = "🏷️";
+
+/// A command category with its own name, icon and matching command prefixes
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CustomCategory {
+    /// Name shown in headings, e.g. "Platform" for "Platform Commands"
+    pub name: String,
+    #[serde(default = "default_icon")]
+    pub icon: String,
+    /// Explanation shown under the category heading
+    #[serde(default)]
+    pub description: String,
+    /// Commands that start with one of these words belong to the category
+    pub prefixes: Vec<String>,
+}
+
+fn default_icon() -> String {
+    DEFAULT_ICON.to_string()
+}
+
+impl CustomCategory {
+    /// Explanation of the category, with a generic one when the config has none
+    pub fn description(&self) -> &str {
+        if self.description.is_empty() {
+            "Commands from a user-defined category"
+        } else {
+            &self.description
+        }
+    }
+
+    /// Check whether a command starts with one of the category's prefixes, matching whole words
+    pub fn matches(&self, command: &str) -> bool {
+        let command = command.trim().to_lowercase();
+        self.prefixes.iter().any(|prefix| {
+            let prefix = prefix.trim().to_lowercase();
+            !prefix.is_empty()
+                && command
+                    .strip_prefix(&prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+        })
+    }
+}
+
+/// The custom categories configuration file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategoryConfig {
+    /// Categories in the order they are checked and shown
+    #[serde(default)]
+    pub categories: Vec<CustomCategory>,
+}
+
+impl CategoryConfig {
+    /// Load the category configuration, falling back to no categories when no file exists
+    pub fn load() -> Result<Self> {
+        let config_path = Self::config_file_path()?;
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&config_path)?;
+        let config: CategoryConfig = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid category configuration {}: {}", config_path.display(), e))?;
+
+        if let Some(category) = config.categories.iter().find(|category| category.name.trim().is_empty()) {
+            return Err(anyhow!("Category with prefixes {:?} has no name", category.prefixes));
+        }
+
+        Ok(config)
+    }
+
+    /// Get the category configuration file path
+    pub fn config_file_path() -> Result<PathBuf> {
+        Ok(crate::settings::config_dir()?.join("categories.json"))
+    }
+}
+
+/// Custom categories from the user's configuration, loaded once per run
+///
+/// An invalid configuration is reported and ignored so documentation still generates.
+pub fn custom_categories() -> &'static [CustomCategory] {
+    static CATEGORIES: OnceLock<Vec<CustomCategory>> = OnceLock::new();
+    CATEGORIES.get_or_init(|| match CategoryConfig::load() {
+        Ok(config) => config.categories,
+        Err(e) => {
+            eprintln!("⚠️  Ignoring custom command categories: {}", e);
+            Vec::new()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_config_defaults_and_prefix_matching() {
+        let config: CategoryConfig = serde_json::from_str(
+            r#"{ "categories": [ { "name": "Platform", "prefixes": ["acmectl", "Make Deploy"] } ] }"#,
+        )
+        .unwrap();
+        let category = &config.categories[0];
+
+        assert_eq!(category.icon, DEFAULT_ICON);
+        assert_eq!(category.description(), "Commands from a user-defined category");
+        assert!(category.matches("acmectl rollout web"));
+        assert!(category.matches("  ACMECTL"));
+        assert!(category.matches("make deploy ENV=prod"));
+        assert!(!category.matches("acmectl-legacy status"));
+        assert!(!category.matches("make build"));
+    }
+}
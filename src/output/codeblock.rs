@@ -659,17 +659,14 @@ impl std::fmt::Display for CodeBlockType {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
 
     fn create_test_command(command: &str, shell: &str) -> CommandEntry {
         CommandEntry {
             command: command.to_string(),
-            timestamp: Utc::now(),
             exit_code: Some(0),
             working_directory: "/test".to_string(),
             shell: shell.to_string(),
-            output: None,
-            error: None,
+            ..Default::default()
         }
     }
 
@@ -0,0 +1,155 @@
+//! How well a session's commands are explained by annotations
+//!
+//! A command counts as annotated when an annotation was added within a couple of minutes of it.
+//! Long runs of commands without one are reported with the workflow phase most of them belong
+//! to, so `generate` can suggest where a note would help the reader most.
+
+use std::collections::HashMap;
+
+use super::markdown::{CommandType, WorkflowPhase};
+use crate::session::manager::Session;
+
+/// An annotation this close to a command, before or after it, explains it
+pub const NEARBY_ANNOTATION_SECONDS: i64 = 120;
+
+/// Shortest run of commands without a nearby annotation that is worth pointing out
+pub const MIN_UNNARRATED_RUN: usize = 5;
+
+/// Consecutive commands with no annotation nearby
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnnarratedRun {
+    /// Step number of the first command, as shown in the document
+    pub first: usize,
+    /// Step number of the last command
+    pub last: usize,
+    /// Number of commands in the run, which can be fewer than the steps it spans
+    pub command_count: usize,
+    /// Workflow phase most of the commands belong to, unless they are unclassified
+    pub phase: Option<WorkflowPhase>,
+}
+
+/// Share of a session's commands that have an annotation nearby
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotationCoverage {
+    pub annotated: usize,
+    pub total: usize,
+    /// Runs of at least `MIN_UNNARRATED_RUN` commands without an annotation, longest first
+    pub unnarrated_runs: Vec<UnnarratedRun>,
+}
+
+impl AnnotationCoverage {
+    /// Annotated commands as a whole percentage
+    pub fn percent(&self) -> usize {
+        (self.annotated * 100).checked_div(self.total).unwrap_or(0)
+    }
+}
+
+/// Work out which of the session's commands have an annotation nearby
+pub fn annotation_coverage(session: &Session) -> AnnotationCoverage {
+    let annotated: Vec<bool> = session
+        .commands
+        .iter()
+        .map(|command| {
+            session
+                .annotations
+                .iter()
+                .any(|annotation| (annotation.timestamp - command.timestamp).num_seconds().abs() <= NEARBY_ANNOTATION_SECONDS)
+        })
+        .collect();
+
+    let mut unnarrated_runs = Vec::new();
+    let mut run_start = None;
+    for (index, is_annotated) in annotated.iter().chain(std::iter::once(&true)).enumerate() {
+        match (run_start, is_annotated) {
+            (None, false) => run_start = Some(index),
+            (Some(start), true) => {
+                if index - start >= MIN_UNNARRATED_RUN {
+                    unnarrated_runs.push(UnnarratedRun {
+                        first: step(session, start),
+                        last: step(session, index - 1),
+                        command_count: index - start,
+                        phase: main_phase(session, start..index),
+                    });
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    unnarrated_runs.sort_by(|a, b| b.command_count.cmp(&a.command_count).then(a.first.cmp(&b.first)));
+
+    AnnotationCoverage {
+        annotated: annotated.iter().filter(|is_annotated| **is_annotated).count(),
+        total: annotated.len(),
+        unnarrated_runs,
+    }
+}
+
+/// Step number of the command at `index`; commands recorded before steps existed count by position
+fn step(session: &Session, index: usize) -> usize {
+    session.commands[index].step.unwrap_or(index + 1)
+}
+
+/// Most common classified workflow phase of a range of the session's commands
+fn main_phase(session: &Session, range: std::ops::Range<usize>) -> Option<WorkflowPhase> {
+    let mut counts: HashMap<WorkflowPhase, usize> = HashMap::new();
+    for command in &session.commands[range] {
+        let phase = WorkflowPhase::classify_command(&command.command, &CommandType::classify_command(&command.command));
+        if phase != WorkflowPhase::Other {
+            *counts.entry(phase).or_default() += 1;
+        }
+    }
+    // Ties go to the phase that comes first in the lifecycle, so the result doesn't depend on hashing
+    WorkflowPhase::DISPLAY_ORDER
+        .into_iter()
+        .filter_map(|phase| counts.get(&phase).map(|count| (phase, *count)))
+        .fold(None, |best: Option<(WorkflowPhase, usize)>, (phase, count)| match best {
+            Some((_, best_count)) if best_count >= count => best,
+            _ => Some((phase, count)),
+        })
+        .map(|(phase, _)| phase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::manager::{Annotation, AnnotationType};
+    use crate::terminal::CommandEntry;
+    use chrono::{DateTime, Duration};
+
+    #[test]
+    fn test_coverage_and_unnarrated_runs() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut session = Session::new("Release".to_string(), None).unwrap();
+        let lines = [
+            "git pull", "npm install",
+            "cargo build", "cargo build --release", "make", "make install", "cargo build --target arm",
+            "vim notes.txt",
+        ];
+        for (minute, line) in lines.iter().enumerate() {
+            // Steps 3 and 4 were removed from the session, so later commands are numbered past their position
+            let step = if minute < 2 { minute + 1 } else { minute + 3 };
+            session.commands.push(CommandEntry {
+                command: line.to_string(),
+                step: Some(step),
+                timestamp: start + Duration::minutes(minute as i64 * 10),
+                exit_code: Some(0),
+                working_directory: "/srv".to_string(),
+                shell: "bash".to_string(),
+                ..Default::default()
+            });
+        }
+        for minute in [1, 11, 69] {
+            session.annotations.push(Annotation {
+                id: minute.to_string(),
+                text: "Explained".to_string(),
+                timestamp: start + Duration::minutes(minute),
+                annotation_type: AnnotationType::Note,
+            });
+        }
+
+        let coverage = annotation_coverage(&session);
+        assert_eq!((coverage.annotated, coverage.total, coverage.percent()), (3, 8, 37));
+        assert_eq!(coverage.unnarrated_runs, vec![UnnarratedRun { first: 5, last: 9, command_count: 5, phase: Some(WorkflowPhase::Build) }]);
+    }
+}
@@ -0,0 +1,68 @@
+//! YAML front-matter for static-site generators such as Hugo and Jekyll
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::Write;
+
+use crate::session::manager::Session;
+
+/// Build a `---` delimited YAML block describing the session, to go before the document title
+pub fn front_matter(session: &Session, title: &str, generated_at: DateTime<Utc>) -> String {
+    let date = session.started_at.unwrap_or(session.created_at);
+    let tags = session.metadata.tags.iter().map(|tag| yaml_string(tag)).collect::<Vec<_>>().join(", ");
+
+    let mut block = String::new();
+    let _ = writeln!(block, "---");
+    let _ = writeln!(block, "title: {}", yaml_string(title));
+    let _ = writeln!(block, "description: {}", yaml_string(&session.description));
+    let _ = writeln!(block, "date: {}", date.to_rfc3339_opts(SecondsFormat::Secs, true));
+    let _ = writeln!(block, "tags: [{}]", tags);
+    let _ = writeln!(block, "session_id: {}", yaml_string(&session.id));
+    let _ = writeln!(block, "shell: {}", yaml_string(&session.metadata.shell_type));
+    let _ = writeln!(block, "platform: {}", yaml_string(&session.metadata.platform));
+    let _ = writeln!(block, "generated: {}", generated_at.to_rfc3339_opts(SecondsFormat::Secs, true));
+    let _ = writeln!(block, "---");
+    let _ = writeln!(block);
+    block
+}
+
+/// Quote a value as a double-quoted YAML scalar, so `:`, `#`, quotes and newlines are safe
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04X}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter_escapes_values() {
+        let mut session = Session::new("Deploy: \"blue\" #2\nrollback".to_string(), None).unwrap();
+        session.metadata.tags = vec!["k8s".to_string(), "ops: prod".to_string()];
+        let generated_at = DateTime::parse_from_rfc3339("2026-10-16T09:30:00Z").unwrap().with_timezone(&Utc);
+
+        let block = front_matter(&session, "Deploy guide", generated_at);
+
+        assert!(block.starts_with("---\ntitle: \"Deploy guide\"\n"));
+        assert!(block.contains("description: \"Deploy: \\\"blue\\\" #2\\nrollback\"\n"));
+        assert!(block.contains("tags: [\"k8s\", \"ops: prod\"]\n"));
+        assert!(block.contains("generated: 2026-10-16T09:30:00Z\n"));
+        assert!(block.ends_with("---\n\n"));
+        assert_eq!(yaml_string("bell\u{7}"), "\"bell\\u0007\"");
+    }
+}
@@ -0,0 +1,125 @@
+//! Glossary of the tools a session used, one line each
+//!
+//! Definitions come from the LLM when AI analysis is enabled (one request for all tools) and
+//! from a built-in list of common tools otherwise, or for tools the LLM left out.
+
+use crate::terminal::CommandEntry;
+
+/// One-line definitions of common command-line tools
+const TOOL_DEFINITIONS: &[(&str, &str)] = &[
+    ("apt", "Debian and Ubuntu package manager"),
+    ("apt-get", "Debian and Ubuntu package manager (scriptable interface)"),
+    ("awk", "Pattern scanning and text processing language"),
+    ("brew", "Homebrew package manager for macOS and Linux"),
+    ("cargo", "Rust build tool and package manager"),
+    ("cat", "Prints the contents of files"),
+    ("cd", "Changes the current directory"),
+    ("chmod", "Changes file permissions"),
+    ("chown", "Changes file owner and group"),
+    ("cp", "Copies files and directories"),
+    ("curl", "Transfers data to or from a URL"),
+    ("docker", "Builds and runs containers"),
+    ("docker-compose", "Runs multi-container Docker applications"),
+    ("dnf", "Fedora and RHEL package manager"),
+    ("echo", "Prints its arguments"),
+    ("find", "Searches for files in a directory tree"),
+    ("gcc", "GNU C compiler"),
+    ("git", "Distributed version control system"),
+    ("go", "Go build tool and toolchain"),
+    ("gpg", "Encrypts, decrypts and signs data (GnuPG)"),
+    ("grep", "Searches text for lines matching a pattern"),
+    ("helm", "Package manager for Kubernetes"),
+    ("java", "Runs Java programs"),
+    ("jq", "Filters and transforms JSON"),
+    ("kill", "Sends a signal to a process"),
+    ("kubectl", "Command-line client for Kubernetes clusters"),
+    ("ln", "Creates links between files"),
+    ("ls", "Lists directory contents"),
+    ("make", "Builds targets defined in a Makefile"),
+    ("mkdir", "Creates directories"),
+    ("mv", "Moves or renames files"),
+    ("mvn", "Maven build tool for Java projects"),
+    ("mysql", "MySQL command-line client"),
+    ("node", "JavaScript runtime"),
+    ("npm", "Node.js package manager"),
+    ("npx", "Runs a command from an npm package"),
+    ("openssl", "Cryptography toolkit for keys, certificates and TLS"),
+    ("pip", "Python package installer"),
+    ("pip3", "Python 3 package installer"),
+    ("pnpm", "Disk-efficient Node.js package manager"),
+    ("ps", "Lists running processes"),
+    ("psql", "PostgreSQL command-line client"),
+    ("pwd", "Prints the current directory"),
+    ("python", "Python interpreter"),
+    ("python3", "Python 3 interpreter"),
+    ("rm", "Removes files and directories"),
+    ("rsync", "Synchronizes files locally or over SSH"),
+    ("rustc", "Rust compiler"),
+    ("rustup", "Rust toolchain installer and version manager"),
+    ("scp", "Copies files over SSH"),
+    ("sed", "Stream editor for filtering and transforming text"),
+    ("ssh", "Opens a secure shell on a remote machine"),
+    ("sudo", "Runs a command as another user, usually root"),
+    ("systemctl", "Controls systemd services"),
+    ("tail", "Prints the end of files, optionally following new lines"),
+    ("tar", "Creates and extracts archive files"),
+    ("terraform", "Provisions infrastructure from configuration files"),
+    ("top", "Shows running processes and resource usage"),
+    ("touch", "Creates empty files or updates timestamps"),
+    ("wget", "Downloads files from the web"),
+    ("yarn", "Node.js package manager"),
+    ("yum", "RHEL and CentOS package manager"),
+];
+
+/// Built-in definition of a tool, if it is a common one
+pub fn static_definition(tool: &str) -> Option<&'static str> {
+    TOOL_DEFINITIONS.iter().find(|(name, _)| *name == tool).map(|(_, definition)| *definition)
+}
+
+/// Words of a command from the program it runs, past a leading `sudo` and variable assignments
+pub fn command_words(command: &str) -> impl Iterator<Item = &str> {
+    command.split_whitespace().skip_while(|word| *word == "sudo" || word.contains('='))
+}
+
+/// The tool a command runs: its first word, past `sudo` and variable assignments, without a path
+pub fn tool_name(command: &str) -> Option<&str> {
+    let word = command_words(command).next()?;
+    Some(word.rsplit('/').next().unwrap_or(word))
+}
+
+/// Distinct tools of the commands, in alphabetical order
+pub fn session_tools(commands: &[CommandEntry]) -> Vec<String> {
+    let mut tools: Vec<String> = commands
+        .iter()
+        .filter_map(|command| tool_name(&command.command))
+        .filter(|tool| !tool.is_empty())
+        .map(str::to_string)
+        .collect();
+    tools.sort();
+    tools.dedup();
+    tools
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tools_from_first_words() {
+        assert_eq!(tool_name("sudo apt-get install -y nginx"), Some("apt-get"));
+        assert_eq!(tool_name("RUST_LOG=debug ./target/release/server --port 80"), Some("server"));
+        assert_eq!(tool_name("  "), None);
+
+        let command = |line: &str| CommandEntry {
+            command: line.to_string(),
+            exit_code: Some(0),
+            working_directory: "/srv".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        };
+        let commands = vec![command("kubectl get pods"), command("git pull"), command("kubectl apply -f app.yml")];
+        assert_eq!(session_tools(&commands), vec!["git", "kubectl"]);
+        assert_eq!(static_definition("kubectl"), Some("Command-line client for Kubernetes clusters"));
+        assert_eq!(static_definition("acmectl"), None);
+    }
+}
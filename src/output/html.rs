@@ -0,0 +1,168 @@
+//! Standalone HTML pages built from generated markdown
+//!
+//! `docpilot export --format html` renders the same markdown as `generate` and converts it here.
+//! The page carries its own `<style>` block, chosen by the template's `OutputTheme` and extended
+//! with the config's `css_classes`, so it can be opened or shared without any other files.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+
+use super::i18n::heading_anchor;
+use super::markdown::OutputTheme;
+
+/// Styles every theme starts from
+const BASE_CSS: &str = "\
+body { margin: 0 auto; max-width: 960px; padding: 2rem; line-height: 1.6; color: #24292f; background: #ffffff; }
+h1, h2, h3, h4 { line-height: 1.25; }
+pre { overflow-x: auto; padding: 1rem; border-radius: 6px; background: #f6f8fa; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 0.9em; }
+table { border-collapse: collapse; margin: 1rem 0; }
+th, td { padding: 0.4rem 0.8rem; border: 1px solid #d0d7de; text-align: left; }
+blockquote { margin: 1rem 0; padding: 0 1rem; border-left: 4px solid #d0d7de; color: #57606a; }
+";
+
+/// Styles added on top of `BASE_CSS` for a theme
+fn theme_css(theme: &OutputTheme) -> &'static str {
+    match theme {
+        OutputTheme::Minimal => "\
+body { font-family: system-ui, sans-serif; max-width: 760px; }
+th, td { border: none; border-bottom: 1px solid #eaeef2; }
+",
+        OutputTheme::Professional => "\
+body { font-family: Georgia, 'Times New Roman', serif; }
+h1, h2, h3 { font-family: system-ui, sans-serif; color: #1f3a5f; }
+h2 { border-bottom: 2px solid #1f3a5f; padding-bottom: 0.3rem; }
+th { background: #eef2f7; }
+",
+        OutputTheme::Technical => "\
+body { font-family: system-ui, sans-serif; max-width: 1100px; }
+pre { background: #1e1e1e; color: #d4d4d4; }
+h2, h3 { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+",
+        OutputTheme::Compact => "\
+body { font-family: system-ui, sans-serif; padding: 1rem; line-height: 1.4; font-size: 0.9rem; }
+pre { padding: 0.5rem; }
+th, td { padding: 0.2rem 0.5rem; }
+",
+        OutputTheme::Rich => "\
+body { font-family: system-ui, sans-serif; background: #fbfbfd; }
+h1 { color: #6f42c1; }
+h2 { color: #0b7285; border-bottom: 1px solid #c5f6fa; padding-bottom: 0.3rem; }
+pre { border-left: 4px solid #6f42c1; }
+blockquote { border-left-color: #0b7285; background: #f1fcfd; }
+",
+        OutputTheme::GitHub => "\
+body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Helvetica, Arial, sans-serif; }
+h1, h2 { border-bottom: 1px solid #d8dee4; padding-bottom: 0.3rem; }
+",
+        // Custom themes are styled entirely by their css_classes
+        OutputTheme::Custom => "",
+    }
+}
+
+/// Tags the markdown generator writes itself: collapsible sections and step and annotation anchors
+fn generated_html_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r#"^(</?details>|</?summary>|<summary>[^<>]*</summary>|<a id="[\w-]+">(</a>)?|</a>)$"#)
+            .expect("generated HTML pattern is a valid regex")
+    })
+}
+
+/// Convert markdown to an HTML fragment, giving headings the anchors the table of contents links to
+///
+/// Raw HTML is shown as text, except the tags the generator writes, so HTML typed into an
+/// annotation or session description can't run scripts or restyle the page.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH;
+    let mut events: Vec<Event> = Parser::new_ext(markdown, options)
+        .map(|event| match event {
+            Event::Html(html) | Event::InlineHtml(html) if !generated_html_regex().is_match(html.trim()) => Event::Text(html),
+            event => event,
+        })
+        .collect();
+
+    let mut used_anchors: HashMap<String, usize> = HashMap::new();
+    let mut index = 0;
+    while index < events.len() {
+        if let Event::Start(Tag::Heading { id: None, .. }) = &events[index] {
+            let text: String = events[index + 1..]
+                .iter()
+                .take_while(|event| !matches!(event, Event::End(TagEnd::Heading(_))))
+                .filter_map(|event| match event {
+                    Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                    _ => None,
+                })
+                .collect();
+            // Repeated headings get -1, -2, ... like GitHub's anchors
+            let anchor = heading_anchor(&text);
+            let seen = used_anchors.entry(anchor.clone()).or_default();
+            let anchor = if *seen == 0 { anchor } else { format!("{}-{}", anchor, seen) };
+            *seen += 1;
+            if let Event::Start(Tag::Heading { id, .. }) = &mut events[index] {
+                *id = Some(CowStr::from(anchor));
+            }
+        }
+        index += 1;
+    }
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    html
+}
+
+/// A complete HTML page for the markdown, styled for the theme
+pub fn standalone_html(markdown: &str, title: &str, theme: &OutputTheme, css_classes: &HashMap<String, String>) -> String {
+    let mut css = format!("{}{}", BASE_CSS, theme_css(theme));
+    // Sorted so exporting the same session twice gives the same file
+    for (selector, declarations) in css_classes.iter().collect::<BTreeMap<_, _>>() {
+        css.push_str(&format!("{} {{ {} }}\n", selector, declarations));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<meta name=\"generator\" content=\"DocPilot\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        css,
+        markdown_to_html(markdown),
+    )
+}
+
+/// Escape text for use in HTML content or attribute values
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standalone_page_escapes_code_and_links_headings() {
+        let markdown = "# Deploy <prod>\n\n## Commands\n\n```bash\necho `date` > /tmp/out && grep '<title>' index.html\n```\n\n## Commands\n";
+        let css_classes = HashMap::from([(".command".to_string(), "color: #0550ae;".to_string())]);
+        let page = standalone_html(markdown, "Deploy <prod>", &OutputTheme::Technical, &css_classes);
+
+        assert!(page.contains("<title>Deploy &lt;prod&gt;</title>"));
+        assert!(page.contains("echo `date` &gt; /tmp/out &amp;&amp; grep '&lt;title&gt;' index.html"));
+        assert!(page.contains("<h2 id=\"commands\">Commands</h2>"));
+        assert!(page.contains("<h2 id=\"commands-1\">Commands</h2>"));
+        assert!(page.contains("pre { background: #1e1e1e;"));
+        assert!(page.contains(".command { color: #0550ae; }"));
+    }
+
+    #[test]
+    fn test_raw_html_is_escaped_except_generated_tags() {
+        let markdown = "<a id=\"step-1\"></a>\n\nRestart <b onclick=\"x()\">now</b>\n\n<script>alert(1)</script>\n\n<details>\n<summary>✅ Successful commands: 2</summary>\n\n- `ls`\n\n</details>\n";
+        let html = markdown_to_html(markdown);
+
+        assert!(html.contains("<a id=\"step-1\"></a>"));
+        assert!(html.contains("Restart &lt;b onclick=\"x()\"&gt;now&lt;/b&gt;"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<details>\n<summary>✅ Successful commands: 2</summary>"));
+        assert!(html.contains("</details>"));
+    }
+}
@@ -0,0 +1,458 @@
+//! Translations of the fixed headings and labels in generated documentation
+//!
+//! Captured content (commands, output, annotations) is never translated. To add a
+//! language, add a `Locale` variant, its codes in `Locale::from_str`, and a table
+//! function mapping every `Label`.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Language used for the fixed text of generated documentation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+    Spanish,
+}
+
+impl Locale {
+    /// Parse a locale from a language code or name, e.g. `de`, `de-DE` or `german`
+    pub fn from_str(s: &str) -> Result<Self> {
+        let lowered = s.to_lowercase();
+        let language = lowered.split(['-', '_']).next().unwrap_or("");
+        match language {
+            "en" | "english" => Ok(Locale::English),
+            "de" | "german" | "deutsch" => Ok(Locale::German),
+            "es" | "spanish" | "español" | "espanol" => Ok(Locale::Spanish),
+            _ => Err(anyhow!("Unsupported language: {} (expected en, de or es)", s)),
+        }
+    }
+
+    /// Get the text of a label in this locale
+    pub fn label(&self, label: Label) -> &'static str {
+        match self {
+            Locale::English => english(label),
+            Locale::German => german(label),
+            Locale::Spanish => spanish(label),
+        }
+    }
+}
+
+/// Fixed text used in generated documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label {
+    SessionOverview,
+    SessionId,
+    Description,
+    Status,
+    Started,
+    Stopped,
+    Duration,
+    TableOfContents,
+    SessionMetadata,
+    Property,
+    Value,
+    WorkingDirectory,
+    ShellType,
+    Platform,
+    Hostname,
+    User,
+    LlmProvider,
+    Tags,
+    CaptureDelay,
+    SessionStatistics,
+    Metric,
+    TotalCommands,
+    SuccessfulCommands,
+    FailedCommands,
+    TerminatedBySignal,
+    InferredSuccessfulCommands,
+    InferredFailedCommands,
+    SuccessRate,
+    TotalAnnotations,
+    PauseResumeCount,
+    SessionDuration,
+    Commands,
+    NoCommandsCaptured,
+    Directory,
+    NowIn,
+    LikelyManualStep,
+    Prerequisites,
+    PrerequisitesIntro,
+    Environment,
+    EnvironmentIntro,
+    FileChanges,
+    Glossary,
+    CustomTool,
+    FileChangesIntro,
+    NoFileChanges,
+    FileChangesPending,
+    Change,
+    File,
+    FileCreated,
+    FileModified,
+    FileDeleted,
+    InstalledWith,
+    CheckedWith,
+    TimePeriod,
+    Tag,
+    Untagged,
+    Command,
+    Step,
+    OutputTruncated,
+    FullOutput,
+    OutputChangedSince,
+    OutputUnchangedSince,
+    AiSkippedTrivial,
+    AiUnavailable,
+    NestedShellInside,
+    NestedShell,
+    Timestamp,
+    Shell,
+    ExitCode,
+    Backgrounded,
+    Inferred,
+    NotCapturedLikelySucceeded,
+    NotCapturedLikelyFailed,
+    Steps,
+    RunsIfPreviousSucceeded,
+    RunsIfPreviousFailed,
+    RunsAfterPrevious,
+    RepeatedRun,
+    PackageScriptRuns,
+    AiAnalysis,
+    Annotations,
+    Annotation,
+    Warnings,
+    Milestones,
+    Explanations,
+    Notes,
+    Warning,
+    Milestone,
+    Explanation,
+    Note,
+    GeneratedOn,
+}
+
+fn english(label: Label) -> &'static str {
+    match label {
+        Label::SessionOverview => "Session Overview",
+        Label::SessionId => "Session ID",
+        Label::Description => "Description",
+        Label::Status => "Status",
+        Label::Started => "Started",
+        Label::Stopped => "Stopped",
+        Label::Duration => "Duration",
+        Label::TableOfContents => "Table of Contents",
+        Label::SessionMetadata => "Session Metadata",
+        Label::Property => "Property",
+        Label::Value => "Value",
+        Label::WorkingDirectory => "Working Directory",
+        Label::ShellType => "Shell Type",
+        Label::Platform => "Platform",
+        Label::Hostname => "Hostname",
+        Label::User => "User",
+        Label::LlmProvider => "LLM Provider",
+        Label::Tags => "Tags",
+        Label::CaptureDelay => "Capture Delay",
+        Label::SessionStatistics => "Session Statistics",
+        Label::Metric => "Metric",
+        Label::TotalCommands => "Total Commands",
+        Label::SuccessfulCommands => "Successful Commands",
+        Label::FailedCommands => "Failed Commands",
+        Label::TerminatedBySignal => "Terminated by Signal",
+        Label::InferredSuccessfulCommands => "Likely Successful (inferred from output)",
+        Label::InferredFailedCommands => "Likely Failed (inferred from output)",
+        Label::SuccessRate => "Success Rate",
+        Label::TotalAnnotations => "Total Annotations",
+        Label::PauseResumeCount => "Pause/Resume Count",
+        Label::SessionDuration => "Session Duration",
+        Label::Commands => "Commands",
+        Label::NoCommandsCaptured => "No commands were captured during this session.",
+        Label::Directory => "Directory",
+        Label::NowIn => "now in",
+        Label::LikelyManualStep => "{gap} gap — likely a manual step here",
+        Label::Prerequisites => "Prerequisites",
+        Label::PrerequisitesIntro => "Inferred from the installs and version checks in this session:",
+        Label::Environment => "Environment",
+        Label::EnvironmentIntro => "Environment variables when the session started:",
+        Label::FileChanges => "File Changes",
+        Label::Glossary => "Glossary",
+        Label::CustomTool => "project-specific or custom tool",
+        Label::FileChangesIntro => "Files matching {patterns} that changed during the session:",
+        Label::NoFileChanges => "None of the files matching {patterns} changed during the session.",
+        Label::FileChangesPending => "Changes to files matching {patterns} are recorded when the session stops.",
+        Label::Change => "Change",
+        Label::File => "File",
+        Label::FileCreated => "Created",
+        Label::FileModified => "Modified",
+        Label::FileDeleted => "Deleted",
+        Label::InstalledWith => "installed with",
+        Label::CheckedWith => "checked with",
+        Label::TimePeriod => "Time Period",
+        Label::Tag => "Tag",
+        Label::Untagged => "Untagged",
+        Label::Command => "Command",
+        Label::Step => "Step",
+        Label::OutputTruncated => "showing {shown} of {total}",
+        Label::FullOutput => "Full output ({size})",
+        Label::OutputChangedSince => "Output changes since session {session}",
+        Label::OutputUnchangedSince => "Output unchanged since session {session}",
+        Label::AiSkippedTrivial => "AI analysis skipped for {count} trivial commands",
+        Label::AiUnavailable => "skipped, no LLM provider reachable ({reason})",
+        Label::NestedShellInside => "inside: {parent}, nested shell level {depth}",
+        Label::NestedShell => "in a nested shell, level {depth}",
+        Label::Timestamp => "Timestamp",
+        Label::Shell => "Shell",
+        Label::ExitCode => "Exit Code",
+        Label::Backgrounded => "backgrounded",
+        Label::Inferred => "inferred",
+        Label::NotCapturedLikelySucceeded => "not captured; output suggests it succeeded",
+        Label::NotCapturedLikelyFailed => "not captured; output suggests it failed",
+        Label::Steps => "Steps",
+        Label::RunsIfPreviousSucceeded => "runs only if the previous step succeeded",
+        Label::RunsIfPreviousFailed => "runs only if the previous step failed",
+        Label::RunsAfterPrevious => "runs after the previous step",
+        Label::RepeatedRun => "Run {count} times in a row over {span}; the last run is shown.",
+        Label::PackageScriptRuns => "`{name}` is a script from `{file}`. It runs:",
+        Label::AiAnalysis => "AI Analysis",
+        Label::Annotations => "Annotations",
+        Label::Annotation => "Annotation",
+        Label::Warnings => "Warnings",
+        Label::Milestones => "Milestones",
+        Label::Explanations => "Explanations",
+        Label::Notes => "Notes",
+        Label::Warning => "Warning",
+        Label::Milestone => "Milestone",
+        Label::Explanation => "Explanation",
+        Label::Note => "Note",
+        Label::GeneratedOn => "Generated by DocPilot on",
+    }
+}
+
+fn german(label: Label) -> &'static str {
+    match label {
+        Label::SessionOverview => "Sitzungsübersicht",
+        Label::SessionId => "Sitzungs-ID",
+        Label::Description => "Beschreibung",
+        Label::Status => "Status",
+        Label::Started => "Gestartet",
+        Label::Stopped => "Beendet",
+        Label::Duration => "Dauer",
+        Label::TableOfContents => "Inhaltsverzeichnis",
+        Label::SessionMetadata => "Sitzungsdetails",
+        Label::Property => "Eigenschaft",
+        Label::Value => "Wert",
+        Label::WorkingDirectory => "Arbeitsverzeichnis",
+        Label::ShellType => "Shell-Typ",
+        Label::Platform => "Plattform",
+        Label::Hostname => "Hostname",
+        Label::User => "Benutzer",
+        Label::LlmProvider => "LLM-Anbieter",
+        Label::Tags => "Tags",
+        Label::CaptureDelay => "Erfassungsverzögerung",
+        Label::SessionStatistics => "Sitzungsstatistik",
+        Label::Metric => "Kennzahl",
+        Label::TotalCommands => "Befehle gesamt",
+        Label::SuccessfulCommands => "Erfolgreiche Befehle",
+        Label::FailedCommands => "Fehlgeschlagene Befehle",
+        Label::TerminatedBySignal => "Durch Signal beendet",
+        Label::InferredSuccessfulCommands => "Vermutlich erfolgreich (aus der Ausgabe geschlossen)",
+        Label::InferredFailedCommands => "Vermutlich fehlgeschlagen (aus der Ausgabe geschlossen)",
+        Label::SuccessRate => "Erfolgsquote",
+        Label::TotalAnnotations => "Anmerkungen gesamt",
+        Label::PauseResumeCount => "Pausen/Fortsetzungen",
+        Label::SessionDuration => "Sitzungsdauer",
+        Label::Commands => "Befehle",
+        Label::NoCommandsCaptured => "In dieser Sitzung wurden keine Befehle aufgezeichnet.",
+        Label::Directory => "Verzeichnis",
+        Label::NowIn => "jetzt in",
+        Label::LikelyManualStep => "{gap} Pause – vermutlich ein manueller Schritt",
+        Label::Prerequisites => "Voraussetzungen",
+        Label::PrerequisitesIntro => "Abgeleitet aus den Installationen und Versionsprüfungen dieser Sitzung:",
+        Label::Environment => "Umgebung",
+        Label::EnvironmentIntro => "Umgebungsvariablen beim Start der Sitzung:",
+        Label::FileChanges => "Dateiänderungen",
+        Label::Glossary => "Glossar",
+        Label::CustomTool => "projektspezifisches oder eigenes Werkzeug",
+        Label::FileChangesIntro => "Dateien, die {patterns} entsprechen und sich während der Sitzung geändert haben:",
+        Label::NoFileChanges => "Keine der Dateien, die {patterns} entsprechen, hat sich während der Sitzung geändert.",
+        Label::FileChangesPending => "Änderungen an Dateien, die {patterns} entsprechen, werden beim Beenden der Sitzung erfasst.",
+        Label::Change => "Änderung",
+        Label::File => "Datei",
+        Label::FileCreated => "Erstellt",
+        Label::FileModified => "Geändert",
+        Label::FileDeleted => "Gelöscht",
+        Label::InstalledWith => "installiert mit",
+        Label::CheckedWith => "geprüft mit",
+        Label::TimePeriod => "Zeitraum",
+        Label::Tag => "Tag",
+        Label::Untagged => "Ohne Tag",
+        Label::Command => "Befehl",
+        Label::Step => "Schritt",
+        Label::OutputTruncated => "{shown} von {total} angezeigt",
+        Label::FullOutput => "Vollständige Ausgabe ({size})",
+        Label::OutputChangedSince => "Änderungen der Ausgabe seit Sitzung {session}",
+        Label::OutputUnchangedSince => "Ausgabe unverändert seit Sitzung {session}",
+        Label::AiSkippedTrivial => "KI-Analyse für {count} triviale Befehle übersprungen",
+        Label::AiUnavailable => "übersprungen, kein LLM-Anbieter erreichbar ({reason})",
+        Label::NestedShellInside => "innerhalb von: {parent}, verschachtelte Shell Ebene {depth}",
+        Label::NestedShell => "in einer verschachtelten Shell, Ebene {depth}",
+        Label::Timestamp => "Zeitpunkt",
+        Label::Shell => "Shell",
+        Label::ExitCode => "Exit-Code",
+        Label::Backgrounded => "im Hintergrund",
+        Label::Inferred => "vermutet",
+        Label::NotCapturedLikelySucceeded => "nicht erfasst; laut Ausgabe vermutlich erfolgreich",
+        Label::NotCapturedLikelyFailed => "nicht erfasst; laut Ausgabe vermutlich fehlgeschlagen",
+        Label::Steps => "Schritte",
+        Label::RunsIfPreviousSucceeded => "läuft nur, wenn der vorherige Schritt erfolgreich war",
+        Label::RunsIfPreviousFailed => "läuft nur, wenn der vorherige Schritt fehlgeschlagen ist",
+        Label::RunsAfterPrevious => "läuft nach dem vorherigen Schritt",
+        Label::RepeatedRun => "{count}-mal hintereinander ausgeführt über {span}; gezeigt wird der letzte Lauf.",
+        Label::PackageScriptRuns => "`{name}` ist ein Skript aus `{file}`. Es führt aus:",
+        Label::AiAnalysis => "KI-Analyse",
+        Label::Annotations => "Anmerkungen",
+        Label::Annotation => "Anmerkung",
+        Label::Warnings => "Warnungen",
+        Label::Milestones => "Meilensteine",
+        Label::Explanations => "Erklärungen",
+        Label::Notes => "Notizen",
+        Label::Warning => "Warnung",
+        Label::Milestone => "Meilenstein",
+        Label::Explanation => "Erklärung",
+        Label::Note => "Notiz",
+        Label::GeneratedOn => "Erstellt mit DocPilot am",
+    }
+}
+
+fn spanish(label: Label) -> &'static str {
+    match label {
+        Label::SessionOverview => "Resumen de la sesión",
+        Label::SessionId => "ID de sesión",
+        Label::Description => "Descripción",
+        Label::Status => "Estado",
+        Label::Started => "Inicio",
+        Label::Stopped => "Fin",
+        Label::Duration => "Duración",
+        Label::TableOfContents => "Índice",
+        Label::SessionMetadata => "Metadatos de la sesión",
+        Label::Property => "Propiedad",
+        Label::Value => "Valor",
+        Label::WorkingDirectory => "Directorio de trabajo",
+        Label::ShellType => "Tipo de shell",
+        Label::Platform => "Plataforma",
+        Label::Hostname => "Nombre de host",
+        Label::User => "Usuario",
+        Label::LlmProvider => "Proveedor de LLM",
+        Label::Tags => "Etiquetas",
+        Label::CaptureDelay => "Retraso de captura",
+        Label::SessionStatistics => "Estadísticas de la sesión",
+        Label::Metric => "Métrica",
+        Label::TotalCommands => "Comandos totales",
+        Label::SuccessfulCommands => "Comandos correctos",
+        Label::FailedCommands => "Comandos fallidos",
+        Label::TerminatedBySignal => "Terminados por señal",
+        Label::InferredSuccessfulCommands => "Probablemente correctos (según la salida)",
+        Label::InferredFailedCommands => "Probablemente fallidos (según la salida)",
+        Label::SuccessRate => "Tasa de éxito",
+        Label::TotalAnnotations => "Anotaciones totales",
+        Label::PauseResumeCount => "Pausas/reanudaciones",
+        Label::SessionDuration => "Duración de la sesión",
+        Label::Commands => "Comandos",
+        Label::NoCommandsCaptured => "No se capturaron comandos durante esta sesión.",
+        Label::Directory => "Directorio",
+        Label::NowIn => "ahora en",
+        Label::LikelyManualStep => "pausa de {gap}: probablemente un paso manual",
+        Label::Prerequisites => "Requisitos previos",
+        Label::PrerequisitesIntro => "Deducidos de las instalaciones y comprobaciones de versión de esta sesión:",
+        Label::Environment => "Entorno",
+        Label::EnvironmentIntro => "Variables de entorno al iniciar la sesión:",
+        Label::FileChanges => "Cambios en archivos",
+        Label::Glossary => "Glosario",
+        Label::CustomTool => "herramienta propia o específica del proyecto",
+        Label::FileChangesIntro => "Archivos que coinciden con {patterns} y cambiaron durante la sesión:",
+        Label::NoFileChanges => "Ninguno de los archivos que coinciden con {patterns} cambió durante la sesión.",
+        Label::FileChangesPending => "Los cambios en archivos que coinciden con {patterns} se registran al detener la sesión.",
+        Label::Change => "Cambio",
+        Label::File => "Archivo",
+        Label::FileCreated => "Creado",
+        Label::FileModified => "Modificado",
+        Label::FileDeleted => "Eliminado",
+        Label::InstalledWith => "instalado con",
+        Label::CheckedWith => "comprobado con",
+        Label::TimePeriod => "Periodo",
+        Label::Tag => "Etiqueta",
+        Label::Untagged => "Sin etiqueta",
+        Label::Command => "Comando",
+        Label::Step => "Paso",
+        Label::OutputTruncated => "se muestran {shown} de {total}",
+        Label::FullOutput => "Salida completa ({size})",
+        Label::OutputChangedSince => "Cambios en la salida desde la sesión {session}",
+        Label::OutputUnchangedSince => "Salida sin cambios desde la sesión {session}",
+        Label::AiSkippedTrivial => "Análisis de IA omitido para {count} comandos triviales",
+        Label::AiUnavailable => "omitido, ningún proveedor de LLM accesible ({reason})",
+        Label::NestedShellInside => "dentro de: {parent}, shell anidada nivel {depth}",
+        Label::NestedShell => "en una shell anidada, nivel {depth}",
+        Label::Timestamp => "Fecha y hora",
+        Label::Shell => "Shell",
+        Label::ExitCode => "Código de salida",
+        Label::Backgrounded => "en segundo plano",
+        Label::Inferred => "inferido",
+        Label::NotCapturedLikelySucceeded => "no capturado; la salida sugiere que tuvo éxito",
+        Label::NotCapturedLikelyFailed => "no capturado; la salida sugiere que falló",
+        Label::Steps => "Pasos",
+        Label::RunsIfPreviousSucceeded => "solo se ejecuta si el paso anterior tuvo éxito",
+        Label::RunsIfPreviousFailed => "solo se ejecuta si el paso anterior falló",
+        Label::RunsAfterPrevious => "se ejecuta después del paso anterior",
+        Label::RepeatedRun => "Ejecutado {count} veces seguidas durante {span}; se muestra la última ejecución.",
+        Label::PackageScriptRuns => "`{name}` es un script de `{file}`. Ejecuta:",
+        Label::AiAnalysis => "Análisis de IA",
+        Label::Annotations => "Anotaciones",
+        Label::Annotation => "Anotación",
+        Label::Warnings => "Advertencias",
+        Label::Milestones => "Hitos",
+        Label::Explanations => "Explicaciones",
+        Label::Notes => "Notas",
+        Label::Warning => "Advertencia",
+        Label::Milestone => "Hito",
+        Label::Explanation => "Explicación",
+        Label::Note => "Nota",
+        Label::GeneratedOn => "Generado con DocPilot el",
+    }
+}
+
+/// Build the link anchor GitHub generates for a heading
+pub fn heading_anchor(heading: &str) -> String {
+    heading
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_parsing() {
+        assert_eq!(Locale::from_str("de").unwrap(), Locale::German);
+        assert_eq!(Locale::from_str("es_MX").unwrap(), Locale::Spanish);
+        assert_eq!(Locale::from_str("English").unwrap(), Locale::English);
+        assert!(Locale::from_str("tlh").is_err());
+    }
+
+    #[test]
+    fn test_heading_anchor() {
+        assert_eq!(heading_anchor("Session Metadata"), "session-metadata");
+        assert_eq!(heading_anchor("Sitzungsübersicht"), "sitzungsübersicht");
+        assert_eq!(heading_anchor("Resumen de la sesión"), "resumen-de-la-sesión");
+    }
+}
@@ -0,0 +1,57 @@
+//! Sessions as JSON for other tools
+//!
+//! `docpilot export --format json` writes the session as it is stored, with its commands,
+//! annotations, events, metadata and statistics. Timestamps are RFC 3339 strings and enums such as
+//! annotation types are lowercase names, so consumers don't need to know DocPilot's types.
+
+use anyhow::{Result, anyhow};
+use std::path::Path;
+
+use crate::session::manager::Session;
+
+/// The session as pretty-printed JSON
+pub fn session_json(session: &Session) -> Result<String> {
+    Ok(serde_json::to_string_pretty(session)? + "\n")
+}
+
+/// Write the session as JSON to `path`
+pub fn generate_json(session: &Session, path: &Path) -> Result<()> {
+    std::fs::write(path, session_json(session)?).map_err(|e| anyhow!("Cannot write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::manager::{Annotation, AnnotationType};
+    use crate::terminal::CommandEntry;
+    use chrono::DateTime;
+
+    #[test]
+    fn test_session_json_uses_plain_values() {
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut session = Session::new("Deploy".to_string(), None).unwrap();
+        session.commands.push(CommandEntry {
+            command: "make deploy".to_string(),
+            timestamp,
+            exit_code: Some(0),
+            working_directory: "/srv".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        });
+        session.annotations.push(Annotation {
+            id: "1".to_string(),
+            text: "Deploys to staging first".to_string(),
+            timestamp,
+            annotation_type: AnnotationType::Warning,
+        });
+
+        let json: serde_json::Value = serde_json::from_str(&session_json(&session).unwrap()).unwrap();
+        assert_eq!(json["commands"][0]["timestamp"], "2023-11-14T22:13:20Z");
+        assert_eq!(json["annotations"][0]["annotation_type"], "warning");
+        assert_eq!(json["description"], "Deploy");
+
+        // Sessions saved before annotation types were lowercase still load
+        let annotation: Annotation = serde_json::from_str(r#"{"id":"1","text":"t","timestamp":"2023-11-14T22:13:20Z","annotation_type":"Milestone"}"#).unwrap();
+        assert!(matches!(annotation.annotation_type, AnnotationType::Milestone));
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::session::manager::Session;
+
+/// Version of the manifest format, bumped on incompatible changes
+pub const MANIFEST_SCHEMA_VERSION: u32 = 1;
+
+/// Machine-readable record of how a document was generated
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DocumentManifest {
+    /// Version of this manifest format
+    pub schema_version: u32,
+    /// DocPilot version that generated the document
+    pub tool_version: String,
+    /// When the document was generated
+    pub generated_at: DateTime<Utc>,
+    /// Template used for generation
+    pub template: String,
+    /// File name of the document, relative to the manifest
+    pub document: String,
+    /// SHA-256 of the document content, prefixed with `sha256:`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Additional files written for split documentation, relative to the manifest
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<String>,
+    /// The session the document was generated from
+    pub session: ManifestSession,
+}
+
+/// Source session details recorded in a manifest
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ManifestSession {
+    pub id: String,
+    pub description: String,
+    pub command_count: usize,
+    pub annotation_count: usize,
+    /// SHA-256 of the captured commands and annotations, used to detect changes to the source
+    pub source_hash: String,
+}
+
+impl DocumentManifest {
+    /// Build the manifest for a generated document, hashing its current content
+    pub fn for_document(session: &Session, document_path: &Path, template: &str) -> Result<Self> {
+        let content = fs::read(document_path)
+            .map_err(|e| anyhow!("Failed to read generated document '{}': {}", document_path.display(), e))?;
+
+        Ok(Self {
+            schema_version: MANIFEST_SCHEMA_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: Utc::now(),
+            template: template.to_string(),
+            document: file_name(document_path),
+            content_hash: Some(sha256_hex(&content)),
+            parts: Vec::new(),
+            session: ManifestSession {
+                id: session.id.clone(),
+                description: session.description.clone(),
+                command_count: session.commands.len(),
+                annotation_count: session.annotations.len(),
+                source_hash: session_source_hash(session)?,
+            },
+        })
+    }
+
+    /// Record the part files of a split document
+    pub fn with_parts(mut self, parts: &[PathBuf]) -> Self {
+        self.parts = parts.iter().map(|path| file_name(path)).collect();
+        self
+    }
+
+    /// Write the manifest next to its document and return the manifest path
+    pub fn write(&self, document_path: &Path) -> Result<PathBuf> {
+        let path = manifest_path(document_path);
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+/// Get the manifest path for a document, e.g. `guide.md` → `guide.docpilot.json`
+pub fn manifest_path(document_path: &Path) -> PathBuf {
+    document_path.with_extension("docpilot.json")
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    let digest = Sha256::digest(data);
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256:{}", hex)
+}
+
+/// Hash the parts of a session that documentation is generated from
+fn session_source_hash(session: &Session) -> Result<String> {
+    let source = serde_json::to_vec(&(&session.commands, &session.annotations))?;
+    Ok(sha256_hex(&source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::manager::AnnotationType;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_path() {
+        assert_eq!(manifest_path(Path::new("docs/guide.md")), PathBuf::from("docs/guide.docpilot.json"));
+    }
+
+    #[test]
+    fn test_manifest_records_document_and_source_hashes() {
+        let temp_dir = TempDir::new().unwrap();
+        let document = temp_dir.path().join("guide.md");
+        fs::write(&document, "# Guide\n").unwrap();
+
+        let mut session = Session::new("Manifest Test".to_string(), None).unwrap();
+        session.add_annotation("First step".to_string(), AnnotationType::Note);
+
+        let manifest = DocumentManifest::for_document(&session, &document, "minimal").unwrap();
+        let path = manifest.write(&document).unwrap();
+        let loaded: DocumentManifest = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert_eq!(loaded.schema_version, MANIFEST_SCHEMA_VERSION);
+        assert_eq!(loaded.session.id, session.id);
+        assert_eq!(loaded.document, "guide.md");
+        assert_eq!(loaded.content_hash.as_deref(), Some(sha256_hex(b"# Guide\n").as_str()));
+        assert_eq!(loaded.session.source_hash, session_source_hash(&session).unwrap());
+
+        session.add_annotation("Second step".to_string(), AnnotationType::Note);
+        assert_ne!(loaded.session.source_hash, session_source_hash(&session).unwrap());
+    }
+}
@@ -1,14 +1,30 @@
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Write;
+use regex::Regex;
+use futures::StreamExt;
 
 use crate::session::manager::{Session, Annotation, AnnotationType};
+use crate::session::files::{FileChangeKind, FileTracking};
 use crate::terminal::CommandEntry;
+use crate::filter::CommandFilter;
+use crate::terminal::monitor::ChainOperator;
 use crate::llm::{AIAnalyzer, AnalysisResult, LlmConfig};
+use crate::llm::cache::format_size;
 use std::cell::RefCell;
+use std::sync::OnceLock;
 use super::codeblock::{CodeBlockGenerator, CodeBlockConfig};
+use super::i18n::{Label, Locale, heading_anchor};
+use super::timings::{Stage, StageTimings};
+use super::prerequisites::{PrerequisiteSource, infer_prerequisites};
+use super::scripts::ScriptResolver;
+use super::sanitize::clean_terminal_output;
+use super::categories::{CustomCategory, custom_categories};
+use super::glossary::{session_tools, static_definition};
+use super::baseline::{DIFF_CONTEXT_LINES, OutputBaseline, diff_lines, render_diff};
+use std::time::Instant;
 
 /// Configuration for markdown output generation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,7 +98,7 @@ pub struct TemplateOptions {
     pub include_working_directory: bool,
     /// Include command exit codes
     pub include_exit_codes: bool,
-    /// Include environment variables used
+    /// Include the environment snapshot taken when the session started
     pub include_environment_vars: bool,
     /// Maximum number of commands to include (0 = no limit)
     pub max_commands: usize,
@@ -110,6 +126,86 @@ pub struct TemplateOptions {
     pub include_command_relationships: bool,
     /// Use compact formatting for space efficiency
     pub use_compact_formatting: bool,
+    /// How annotations are ordered in the annotations section
+    #[serde(default)]
+    pub annotation_grouping: AnnotationGrouping,
+    /// Language for headings and labels; captured content is never translated
+    #[serde(default)]
+    pub locale: Locale,
+    /// Show failed commands in full and collapse runs of successful ones, for post-mortems
+    #[serde(default)]
+    pub focus_failures: bool,
+    /// List the steps of `&&`/`||`/`;` chained command lines and classify them by their main step
+    #[serde(default)]
+    pub split_chained_commands: bool,
+    /// Commands longer than this are shortened in the details table and shown in full in the
+    /// code block below it (0 = never shorten); multi-line commands are always shortened
+    #[serde(default = "default_max_inline_command_length")]
+    pub max_inline_command_length: usize,
+    /// Show consecutive identical commands as one entry with a run count and time span
+    #[serde(default)]
+    pub collapse_repeated_commands: bool,
+    /// Mark where the working directory changes in the chronological command list
+    #[serde(default = "default_show_directory_changes")]
+    pub show_directory_changes: bool,
+    /// Whether annotations are numbered across the session or separately per type
+    #[serde(default)]
+    pub annotation_numbering: AnnotationNumbering,
+    /// Add a checklist of packages and tools inferred from the session's install and version-check commands
+    #[serde(default)]
+    pub include_prerequisites: bool,
+    /// Show the command behind package scripts such as `npm run build`, read from the working directory
+    #[serde(default)]
+    pub resolve_package_scripts: bool,
+    /// Keep escape codes and `\r` redraws in captured output instead of showing what the terminal displayed
+    #[serde(default)]
+    pub preserve_raw_output: bool,
+    /// Group commands under the first tag added with `docpilot tag-command`, untagged ones last
+    #[serde(default)]
+    pub group_by_tag: bool,
+    /// Number commands by their step in the whole session instead of their position in the
+    /// document, with a `step-N` anchor on each, so references stay valid across regenerations
+    #[serde(default)]
+    pub show_step_numbers: bool,
+    /// Levels listed in the table of contents: 1 is the document's sections, each further level
+    /// one level of the command hierarchy (phases or types, nested types, then individual commands)
+    #[serde(default = "default_toc_depth")]
+    pub toc_depth: usize,
+    /// Pauses of at least this many minutes between consecutive commands are noted in the
+    /// chronological list as likely manual steps (0 = don't note pauses)
+    #[serde(default = "default_gap_threshold_minutes")]
+    pub gap_threshold_minutes: u64,
+    /// End the document with a glossary defining each distinct tool the session used
+    #[serde(default)]
+    pub include_glossary: bool,
+    /// Note which commands ran in a nested shell (subshell, `docker exec`, ...) and what started it
+    #[serde(default)]
+    pub show_nesting: bool,
+}
+
+/// Longest command shown in full inside the command details table
+pub const DEFAULT_MAX_INLINE_COMMAND_LENGTH: usize = 80;
+
+fn default_max_inline_command_length() -> usize {
+    DEFAULT_MAX_INLINE_COMMAND_LENGTH
+}
+
+/// Table of contents levels listed unless configured: sections, then phases and the command types in them
+pub const DEFAULT_TOC_DEPTH: usize = 3;
+
+fn default_toc_depth() -> usize {
+    DEFAULT_TOC_DEPTH
+}
+
+/// Pause between commands, in minutes, noted as a likely manual step unless configured
+pub const DEFAULT_GAP_THRESHOLD_MINUTES: u64 = 5;
+
+fn default_gap_threshold_minutes() -> u64 {
+    DEFAULT_GAP_THRESHOLD_MINUTES
+}
+
+fn default_show_directory_changes() -> bool {
+    true
 }
 
 impl Default for MarkdownConfig {
@@ -181,6 +277,23 @@ impl Default for TemplateOptions {
             verbosity_level: VerbosityLevel::default(),
             include_command_relationships: false,
             use_compact_formatting: false,
+            annotation_grouping: AnnotationGrouping::default(),
+            locale: Locale::default(),
+            focus_failures: false,
+            split_chained_commands: false,
+            max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+            collapse_repeated_commands: false,
+            show_directory_changes: true,
+            annotation_numbering: AnnotationNumbering::default(),
+            include_prerequisites: false,
+            resolve_package_scripts: false,
+            preserve_raw_output: false,
+            group_by_tag: false,
+            toc_depth: DEFAULT_TOC_DEPTH,
+            show_step_numbers: false,
+            gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+            include_glossary: false,
+            show_nesting: false,
         }
     }
 }
@@ -206,10 +319,88 @@ pub struct AIAnalysisConfig {
     pub max_recommendations: usize,
     /// Minimum confidence score to include analysis (0.0-1.0)
     pub min_confidence_score: f32,
-    /// Enable analysis caching for performance
+    /// Reuse analysis from earlier runs kept in the on-disk cache
     pub enable_caching: bool,
     /// Custom analysis prompt context
     pub custom_context: Option<String>,
+    /// Depth of the per-command analysis
+    #[serde(default)]
+    pub mode: AIAnalysisMode,
+    /// Fix spelling and grammar of annotations in the document; the session keeps the original text
+    #[serde(default)]
+    pub polish_annotations: bool,
+    /// Commands scoring below this on `command_significance` are not analyzed (0 = analyze all)
+    #[serde(default = "default_min_significance")]
+    pub min_significance: u8,
+}
+
+/// Significance of commands that are always analyzed: failures, security and development tools
+pub const MAX_SIGNIFICANCE: u8 = 5;
+
+/// Significance a command needs for AI analysis unless configured otherwise
+pub const DEFAULT_AI_MIN_SIGNIFICANCE: u8 = 2;
+
+fn default_min_significance() -> u8 {
+    DEFAULT_AI_MIN_SIGNIFICANCE
+}
+
+/// Commands that only look around or move between directories
+const NAVIGATION_COMMANDS: &[&str] = &[
+    "cd", "pwd", "ls", "ll", "la", "dir", "tree", "pushd", "popd", "dirs",
+    "clear", "echo", "whoami", "which", "history", "exit", "date",
+];
+
+/// How worthwhile AI analysis of a command is, from 0 (navigation) to `MAX_SIGNIFICANCE`
+///
+/// Failed commands and security or development commands always score `MAX_SIGNIFICANCE`. Other
+/// commands start from their type and gain a point for pipes, chains or redirects and one for
+/// being long or taking many arguments.
+pub fn command_significance(command: &CommandEntry) -> u8 {
+    if command.exit_code.is_some_and(|code| code != 0) {
+        return MAX_SIGNIFICANCE;
+    }
+
+    let line = command.command.trim();
+    let combined = line.contains(['|', ';', '>', '<']) || line.contains("&&");
+    let first_word = line.split_whitespace().next().unwrap_or("");
+    if !combined && NAVIGATION_COMMANDS.contains(&first_word) {
+        return 0;
+    }
+
+    let mut significance = match CommandType::classify_command(line) {
+        CommandType::Security | CommandType::Development => return MAX_SIGNIFICANCE,
+        CommandType::Network | CommandType::System | CommandType::PackageManagement
+        | CommandType::VersionControl | CommandType::Database => 2,
+        _ => 1,
+    };
+    if combined {
+        significance += 1;
+    }
+    if line.len() > 60 || line.split_whitespace().count() > 6 {
+        significance += 1;
+    }
+    significance.min(MAX_SIGNIFICANCE)
+}
+
+/// How much AI analysis is generated for each command
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AIAnalysisMode {
+    /// Full analysis with issues, alternatives, insights and recommendations
+    #[default]
+    Full,
+    /// A single plain-English sentence per command, much cheaper and faster
+    Explain,
+}
+
+impl AIAnalysisMode {
+    /// Parse an analysis mode from its command line name
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "full" => Ok(AIAnalysisMode::Full),
+            "explain" => Ok(AIAnalysisMode::Explain),
+            _ => Err(anyhow!("Unknown AI mode: {} (expected full or explain)", s)),
+        }
+    }
 }
 
 impl Default for AIAnalysisConfig {
@@ -226,6 +417,9 @@ impl Default for AIAnalysisConfig {
             min_confidence_score: 0.7,
             enable_caching: true,
             custom_context: None,
+            mode: AIAnalysisMode::default(),
+            polish_annotations: false,
+            min_significance: DEFAULT_AI_MIN_SIGNIFICANCE,
         }
     }
 }
@@ -255,11 +449,57 @@ pub enum CommandType {
     Security,
     /// Other/uncategorized commands
     Other,
+    /// User-defined category from `categories.json`
+    Custom(CustomCategory),
 }
 
 impl CommandType {
+    /// Order of the built-in command types in documents
+    pub const BUILT_IN_ORDER: [CommandType; 11] = [
+        CommandType::FileSystem,
+        CommandType::Development,
+        CommandType::VersionControl,
+        CommandType::PackageManagement,
+        CommandType::System,
+        CommandType::Network,
+        CommandType::Database,
+        CommandType::TextProcessing,
+        CommandType::Monitoring,
+        CommandType::Security,
+        CommandType::Other,
+    ];
+
+    /// Order in which command type groups appear in documents, with custom categories before `Other`
+    pub fn display_order() -> Vec<CommandType> {
+        let (other, built_in) = Self::BUILT_IN_ORDER.split_last().expect("built-in order is not empty");
+        built_in
+            .iter()
+            .cloned()
+            .chain(custom_categories().iter().cloned().map(CommandType::Custom))
+            .chain(std::iter::once(other.clone()))
+            .collect()
+    }
+
+    /// Name of the command type in headings, e.g. `FileSystem` in "FileSystem Commands"
+    pub fn name(&self) -> &str {
+        match self {
+            CommandType::FileSystem => "FileSystem",
+            CommandType::Network => "Network",
+            CommandType::System => "System",
+            CommandType::Development => "Development",
+            CommandType::PackageManagement => "PackageManagement",
+            CommandType::TextProcessing => "TextProcessing",
+            CommandType::VersionControl => "VersionControl",
+            CommandType::Database => "Database",
+            CommandType::Monitoring => "Monitoring",
+            CommandType::Security => "Security",
+            CommandType::Other => "Other",
+            CommandType::Custom(category) => &category.name,
+        }
+    }
+
     /// Get a human-readable description of the command type
-    pub fn description(&self) -> &'static str {
+    pub fn description(&self) -> &str {
         match self {
             CommandType::FileSystem => "File and directory operations including navigation, creation, deletion, and permissions",
             CommandType::Network => "Network connectivity, transfers, and communication commands",
@@ -272,11 +512,12 @@ impl CommandType {
             CommandType::Monitoring => "System monitoring, process management, and diagnostics",
             CommandType::Security => "Security tools, encryption, and access control",
             CommandType::Other => "Miscellaneous commands that don't fit other categories",
+            CommandType::Custom(category) => category.description(),
         }
     }
 
     /// Get an emoji icon for the command type
-    pub fn icon(&self) -> &'static str {
+    pub fn icon(&self) -> &str {
         match self {
             CommandType::FileSystem => "📁",
             CommandType::Network => "🌐",
@@ -289,11 +530,21 @@ impl CommandType {
             CommandType::Monitoring => "📊",
             CommandType::Security => "🔒",
             CommandType::Other => "🔧",
+            CommandType::Custom(category) => &category.icon,
         }
     }
 
-    /// Classify a command based on its content
+    /// Classify a command based on its content, checking the user's custom categories first
     pub fn classify_command(command: &str) -> Self {
+        Self::classify_with_categories(command, custom_categories())
+    }
+
+    /// Classify a command, checking `categories` in order before the built-in types
+    pub fn classify_with_categories(command: &str, categories: &[CustomCategory]) -> Self {
+        if let Some(category) = categories.iter().find(|category| category.matches(command)) {
+            return CommandType::Custom(category.clone());
+        }
+
         let cmd = command.trim().to_lowercase();
         let first_word = cmd.split_whitespace().next().unwrap_or("");
 
@@ -355,6 +606,18 @@ pub enum WorkflowPhase {
 }
 
 impl WorkflowPhase {
+    /// Order in which workflow phase groups appear in documents, following the development lifecycle
+    pub const DISPLAY_ORDER: [WorkflowPhase; 8] = [
+        WorkflowPhase::Setup,
+        WorkflowPhase::Development,
+        WorkflowPhase::Build,
+        WorkflowPhase::Testing,
+        WorkflowPhase::Deployment,
+        WorkflowPhase::Maintenance,
+        WorkflowPhase::Debugging,
+        WorkflowPhase::Other,
+    ];
+
     /// Get a human-readable description of the workflow phase
     pub fn description(&self) -> &'static str {
         match self {
@@ -506,6 +769,103 @@ impl Default for VerbosityLevel {
     }
 }
 
+/// Ordering of the annotations section
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnnotationGrouping {
+    /// Annotations in the order they were added
+    #[default]
+    Chronological,
+    /// Annotations grouped under a subsection per annotation type
+    ByType,
+}
+
+/// How annotations are numbered in the annotations section
+///
+/// Numbers double as link targets for `[[ann:N]]` and `[[warning:N]]`-style references.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnnotationNumbering {
+    /// One sequence across all annotations: Annotation 1, 2, 3, ...
+    #[default]
+    Global,
+    /// A sequence per annotation type: Warning 1, Note 1, Note 2, ...
+    PerType,
+}
+
+/// Chronological and per-type number of an annotation, both counting from 1
+#[derive(Debug, Clone, Copy)]
+struct AnnotationNumber {
+    global: usize,
+    per_type: usize,
+}
+
+/// Number every annotation in the order they were added
+fn number_annotations(annotations: &[Annotation]) -> Vec<AnnotationNumber> {
+    let mut per_type_counts: HashMap<&'static str, usize> = HashMap::new();
+    annotations
+        .iter()
+        .enumerate()
+        .map(|(index, annotation)| {
+            let count = per_type_counts.entry(annotation_type_key(&annotation.annotation_type)).or_default();
+            *count += 1;
+            AnnotationNumber { global: index + 1, per_type: *count }
+        })
+        .collect()
+}
+
+/// Annotation references in annotation text, e.g. `[[ann:3]]` or `[[warning:2]]`
+fn annotation_reference_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\[\[(ann|note|explanation|warning|milestone):(\d+)\]\]").expect("valid reference pattern"))
+}
+
+/// Name of an annotation type in references and anchors, e.g. `warning` in `[[warning:2]]`
+fn annotation_type_key(annotation_type: &AnnotationType) -> &'static str {
+    match annotation_type {
+        AnnotationType::Note => "note",
+        AnnotationType::Explanation => "explanation",
+        AnnotationType::Warning => "warning",
+        AnnotationType::Milestone => "milestone",
+    }
+}
+
+/// Group commands by type, in `CommandType::display_order()` and without empty groups
+fn group_by_command_type(commands: &[CommandEntry]) -> Vec<(CommandType, Vec<&CommandEntry>)> {
+    CommandType::display_order()
+        .into_iter()
+        .map(|cmd_type| {
+            let group = commands.iter().filter(|command| CommandType::classify_command(&command.command) == cmd_type).collect();
+            (cmd_type, group)
+        })
+        .filter(|(_, group): &(CommandType, Vec<&CommandEntry>)| !group.is_empty())
+        .collect()
+}
+
+/// Heading of a workflow phase when commands are grouped by phase only
+fn workflow_heading(phase: &WorkflowPhase) -> String {
+    format!("{} {:?} - {}", phase.icon(), phase, phase.description())
+}
+
+/// Heading of a workflow phase with its commands grouped by type
+fn phase_heading(phase: &WorkflowPhase) -> String {
+    format!("{} {:?} Phase", phase.icon(), phase)
+}
+
+fn command_type_heading(cmd_type: &CommandType) -> String {
+    format!("{} {} Commands", cmd_type.icon(), cmd_type.name())
+}
+
+/// Write a TOC list entry `indent` levels deep linking to `heading`
+///
+/// A heading that appeared before gets the `-1`, `-2`, ... suffix GitHub gives its anchor.
+fn write_toc_entry(content: &mut String, anchors: &mut HashMap<String, usize>, indent: usize, title: &str, heading: &str) -> Result<()> {
+    let anchor = heading_anchor(heading);
+    let seen = anchors.entry(anchor.clone()).or_insert(0);
+    let anchor = if *seen == 0 { anchor } else { format!("{}-{}", anchor, seen) };
+    *seen += 1;
+    writeln!(content, "{}- [{}](#{})", "  ".repeat(indent), title, anchor)?;
+    Ok(())
+}
+
 /// Hierarchical structure for organizing commands
 #[derive(Debug, Clone)]
 pub struct HierarchicalStructure {
@@ -520,13 +880,19 @@ pub struct HierarchicalStructure {
 impl HierarchicalStructure {
     /// Create a new hierarchical structure from a list of commands
     pub fn new(commands: &[CommandEntry]) -> Self {
+        Self::with_chain_splitting(commands, false)
+    }
+
+    /// Create a hierarchical structure, optionally classifying chained command lines by their main step
+    pub fn with_chain_splitting(commands: &[CommandEntry], split_chains: bool) -> Self {
         let mut workflow_groups: HashMap<WorkflowPhase, Vec<CommandEntry>> = HashMap::new();
         let mut type_groups: HashMap<CommandType, Vec<CommandEntry>> = HashMap::new();
         let mut nested_groups: HashMap<String, HashMap<String, Vec<CommandEntry>>> = HashMap::new();
 
         for command in commands {
-            let command_type = CommandType::classify_command(&command.command);
-            let workflow_phase = WorkflowPhase::classify_command(&command.command, &command_type);
+            let classified = if split_chains { Self::main_chain_step(&command.command) } else { command.command.clone() };
+            let command_type = CommandType::classify_command(&classified);
+            let workflow_phase = WorkflowPhase::classify_command(&classified, &command_type);
 
             // Group by workflow phase
             workflow_groups
@@ -541,7 +907,7 @@ impl HierarchicalStructure {
                 .push(command.clone());
 
             // Create nested grouping (type -> directory -> commands)
-            let type_key = format!("{:?}", command_type);
+            let type_key = command_type.name().to_string();
             nested_groups
                 .entry(type_key)
                 .or_insert_with(HashMap::new)
@@ -556,6 +922,68 @@ impl HierarchicalStructure {
             nested_groups,
         }
     }
+
+    /// The step that characterizes a chained line, skipping leading directory changes (`cd app && make`)
+    fn main_chain_step(command: &str) -> String {
+        let steps = CommandEntry::chain_steps(command);
+        steps
+            .iter()
+            .find(|step| !matches!(step.command.split_whitespace().next(), Some("cd" | "pushd" | "popd")))
+            .or(steps.first())
+            .map(|step| step.command.clone())
+            .unwrap_or_else(|| command.to_string())
+    }
+}
+
+/// A run of consecutive identical commands collapsed into its last entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatedRun {
+    /// How many times the command ran in a row
+    pub count: usize,
+    /// When the first run of the command started
+    pub first_timestamp: DateTime<Utc>,
+}
+
+/// Collapse consecutive runs of the same command in the same directory into their last entry,
+/// which carries the final exit code and output
+///
+/// Returns the remaining commands and, for each collapsed entry, its position in them.
+pub fn collapse_repeated_commands(commands: &[CommandEntry]) -> (Vec<CommandEntry>, HashMap<usize, RepeatedRun>) {
+    let mut collapsed: Vec<CommandEntry> = Vec::new();
+    let mut runs: HashMap<usize, RepeatedRun> = HashMap::new();
+
+    for command in commands {
+        let index = collapsed.len().saturating_sub(1);
+        if let Some(last) = collapsed.last_mut()
+            && last.command.trim() == command.command.trim()
+            && last.working_directory == command.working_directory
+        {
+            let run = runs.entry(index).or_insert(RepeatedRun { count: 1, first_timestamp: last.timestamp });
+            run.count += 1;
+            *last = command.clone();
+        } else {
+            collapsed.push(command.clone());
+        }
+    }
+
+    (collapsed, runs)
+}
+
+/// A session's annotations with their numbers, for rendering and resolving references
+struct AnnotationSet<'a> {
+    annotations: &'a [Annotation],
+    numbers: &'a [AnnotationNumber],
+}
+
+/// Rough length of a pause for a timeline note, e.g. `~5 min` or `~1 h 20 min`
+fn format_gap(seconds: u64) -> String {
+    let minutes = (seconds + 30) / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => format!("~{} s", seconds),
+        (0, minutes) => format!("~{} min", minutes),
+        (hours, 0) => format!("~{} h", hours),
+        (hours, minutes) => format!("~{} h {} min", hours, minutes),
+    }
 }
 
 /// Markdown template system for generating documentation
@@ -563,6 +991,20 @@ pub struct MarkdownTemplate {
     config: MarkdownConfig,
     code_block_generator: CodeBlockGenerator,
     ai_analyzer: Option<RefCell<AIAnalyzer>>,
+    /// Collapsed runs of the document being generated, keyed by the step number of their last run
+    repeated_runs: RefCell<HashMap<usize, RepeatedRun>>,
+    /// Where to record stage durations, when generation is being timed
+    stage_timings: Option<StageTimings>,
+    /// Package scripts of the directories commands ran in
+    package_scripts: ScriptResolver,
+    /// Commands of the document being generated that were too trivial for AI analysis
+    trivial_commands: RefCell<Vec<String>>,
+    /// Earlier session whose command output is compared with this one's
+    baseline: Option<OutputBaseline>,
+    /// Baseline output of the document's commands, keyed by their step number
+    baseline_outputs: RefCell<HashMap<usize, String>>,
+    /// Why AI analysis was skipped because no LLM provider could be reached
+    ai_unavailable: Option<String>,
 }
 
 impl MarkdownTemplate {
@@ -574,6 +1016,13 @@ impl MarkdownTemplate {
             config,
             code_block_generator,
             ai_analyzer: None,
+            repeated_runs: RefCell::new(HashMap::new()),
+            stage_timings: None,
+            package_scripts: ScriptResolver::new(),
+            trivial_commands: RefCell::new(Vec::new()),
+            baseline: None,
+            baseline_outputs: RefCell::new(HashMap::new()),
+            ai_unavailable: None,
         }
     }
 
@@ -584,17 +1033,57 @@ impl MarkdownTemplate {
             config,
             code_block_generator,
             ai_analyzer: None,
+            repeated_runs: RefCell::new(HashMap::new()),
+            stage_timings: None,
+            package_scripts: ScriptResolver::new(),
+            trivial_commands: RefCell::new(Vec::new()),
+            baseline: None,
+            baseline_outputs: RefCell::new(HashMap::new()),
+            ai_unavailable: None,
         }
     }
 
     /// Set up AI analyzer with LLM configuration
     pub fn with_ai_analyzer(mut self, llm_config: LlmConfig) -> Self {
-        if self.config.ai_analysis_config.enable_ai_explanations {
-            self.ai_analyzer = Some(RefCell::new(AIAnalyzer::new(llm_config)));
+        if self.config.ai_analysis_config.enable_ai_explanations || self.config.ai_analysis_config.polish_annotations {
+            // Canned mock responses must not end up in the cache used for real analysis
+            let use_disk_cache = self.config.ai_analysis_config.enable_caching && !llm_config.is_mock();
+            let mut analyzer = AIAnalyzer::new(llm_config);
+            if use_disk_cache && let Ok(cache) = crate::llm::AnalysisCache::open_default() {
+                analyzer = analyzer.with_disk_cache(cache);
+            }
+            self.ai_analyzer = Some(RefCell::new(analyzer));
         }
         self
     }
 
+    /// Record how long each generation stage takes in `timings`
+    pub fn with_stage_timings(mut self, timings: Option<StageTimings>) -> Self {
+        self.stage_timings = timings;
+        self
+    }
+
+    /// Compare each command's output with the matching command of `baseline`
+    pub fn with_baseline(mut self, baseline: Option<OutputBaseline>) -> Self {
+        self.baseline = baseline;
+        self
+    }
+
+    /// Add the time since `start` to `stage` when generation is being timed
+    fn record_stage(&self, stage: Stage, start: Instant) {
+        if let Some(timings) = &self.stage_timings {
+            timings.add_since(stage, start);
+        }
+    }
+
+    /// Group the session's commands into workflow phases and command types
+    fn build_hierarchy(&self, session: &Session) -> HierarchicalStructure {
+        let start = Instant::now();
+        let hierarchy = HierarchicalStructure::with_chain_splitting(&session.commands, self.config.template_options.split_chained_commands);
+        self.record_stage(Stage::Structuring, start);
+        hierarchy
+    }
+
     /// Update the configuration
     pub fn set_config(&mut self, config: MarkdownConfig) {
         self.code_block_generator.set_config(config.code_block_config.clone());
@@ -608,14 +1097,68 @@ impl MarkdownTemplate {
 
     /// Generate markdown content from a session
     pub async fn generate(&self, session: &Session) -> Result<String> {
+        let start = Instant::now();
+        let nested_before = self.stage_timings.as_ref().map(StageTimings::total).unwrap_or_default();
         let mut content = String::new();
+        self.trivial_commands.borrow_mut().clear();
+
+        // Notes gathered for individual commands are looked up by step number, which sessions
+        // built in memory may not have assigned yet
+        let numbered_session;
+        let session = if session.commands.iter().any(|command| command.step.is_none()) {
+            let mut numbered = session.clone();
+            numbered.number_steps();
+            numbered_session = numbered;
+            &numbered_session
+        } else {
+            session
+        };
+
+        *self.baseline_outputs.borrow_mut() = match &self.baseline {
+            Some(baseline) => baseline.match_outputs(&session.commands)
+                .into_iter()
+                .filter_map(|(index, output)| Some((session.commands[index].step?, output)))
+                .collect(),
+            None => HashMap::new(),
+        };
+
+        // Only the document gets the polished wording; the session keeps what was typed
+        let polished_session;
+        let session = if self.config.ai_analysis_config.polish_annotations && !session.annotations.is_empty() {
+            polished_session = self.polish_annotations(session).await;
+            &polished_session
+        } else {
+            session
+        };
 
         // Generate document header
         self.write_header(&mut content, session)?;
 
+        // Statistics and annotations cover every run; the command listing shows repeats once
+        let collapsed_session;
+        let listed_session = if self.config.template_options.collapse_repeated_commands {
+            let collapse_start = Instant::now();
+            let (commands, runs) = collapse_repeated_commands(&session.commands);
+            self.record_stage(Stage::Filtering, collapse_start);
+            *self.repeated_runs.borrow_mut() = runs
+                .into_iter()
+                .filter_map(|(index, run)| Some((commands[index].step?, run)))
+                .collect();
+            collapsed_session = Session { commands, ..session.clone() };
+            &collapsed_session
+        } else {
+            self.repeated_runs.borrow_mut().clear();
+            session
+        };
+
         // Generate table of contents if enabled
         if self.config.template_options.include_toc {
-            self.write_table_of_contents(&mut content, session)?;
+            self.write_table_of_contents(&mut content, listed_session)?;
+        }
+
+        // What a reader needs before following along
+        if self.config.template_options.include_prerequisites {
+            self.write_prerequisites(&mut content, session)?;
         }
 
         // Generate session metadata
@@ -623,22 +1166,43 @@ impl MarkdownTemplate {
             self.write_metadata(&mut content, session)?;
         }
 
+        // Recorded once at the start rather than per command, which would repeat it everywhere
+        if self.config.template_options.include_environment_vars {
+            self.write_environment(&mut content, session)?;
+        }
+
         // Generate session statistics
         if self.config.include_statistics {
             self.write_statistics(&mut content, session)?;
         }
 
         // Generate commands section
-        self.write_commands(&mut content, session).await?;
+        self.write_commands(&mut content, listed_session).await?;
+
+        // What the commands changed on disk, for sessions started with --track-files
+        if let Some(tracking) = &session.metadata.file_tracking {
+            self.write_file_changes(&mut content, tracking)?;
+        }
 
         // Generate annotations section
         if self.config.include_annotations && !session.annotations.is_empty() {
             self.write_annotations(&mut content, session)?;
         }
 
+        // Definitions of the tools used, for readers new to the toolchain
+        if self.config.template_options.include_glossary {
+            self.write_glossary(&mut content, session).await?;
+        }
+
         // Generate document footer
         self.write_footer(&mut content, session)?;
 
+        // Markdown writing is what remains after the stages recorded while writing
+        if let Some(timings) = &self.stage_timings {
+            let nested = timings.total().saturating_sub(nested_before);
+            timings.add(Stage::Markdown, start.elapsed().saturating_sub(nested));
+        }
+
         Ok(content)
     }
 
@@ -657,22 +1221,22 @@ impl MarkdownTemplate {
         }
 
         // Add session overview
-        writeln!(content, "## Session Overview")?;
+        writeln!(content, "## {}", self.label(Label::SessionOverview))?;
         writeln!(content)?;
-        writeln!(content, "**Session ID:** `{}`", session.id)?;
-        writeln!(content, "**Description:** {}", session.description)?;
-        writeln!(content, "**Status:** {:?}", session.state)?;
+        writeln!(content, "**{}:** `{}`", self.label(Label::SessionId), session.id)?;
+        writeln!(content, "**{}:** {}", self.label(Label::Description), session.description)?;
+        writeln!(content, "**{}:** {:?}", self.label(Label::Status), session.state)?;
         
         if let Some(started_at) = session.started_at {
-            writeln!(content, "**Started:** {}", self.format_timestamp(started_at))?;
+            writeln!(content, "**{}:** {}", self.label(Label::Started), self.format_timestamp(started_at))?;
         }
         
         if let Some(stopped_at) = session.stopped_at {
-            writeln!(content, "**Stopped:** {}", self.format_timestamp(stopped_at))?;
+            writeln!(content, "**{}:** {}", self.label(Label::Stopped), self.format_timestamp(stopped_at))?;
         }
 
         if let Some(duration) = session.get_duration_seconds() {
-            writeln!(content, "**Duration:** {}", self.format_duration(duration))?;
+            writeln!(content, "**{}:** {}", self.label(Label::Duration), self.format_duration(duration))?;
         }
 
         writeln!(content)?;
@@ -682,67 +1246,84 @@ impl MarkdownTemplate {
 
     /// Write table of contents
     fn write_table_of_contents(&self, content: &mut String, session: &Session) -> Result<()> {
-        writeln!(content, "## Table of Contents")?;
+        writeln!(content, "## {}", self.label(Label::TableOfContents))?;
         writeln!(content)?;
 
+        let mut anchors = HashMap::new();
+
+        if self.config.template_options.include_prerequisites && !infer_prerequisites(&session.commands).is_empty() {
+            self.write_toc_link(content, &mut anchors, Label::Prerequisites)?;
+        }
+
         if self.config.include_metadata {
-            writeln!(content, "- [Session Metadata](#session-metadata)")?;
+            self.write_toc_link(content, &mut anchors, Label::SessionMetadata)?;
+        }
+
+        if self.config.template_options.include_environment_vars && !session.metadata.environment.is_empty() {
+            self.write_toc_link(content, &mut anchors, Label::Environment)?;
         }
 
         if self.config.include_statistics {
-            writeln!(content, "- [Session Statistics](#session-statistics)")?;
+            self.write_toc_link(content, &mut anchors, Label::SessionStatistics)?;
         }
 
-        writeln!(content, "- [Commands](#commands)")?;
+        self.write_toc_link(content, &mut anchors, Label::Commands)?;
 
-        // Add hierarchical TOC entries if enabled
-        if self.config.template_options.enable_hierarchical_structure && !session.commands.is_empty() {
-            self.write_hierarchical_toc(content, session)?;
+        // Add hierarchical TOC entries if enabled and the commands are written that way
+        let options = &self.config.template_options;
+        let hierarchical = options.enable_hierarchical_structure && !options.focus_failures && !options.group_by_tag;
+        if hierarchical && options.toc_depth > 1 && !session.commands.is_empty() {
+            self.write_hierarchical_toc(content, &mut anchors, session, options.toc_depth - 1)?;
+        }
+
+        if session.metadata.file_tracking.is_some() {
+            self.write_toc_link(content, &mut anchors, Label::FileChanges)?;
         }
 
         if self.config.include_annotations && !session.annotations.is_empty() {
-            writeln!(content, "- [Annotations](#annotations)")?;
+            self.write_toc_link(content, &mut anchors, Label::Annotations)?;
+        }
+
+        if self.config.template_options.include_glossary && !session_tools(&session.commands).is_empty() {
+            self.write_toc_link(content, &mut anchors, Label::Glossary)?;
         }
 
         writeln!(content)?;
         Ok(())
     }
 
-    /// Write hierarchical table of contents entries
-    fn write_hierarchical_toc(&self, content: &mut String, session: &Session) -> Result<()> {
-        let hierarchy = HierarchicalStructure::new(&session.commands);
+    /// Write a top-level TOC entry linking to a section heading
+    fn write_toc_link(&self, content: &mut String, anchors: &mut HashMap<String, usize>, section: Label) -> Result<()> {
+        let heading = self.label(section);
+        write_toc_entry(content, anchors, 0, heading, heading)
+    }
+
+    /// Write hierarchical table of contents entries, `depth` levels below the sections
+    fn write_hierarchical_toc(&self, content: &mut String, anchors: &mut HashMap<String, usize>, session: &Session, depth: usize) -> Result<()> {
+        let hierarchy = self.build_hierarchy(session);
 
         if self.config.template_options.group_by_workflow {
-            self.write_workflow_toc(content, &hierarchy)?;
+            self.write_workflow_toc(content, anchors, &hierarchy, depth)?;
         } else if self.config.template_options.group_by_command_type {
-            self.write_command_type_toc(content, &hierarchy)?;
+            self.write_command_type_toc(content, anchors, &hierarchy, depth)?;
         } else {
             // Default: workflow phases with nested command types
-            self.write_workflow_with_types_toc(content, &hierarchy)?;
+            self.write_workflow_with_types_toc(content, anchors, &hierarchy, depth)?;
         }
 
         Ok(())
     }
 
     /// Write workflow-based TOC entries
-    fn write_workflow_toc(&self, content: &mut String, hierarchy: &HierarchicalStructure) -> Result<()> {
-        let workflow_order = [
-            WorkflowPhase::Setup,
-            WorkflowPhase::Development,
-            WorkflowPhase::Build,
-            WorkflowPhase::Testing,
-            WorkflowPhase::Deployment,
-            WorkflowPhase::Maintenance,
-            WorkflowPhase::Debugging,
-            WorkflowPhase::Other,
-        ];
-
-        for phase in &workflow_order {
+    fn write_workflow_toc(&self, content: &mut String, anchors: &mut HashMap<String, usize>, hierarchy: &HierarchicalStructure, depth: usize) -> Result<()> {
+        for phase in &WorkflowPhase::DISPLAY_ORDER {
             if let Some(commands) = hierarchy.workflow_groups.get(phase) {
                 if !commands.is_empty() {
-                    let phase_name = format!("{:?}", phase);
-                    let anchor = phase_name.to_lowercase().replace(" ", "-");
-                    writeln!(content, "  - [{} {} Phase](#{})", phase.icon(), phase_name, anchor)?;
+                    let title = format!("{} {:?} Phase", phase.icon(), phase);
+                    write_toc_entry(content, anchors, 1, &title, &workflow_heading(phase))?;
+                    if depth > 1 {
+                        self.write_command_toc(content, anchors, 2, commands)?;
+                    }
                 }
             }
         }
@@ -751,27 +1332,15 @@ impl MarkdownTemplate {
     }
 
     /// Write command type-based TOC entries
-    fn write_command_type_toc(&self, content: &mut String, hierarchy: &HierarchicalStructure) -> Result<()> {
-        let type_order = [
-            CommandType::FileSystem,
-            CommandType::Development,
-            CommandType::VersionControl,
-            CommandType::PackageManagement,
-            CommandType::System,
-            CommandType::Network,
-            CommandType::Database,
-            CommandType::TextProcessing,
-            CommandType::Monitoring,
-            CommandType::Security,
-            CommandType::Other,
-        ];
-
-        for cmd_type in &type_order {
+    fn write_command_type_toc(&self, content: &mut String, anchors: &mut HashMap<String, usize>, hierarchy: &HierarchicalStructure, depth: usize) -> Result<()> {
+        for cmd_type in &CommandType::display_order() {
             if let Some(commands) = hierarchy.type_groups.get(cmd_type) {
                 if !commands.is_empty() {
-                    let type_name = format!("{:?}", cmd_type).replace("_", " ");
-                    let anchor = format!("{:?}", cmd_type).to_lowercase().replace("_", "-");
-                    writeln!(content, "  - [{} {} Commands](#{}-commands)", cmd_type.icon(), type_name, anchor)?;
+                    let heading = command_type_heading(cmd_type);
+                    write_toc_entry(content, anchors, 1, &heading, &heading)?;
+                    if depth > 1 {
+                        self.write_command_toc(content, anchors, 2, commands)?;
+                    }
                 }
             }
         }
@@ -780,42 +1349,28 @@ impl MarkdownTemplate {
     }
 
     /// Write workflow with nested command types TOC entries
-    fn write_workflow_with_types_toc(&self, content: &mut String, hierarchy: &HierarchicalStructure) -> Result<()> {
-        let workflow_order = [
-            WorkflowPhase::Setup,
-            WorkflowPhase::Development,
-            WorkflowPhase::Build,
-            WorkflowPhase::Testing,
-            WorkflowPhase::Deployment,
-            WorkflowPhase::Maintenance,
-            WorkflowPhase::Debugging,
-            WorkflowPhase::Other,
-        ];
-
-        for phase in &workflow_order {
+    fn write_workflow_with_types_toc(&self, content: &mut String, anchors: &mut HashMap<String, usize>, hierarchy: &HierarchicalStructure, depth: usize) -> Result<()> {
+        for phase in &WorkflowPhase::DISPLAY_ORDER {
             if let Some(workflow_commands) = hierarchy.workflow_groups.get(phase) {
                 if !workflow_commands.is_empty() {
-                    let phase_name = format!("{:?}", phase);
-                    let phase_anchor = phase_name.to_lowercase().replace(" ", "-");
-                    writeln!(content, "  - [{} {} Phase](#{})", phase.icon(), phase_name, phase_anchor)?;
-
-                    // Add nested command type entries if there are multiple types
-                    let mut phase_type_groups: HashMap<CommandType, Vec<&CommandEntry>> = HashMap::new();
-                    for command in workflow_commands {
-                        let cmd_type = CommandType::classify_command(&command.command);
-                        phase_type_groups
-                            .entry(cmd_type)
-                            .or_insert_with(Vec::new)
-                            .push(command);
+                    let heading = phase_heading(phase);
+                    write_toc_entry(content, anchors, 1, &heading, &heading)?;
+                    if depth < 2 {
+                        continue;
                     }
 
-                    if phase_type_groups.len() > 1 {
-                        for (cmd_type, type_commands) in &phase_type_groups {
-                            if !type_commands.is_empty() {
-                                let type_name = format!("{:?}", cmd_type).replace("_", " ");
-                                let type_anchor = format!("{:?}", cmd_type).to_lowercase().replace("_", "-");
-                                writeln!(content, "    - [{} {} Commands](#{})", cmd_type.icon(), type_name, type_anchor)?;
-                            }
+                    // Nested command types only have a heading of their own where the commands section writes one
+                    for (cmd_type, type_commands) in &group_by_command_type(workflow_commands) {
+                        let level = if self.has_command_type_heading(type_commands) {
+                            let heading = command_type_heading(cmd_type);
+                            write_toc_entry(content, anchors, 2, &heading, &heading)?;
+                            3
+                        } else {
+                            2
+                        };
+                        // Commands are the third level below the sections, whether or not their type has a heading
+                        if depth > 2 {
+                            self.write_command_toc(content, anchors, level, type_commands.iter().copied())?;
                         }
                     }
                 }
@@ -825,29 +1380,52 @@ impl MarkdownTemplate {
         Ok(())
     }
 
+    /// Write TOC entries for individual commands, numbered as in their group
+    fn write_command_toc<'a>(&self, content: &mut String, anchors: &mut HashMap<String, usize>, indent: usize, commands: impl IntoIterator<Item = &'a CommandEntry>) -> Result<()> {
+        for (index, command) in commands.into_iter().enumerate() {
+            let title = format!("{}: `{}`", self.command_label(command, index + 1), self.table_command(&command.command));
+            write_toc_entry(content, anchors, indent, &title, &self.command_heading(command, index + 1))?;
+        }
+        Ok(())
+    }
+
+    /// Whether the commands of one type within a workflow phase are written under a heading of their own
+    fn has_command_type_heading(&self, type_commands: &[&CommandEntry]) -> bool {
+        type_commands.len() > 1 || self.config.template_options.include_command_type_explanations
+    }
+
     /// Write session metadata
     fn write_metadata(&self, content: &mut String, session: &Session) -> Result<()> {
-        writeln!(content, "## Session Metadata")?;
+        writeln!(content, "## {}", self.label(Label::SessionMetadata))?;
         writeln!(content)?;
 
-        writeln!(content, "| Property | Value |")?;
+        writeln!(content, "| {} | {} |", self.label(Label::Property), self.label(Label::Value))?;
         writeln!(content, "|----------|-------|")?;
-        writeln!(content, "| Working Directory | `{}` |", session.metadata.working_directory.display())?;
-        writeln!(content, "| Shell Type | `{}` |", session.metadata.shell_type)?;
-        writeln!(content, "| Platform | `{}` |", session.metadata.platform)?;
-        writeln!(content, "| Hostname | `{}` |", session.metadata.hostname)?;
+        writeln!(content, "| {} | `{}` |", self.label(Label::WorkingDirectory), session.metadata.working_directory.display())?;
+        writeln!(content, "| {} | `{}` |", self.label(Label::ShellType), session.metadata.shell_type)?;
+        writeln!(content, "| {} | `{}` |", self.label(Label::Platform), session.metadata.platform)?;
+        writeln!(content, "| {} | `{}` |", self.label(Label::Hostname), session.metadata.hostname)?;
         
         if let Some(user) = &session.metadata.user {
-            writeln!(content, "| User | `{}` |", user)?;
+            writeln!(content, "| {} | `{}` |", self.label(Label::User), user)?;
         }
 
         if let Some(llm_provider) = &session.metadata.llm_provider {
-            writeln!(content, "| LLM Provider | `{}` |", llm_provider)?;
+            writeln!(content, "| {} | `{}` |", self.label(Label::LlmProvider), llm_provider)?;
         }
 
         if !session.metadata.tags.is_empty() {
             let tags = session.metadata.tags.join(", ");
-            writeln!(content, "| Tags | `{}` |", tags)?;
+            writeln!(content, "| {} | `{}` |", self.label(Label::Tags), tags)?;
+        }
+
+        if let Some(delay) = session.capture_delay() {
+            writeln!(content, "| {} | {} s |", self.label(Label::CaptureDelay), delay)?;
+        }
+
+        if let Some(reason) = &self.ai_unavailable {
+            let note = self.label(Label::AiUnavailable).replace("{reason}", reason);
+            writeln!(content, "| {} | {} |", self.label(Label::AiAnalysis), note)?;
         }
 
         writeln!(content)?;
@@ -856,7 +1434,7 @@ impl MarkdownTemplate {
 
     /// Write session statistics
     fn write_statistics(&self, content: &mut String, session: &Session) -> Result<()> {
-        writeln!(content, "## Session Statistics")?;
+        writeln!(content, "## {}", self.label(Label::SessionStatistics))?;
         writeln!(content)?;
 
         let stats = &session.stats;
@@ -866,17 +1444,26 @@ impl MarkdownTemplate {
             0.0
         };
 
-        writeln!(content, "| Metric | Value |")?;
+        writeln!(content, "| {} | {} |", self.label(Label::Metric), self.label(Label::Value))?;
         writeln!(content, "|--------|-------|")?;
-        writeln!(content, "| Total Commands | {} |", stats.total_commands)?;
-        writeln!(content, "| Successful Commands | {} |", stats.successful_commands)?;
-        writeln!(content, "| Failed Commands | {} |", stats.failed_commands)?;
-        writeln!(content, "| Success Rate | {:.1}% |", success_rate)?;
-        writeln!(content, "| Total Annotations | {} |", stats.total_annotations)?;
-        writeln!(content, "| Pause/Resume Count | {} |", stats.pause_resume_count)?;
+        writeln!(content, "| {} | {} |", self.label(Label::TotalCommands), stats.total_commands)?;
+        writeln!(content, "| {} | {} |", self.label(Label::SuccessfulCommands), stats.successful_commands)?;
+        writeln!(content, "| {} | {} |", self.label(Label::FailedCommands), stats.failed_commands)?;
+        if stats.terminated_commands > 0 {
+            writeln!(content, "| {} | {} |", self.label(Label::TerminatedBySignal), stats.terminated_commands)?;
+        }
+        // Guesses from output are kept apart from the counts based on real exit codes
+        let (inferred_successful, inferred_failed) = session.inferred_outcome_counts();
+        if inferred_successful + inferred_failed > 0 {
+            writeln!(content, "| {} | {} |", self.label(Label::InferredSuccessfulCommands), inferred_successful)?;
+            writeln!(content, "| {} | {} |", self.label(Label::InferredFailedCommands), inferred_failed)?;
+        }
+        writeln!(content, "| {} | {:.1}% |", self.label(Label::SuccessRate), success_rate)?;
+        writeln!(content, "| {} | {} |", self.label(Label::TotalAnnotations), stats.total_annotations)?;
+        writeln!(content, "| {} | {} |", self.label(Label::PauseResumeCount), stats.pause_resume_count)?;
 
         if let Some(duration) = stats.duration_seconds {
-            writeln!(content, "| Session Duration | {} |", self.format_duration(duration))?;
+            writeln!(content, "| {} | {} |", self.label(Label::SessionDuration), self.format_duration(duration))?;
         }
 
         writeln!(content)?;
@@ -885,17 +1472,21 @@ impl MarkdownTemplate {
 
     /// Write commands section
     async fn write_commands(&self, content: &mut String, session: &Session) -> Result<()> {
-        writeln!(content, "## Commands")?;
+        writeln!(content, "## {}", self.label(Label::Commands))?;
         writeln!(content)?;
 
         if session.commands.is_empty() {
-            writeln!(content, "*No commands were captured during this session.*")?;
+            writeln!(content, "*{}*", self.label(Label::NoCommandsCaptured))?;
             writeln!(content)?;
             return Ok(());
         }
 
         // Use hierarchical structure if enabled
-        if self.config.template_options.enable_hierarchical_structure {
+        if self.config.template_options.focus_failures {
+            self.write_commands_focus_failures(content, session).await?;
+        } else if self.config.template_options.group_by_tag {
+            self.write_commands_grouped_by_tag(content, session).await?;
+        } else if self.config.template_options.enable_hierarchical_structure {
             self.write_commands_hierarchical(content, session).await?;
         } else if self.config.template_options.group_by_directory {
             self.write_commands_grouped_by_directory(content, session).await?;
@@ -910,15 +1501,84 @@ impl MarkdownTemplate {
 
     /// Write commands in chronological order
     async fn write_commands_chronological(&self, content: &mut String, session: &Session) -> Result<()> {
+        let show_directory_changes = self.config.template_options.show_directory_changes
+            && !self.config.template_options.group_by_directory;
+        let gap_threshold = self.config.template_options.gap_threshold_minutes.saturating_mul(60);
+        let mut previous_directory: Option<&str> = None;
+        let mut previous_timestamp: Option<DateTime<Utc>> = None;
+
         for (index, command) in session.commands.iter().enumerate() {
+            // Long pauses usually mean something was done outside the terminal
+            if let Some(previous) = previous_timestamp {
+                // Commands are timestamped when they finish, so their own run time isn't a pause
+                let run_time = command.duration_ms
+                    .and_then(|ms| i64::try_from(ms).ok())
+                    .and_then(chrono::Duration::try_milliseconds)
+                    .unwrap_or_default();
+                let gap = (command.timestamp - previous).checked_sub(&run_time).unwrap_or_default().num_seconds().max(0) as u64;
+                if gap_threshold > 0 && gap >= gap_threshold {
+                    writeln!(content, "*⏸️ {}*", self.label(Label::LikelyManualStep).replace("{gap}", &format_gap(gap)))?;
+                    writeln!(content)?;
+                }
+            }
+            previous_timestamp = Some(command.timestamp);
+
+            // The first command's directory is in its details table; only changes need pointing out
+            if show_directory_changes
+                && previous_directory.is_some_and(|previous| previous != command.working_directory)
+            {
+                writeln!(content, "> 📁 {} `{}`", self.label(Label::NowIn), command.working_directory)?;
+                writeln!(content)?;
+            }
+            previous_directory = Some(&command.working_directory);
+
             self.write_command(content, command, index + 1).await?;
         }
         Ok(())
     }
 
+    /// Write failed commands in full, collapsing each run of successful commands between them
+    async fn write_commands_focus_failures(&self, content: &mut String, session: &Session) -> Result<()> {
+        let is_failure = |command: &CommandEntry| command.exit_code.is_some_and(|code| code != 0);
+        let mut successful_run: Vec<(usize, &CommandEntry)> = Vec::new();
+
+        for (index, command) in session.commands.iter().enumerate() {
+            if is_failure(command) {
+                self.write_successful_run(content, &successful_run)?;
+                successful_run.clear();
+                self.write_command(content, command, index + 1).await?;
+            } else {
+                successful_run.push((index + 1, command));
+            }
+        }
+        self.write_successful_run(content, &successful_run)?;
+
+        Ok(())
+    }
+
+    /// Write a run of successful commands as a collapsed, numbered list
+    fn write_successful_run(&self, content: &mut String, run: &[(usize, &CommandEntry)]) -> Result<()> {
+        if run.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(content, "<details>")?;
+        writeln!(content, "<summary>✅ {}: {}</summary>", self.label(Label::SuccessfulCommands), run.len())?;
+        writeln!(content)?;
+        for (index, command) in run {
+            writeln!(content, "{}. `{}`", index, self.escape_markdown(&command.command))?;
+        }
+        writeln!(content)?;
+        writeln!(content, "</details>")?;
+        writeln!(content)?;
+
+        Ok(())
+    }
+
     /// Write commands grouped by working directory
     async fn write_commands_grouped_by_directory(&self, content: &mut String, session: &Session) -> Result<()> {
-        let mut directory_groups: HashMap<String, Vec<&CommandEntry>> = HashMap::new();
+        // Sorted by path so regenerating a session gives the same document
+        let mut directory_groups: BTreeMap<String, Vec<&CommandEntry>> = BTreeMap::new();
 
         for command in &session.commands {
             directory_groups
@@ -928,7 +1588,7 @@ impl MarkdownTemplate {
         }
 
         for (directory, commands) in directory_groups {
-            writeln!(content, "### Directory: `{}`", directory)?;
+            writeln!(content, "### {}: `{}`", self.label(Label::Directory), directory)?;
             writeln!(content)?;
 
             for (index, command) in commands.iter().enumerate() {
@@ -939,6 +1599,37 @@ impl MarkdownTemplate {
         Ok(())
     }
 
+    /// Write commands grouped by their first tag, keeping their numbers from the session
+    async fn write_commands_grouped_by_tag(&self, content: &mut String, session: &Session) -> Result<()> {
+        // Sorted by tag so regenerating a session gives the same document
+        let mut tag_groups: BTreeMap<&str, Vec<(usize, &CommandEntry)>> = BTreeMap::new();
+        let mut untagged = Vec::new();
+
+        // Numbered by step, the number 'docpilot tag-command' takes, even when --tag left some out
+        for (index, command) in session.commands.iter().enumerate() {
+            let number = command.step.unwrap_or(index + 1);
+            match command.tags.first() {
+                Some(tag) => tag_groups.entry(tag.as_str()).or_default().push((number, command)),
+                None => untagged.push((number, command)),
+            }
+        }
+
+        let groups = tag_groups
+            .into_iter()
+            .map(|(tag, commands)| (format!("{}: `{}`", self.label(Label::Tag), tag), commands))
+            .chain((!untagged.is_empty()).then(|| (self.label(Label::Untagged).to_string(), untagged)));
+        for (heading, commands) in groups {
+            writeln!(content, "### {}", heading)?;
+            writeln!(content)?;
+
+            for (number, command) in commands {
+                self.write_command(content, command, number).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Write commands grouped by time periods
     async fn write_commands_grouped_by_time(&self, content: &mut String, session: &Session) -> Result<()> {
         let interval_minutes = self.config.template_options.time_group_interval;
@@ -956,7 +1647,7 @@ impl MarkdownTemplate {
         sorted_groups.sort_by_key(|(key, _)| key.clone());
 
         for (time_group, commands) in sorted_groups {
-            writeln!(content, "### Time Period: {}", time_group)?;
+            writeln!(content, "### {}: {}", self.label(Label::TimePeriod), time_group)?;
             writeln!(content)?;
 
             for (index, command) in commands.iter().enumerate() {
@@ -969,7 +1660,7 @@ impl MarkdownTemplate {
 
     /// Write commands using hierarchical structure
     async fn write_commands_hierarchical(&self, content: &mut String, session: &Session) -> Result<()> {
-        let hierarchy = HierarchicalStructure::new(&session.commands);
+        let hierarchy = self.build_hierarchy(session);
         
         if self.config.template_options.group_by_workflow {
             self.write_commands_by_workflow(content, &hierarchy).await?;
@@ -985,21 +1676,10 @@ impl MarkdownTemplate {
 
     /// Write commands grouped by workflow phases
     async fn write_commands_by_workflow(&self, content: &mut String, hierarchy: &HierarchicalStructure) -> Result<()> {
-        let workflow_order = [
-            WorkflowPhase::Setup,
-            WorkflowPhase::Development,
-            WorkflowPhase::Build,
-            WorkflowPhase::Testing,
-            WorkflowPhase::Deployment,
-            WorkflowPhase::Maintenance,
-            WorkflowPhase::Debugging,
-            WorkflowPhase::Other,
-        ];
-
-        for phase in &workflow_order {
+        for phase in &WorkflowPhase::DISPLAY_ORDER {
             if let Some(commands) = hierarchy.workflow_groups.get(phase) {
                 if !commands.is_empty() {
-                    writeln!(content, "### {} {} - {}", phase.icon(), format!("{:?}", phase), phase.description())?;
+                    writeln!(content, "### {}", workflow_heading(phase))?;
                     writeln!(content)?;
 
                     if self.config.template_options.include_workflow_summaries {
@@ -1018,24 +1698,10 @@ impl MarkdownTemplate {
 
     /// Write commands grouped by command types
     async fn write_commands_by_type(&self, content: &mut String, hierarchy: &HierarchicalStructure) -> Result<()> {
-        let type_order = [
-            CommandType::FileSystem,
-            CommandType::Development,
-            CommandType::VersionControl,
-            CommandType::PackageManagement,
-            CommandType::System,
-            CommandType::Network,
-            CommandType::Database,
-            CommandType::TextProcessing,
-            CommandType::Monitoring,
-            CommandType::Security,
-            CommandType::Other,
-        ];
-
-        for cmd_type in &type_order {
+        for cmd_type in &CommandType::display_order() {
             if let Some(commands) = hierarchy.type_groups.get(cmd_type) {
                 if !commands.is_empty() {
-                    writeln!(content, "### {} {} Commands", cmd_type.icon(), format!("{:?}", cmd_type).replace("_", " "))?;
+                    writeln!(content, "### {}", command_type_heading(cmd_type))?;
                     writeln!(content)?;
 
                     if self.config.template_options.include_command_type_explanations {
@@ -1055,21 +1721,10 @@ impl MarkdownTemplate {
 
     /// Write commands with workflow phases containing nested command types
     async fn write_commands_workflow_with_types(&self, content: &mut String, hierarchy: &HierarchicalStructure) -> Result<()> {
-        let workflow_order = [
-            WorkflowPhase::Setup,
-            WorkflowPhase::Development,
-            WorkflowPhase::Build,
-            WorkflowPhase::Testing,
-            WorkflowPhase::Deployment,
-            WorkflowPhase::Maintenance,
-            WorkflowPhase::Debugging,
-            WorkflowPhase::Other,
-        ];
-
-        for phase in &workflow_order {
+        for phase in &WorkflowPhase::DISPLAY_ORDER {
             if let Some(workflow_commands) = hierarchy.workflow_groups.get(phase) {
                 if !workflow_commands.is_empty() {
-                    writeln!(content, "### {} {} Phase", phase.icon(), format!("{:?}", phase))?;
+                    writeln!(content, "### {}", phase_heading(phase))?;
                     writeln!(content)?;
 
                     if self.config.template_options.include_workflow_summaries {
@@ -1078,19 +1733,12 @@ impl MarkdownTemplate {
                     }
 
                     // Group commands within this workflow phase by type
-                    let mut phase_type_groups: HashMap<CommandType, Vec<&CommandEntry>> = HashMap::new();
-                    for command in workflow_commands {
-                        let cmd_type = CommandType::classify_command(&command.command);
-                        phase_type_groups
-                            .entry(cmd_type)
-                            .or_insert_with(Vec::new)
-                            .push(command);
-                    }
+                    let phase_type_groups = group_by_command_type(workflow_commands);
 
                     // Write each command type within this workflow phase
                     for (cmd_type, type_commands) in phase_type_groups {
-                        if type_commands.len() > 1 || self.config.template_options.include_command_type_explanations {
-                            writeln!(content, "#### {} {} Commands", cmd_type.icon(), format!("{:?}", cmd_type).replace("_", " "))?;
+                        if self.has_command_type_heading(&type_commands) {
+                            writeln!(content, "#### {}", command_type_heading(&cmd_type))?;
                             writeln!(content)?;
                         }
 
@@ -1118,36 +1766,86 @@ impl MarkdownTemplate {
         Ok(())
     }
 
-    /// Write a single command entry
-    async fn write_command(&self, content: &mut String, command: &CommandEntry, index: usize) -> Result<()> {
-        // Command header with status indicator
+    /// Heading of a command entry: its number and, if enabled, a status indicator
+    fn command_heading(&self, command: &CommandEntry, index: usize) -> String {
+        let inferred_success = CommandFilter::new().infer_success(command);
+
+        // Status indicator; guesses from output get their own, lighter marks
         let status_indicator = if self.config.template_options.include_status_indicators {
-            match command.exit_code {
-                Some(0) => " ✅",
-                Some(_) => " ❌",
-                None => " ⏳",
+            match (command.exit_code, inferred_success) {
+                (Some(0), _) => " ✅".to_string(),
+                (Some(_), _) if command.termination_signal().is_some() => " 🛑".to_string(),
+                (Some(_), _) => " ❌".to_string(),
+                (None, Some(true)) => format!(" ✔️ *({})*", self.label(Label::Inferred)),
+                (None, Some(false)) => format!(" ✖️ *({})*", self.label(Label::Inferred)),
+                (None, None) => " ⏳".to_string(),
             }
         } else {
-            ""
+            String::new()
         };
 
-        writeln!(content, "### Command {}{}", index, status_indicator)?;
+        format!("{}{}", self.command_label(command, index), status_indicator)
+    }
+
+    /// "Step 12" with step numbers enabled, otherwise "Command 3" with the command's number in its group
+    fn command_label(&self, command: &CommandEntry, index: usize) -> String {
+        match command.step.filter(|_| self.config.template_options.show_step_numbers) {
+            Some(step) => format!("{} {}", self.label(Label::Step), step),
+            None => format!("{} {}", self.label(Label::Command), index),
+        }
+    }
+
+    /// Write a single command entry
+    async fn write_command(&self, content: &mut String, command: &CommandEntry, index: usize) -> Result<()> {
+        let inferred_success = CommandFilter::new().infer_success(command);
+
+        // A fixed anchor, so links like guide.md#step-12 survive regenerating and filtering
+        if let Some(step) = command.step.filter(|_| self.config.template_options.show_step_numbers) {
+            writeln!(content, "<a id=\"step-{}\"></a>", step)?;
+            writeln!(content)?;
+        }
+        writeln!(content, "### {}", self.command_heading(command, index))?;
         writeln!(content)?;
 
         // Command details table
-        writeln!(content, "| Property | Value |")?;
+        writeln!(content, "| {} | {} |", self.label(Label::Property), self.label(Label::Value))?;
         writeln!(content, "|----------|-------|")?;
-        writeln!(content, "| Command | `{}` |", self.escape_markdown(&command.command))?;
+        writeln!(content, "| {} | `{}` |", self.label(Label::Command), self.table_command(&command.command))?;
         
         if self.config.include_timestamps {
-            writeln!(content, "| Timestamp | {} |", self.format_timestamp(command.timestamp))?;
+            writeln!(content, "| {} | {} |", self.label(Label::Timestamp), self.format_timestamp(command.timestamp))?;
+        }
+        if self.config.template_options.include_duration
+            && let Some(duration_ms) = command.duration_ms
+        {
+            writeln!(content, "| {} | {} |", self.label(Label::Duration), self.format_command_duration(duration_ms))?;
         }
 
-        writeln!(content, "| Working Directory | `{}` |", command.working_directory)?;
-        writeln!(content, "| Shell | `{}` |", command.shell)?;
+        writeln!(content, "| {} | `{}` |", self.label(Label::WorkingDirectory), command.working_directory)?;
+        writeln!(content, "| {} | `{}` |", self.label(Label::Shell), command.shell)?;
+        if !command.tags.is_empty() {
+            let tags: Vec<String> = command.tags.iter().map(|tag| format!("`{}`", tag)).collect();
+            writeln!(content, "| {} | {} |", self.label(Label::Tags), tags.join(", "))?;
+        }
 
-        if let Some(exit_code) = command.exit_code {
-            writeln!(content, "| Exit Code | `{}` |", exit_code)?;
+        let mut exit_notes = Vec::new();
+        if command.backgrounded {
+            exit_notes.push(self.label(Label::Backgrounded).to_string());
+        }
+        if let Some(description) = command.termination_description() {
+            exit_notes.push(description);
+        }
+        let exit_notes = if exit_notes.is_empty() { String::new() } else { format!(" ({})", exit_notes.join(", ")) };
+
+        // A backgrounded job that was still running when the session ended has no exit code
+        match command.exit_code {
+            Some(exit_code) => writeln!(content, "| {} | `{}`{} |", self.label(Label::ExitCode), exit_code, exit_notes)?,
+            None if command.backgrounded => writeln!(content, "| {} |{} |", self.label(Label::ExitCode), exit_notes)?,
+            None => match inferred_success {
+                Some(true) => writeln!(content, "| {} | {} |", self.label(Label::ExitCode), self.label(Label::NotCapturedLikelySucceeded))?,
+                Some(false) => writeln!(content, "| {} | {} |", self.label(Label::ExitCode), self.label(Label::NotCapturedLikelyFailed))?,
+                None => {}
+            },
         }
 
         writeln!(content)?;
@@ -1157,23 +1855,122 @@ impl MarkdownTemplate {
         let formatted_command = self.code_block_generator.format_code_block(&command_block);
         writeln!(content, "{}", formatted_command)?;
 
+        if let Some(run) = command.step.and_then(|step| self.repeated_runs.borrow().get(&step).cloned()) {
+            let span = (command.timestamp - run.first_timestamp).num_seconds().max(0) as u64;
+            let note = self.label(Label::RepeatedRun)
+                .replace("{count}", &run.count.to_string())
+                .replace("{span}", &self.format_duration(span));
+            writeln!(content, "> 🔁 {}", note)?;
+            writeln!(content)?;
+        }
+
+        // Commands typed into a nested shell ran somewhere other than the reader's own shell
+        if self.config.template_options.show_nesting
+            && let Some(nesting) = &command.nesting
+        {
+            let note = match &nesting.parent {
+                Some(parent) => self.label(Label::NestedShellInside).replace("{parent}", &format!("`{}`", self.escape_markdown(parent))),
+                None => self.label(Label::NestedShell).to_string(),
+            };
+            writeln!(content, "> ↪️ *({})*", note.replace("{depth}", &nesting.depth.to_string()))?;
+            writeln!(content)?;
+        }
+
+        // A chained line is several logical steps; list them with when each one runs
+        if self.config.template_options.split_chained_commands {
+            let steps = CommandEntry::chain_steps(&command.command);
+            if steps.len() > 1 {
+                writeln!(content, "**{}:**", self.label(Label::Steps))?;
+                writeln!(content)?;
+                for (number, step) in steps.iter().enumerate() {
+                    let condition = match step.operator {
+                        Some(ChainOperator::And) => format!(" — {}", self.label(Label::RunsIfPreviousSucceeded)),
+                        Some(ChainOperator::Or) => format!(" — {}", self.label(Label::RunsIfPreviousFailed)),
+                        Some(ChainOperator::Sequence) => format!(" — {}", self.label(Label::RunsAfterPrevious)),
+                        None => String::new(),
+                    };
+                    writeln!(content, "{}. `{}`{}", number + 1, self.escape_markdown(&step.command), condition)?;
+                }
+                writeln!(content)?;
+            }
+        }
+
+        // Aliases and shell functions don't exist on the reader's machine, so show what actually ran
+        if let Some(expansion) = &command.expanded_command {
+            let name = command.command.split_whitespace().next().unwrap_or(&command.command);
+            writeln!(content, "> 💡 **Note:** `{}` is an alias or shell function. It runs:", self.escape_markdown(name))?;
+            writeln!(content)?;
+            writeln!(content, "```{}", self.config.code_language)?;
+            writeln!(content, "{}", expansion)?;
+            writeln!(content, "```")?;
+            writeln!(content)?;
+        }
+
+        // Likewise for package scripts, which are defined in the project rather than the command
+        if self.config.template_options.resolve_package_scripts
+            && let Some(script) = self.package_scripts.resolve(&command.command, &command.working_directory)
+        {
+            let note = self.label(Label::PackageScriptRuns)
+                .replace("{name}", &self.escape_markdown(&script.name))
+                .replace("{file}", script.file);
+            writeln!(content, "> 📜 {}", note)?;
+            writeln!(content)?;
+            writeln!(content, "```{}", self.config.code_language)?;
+            writeln!(content, "{}", script.command)?;
+            writeln!(content, "```")?;
+            writeln!(content)?;
+        }
+
         // Command output with enhanced formatting
         if self.config.include_output {
             if let Some(output) = &command.output {
                 if !output.trim().is_empty() {
-                    let truncated_output = self.truncate_output(output);
+                    let truncated_output = self.truncate_output(output, command.output_size);
                     let output_block = self.code_block_generator.generate_output_block(&truncated_output, &command.command);
                     let formatted_output = self.code_block_generator.format_code_block(&output_block);
                     writeln!(content, "{}", formatted_output)?;
+
+                    // Output too large for the session is in a sidecar log next to this preview
+                    if let Some(log) = &command.output_log {
+                        let file_name = std::path::Path::new(log).file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+                        let size = command.output_size.map(format_size).unwrap_or_default();
+                        writeln!(content, "📄 {}: [{}](<{}>)", self.label(Label::FullOutput).replace("{size}", &size), file_name, log)?;
+                        writeln!(content)?;
+                    }
                 }
             }
         }
 
+        // How the output differs from the same command in the baseline session
+        if self.config.include_output
+            && let Some(baseline) = &self.baseline
+            && let Some(baseline_output) = command.step.and_then(|step| self.baseline_outputs.borrow().get(&step).cloned())
+        {
+            let baseline_output = self.clean_output(&baseline_output);
+            let output = self.clean_output(command.output.as_deref().unwrap_or_default());
+            match render_diff(&diff_lines(&baseline_output, &output), DIFF_CONTEXT_LINES) {
+                Some(diff) => {
+                    writeln!(content, "**🔁 {}:**", self.label(Label::OutputChangedSince).replace("{session}", &format!("`{}`", baseline.session_id())))?;
+                    writeln!(content)?;
+                    writeln!(content, "```diff")?;
+                    writeln!(content, "{}", self.truncate_output(&diff, None))?;
+                    writeln!(content, "```")?;
+                }
+                None => writeln!(content, "*🔁 {}*", self.label(Label::OutputUnchangedSince).replace("{session}", &format!("`{}`", baseline.session_id())))?,
+            }
+            writeln!(content)?;
+        }
+
         // Command errors with enhanced formatting
         if self.config.include_errors {
             if let Some(error) = &command.error {
                 if !error.trim().is_empty() {
-                    let truncated_error = self.truncate_output(error);
+                    // When focusing on failures the error output is the point, so keep all of it
+                    let truncated_error = if self.config.template_options.focus_failures {
+                        self.clean_output(error)
+                    } else {
+                        self.truncate_output(error, None)
+                    };
                     let error_block = self.code_block_generator.generate_error_block(&truncated_error, &command.command);
                     let formatted_error = self.code_block_generator.format_code_block(&error_block);
                     writeln!(content, "{}", formatted_error)?;
@@ -1191,10 +1988,41 @@ impl MarkdownTemplate {
         Ok(())
     }
 
+    /// Copy of the session with the spelling and grammar of its annotations fixed by the LLM
+    ///
+    /// Annotations keep their original text if the LLM can't be reached.
+    async fn polish_annotations(&self, session: &Session) -> Session {
+        let mut polished = session.clone();
+        let Some(analyzer_cell) = &self.ai_analyzer else {
+            return polished;
+        };
+
+        println!("   ✍️  Polishing {} annotations...", polished.annotations.len());
+        let start = Instant::now();
+        // A separate analyzer, so the shared one isn't borrowed across the LLM calls
+        let analyzer = AIAnalyzer::new(analyzer_cell.borrow().get_config().clone());
+        for annotation in &mut polished.annotations {
+            match analyzer.polish_annotation(&annotation.text).await {
+                Ok(text) => annotation.text = text,
+                Err(e) => {
+                    eprintln!("⚠️  Could not polish annotations, keeping them as typed: {}", e);
+                    polished.annotations = session.annotations.clone();
+                    break;
+                }
+            }
+        }
+        self.record_stage(Stage::AiAnalysis, start);
+        polished
+    }
+
     /// Generate AI analysis for a command
     async fn generate_ai_analysis(&self, command: &CommandEntry) -> Result<Option<AnalysisResult>> {
         if let Some(analyzer_cell) = &self.ai_analyzer {
             let config = &self.config.ai_analysis_config;
+            if command_significance(command) < config.min_significance {
+                self.trivial_commands.borrow_mut().push(command.command.clone());
+                return Ok(None);
+            }
             
             // Show progress for AI analysis
             println!("   🔍 Analyzing command: {}",
@@ -1216,14 +2044,14 @@ impl MarkdownTemplate {
             
             // Add output context if available
             let full_context = if let Some(output) = &command.output {
-                format!("{}\nOutput: {}", context, self.truncate_output(output))
+                format!("{}\nOutput: {}", context, self.truncate_output(output, command.output_size))
             } else {
                 context
             };
             
             // Add error context if available
             let full_context = if let Some(error) = &command.error {
-                format!("{}\nError: {}", full_context, self.truncate_output(error))
+                format!("{}\nError: {}", full_context, self.truncate_output(error, None))
             } else {
                 full_context
             };
@@ -1236,11 +2064,13 @@ impl MarkdownTemplate {
             };
             
             // Try to borrow mutably and perform analysis
+            let analysis_start = Instant::now();
             let analysis_result = {
                 match analyzer_cell.try_borrow_mut() {
-                    Ok(mut analyzer) => {
-                        analyzer.analyze_command(command, Some(&analysis_context)).await
-                    }
+                    Ok(mut analyzer) => match config.mode {
+                        AIAnalysisMode::Full => analyzer.analyze_command(command, Some(&analysis_context)).await,
+                        AIAnalysisMode::Explain => analyzer.explain_command(command, config.custom_context.as_deref()).await,
+                    },
                     Err(_) => {
                         // RefCell is already borrowed, skip AI analysis for this command
                         println!("   ⏭️  AI analyzer busy, skipping analysis for: {}", command.command);
@@ -1248,6 +2078,7 @@ impl MarkdownTemplate {
                     }
                 }
             };
+            self.record_stage(Stage::AiAnalysis, analysis_start);
 
             match analysis_result {
                 Ok(analysis) => {
@@ -1274,8 +2105,17 @@ impl MarkdownTemplate {
     /// Write AI analysis section to markdown
     fn write_ai_analysis(&self, content: &mut String, analysis: &AnalysisResult) -> Result<()> {
         let config = &self.config.ai_analysis_config;
+
+        // Explain mode only has a one-sentence summary to show
+        if config.mode == AIAnalysisMode::Explain {
+            if !analysis.summary.is_empty() {
+                writeln!(content, "💡 {}", analysis.summary)?;
+                writeln!(content)?;
+            }
+            return Ok(());
+        }
         
-        writeln!(content, "#### 🤖 AI Analysis")?;
+        writeln!(content, "#### 🤖 {}", self.label(Label::AiAnalysis))?;
         writeln!(content)?;
         
         // Main explanation (using summary)
@@ -1364,43 +2204,282 @@ impl MarkdownTemplate {
         Ok(())
     }
 
+    /// Write a checklist of the packages and tools the session installed or checked
+    fn write_prerequisites(&self, content: &mut String, session: &Session) -> Result<()> {
+        let prerequisites = infer_prerequisites(&session.commands);
+        if prerequisites.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(content, "## {}", self.label(Label::Prerequisites))?;
+        writeln!(content)?;
+        writeln!(content, "{}", self.label(Label::PrerequisitesIntro))?;
+        writeln!(content)?;
+        for prerequisite in prerequisites {
+            match prerequisite.source {
+                PrerequisiteSource::Installed(command) => {
+                    writeln!(content, "- [ ] `{}` ({} `{}`)", prerequisite.name, self.label(Label::InstalledWith), command)?;
+                }
+                PrerequisiteSource::Checked { command, version: Some(version) } => {
+                    writeln!(content, "- [ ] `{}`: {} ({} `{}`)", prerequisite.name, version, self.label(Label::CheckedWith), command)?;
+                }
+                PrerequisiteSource::Checked { command, version: None } => {
+                    writeln!(content, "- [ ] `{}` ({} `{}`)", prerequisite.name, self.label(Label::CheckedWith), command)?;
+                }
+            }
+        }
+        writeln!(content)?;
+
+        Ok(())
+    }
+
+    /// Write a glossary of the session's tools, defined by the LLM when available
+    async fn write_glossary(&self, content: &mut String, session: &Session) -> Result<()> {
+        let tools = session_tools(&session.commands);
+        if tools.is_empty() {
+            return Ok(());
+        }
+
+        let mut definitions = HashMap::new();
+        if let Some(analyzer_cell) = &self.ai_analyzer {
+            println!("   📖 Defining {} tools for the glossary...", tools.len());
+            let start = Instant::now();
+            // A separate analyzer, so the shared one isn't borrowed across the LLM call
+            let analyzer = AIAnalyzer::new(analyzer_cell.borrow().get_config().clone());
+            match analyzer.define_tools(&tools).await {
+                Ok(defined) => definitions = defined,
+                Err(e) => eprintln!("⚠️  Could not define tools with the LLM, using built-in definitions: {}", e),
+            }
+            self.record_stage(Stage::AiAnalysis, start);
+        }
+
+        writeln!(content, "## {}", self.label(Label::Glossary))?;
+        writeln!(content)?;
+        for tool in &tools {
+            let definition = definitions.get(tool).map(String::as_str)
+                .or_else(|| static_definition(tool))
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("*{}*", self.label(Label::CustomTool)));
+            writeln!(content, "- **`{}`**: {}", tool, definition)?;
+        }
+        writeln!(content)?;
+
+        Ok(())
+    }
+
+    /// Write the environment snapshot taken when the session started
+    fn write_environment(&self, content: &mut String, session: &Session) -> Result<()> {
+        if session.metadata.environment.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(content, "## {}", self.label(Label::Environment))?;
+        writeln!(content)?;
+        writeln!(content, "{}", self.label(Label::EnvironmentIntro))?;
+        writeln!(content)?;
+        writeln!(content, "```{}", self.config.code_language)?;
+        for (name, value) in &session.metadata.environment {
+            writeln!(content, "{}={}", name, value)?;
+        }
+        writeln!(content, "```")?;
+        writeln!(content)?;
+
+        Ok(())
+    }
+
+    /// Write the files created, modified or deleted while the session ran
+    fn write_file_changes(&self, content: &mut String, tracking: &FileTracking) -> Result<()> {
+        writeln!(content, "## {}", self.label(Label::FileChanges))?;
+        writeln!(content)?;
+
+        let patterns: Vec<String> = tracking.patterns.iter().map(|pattern| format!("`{}`", pattern)).collect();
+        let patterns = patterns.join(", ");
+        let changes = match &tracking.changes {
+            Some(changes) if !changes.is_empty() => changes,
+            Some(_) => {
+                writeln!(content, "{}", self.label(Label::NoFileChanges).replace("{patterns}", &patterns))?;
+                writeln!(content)?;
+                return Ok(());
+            }
+            None => {
+                writeln!(content, "*{}*", self.label(Label::FileChangesPending).replace("{patterns}", &patterns))?;
+                writeln!(content)?;
+                return Ok(());
+            }
+        };
+
+        writeln!(content, "{}", self.label(Label::FileChangesIntro).replace("{patterns}", &patterns))?;
+        writeln!(content)?;
+        writeln!(content, "| {} | {} |", self.label(Label::Change), self.label(Label::File))?;
+        writeln!(content, "|--------|------|")?;
+        for change in changes {
+            let kind = match change.kind {
+                FileChangeKind::Created => format!("🆕 {}", self.label(Label::FileCreated)),
+                FileChangeKind::Modified => format!("✏️ {}", self.label(Label::FileModified)),
+                FileChangeKind::Deleted => format!("🗑️ {}", self.label(Label::FileDeleted)),
+            };
+            writeln!(content, "| {} | `{}` |", kind, change.path.replace('|', "\\|"))?;
+        }
+        writeln!(content)?;
+
+        Ok(())
+    }
+
     /// Write annotations section
     fn write_annotations(&self, content: &mut String, session: &Session) -> Result<()> {
-        writeln!(content, "## Annotations")?;
+        writeln!(content, "## {}", self.label(Label::Annotations))?;
         writeln!(content)?;
 
-        for (index, annotation) in session.annotations.iter().enumerate() {
-            self.write_annotation(content, annotation, index + 1)?;
+        let numbers = number_annotations(&session.annotations);
+        let annotations = AnnotationSet { annotations: &session.annotations, numbers: &numbers };
+
+        match self.config.template_options.annotation_grouping {
+            AnnotationGrouping::Chronological => {
+                for index in 0..session.annotations.len() {
+                    self.write_annotation(content, &annotations, index, "###")?;
+                }
+            }
+            AnnotationGrouping::ByType => {
+                let type_order = [
+                    (AnnotationType::Warning, Label::Warnings),
+                    (AnnotationType::Milestone, Label::Milestones),
+                    (AnnotationType::Explanation, Label::Explanations),
+                    (AnnotationType::Note, Label::Notes),
+                ];
+
+                for (annotation_type, heading) in &type_order {
+                    let group: Vec<_> = session.annotations.iter()
+                        .enumerate()
+                        .filter(|(_, annotation)| std::mem::discriminant(&annotation.annotation_type) == std::mem::discriminant(annotation_type))
+                        .collect();
+                    if group.is_empty() {
+                        continue;
+                    }
+
+                    writeln!(content, "### {} {}", Self::annotation_emoji(annotation_type), self.label(*heading))?;
+                    writeln!(content)?;
+
+                    // Keep the chronological numbers so references stay the same in both layouts
+                    for (index, _) in group {
+                        self.write_annotation(content, &annotations, index, "####")?;
+                    }
+                }
+            }
         }
 
         Ok(())
     }
 
-    /// Write a single annotation
-    fn write_annotation(&self, content: &mut String, annotation: &Annotation, index: usize) -> Result<()> {
-        let type_emoji = match annotation.annotation_type {
+    /// Render only the annotations of a session under its description, without the command log
+    pub fn generate_annotations(&self, session: &Session) -> Result<String> {
+        let mut content = String::new();
+        writeln!(content, "# {}", session.description)?;
+        writeln!(content)?;
+        self.write_annotations(&mut content, session)?;
+        Ok(content)
+    }
+
+    /// Get the emoji used for an annotation type
+    fn annotation_emoji(annotation_type: &AnnotationType) -> &'static str {
+        match annotation_type {
             AnnotationType::Note => "📝",
             AnnotationType::Explanation => "💡",
             AnnotationType::Warning => "⚠️",
             AnnotationType::Milestone => "🎯",
-        };
+        }
+    }
+
+    /// Write a single annotation, with an anchor that references can link to
+    fn write_annotation(&self, content: &mut String, annotations: &AnnotationSet, index: usize, heading: &str) -> Result<()> {
+        let annotation = &annotations.annotations[index];
+        let type_emoji = Self::annotation_emoji(&annotation.annotation_type);
 
-        writeln!(content, "### {} Annotation {}", type_emoji, index)?;
+        writeln!(content, "<a id=\"{}\"></a>", self.annotation_anchor(annotations, index))?;
+        writeln!(content)?;
+        writeln!(content, "{} {} {}", heading, type_emoji, self.annotation_title(annotations, index))?;
         writeln!(content)?;
 
         if self.config.include_timestamps {
-            writeln!(content, "**Timestamp:** {}", self.format_timestamp(annotation.timestamp))?;
+            writeln!(content, "**{}:** {}", self.label(Label::Timestamp), self.format_timestamp(annotation.timestamp))?;
             writeln!(content)?;
         }
 
-        writeln!(content, "{}", annotation.text)?;
+        writeln!(content, "{}", self.link_annotation_references(&annotation.text, annotations))?;
         writeln!(content)?;
 
         Ok(())
     }
 
+    /// Heading text of an annotation, e.g. "Annotation 3" or "Warning 2" when numbered per type
+    fn annotation_title(&self, annotations: &AnnotationSet, index: usize) -> String {
+        let number = annotations.numbers[index];
+        match self.config.template_options.annotation_numbering {
+            AnnotationNumbering::Global => format!("{} {}", self.label(Label::Annotation), number.global),
+            AnnotationNumbering::PerType => {
+                let label = match annotations.annotations[index].annotation_type {
+                    AnnotationType::Note => Label::Note,
+                    AnnotationType::Explanation => Label::Explanation,
+                    AnnotationType::Warning => Label::Warning,
+                    AnnotationType::Milestone => Label::Milestone,
+                };
+                format!("{} {}", self.label(label), number.per_type)
+            }
+        }
+    }
+
+    /// Anchor ID of an annotation: `ann-3`, or `ann-warning-2` when numbered per type
+    fn annotation_anchor(&self, annotations: &AnnotationSet, index: usize) -> String {
+        let number = annotations.numbers[index];
+        match self.config.template_options.annotation_numbering {
+            AnnotationNumbering::Global => format!("ann-{}", number.global),
+            AnnotationNumbering::PerType => {
+                format!("ann-{}-{}", annotation_type_key(&annotations.annotations[index].annotation_type), number.per_type)
+            }
+        }
+    }
+
+    /// Turn `[[ann:3]]` (third annotation) and `[[warning:2]]` (second warning) into links
+    ///
+    /// Both forms work with either numbering; references to annotations that don't exist are left as typed.
+    fn link_annotation_references(&self, text: &str, annotations: &AnnotationSet) -> String {
+        annotation_reference_regex()
+            .replace_all(text, |captures: &regex::Captures| {
+                let number: usize = captures[2].parse().unwrap_or(0);
+                let target = (0..annotations.annotations.len()).find(|&index| match &captures[1] {
+                    "ann" => annotations.numbers[index].global == number,
+                    kind => {
+                        annotation_type_key(&annotations.annotations[index].annotation_type) == kind
+                            && annotations.numbers[index].per_type == number
+                    }
+                });
+                match target {
+                    Some(index) => format!(
+                        "[{}](#{})",
+                        self.annotation_title(annotations, index),
+                        self.annotation_anchor(annotations, index)
+                    ),
+                    None => captures[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+
     /// Write document footer
     fn write_footer(&self, content: &mut String, session: &Session) -> Result<()> {
+        let trivial_commands = self.trivial_commands.borrow();
+        if !trivial_commands.is_empty() {
+            let mut listed: Vec<&String> = Vec::new();
+            for command in trivial_commands.iter() {
+                if !listed.contains(&command) {
+                    listed.push(command);
+                }
+            }
+            let listed: Vec<String> = listed.iter().map(|command| format!("`{}`", command)).collect();
+            let note = self.label(Label::AiSkippedTrivial).replace("{count}", &trivial_commands.len().to_string());
+            writeln!(content, "*{}: {}*", note, listed.join(", "))?;
+            writeln!(content)?;
+        }
+
         if let Some(custom_footer) = &self.config.template_options.custom_footer {
             writeln!(content, "{}", custom_footer)?;
             writeln!(content)?;
@@ -1408,11 +2487,16 @@ impl MarkdownTemplate {
 
         writeln!(content, "---")?;
         writeln!(content)?;
-        writeln!(content, "*Generated by DocPilot on {}*", self.format_timestamp(Utc::now()))?;
+        writeln!(content, "*{} {}*", self.label(Label::GeneratedOn), self.format_timestamp(Utc::now()))?;
 
         Ok(())
     }
 
+    /// Get a heading or label in the configured language
+    fn label(&self, label: Label) -> &'static str {
+        self.config.template_options.locale.label(label)
+    }
+
     /// Format a timestamp for display
     fn format_timestamp(&self, timestamp: DateTime<Utc>) -> String {
         timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string()
@@ -1433,6 +2517,15 @@ impl MarkdownTemplate {
         }
     }
 
+    /// Format how long a command ran, in milliseconds below a second
+    pub fn format_command_duration(&self, duration_ms: u64) -> String {
+        if duration_ms < 1000 {
+            format!("{}ms", duration_ms)
+        } else {
+            self.format_duration(duration_ms / 1000)
+        }
+    }
+
     /// Get time group key for grouping commands by time periods
     fn get_time_group_key(&self, timestamp: DateTime<Utc>, interval_minutes: u64) -> String {
         let interval_seconds = interval_minutes * 60;
@@ -1461,15 +2554,60 @@ impl MarkdownTemplate {
             .replace(')', "\\)")
     }
 
+    /// Command text for a table cell
+    ///
+    /// Long and multi-line commands would stretch or break the table, so they are cut short here;
+    /// the command code block below the table always shows them in full.
+    fn table_command(&self, command: &str) -> String {
+        let limit = self.config.template_options.max_inline_command_length;
+        let command = command.trim();
+        let first_line = command.lines().next().unwrap_or("");
+
+        let mut shown: String = if limit > 0 && first_line.chars().count() > limit {
+            first_line.chars().take(limit).collect()
+        } else {
+            first_line.to_string()
+        };
+        if shown.len() < command.len() {
+            shown = format!("{} …", shown.trim_end());
+        }
+
+        // A pipe ends the cell even inside backticks
+        self.escape_markdown(&shown).replace('|', "\\|")
+    }
+
     /// Truncate output if it exceeds the maximum length
-    fn truncate_output(&self, output: &str) -> String {
-        if self.config.max_output_length == 0 || output.len() <= self.config.max_output_length {
+    /// Captured output as the terminal displayed it, unless the raw text was asked for
+    fn clean_output(&self, output: &str) -> String {
+        if self.config.template_options.preserve_raw_output {
             output.to_string()
         } else {
-            let truncated = &output[..self.config.max_output_length];
-            format!("{}\n\n... (output truncated)", truncated)
+            clean_terminal_output(output)
         }
     }
+
+    /// Output cut to `max_output_length`, noting how much of it is shown when any is left out
+    ///
+    /// `original_size` is the byte length recorded at capture, which is larger than the
+    /// stored text when that was already cut.
+    fn truncate_output(&self, output: &str, original_size: Option<u64>) -> String {
+        let cleaned = self.clean_output(output);
+        let limit = self.config.max_output_length;
+        let shown = if limit == 0 || cleaned.len() <= limit {
+            cleaned.as_str()
+        } else {
+            &cleaned[..cleaned.floor_char_boundary(limit)]
+        };
+        let total = original_size.filter(|&size| size > output.len() as u64).unwrap_or(cleaned.len() as u64);
+        if shown.len() as u64 >= total {
+            return cleaned;
+        }
+
+        let note = self.label(Label::OutputTruncated)
+            .replace("{shown}", &format_size(shown.len() as u64))
+            .replace("{total}", &format_size(total));
+        format!("{}\n\n... ({})", shown, note)
+    }
 }
 
 impl Default for MarkdownTemplate {
@@ -1503,18 +2641,26 @@ impl MarkdownGenerator {
         self.template.generate(session).await
     }
 
-    /// Generate markdown documentation and write to file
-    pub async fn generate_to_file(&self, session: &Session, output_path: &std::path::Path) -> Result<()> {
-        let content = self.generate_documentation(session).await?;
-        std::fs::write(output_path, content)?;
-        Ok(())
-    }
-
     /// Update the generator configuration
     pub fn set_config(&mut self, config: MarkdownConfig) {
         self.template.set_config(config);
     }
 
+    /// Record how long each generation stage takes in `timings`
+    pub fn set_stage_timings(&mut self, timings: StageTimings) {
+        self.template.stage_timings = Some(timings);
+    }
+
+    /// Compare each command's output with the matching command of `baseline`
+    pub fn set_baseline(&mut self, baseline: OutputBaseline) {
+        self.template.baseline = Some(baseline);
+    }
+
+    /// Note in the document's metadata that AI analysis was skipped, and why
+    pub fn set_ai_unavailable(&mut self, reason: String) {
+        self.template.ai_unavailable = Some(reason);
+    }
+
     /// Get the current configuration
     pub fn get_config(&self) -> &MarkdownConfig {
         self.template.get_config()
@@ -1527,7 +2673,19 @@ impl MarkdownGenerator {
         config.ai_analysis_config.enable_ai_explanations = true;
         
         // Create a new template with AI analyzer
-        self.template = MarkdownTemplate::with_config(config).with_ai_analyzer(llm_config);
+        let timings = self.template.stage_timings.take();
+        let baseline = self.template.baseline.take();
+        self.template = MarkdownTemplate::with_config(config).with_ai_analyzer(llm_config).with_stage_timings(timings).with_baseline(baseline);
+    }
+
+    /// Proofread annotations with the LLM, without enabling the per-command analysis
+    pub fn enable_annotation_polishing(&mut self, llm_config: crate::llm::LlmConfig) {
+        let mut config = self.template.get_config().clone();
+        config.ai_analysis_config.polish_annotations = true;
+
+        let timings = self.template.stage_timings.take();
+        let baseline = self.template.baseline.take();
+        self.template = MarkdownTemplate::with_config(config).with_ai_analyzer(llm_config).with_stage_timings(timings).with_baseline(baseline);
     }
 
     /// Generate AI-enhanced documentation with post-processing
@@ -1539,7 +2697,9 @@ impl MarkdownGenerator {
             let mut ai_analyzer = ai_analyzer_cell.borrow_mut();
             
             // Filter and validate commands
+            let validation_start = Instant::now();
             let validated_commands = ai_analyzer.validate_and_enhance_commands(&session.commands).await?;
+            self.template.record_stage(Stage::AiAnalysis, validation_start);
             println!("✅ Command validation complete ({} commands processed)", validated_commands.len());
             
             // Create a temporary session with validated commands for generation
@@ -1567,19 +2727,27 @@ impl MarkdownGenerator {
         if let Some(ai_analyzer_cell) = &self.template.ai_analyzer {
             // Use try_borrow to avoid conflicts
             match ai_analyzer_cell.try_borrow() {
+                Ok(ai_analyzer) if ai_analyzer.get_config().is_heuristic() => {
+                    // Rewriting the document needs a language model
+                    println!("   ⏭️  Heuristic analysis only, skipping markdown post-processing");
+                    Ok(markdown.to_string())
+                }
                 Ok(_ai_analyzer) => {
                     println!("   🎯 Creating enhancement prompts...");
                     // Use the prompt engine to create a markdown post-processing prompt
                     let prompt_engine = crate::llm::prompt::PromptEngine::new();
                     let (system_prompt, user_prompt) = prompt_engine.generate_markdown_processing_prompt(
                         markdown,
-                        Some(&session.description),
+                        Some(&self.ai_session_context(session)),
                         Some("Development team")
                     )?;
                     
                     println!("   🤖 Sending to AI for final optimization...");
                     // Query the LLM to improve the markdown
-                    let llm_response = self.query_llm_for_enhancement(&system_prompt, &user_prompt).await?;
+                    let query_start = Instant::now();
+                    let llm_response = self.query_llm_for_enhancement(&system_prompt, &user_prompt).await;
+                    self.template.record_stage(Stage::AiAnalysis, query_start);
+                    let llm_response = llm_response?;
                     
                     // Return the enhanced markdown or fall back to original if processing fails
                     if llm_response.len() > 100 && !llm_response.contains("Analysis unavailable") {
@@ -1601,6 +2769,14 @@ impl MarkdownGenerator {
         }
     }
 
+    /// Session description combined with any custom context, for session-level AI prompts
+    fn ai_session_context(&self, session: &Session) -> String {
+        match &self.template.get_config().ai_analysis_config.custom_context {
+            Some(context) => format!("{}\n\nAdditional Context: {}", session.description, context),
+            None => session.description.clone(),
+        }
+    }
+
     /// Query LLM for markdown enhancement
     async fn query_llm_for_enhancement(&self, system_prompt: &str, user_prompt: &str) -> Result<String> {
         if let Some(ai_analyzer_cell) = &self.template.ai_analyzer {
@@ -1637,14 +2813,33 @@ impl MarkdownGenerator {
                 system_prompt: Some(system_prompt.to_string()),
             };
 
-            // Get response
-            match client.generate(request).await {
-                Ok(response) => Ok(response.content),
+            // Show the response as it arrives, so long documents don't look like a hang
+            let mut chunks = match client.generate_streaming(request).await {
+                Ok(chunks) => chunks,
                 Err(e) => {
                     eprintln!("LLM enhancement failed: {}", e);
-                    Err(anyhow!("Failed to enhance markdown: {}", e))
+                    return Err(anyhow!("Failed to enhance markdown: {}", e));
                 }
+            };
+            let mut content = String::new();
+            while let Some(chunk) = chunks.next().await {
+                match chunk {
+                    Ok(text) => {
+                        print!("{}", text);
+                        let _ = std::io::Write::flush(&mut std::io::stdout());
+                        content.push_str(&text);
+                    }
+                    Err(e) => {
+                        println!();
+                        eprintln!("LLM enhancement failed: {}", e);
+                        return Err(anyhow!("Failed to enhance markdown: {}", e));
+                    }
+                }
+            }
+            if !content.is_empty() {
+                println!();
             }
+            Ok(content)
         } else {
             Err(anyhow!("No AI analyzer available for enhancement"))
         }
@@ -1654,6 +2849,7 @@ impl MarkdownGenerator {
     pub async fn generate_comprehensive_ai_documentation(&mut self, session: &Session) -> Result<String> {
         if let Some(ai_analyzer_cell) = &self.template.ai_analyzer {
             println!("🔬 Generating comprehensive AI analysis...");
+            let session_context = self.ai_session_context(session);
             
             // Try to borrow and generate enhanced documentation
             let enhanced_doc = match ai_analyzer_cell.try_borrow_mut() {
@@ -1661,7 +2857,10 @@ impl MarkdownGenerator {
                     // Use AI to generate enhanced documentation structure
                     println!("📊 Analyzing workflow patterns and command relationships...");
                     let _commands: Vec<String> = session.commands.iter().map(|c| c.command.clone()).collect();
-                    let doc = ai_analyzer.generate_enhanced_documentation(&session.commands, Some(&session.description)).await?;
+                    let analysis_start = Instant::now();
+                    let doc = ai_analyzer.generate_enhanced_documentation(&session.commands, Some(&session_context)).await;
+                    self.template.record_stage(Stage::AiAnalysis, analysis_start);
+                    let doc = doc?;
                     println!("✅ Workflow analysis complete");
                     doc
                 }
@@ -1706,7 +2905,12 @@ impl MarkdownGenerator {
         summary.push_str("## Session Overview\n\n");
         summary.push_str(&format!("- **Total Commands**: {}\n", session.stats.total_commands));
         summary.push_str(&format!("- **Successful Commands**: {}\n", session.stats.successful_commands));
-        summary.push_str(&format!("- **Failed Commands**: {}\n", session.stats.failed_commands));
+        if session.stats.terminated_commands > 0 {
+            summary.push_str(&format!("- **Failed Commands**: {} ({} terminated by a signal)\n",
+                                     session.stats.failed_commands, session.stats.terminated_commands));
+        } else {
+            summary.push_str(&format!("- **Failed Commands**: {}\n", session.stats.failed_commands));
+        }
         summary.push_str(&format!("- **Annotations**: {}\n", session.stats.total_annotations));
         
         if let Some(duration) = session.get_duration_seconds() {
@@ -1772,6 +2976,9 @@ impl MarkdownGenerator {
                 min_confidence_score: 0.8,
                 enable_caching: true,
                 custom_context: None,
+                mode: AIAnalysisMode::Full,
+                polish_annotations: false,
+                min_significance: DEFAULT_AI_MIN_SIGNIFICANCE,
             },
         }
     }
@@ -1803,6 +3010,7 @@ impl MarkdownGenerator {
                 max_hierarchy_depth: 3,
                 include_workflow_summaries: true,
                 include_command_type_explanations: true,
+                include_prerequisites: true,
                 ..TemplateOptions::default()
             },
             code_block_config: {
@@ -1825,6 +3033,9 @@ impl MarkdownGenerator {
                 min_confidence_score: 0.7,
                 enable_caching: true,
                 custom_context: None,
+                mode: AIAnalysisMode::Full,
+                polish_annotations: false,
+                min_significance: DEFAULT_AI_MIN_SIGNIFICANCE,
             },
         }
     }
@@ -1844,6 +3055,9 @@ impl MarkdownGenerator {
             min_confidence_score: 0.7,
             enable_caching: true,
             custom_context: Some("Focus on practical insights and actionable recommendations for terminal commands.".to_string()),
+            mode: AIAnalysisMode::Full,
+            polish_annotations: false,
+            min_significance: DEFAULT_AI_MIN_SIGNIFICANCE,
         };
         config
     }
@@ -1897,6 +3111,9 @@ impl MarkdownGenerator {
                 min_confidence_score: 0.75,
                 enable_caching: true,
                 custom_context: Some("Focus on workflow organization and command categorization.".to_string()),
+                mode: AIAnalysisMode::Full,
+                polish_annotations: false,
+                min_significance: DEFAULT_AI_MIN_SIGNIFICANCE,
             },
         }
     }
@@ -1958,6 +3175,23 @@ impl MarkdownGenerator {
                 verbosity_level: VerbosityLevel::Standard,
                 include_command_relationships: false,
                 use_compact_formatting: false,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: false,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
@@ -2016,6 +3250,23 @@ impl MarkdownGenerator {
                 verbosity_level: VerbosityLevel::Minimal,
                 include_command_relationships: false,
                 use_compact_formatting: true,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: false,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
@@ -2086,6 +3337,23 @@ impl MarkdownGenerator {
                 verbosity_level: VerbosityLevel::Verbose,
                 include_command_relationships: true,
                 use_compact_formatting: false,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: false,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
@@ -2155,6 +3423,23 @@ impl MarkdownGenerator {
                 verbosity_level: VerbosityLevel::Detailed,
                 include_command_relationships: true,
                 use_compact_formatting: false,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: true,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
@@ -2220,6 +3505,23 @@ impl MarkdownGenerator {
                 verbosity_level: VerbosityLevel::Standard,
                 include_command_relationships: false,
                 use_compact_formatting: false,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: false,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
@@ -2252,8 +3554,8 @@ mod markdown_hierarchical_test {
                 timestamp: DateTime::parse_from_rfc3339("2023-01-01T10:00:00Z").unwrap().with_timezone(&Utc),
                 exit_code: Some(0),
                 output: Some("".to_string()),
-                error: None,
                 shell: "bash".to_string(),
+                ..Default::default()
             },
             CommandEntry {
                 command: "cd project".to_string(),
@@ -2261,8 +3563,8 @@ mod markdown_hierarchical_test {
                 timestamp: DateTime::parse_from_rfc3339("2023-01-01T10:01:00Z").unwrap().with_timezone(&Utc),
                 exit_code: Some(0),
                 output: Some("".to_string()),
-                error: None,
                 shell: "bash".to_string(),
+                ..Default::default()
             },
             
             // Development phase - Development commands
@@ -2272,8 +3574,8 @@ mod markdown_hierarchical_test {
                 timestamp: DateTime::parse_from_rfc3339("2023-01-01T10:02:00Z").unwrap().with_timezone(&Utc),
                 exit_code: Some(0),
                 output: Some("package.json created".to_string()),
-                error: None,
                 shell: "bash".to_string(),
+                ..Default::default()
             },
             CommandEntry {
                 command: "git init".to_string(),
@@ -2281,8 +3583,8 @@ mod markdown_hierarchical_test {
                 timestamp: DateTime::parse_from_rfc3339("2023-01-01T10:03:00Z").unwrap().with_timezone(&Utc),
                 exit_code: Some(0),
                 output: Some("Initialized empty Git repository".to_string()),
-                error: None,
                 shell: "bash".to_string(),
+                ..Default::default()
             },
             
             // Build phase - Development commands
@@ -2292,8 +3594,8 @@ mod markdown_hierarchical_test {
                 timestamp: DateTime::parse_from_rfc3339("2023-01-01T10:04:00Z").unwrap().with_timezone(&Utc),
                 exit_code: Some(0),
                 output: Some("added 1 package".to_string()),
-                error: None,
                 shell: "bash".to_string(),
+                ..Default::default()
             },
             CommandEntry {
                 command: "npm run build".to_string(),
@@ -2301,8 +3603,8 @@ mod markdown_hierarchical_test {
                 timestamp: DateTime::parse_from_rfc3339("2023-01-01T10:05:00Z").unwrap().with_timezone(&Utc),
                 exit_code: Some(0),
                 output: Some("Build completed successfully".to_string()),
-                error: None,
                 shell: "bash".to_string(),
+                ..Default::default()
             },
             
             // Testing phase - Development commands
@@ -2312,8 +3614,8 @@ mod markdown_hierarchical_test {
                 timestamp: DateTime::parse_from_rfc3339("2023-01-01T10:06:00Z").unwrap().with_timezone(&Utc),
                 exit_code: Some(0),
                 output: Some("All tests passed".to_string()),
-                error: None,
                 shell: "bash".to_string(),
+                ..Default::default()
             },
             
             // Deployment phase - System commands
@@ -2323,8 +3625,8 @@ mod markdown_hierarchical_test {
                 timestamp: DateTime::parse_from_rfc3339("2023-01-01T10:07:00Z").unwrap().with_timezone(&Utc),
                 exit_code: Some(0),
                 output: Some("Successfully built image".to_string()),
-                error: None,
                 shell: "bash".to_string(),
+                ..Default::default()
             },
             
             // Monitoring phase - System commands
@@ -2334,8 +3636,8 @@ mod markdown_hierarchical_test {
                 timestamp: DateTime::parse_from_rfc3339("2023-01-01T10:08:00Z").unwrap().with_timezone(&Utc),
                 exit_code: Some(0),
                 output: Some("node process running".to_string()),
-                error: None,
                 shell: "bash".to_string(),
+                ..Default::default()
             },
         ];
         
@@ -2366,6 +3668,26 @@ mod markdown_hierarchical_test {
         assert!(hierarchy.type_groups.contains_key(&CommandType::System));
     }
 
+    #[test]
+    fn test_command_significance() {
+        let entry = |command: &str, exit_code: i32| CommandEntry {
+            command: command.to_string(),
+            exit_code: Some(exit_code),
+            working_directory: "/tmp".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(command_significance(&entry("cd /etc", 0)), 0);
+        assert_eq!(command_significance(&entry("ls -la", 0)), 0);
+        assert_eq!(command_significance(&entry("ls -la", 2)), MAX_SIGNIFICANCE);
+        assert_eq!(command_significance(&entry("cargo build", 0)), MAX_SIGNIFICANCE);
+        assert_eq!(command_significance(&entry("mkdir build", 0)), 1);
+        assert_eq!(command_significance(&entry("git status", 0)), 2);
+        assert_eq!(command_significance(&entry("ls -la | grep conf", 0)), 2);
+        assert_eq!(command_significance(&entry("curl -s -H 'Accept: json' https://api.example.com | jq .", 0)), 4);
+    }
+
     #[tokio::test]
     async fn test_command_type_classification() {
         // Test file system commands
@@ -2392,6 +3714,25 @@ mod markdown_hierarchical_test {
         assert_eq!(CommandType::classify_command("sed 's/old/new/g'"), CommandType::TextProcessing);
     }
 
+    #[test]
+    fn test_custom_categories_checked_before_built_in_types() {
+        let platform = CustomCategory {
+            name: "Platform Tools".to_string(),
+            icon: "🛰️".to_string(),
+            description: String::new(),
+            prefixes: vec!["acmectl".to_string(), "make deploy".to_string()],
+        };
+        let categories = vec![platform.clone()];
+
+        let deploy = CommandType::classify_with_categories("make deploy ENV=prod", &categories);
+        assert_eq!(deploy, CommandType::Custom(platform));
+        assert_eq!(deploy.name(), "Platform Tools");
+        assert_eq!(deploy.icon(), "🛰️");
+        assert_eq!(WorkflowPhase::classify_command("acmectl status", &deploy), WorkflowPhase::Other);
+        assert_eq!(CommandType::classify_with_categories("make build", &categories), CommandType::Development);
+        assert_eq!(CommandType::classify_with_categories("acmectl-legacy", &categories), CommandType::Other);
+    }
+
     #[tokio::test]
     async fn test_workflow_phase_classification() {
         // Test setup phase (commands with setup keywords)
@@ -2474,9 +3815,9 @@ mod markdown_hierarchical_test {
         assert!(markdown.contains("1 commands executed"));
         
         // Check that hierarchical TOC is present
-        assert!(markdown.contains("[🔧 Setup Phase](#setup)"));
-        assert!(markdown.contains("[🏗️ Build Phase](#build)"));
-        assert!(markdown.contains("[🧪 Testing Phase](#testing)"));
+        assert!(markdown.contains("[🔧 Setup Phase](#-setup---initial-project-setup-environment-configuration-and-dependency-installation)"));
+        assert!(markdown.contains("[🏗️ Build Phase](#-build---compilation-building-and-packaging-of-the-application)"));
+        assert!(markdown.contains("[🧪 Testing Phase](#-testing---running-tests-validation-and-quality-assurance)"));
         
         // Check that specific commands are present
         assert!(markdown.contains("mkdir project"));
@@ -2572,6 +3913,23 @@ mod markdown_hierarchical_test {
                 verbosity_level: VerbosityLevel::Standard,
                 include_command_relationships: false,
                 use_compact_formatting: false,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: false,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
@@ -2630,6 +3988,23 @@ mod markdown_hierarchical_test {
                 verbosity_level: VerbosityLevel::Minimal,
                 include_command_relationships: false,
                 use_compact_formatting: true,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: false,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
@@ -2700,6 +4075,23 @@ mod markdown_hierarchical_test {
                 verbosity_level: VerbosityLevel::Verbose,
                 include_command_relationships: true,
                 use_compact_formatting: false,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: false,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
@@ -2769,6 +4161,23 @@ mod markdown_hierarchical_test {
                 verbosity_level: VerbosityLevel::Detailed,
                 include_command_relationships: true,
                 use_compact_formatting: false,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: false,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
@@ -2834,6 +4243,23 @@ mod markdown_hierarchical_test {
                 verbosity_level: VerbosityLevel::Standard,
                 include_command_relationships: false,
                 use_compact_formatting: false,
+                annotation_grouping: AnnotationGrouping::Chronological,
+                locale: Locale::English,
+                focus_failures: false,
+                split_chained_commands: false,
+                max_inline_command_length: DEFAULT_MAX_INLINE_COMMAND_LENGTH,
+                collapse_repeated_commands: false,
+                show_directory_changes: true,
+                annotation_numbering: AnnotationNumbering::default(),
+                include_prerequisites: false,
+                resolve_package_scripts: false,
+                preserve_raw_output: false,
+                group_by_tag: false,
+                toc_depth: DEFAULT_TOC_DEPTH,
+                show_step_numbers: false,
+                gap_threshold_minutes: DEFAULT_GAP_THRESHOLD_MINUTES,
+                include_glossary: false,
+                show_nesting: false,
             },
             code_block_config: CodeBlockConfig::default(),
             ai_analysis_config: AIAnalysisConfig::default(),
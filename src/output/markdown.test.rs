@@ -1,7 +1,8 @@
 use super::*;
 use crate::session::manager::{Session, Annotation, AnnotationType, SessionState};
 use crate::terminal::CommandEntry;
-use chrono::Utc;
+use crate::output::markdown::{AnnotationGrouping, AnnotationNumbering, CommandType, HierarchicalStructure, RepeatedRun, collapse_repeated_commands};
+use crate::output::i18n::{Locale, heading_anchor};
 use std::collections::HashMap;
 
 fn create_test_session() -> Session {
@@ -13,32 +14,29 @@ fn create_test_session() -> Session {
     // Add some test commands
     let command1 = CommandEntry {
         command: "ls -la".to_string(),
-        timestamp: Utc::now(),
         exit_code: Some(0),
         working_directory: "/home/user/project".to_string(),
         shell: "bash".to_string(),
         output: Some("total 8\ndrwxr-xr-x 2 user user 4096 Jan 1 12:00 .\ndrwxr-xr-x 3 user user 4096 Jan 1 12:00 ..".to_string()),
-        error: None,
+        ..Default::default()
     };
 
     let command2 = CommandEntry {
         command: "cargo build".to_string(),
-        timestamp: Utc::now(),
         exit_code: Some(0),
         working_directory: "/home/user/project".to_string(),
         shell: "bash".to_string(),
         output: Some("   Compiling docpilot v0.1.0\n    Finished dev [unoptimized + debuginfo] target(s) in 2.34s".to_string()),
-        error: None,
+        ..Default::default()
     };
 
     let command3 = CommandEntry {
         command: "cargo test nonexistent".to_string(),
-        timestamp: Utc::now(),
         exit_code: Some(1),
         working_directory: "/home/user/project".to_string(),
         shell: "bash".to_string(),
-        output: None,
         error: Some("error: no tests to run".to_string()),
+        ..Default::default()
     };
 
     session.add_command(command1);
@@ -121,6 +119,30 @@ async fn test_markdown_generation_without_metadata() {
     assert!(content.contains("## Commands"));
 }
 
+#[tokio::test]
+async fn test_metadata_shows_capture_delay() {
+    let mut session = create_test_session();
+    session.metadata.settings.insert(crate::session::manager::CAPTURE_DELAY_SETTING.to_string(), "30".to_string());
+    let content = MarkdownGenerator::new().generate_documentation(&session).await.unwrap();
+    assert!(content.contains("| Capture Delay | 30 s |"));
+
+    let content = MarkdownGenerator::new().generate_documentation(&create_test_session()).await.unwrap();
+    assert!(!content.contains("Capture Delay"));
+}
+
+#[tokio::test]
+async fn test_metadata_notes_unreachable_llm() {
+    let session = create_test_session();
+
+    let mut generator = MarkdownGenerator::new();
+    generator.set_ai_unavailable("claude: auth failed; ollama: connection refused".to_string());
+    let content = generator.generate_documentation(&session).await.unwrap();
+    assert!(content.contains("| AI Analysis | skipped, no LLM provider reachable (claude: auth failed; ollama: connection refused) |"));
+
+    let content = MarkdownGenerator::new().generate_documentation(&session).await.unwrap();
+    assert!(!content.contains("no LLM provider reachable"));
+}
+
 #[tokio::test]
 async fn test_markdown_generation_without_timestamps() {
     let session = create_test_session();
@@ -141,6 +163,25 @@ async fn test_markdown_generation_without_timestamps() {
     assert!(timestamp_lines.len() <= 2); // Started/Stopped timestamps in overview
 }
 
+#[tokio::test]
+async fn test_command_duration_rows() {
+    let mut session = create_test_session();
+    session.commands[0].duration_ms = Some(420);
+    session.commands[1].duration_ms = Some(83_500);
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.include_duration = true;
+    let content = MarkdownTemplate::with_config(config.clone()).generate(&session).await.unwrap();
+    assert!(content.contains("| Duration | 420ms |"));
+    assert!(content.contains("| Duration | 1m 23s |"));
+    // Commands without a recorded duration get no row
+    assert_eq!(content.matches("| Duration |").count(), 2);
+
+    config.template_options.include_duration = false;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(!content.contains("| Duration |"));
+}
+
 #[tokio::test]
 async fn test_markdown_generation_without_output() {
     let session = create_test_session();
@@ -225,12 +266,11 @@ async fn test_output_truncation() {
     let long_output = "a".repeat(2000);
     let command_with_long_output = CommandEntry {
         command: "echo long_output".to_string(),
-        timestamp: Utc::now(),
         exit_code: Some(0),
         working_directory: "/home/user".to_string(),
         shell: "bash".to_string(),
         output: Some(long_output),
-        error: None,
+        ..Default::default()
     };
     
     session.add_command(command_with_long_output);
@@ -238,11 +278,51 @@ async fn test_output_truncation() {
     let mut config = MarkdownConfig::default();
     config.max_output_length = 100;
     
-    let template = MarkdownTemplate::with_config(config);
+    let template = MarkdownTemplate::with_config(config.clone());
     let content = template.generate(&session).await.unwrap();
     
-    // Should contain truncation message
-    assert!(content.contains("... (output truncated)"));
+    // Should say how much of the output is shown
+    assert!(content.contains(&format!("{}\n\n... (showing 100 B of 2.0 KB)", "a".repeat(100))));
+
+    // Output already cut when it was captured reports its original size, and cuts never split a character
+    let last = session.commands.last_mut().unwrap();
+    last.output = Some("é".repeat(60));
+    last.output_size = Some(4_404_019);
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains(&format!("{}\n\n... (showing 100 B of 4.2 MB)", "é".repeat(50))));
+}
+
+#[tokio::test]
+async fn test_spilled_output_links_sidecar() {
+    let mut session = create_test_session();
+    let build = &mut session.commands[1];
+    build.output = Some("Compiling app v0.1.0".to_string());
+    build.output_size = Some(3 * 1024 * 1024);
+    build.output_log = Some("/home/user/.docpilot/logs/session-abc-cmd-2.log".to_string());
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(content.contains("Compiling app v0.1.0\n\n... (showing 20 B of 3.0 MB)"));
+    assert!(content.contains("📄 Full output (3.0 MB): [session-abc-cmd-2.log](</home/user/.docpilot/logs/session-abc-cmd-2.log>)\n"));
+}
+
+#[tokio::test]
+async fn test_daily_log_appends_sessions() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = create_test_session();
+    let path = crate::output::append_to_daily_log(&first, dir.path(), "minimal").await.unwrap();
+    assert_eq!(path, crate::output::daily_log_path(dir.path(), &first));
+    let day = path.file_stem().unwrap().to_string_lossy().to_string();
+
+    let mut second = create_test_session();
+    second.id = "second-session".to_string();
+    second.commands[0].output = Some("# not a heading\n".to_string());
+    crate::output::append_to_daily_log(&second, dir.path(), "minimal").await.unwrap();
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    assert!(log.starts_with(&format!("# Engineering Log: {}\n\n<!-- docpilot session {} -->\n## ", day, first.id)));
+    assert!(log.contains("\n---\n\n<!-- docpilot session second-session -->\n## "));
+    // Only the day's heading stays at the top level; output inside code blocks is left alone
+    assert_eq!(log.lines().filter(|line| line.starts_with("# ")).collect::<Vec<_>>(), vec![format!("# Engineering Log: {}", day), "# not a heading".to_string()]);
 }
 
 #[tokio::test]
@@ -297,6 +377,282 @@ async fn test_empty_session_generation() {
     assert!(!content.contains("## Annotations"));
 }
 
+#[tokio::test]
+async fn test_annotations_grouped_by_type() {
+    let mut session = create_test_session();
+    session.add_annotation("Do not run this on production".to_string(), AnnotationType::Warning);
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.annotation_grouping = AnnotationGrouping::ByType;
+
+    let template = MarkdownTemplate::with_config(config);
+    let content = template.generate(&session).await.unwrap();
+
+    let warnings = content.find("### ⚠️ Warnings").expect("warnings group");
+    let milestones = content.find("### 🎯 Milestones").expect("milestones group");
+    let notes = content.find("### 📝 Notes").expect("notes group");
+    assert!(warnings < milestones && milestones < notes);
+
+    // Both warnings are listed together and keep their chronological numbers
+    assert!(content.contains("#### ⚠️ Annotation 4"));
+    assert!(content.contains("#### ⚠️ Annotation 5"));
+    assert!(content.find("#### ⚠️ Annotation 5").unwrap() < milestones);
+}
+
+#[tokio::test]
+async fn test_focus_failures_collapses_successful_commands() {
+    let session = create_test_session();
+    let mut config = MarkdownConfig::default();
+    config.max_output_length = 5;
+    config.template_options.focus_failures = true;
+
+    let template = MarkdownTemplate::with_config(config);
+    let content = template.generate(&session).await.unwrap();
+
+    // Successful commands are listed compactly with their chronological numbers
+    assert!(content.contains("<summary>✅ Successful Commands: 2</summary>"));
+    assert!(content.contains("1. `ls -la`"));
+    assert!(content.contains("2. `cargo build`"));
+    assert!(!content.contains("### Command 1"));
+
+    // The failure is written in full, with its error output untruncated
+    assert!(content.contains("### Command 3"));
+    assert!(content.contains("error: no tests to run"));
+}
+
+#[tokio::test]
+async fn test_split_chained_commands() {
+    let mut session = create_test_session();
+    session.commands[1].command = "cd web && npm install || echo retry; ls".to_string();
+    let mut config = MarkdownConfig::default();
+    config.template_options.split_chained_commands = true;
+
+    let template = MarkdownTemplate::with_config(config);
+    let content = template.generate(&session).await.unwrap();
+
+    // The original line is kept and its steps are listed with when each one runs
+    assert!(content.contains("cd web && npm install || echo retry; ls"));
+    assert!(content.contains("**Steps:**"));
+    assert!(content.contains("1. `cd web`\n"));
+    assert!(content.contains("2. `npm install` — runs only if the previous step succeeded"));
+    assert!(content.contains("3. `echo retry` — runs only if the previous step failed"));
+    assert!(content.contains("4. `ls` — runs after the previous step"));
+    assert_eq!(content.matches("**Steps:**").count(), 1);
+
+    // Chains are classified by their main step rather than the leading `cd`
+    let hierarchy = HierarchicalStructure::with_chain_splitting(&session.commands, true);
+    assert!(hierarchy.type_groups[&CommandType::Development].iter().any(|c| c.command.starts_with("cd web")));
+}
+
+#[tokio::test]
+async fn test_long_commands_are_shortened_in_table() {
+    let mut session = create_test_session();
+    let long_command = format!("docker run --rm -it {} alpine sh", "-e KEY=value ".repeat(10));
+    session.commands[0].command = long_command.clone();
+    session.commands[1].command = "ps aux | grep node".to_string();
+    session.commands[2].command = "cat <<EOF\nhello\nEOF".to_string();
+    let mut config = MarkdownConfig::default();
+    config.template_options.max_inline_command_length = 30;
+
+    let template = MarkdownTemplate::with_config(config);
+    let content = template.generate(&session).await.unwrap();
+
+    // The table gets a shortened command; the code block below keeps it whole
+    assert!(content.contains("| Command | `docker run --rm -it -e KEY=val …` |"));
+    assert!(content.contains(&long_command));
+    assert!(content.contains("| Command | `ps aux \\| grep node` |"));
+    assert!(content.contains("| Command | `cat <<EOF …` |"));
+}
+
+#[tokio::test]
+async fn test_repeated_commands_are_collapsed() {
+    let mut session = create_test_session();
+    let start = session.commands[0].timestamp;
+    let poll = session.commands[0].clone();
+    session.commands = (0..4)
+        .map(|i| CommandEntry {
+            command: "kubectl get pods".to_string(),
+            timestamp: start + chrono::Duration::seconds(30 * i),
+            exit_code: Some(if i == 3 { 0 } else { 1 }),
+            step: Some(i as usize + 1),
+            ..poll.clone()
+        })
+        .collect();
+    // Logged in the same second as the last run, but not part of it
+    session.commands.push(CommandEntry { command: "kubectl logs web".to_string(), step: Some(5), ..session.commands[3].clone() });
+
+    let (collapsed, runs) = collapse_repeated_commands(&session.commands);
+    assert_eq!(collapsed.len(), 2);
+    assert_eq!(collapsed[0].exit_code, Some(0));
+    assert_eq!(runs[&0], RepeatedRun { count: 4, first_timestamp: start });
+    assert!(!runs.contains_key(&1));
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.collapse_repeated_commands = true;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+
+    assert_eq!(content.matches("Run 4 times in a row over 1m 30s; the last run is shown.").count(), 1);
+    assert!(content.contains("### Command 1 ✅"));
+    assert!(content.contains("### Command 2"));
+    assert!(!content.contains("### Command 3"));
+}
+
+#[test]
+fn test_numbered_output_path_skips_existing_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let guide = dir.path().join("guide.md");
+    std::fs::write(&guide, "edited").unwrap();
+    assert_eq!(crate::output::numbered_output_path(&guide), dir.path().join("guide-1.md"));
+
+    std::fs::write(dir.path().join("guide-1.md"), "").unwrap();
+    assert_eq!(crate::output::numbered_output_path(&guide), dir.path().join("guide-2.md"));
+    assert_eq!(crate::output::numbered_output_path(&dir.path().join("notes")), dir.path().join("notes-1"));
+}
+
+#[tokio::test]
+async fn test_template_previews_written_per_template() {
+    let dir = tempfile::tempdir().unwrap();
+    let session = create_test_session();
+    let options = crate::output::GenerateOptions { mock_llm: true, ..Default::default() };
+    let templates = vec!["minimal".to_string(), "hierarchical".to_string()];
+
+    let previews = crate::output::write_template_previews(&session, &templates, dir.path(), &options).await.unwrap();
+    assert_eq!(previews.len(), 2);
+    assert_eq!(previews[0].path, dir.path().join("preview-minimal.md"));
+    assert_eq!(std::fs::read_to_string(&previews[1].path).unwrap(), previews[1].content);
+    assert!(previews[1].section_count() > previews[0].section_count());
+}
+
+#[tokio::test]
+async fn test_outcome_inferred_when_exit_code_missing() {
+    let mut session = create_test_session();
+    session.commands[0].exit_code = None;
+    session.commands[0].output = Some("Compiling docpilot v0.1.0".to_string());
+    session.commands[1].exit_code = None;
+    session.commands[1].error = Some("ls: cannot access 'missing': No such file or directory".to_string());
+    session.commands[2].exit_code = None;
+    session.commands[2].output = None;
+    session.commands[2].error = None;
+
+    assert_eq!(session.inferred_outcome_counts(), (1, 1));
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(content.contains("### Command 1 ✔️ *(inferred)*"));
+    assert!(content.contains("### Command 2 ✖️ *(inferred)*"));
+    assert!(content.contains("### Command 3 ⏳"));
+    assert!(content.contains("| Exit Code | not captured; output suggests it failed |"));
+    assert!(content.contains("| Likely Successful (inferred from output) | 1 |"));
+}
+
+#[tokio::test]
+async fn test_directory_changes_marked_in_chronological_list() {
+    let mut session = create_test_session();
+    session.commands[1].working_directory = "/home/user/project/web".to_string();
+    session.commands[2].working_directory = "/home/user/project/web".to_string();
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert_eq!(content.matches("> 📁 now in").count(), 1);
+    let marker = content.find("> 📁 now in `/home/user/project/web`").unwrap();
+    assert!(marker < content.find("### Command 2").unwrap());
+    assert!(marker > content.find("### Command 1").unwrap());
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.show_directory_changes = false;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(!content.contains("📁 now in"));
+}
+
+#[tokio::test]
+async fn test_annotation_references_link_to_numbered_anchors() {
+    let mut session = create_test_session();
+    session.add_annotation("Do not run this on production".to_string(), AnnotationType::Warning);
+    session.add_annotation("Skipped because of [[warning:2]], see also [[ann:1]] and [[note:9]]".to_string(), AnnotationType::Note);
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(content.contains("<a id=\"ann-5\"></a>\n\n### ⚠️ Annotation 5"));
+    assert!(content.contains("Skipped because of [Annotation 5](#ann-5), see also [Annotation 1](#ann-1) and [[note:9]]"));
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.annotation_numbering = AnnotationNumbering::PerType;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("<a id=\"ann-warning-2\"></a>\n\n### ⚠️ Warning 2"));
+    assert!(content.contains("Skipped because of [Warning 2](#ann-warning-2), see also"));
+}
+
+#[tokio::test]
+async fn test_prerequisites_checklist() {
+    let mut session = create_test_session();
+    let mut install = session.commands[0].clone();
+    install.command = "sudo apt-get install -y jq".to_string();
+    session.commands.insert(0, install);
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.include_prerequisites = true;
+    config.template_options.include_toc = true;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("- [Prerequisites](#prerequisites)"));
+    assert!(content.contains("## Prerequisites\n\nInferred from the installs and version checks in this session:\n\n- [ ] `jq` (installed with `sudo apt-get install -y jq`)\n"));
+    assert!(content.find("## Prerequisites").unwrap() < content.find("## Session Metadata").unwrap());
+
+    // Nothing to list, no section
+    let mut config = MarkdownConfig::default();
+    config.template_options.include_prerequisites = true;
+    let content = MarkdownTemplate::with_config(config).generate(&create_test_session()).await.unwrap();
+    assert!(!content.contains("Prerequisites"));
+}
+
+#[tokio::test]
+async fn test_package_scripts_resolved_when_enabled() {
+    let project = tempfile::TempDir::new().unwrap();
+    std::fs::write(project.path().join("package.json"), r#"{ "scripts": { "build": "tsc && vite build" } }"#).unwrap();
+    let mut session = create_test_session();
+    let mut build = session.commands[0].clone();
+    build.command = "npm run build".to_string();
+    build.working_directory = project.path().to_string_lossy().to_string();
+    session.commands = vec![build];
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(!content.contains("package.json"));
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.resolve_package_scripts = true;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("> 📜 `build` is a script from `package.json`. It runs:\n\n```bash\ntsc && vite build\n```\n"));
+}
+
+#[tokio::test]
+async fn test_terminal_escapes_cleaned_from_output() {
+    let mut session = create_test_session();
+    session.commands[0].output = Some("\u{1b}[32mfetching\u{1b}[0m 10%\r\u{1b}[Kfetched 3 packages\n".to_string());
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(content.contains("fetched 3 packages"));
+    assert!(!content.contains('\u{1b}'));
+    assert!(!content.contains("10%"));
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.preserve_raw_output = true;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("\u{1b}[32mfetching"));
+}
+
+#[tokio::test]
+async fn test_localized_headings() {
+    let session = create_test_session();
+    let mut config = MarkdownConfig::default();
+    config.template_options.include_toc = true;
+    config.template_options.locale = Locale::German;
+
+    let template = MarkdownTemplate::with_config(config);
+    let content = template.generate(&session).await.unwrap();
+
+    assert!(content.contains("## Sitzungsübersicht"));
+    assert!(content.contains("## Befehle"));
+    assert!(content.contains("- [Befehle](#befehle)"));
+    assert!(content.contains("| Befehl | `cargo build` |"));
+    assert!(!content.contains("## Session Overview"));
+}
+
 #[tokio::test]
 async fn test_custom_title_and_headers() {
     let session = create_test_session();
@@ -408,12 +764,11 @@ async fn test_working_directory_grouping() {
     // Add commands from different directories
     let command_different_dir = CommandEntry {
         command: "pwd".to_string(),
-        timestamp: Utc::now(),
         exit_code: Some(0),
         working_directory: "/home/user/other".to_string(),
         shell: "bash".to_string(),
         output: Some("/home/user/other".to_string()),
-        error: None,
+        ..Default::default()
     };
     
     session.add_command(command_different_dir);
@@ -429,6 +784,51 @@ async fn test_working_directory_grouping() {
     assert!(content.contains("### Directory: `/home/user/other`"));
 }
 
+#[tokio::test]
+async fn test_grouped_output_is_deterministic() {
+    let mut session = create_test_session();
+    for (command, directory) in [
+        ("cargo build", "/home/user/zeta"),
+        ("docker ps", "/home/user/alpha"),
+        ("git status", "/home/user/mid"),
+        ("npm install", "/home/user/alpha"),
+        ("curl localhost:8080", "/home/user/zeta"),
+    ] {
+        session.add_command(CommandEntry {
+            command: command.to_string(),
+            exit_code: Some(0),
+            working_directory: directory.to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        });
+    }
+
+    // The footer's generation time and an active session's duration may tick between runs
+    let without_footer = |content: String| -> String {
+        content
+            .lines()
+            .filter(|line| !line.contains("Generated by DocPilot on") && !line.starts_with("**Duration:**"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let mut by_directory = MarkdownConfig::default();
+    by_directory.template_options.group_by_directory = true;
+    for config in [MarkdownGenerator::comprehensive_config(), by_directory] {
+        let first = without_footer(MarkdownTemplate::with_config(config.clone()).generate(&session).await.unwrap());
+        let second = without_footer(MarkdownTemplate::with_config(config).generate(&session).await.unwrap());
+        assert_eq!(first, second);
+    }
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.group_by_directory = true;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    let alpha = content.find("### Directory: `/home/user/alpha`").unwrap();
+    let mid = content.find("### Directory: `/home/user/mid`").unwrap();
+    let zeta = content.find("### Directory: `/home/user/zeta`").unwrap();
+    assert!(alpha < mid && mid < zeta);
+}
+
 #[tokio::test]
 async fn test_time_grouping() {
     let session = create_test_session();
@@ -473,4 +873,298 @@ async fn test_config_update() {
     let new_config = MarkdownGenerator::minimal_config();
     generator.set_config(new_config);
     assert!(!generator.get_config().include_metadata);
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_alias_expansion_rendered_as_note() {
+    let mut session = Session::new("Alias Session".to_string(), None).unwrap();
+    session.add_command(CommandEntry {
+        command: "ll /var/log".to_string(),
+        exit_code: Some(0),
+        working_directory: "/home/user".to_string(),
+        shell: "bash".to_string(),
+        expanded_command: Some("ls -alF /var/log".to_string()),
+        ..Default::default()
+    });
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(content.contains("`ll` is an alias or shell function"));
+    assert!(content.contains("```bash\nls -alF /var/log\n```"));
+}
+
+#[tokio::test]
+async fn test_nested_shell_noted_when_enabled() {
+    let mut session = create_test_session();
+    session.commands[1].nesting = Some(crate::terminal::monitor::CommandNesting { depth: 1, parent: Some("docker exec -it web bash".to_string()) });
+    session.commands[2].nesting = Some(crate::terminal::monitor::CommandNesting { depth: 2, parent: None });
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(!content.contains("nested shell"));
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.show_nesting = true;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("> ↪️ *(inside: `docker exec -it web bash`, nested shell level 1)*"));
+    assert!(content.contains("> ↪️ *(in a nested shell, level 2)*"));
+}
+
+#[tokio::test]
+async fn test_group_by_tag_keeps_command_numbers() {
+    let mut session = create_test_session();
+    session.commands.clear();
+    for (command, tags) in [
+        ("psql -f schema.sql", vec!["database"]),
+        ("git status", vec![]),
+        ("kubectl apply -f app.yaml", vec!["deploy", "database"]),
+        ("pg_dump app > backup.sql", vec!["database"]),
+    ] {
+        session.add_command(CommandEntry {
+            command: command.to_string(),
+            exit_code: Some(0),
+            working_directory: "/home/user/project".to_string(),
+            shell: "bash".to_string(),
+            tags: tags.into_iter().map(str::to_string).collect(),
+            ..Default::default()
+        });
+    }
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.group_by_tag = true;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+
+    let database = content.find("### Tag: `database`").unwrap();
+    let deploy = content.find("### Tag: `deploy`").unwrap();
+    let untagged = content.find("### Untagged").unwrap();
+    assert!(database < deploy && deploy < untagged);
+    // Commands keep their capture numbers and are listed under their first tag only
+    assert!(content[database..deploy].contains("### Command 1") && content[database..deploy].contains("### Command 4"));
+    assert!(content[deploy..untagged].contains("### Command 3"));
+    assert!(content[untagged..].contains("### Command 2"));
+    assert!(content.contains("| Tags | `deploy`, `database` |"));
+}
+
+#[tokio::test]
+async fn test_environment_section() {
+    let mut session = create_test_session();
+    session.metadata.environment = [("PATH", "/usr/local/bin:/usr/bin"), ("NODE_ENV", "development")]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(!content.contains("## Environment"));
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.include_environment_vars = true;
+    config.template_options.include_toc = true;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("- [Environment](#environment)"));
+    assert!(content.contains("## Environment\n\nEnvironment variables when the session started:\n\n```bash\nNODE_ENV=development\nPATH=/usr/local/bin:/usr/bin\n```"));
+}
+
+#[tokio::test]
+async fn test_toc_depth() {
+    let mut session = create_test_session();
+    session.add_command(CommandEntry {
+        command: "cargo build --release".to_string(),
+        exit_code: Some(0),
+        working_directory: "/home/user/project".to_string(),
+        shell: "bash".to_string(),
+        ..Default::default()
+    });
+    let mut config = MarkdownGenerator::hierarchical_config();
+    config.template_options.include_toc = true;
+    config.template_options.toc_depth = 9;
+
+    let content = MarkdownTemplate::with_config(config.clone()).generate(&session).await.unwrap();
+    let toc = content.split("## Table of Contents\n\n").nth(1).unwrap().split("\n\n").next().unwrap();
+
+    // Deeper than the document's nesting lists everything down to the commands
+    assert!(toc.contains("  - [🏗️ Build Phase](#-build---compilation-building-and-packaging-of-the-application)"));
+    assert!(toc.contains("    - [Command 2: `cargo build --release`](#command-2-)"));
+
+    // Every link points at a heading, with repeated headings numbered like GitHub does
+    let mut anchors: HashMap<String, usize> = HashMap::new();
+    let mut headings = Vec::new();
+    for line in content.lines().filter(|line| line.starts_with('#')) {
+        let anchor = heading_anchor(line.trim_start_matches('#'));
+        let seen = anchors.entry(anchor.clone()).or_insert(0);
+        headings.push(if *seen == 0 { anchor } else { format!("{}-{}", anchor, seen) });
+        *seen += 1;
+    }
+    for link in toc.lines() {
+        let anchor = link.rsplit("](#").next().unwrap().trim_end_matches(')');
+        assert!(headings.iter().any(|heading| heading == anchor), "no heading for {}", link);
+    }
+    assert!(toc.contains("(#command-1--2)"));
+
+    config.template_options.toc_depth = 2;
+    let content = MarkdownTemplate::with_config(config.clone()).generate(&session).await.unwrap();
+    assert!(content.contains("  - [🧪 Testing Phase]"));
+    assert!(!content.contains("- [Command 1"));
+
+    // Phases with nested command types: types only where the commands section gives them a heading
+    config.template_options.group_by_workflow = false;
+    config.template_options.group_by_command_type = false;
+    config.template_options.include_command_type_explanations = false;
+    config.template_options.toc_depth = 3;
+    let content = MarkdownTemplate::with_config(config.clone()).generate(&session).await.unwrap();
+    assert!(content.contains("  - [🏗️ Build Phase](#-build-phase)\n    - [💻 Development Commands](#-development-commands)\n"));
+    assert!(content.contains("  - [📋 Other Phase](#-other-phase)\n"));
+    assert!(!content.contains("- [📁 FileSystem Commands]"));
+
+    config.template_options.toc_depth = 1;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("- [Commands](#commands)\n- [Annotations](#annotations)"));
+}
+
+#[tokio::test]
+async fn test_file_changes_section() {
+    use crate::session::files::{FileChange, FileChangeKind, FileTracking};
+
+    let mut session = create_test_session();
+    let mut tracking = FileTracking { patterns: vec!["config/*.yml".to_string()], changes: None };
+    session.metadata.file_tracking = Some(tracking.clone());
+    let mut config = MarkdownConfig::default();
+    config.template_options.include_toc = true;
+
+    let content = MarkdownTemplate::with_config(config.clone()).generate(&session).await.unwrap();
+    assert!(content.contains("- [File Changes](#file-changes)"));
+    assert!(content.contains("*Changes to files matching `config/*.yml` are recorded when the session stops.*"));
+
+    tracking.changes = Some(vec![
+        FileChange { path: "config/app.yml".to_string(), kind: FileChangeKind::Modified },
+        FileChange { path: "config/db.yml".to_string(), kind: FileChangeKind::Deleted },
+    ]);
+    session.metadata.file_tracking = Some(tracking.clone());
+    let content = MarkdownTemplate::with_config(config.clone()).generate(&session).await.unwrap();
+    let section = content.split("## File Changes\n\n").nth(1).unwrap();
+    assert!(section.starts_with("Files matching `config/*.yml` that changed during the session:\n\n| Change | File |"));
+    assert!(section.contains("| ✏️ Modified | `config/app.yml` |\n| 🗑️ Deleted | `config/db.yml` |"));
+    // Listed after the commands, before the annotations
+    assert!(content.find("## Commands").unwrap() < content.find("## File Changes").unwrap());
+    assert!(content.find("## File Changes").unwrap() < content.find("## Annotations").unwrap());
+
+    tracking.changes = Some(Vec::new());
+    session.metadata.file_tracking = Some(tracking);
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("None of the files matching `config/*.yml` changed during the session."));
+
+    session.metadata.file_tracking = None;
+    let content = MarkdownTemplate::with_config(MarkdownConfig::default()).generate(&session).await.unwrap();
+    assert!(!content.contains("File Changes"));
+}
+
+#[tokio::test]
+async fn test_step_numbers_ignore_filtering() {
+    let mut session = create_test_session();
+    // Leave out `cargo build`, as --exclude-pattern would
+    session.commands.remove(1);
+    let mut config = MarkdownConfig::default();
+    config.template_options.show_step_numbers = true;
+
+    let content = MarkdownTemplate::with_config(config.clone()).generate(&session).await.unwrap();
+    assert!(content.contains("<a id=\"step-1\"></a>\n\n### Step 1 ✅"));
+    assert!(content.contains("<a id=\"step-3\"></a>\n\n### Step 3 ❌"));
+    assert!(!content.contains("Step 2"));
+    assert!(!content.contains("### Command "));
+
+    config.template_options.show_step_numbers = false;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("### Command 2 ❌"));
+    assert!(!content.contains("step-"));
+}
+
+#[tokio::test]
+async fn test_trivial_commands_skip_ai_analysis() {
+    let mut session = create_test_session();
+    session.commands.push(CommandEntry { command: "pwd".to_string(), ..session.commands[0].clone() });
+    assert_eq!(crate::output::markdown::command_significance(&session.commands[0]), 0);
+    assert_eq!(crate::output::markdown::command_significance(&session.commands[1]), crate::output::markdown::MAX_SIGNIFICANCE);
+
+    let mut config = MarkdownConfig::default();
+    config.ai_analysis_config.enable_ai_explanations = true;
+    let template = MarkdownTemplate::with_config(config.clone()).with_ai_analyzer(crate::llm::LlmConfig::mock());
+    let content = template.generate(&session).await.unwrap();
+    assert!(content.contains("*AI analysis skipped for 2 trivial commands: `ls -la`, `pwd`*"));
+
+    config.ai_analysis_config.min_significance = 0;
+    let template = MarkdownTemplate::with_config(config).with_ai_analyzer(crate::llm::LlmConfig::mock());
+    let content = template.generate(&session).await.unwrap();
+    assert!(!content.contains("trivial commands"));
+}
+
+#[tokio::test]
+async fn test_long_pauses_noted_as_manual_steps() {
+    let mut session = create_test_session();
+    let start = session.commands[0].timestamp;
+    session.commands[1].timestamp = start + chrono::Duration::seconds(7 * 60 + 10);
+    session.commands[2].timestamp = start + chrono::Duration::seconds(8 * 60);
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(content.contains("*⏸️ ~7 min gap — likely a manual step here*\n\n### Command 2"));
+    assert_eq!(content.matches("⏸️").count(), 1);
+
+    let mut config = MarkdownConfig::default();
+    config.template_options.gap_threshold_minutes = 0;
+    let content = MarkdownTemplate::with_config(config.clone()).generate(&session).await.unwrap();
+    assert!(!content.contains("⏸️"));
+
+    // A long-running command is not a pause, and huge thresholds don't overflow
+    session.commands[1].duration_ms = Some(7 * 60 * 1000);
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(!content.contains("⏸️"));
+    config.template_options.gap_threshold_minutes = u64::MAX;
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(!content.contains("⏸️"));
+}
+
+#[tokio::test]
+async fn test_output_compared_with_baseline_session() {
+    let session = create_test_session();
+    let mut baseline = session.clone();
+    baseline.id = "last-release".to_string();
+    baseline.commands[1].output = Some("   Compiling docpilot v0.1.0\n    Finished dev [unoptimized + debuginfo] target(s) in 9.81s".to_string());
+    // Not run in the baseline, so documented without a comparison
+    baseline.commands.remove(2);
+
+    let template = MarkdownTemplate::new().with_baseline(Some(crate::output::baseline::OutputBaseline::new(baseline)));
+    let content = template.generate(&session).await.unwrap();
+    assert!(content.contains("*🔁 Output unchanged since session `last-release`*"));
+    assert!(content.contains("**🔁 Output changes since session `last-release`:**\n\n```diff\n    Compiling docpilot v0.1.0\n-    Finished dev [unoptimized + debuginfo] target(s) in 9.81s\n+    Finished dev [unoptimized + debuginfo] target(s) in 2.34s\n```"));
+    assert_eq!(content.matches("🔁").count(), 2);
+}
+
+#[tokio::test]
+async fn test_baseline_diff_respects_max_output_length() {
+    let mut session = create_test_session();
+    session.commands.truncate(1);
+    session.commands[0].output = Some((1..=200).map(|line| format!("new line {}\n", line)).collect());
+    let mut baseline = session.clone();
+    baseline.id = "last-release".to_string();
+    baseline.commands[0].output = Some((1..=200).map(|line| format!("old line {}\n", line)).collect());
+
+    let mut config = MarkdownConfig::default();
+    config.max_output_length = 500;
+    let template = MarkdownTemplate::with_config(config).with_baseline(Some(crate::output::baseline::OutputBaseline::new(baseline)));
+    let content = template.generate(&session).await.unwrap();
+    let diff = content.split("```diff\n").nth(1).unwrap().split("\n```").next().unwrap();
+    assert!(diff.len() < 600, "diff is {} bytes", diff.len());
+    assert!(diff.contains("... (showing 500 B of "));
+}
+
+#[tokio::test]
+async fn test_glossary_defines_session_tools() {
+    let mut session = create_test_session();
+    session.commands.push(CommandEntry { command: "sudo acmectl rollout".to_string(), ..session.commands[0].clone() });
+    let mut config = MarkdownConfig::default();
+    config.template_options.include_glossary = true;
+    config.template_options.include_toc = true;
+
+    let content = MarkdownTemplate::with_config(config).generate(&session).await.unwrap();
+    assert!(content.contains("- [Glossary](#glossary)"));
+    assert!(content.contains("## Glossary\n\n- **`acmectl`**: *project-specific or custom tool*\n- **`cargo`**: Rust build tool and package manager\n- **`ls`**: Lists directory contents\n"));
+
+    let content = MarkdownTemplate::new().generate(&session).await.unwrap();
+    assert!(!content.contains("Glossary"));
+}
@@ -3,7 +3,6 @@ mod tests {
     use crate::output::markdown::{MarkdownGenerator, MarkdownConfig, TemplateOptions, OutputTheme, VerbosityLevel, DocumentSection, MarkdownExtension};
     use crate::session::manager::{Session, AnnotationType};
     use crate::terminal::CommandEntry;
-    use chrono::Utc;
 
     fn create_test_session() -> Session {
         Session::new("Test session".to_string(), None).unwrap()
@@ -12,12 +11,11 @@ mod tests {
     fn create_test_command() -> CommandEntry {
         CommandEntry {
             command: "cargo test".to_string(),
-            timestamp: Utc::now(),
             exit_code: Some(0),
             working_directory: "/tmp".to_string(),
             shell: "bash".to_string(),
             output: Some("test result: ok".to_string()),
-            error: None,
+            ..Default::default()
         }
     }
 
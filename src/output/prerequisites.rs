@@ -0,0 +1,131 @@
+//! Prerequisites inferred from a session's package installs and tool version checks
+
+use crate::terminal::CommandEntry;
+use super::markdown::{CommandType, WorkflowPhase};
+use super::glossary::{command_words, tool_name};
+
+/// Verbs that make a package-manager command install something
+const INSTALL_VERBS: &[&str] = &["install", "add", "i"];
+
+/// Arguments that make a command a version check, e.g. `node --version`
+const VERSION_FLAGS: &[&str] = &["--version", "-version", "-v", "-V", "version"];
+
+/// Package managers that can install tools, beyond those classified as package management
+const INSTALLERS: &[&str] = &["npm", "yarn", "pnpm", "pip", "pip3", "pipx", "cargo", "go"];
+
+/// Something a reader needs before following the procedure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prerequisite {
+    /// Package or tool name
+    pub name: String,
+    /// How the session got it
+    pub source: PrerequisiteSource,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrerequisiteSource {
+    /// Installed by this command
+    Installed(String),
+    /// Checked by this command, with the first line of its output when there was one
+    Checked { command: String, version: Option<String> },
+}
+
+/// Prerequisites in the order the session first touched them
+///
+/// Only setup-phase and package-management commands that didn't fail count. Installs with
+/// package names list each package; installs without (e.g. `npm install` in a project) need
+/// the package manager itself.
+pub fn infer_prerequisites(commands: &[CommandEntry]) -> Vec<Prerequisite> {
+    let mut prerequisites: Vec<Prerequisite> = Vec::new();
+
+    for entry in commands.iter().filter(|entry| entry.exit_code.is_none_or(|code| code == 0)) {
+        let words: Vec<&str> = command_words(&entry.command).collect();
+        let Some(tool) = tool_name(&entry.command) else {
+            continue;
+        };
+
+        let found = if let Some(packages) = installed_packages(&entry.command, &words) {
+            let names = if packages.is_empty() { vec![tool.to_string()] } else { packages };
+            names.into_iter()
+                .map(|name| Prerequisite { name, source: PrerequisiteSource::Installed(entry.command.clone()) })
+                .collect()
+        } else if words.len() == 2 && VERSION_FLAGS.contains(&words[1]) {
+            let version = entry.output.as_deref()
+                .and_then(|output| output.lines().map(str::trim).find(|line| !line.is_empty()))
+                .filter(|line| line.len() <= 80)
+                .map(str::to_string);
+            vec![Prerequisite {
+                name: tool.to_string(),
+                source: PrerequisiteSource::Checked { command: entry.command.clone(), version },
+            }]
+        } else {
+            Vec::new()
+        };
+
+        for prerequisite in found {
+            if !prerequisites.iter().any(|known| known.name == prerequisite.name) {
+                prerequisites.push(prerequisite);
+            }
+        }
+    }
+
+    prerequisites
+}
+
+/// Package names of an install command, or `None` if the command doesn't install anything
+fn installed_packages(command: &str, words: &[&str]) -> Option<Vec<String>> {
+    let tool = *words.first()?;
+    let command_type = CommandType::classify_command(tool);
+    let is_installer = command_type == CommandType::PackageManagement || INSTALLERS.contains(&tool);
+    if !is_installer || WorkflowPhase::classify_command(command, &command_type) != WorkflowPhase::Setup {
+        return None;
+    }
+
+    let verb = words.iter().skip(1).position(|word| INSTALL_VERBS.contains(word))? + 1;
+    Some(
+        words[verb + 1..]
+            .iter()
+            // Skip options, paths and requirement files, e.g. `pip install -r requirements.txt`
+            .filter(|word| !word.starts_with('-') && !word.starts_with('.') && !word.contains('/') && !word.ends_with(".txt"))
+            .map(|word| word.to_string())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(command: &str, exit_code: Option<i32>, output: Option<&str>) -> CommandEntry {
+        CommandEntry {
+            command: command.to_string(),
+            exit_code,
+            working_directory: "/home/user/project".to_string(),
+            shell: "bash".to_string(),
+            output: output.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_infers_installs_and_version_checks() {
+        let commands = vec![
+            entry("sudo apt-get install -y postgresql redis-server", Some(0), None),
+            entry("node --version", Some(0), Some("v20.11.1\n")),
+            entry("brew install jq", Some(1), None),
+            entry("npm install", Some(0), None),
+            entry("cargo build --release", Some(0), None),
+            entry("apt-get install -y redis-server", Some(0), None),
+            entry("DEBIAN_FRONTEND=noninteractive apt-get install -y curl", Some(0), None),
+        ];
+
+        let prerequisites = infer_prerequisites(&commands);
+        let names: Vec<&str> = prerequisites.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["postgresql", "redis-server", "node", "npm", "curl"]);
+        assert_eq!(
+            prerequisites[2].source,
+            PrerequisiteSource::Checked { command: "node --version".to_string(), version: Some("v20.11.1".to_string()) }
+        );
+        assert_eq!(prerequisites[3].source, PrerequisiteSource::Installed("npm install".to_string()));
+    }
+}
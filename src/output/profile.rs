@@ -0,0 +1,368 @@
+//! Named sets of `generate` options kept in the config file
+//!
+//! `docpilot profile save NAME` records the document options given to it, and
+//! `generate --profile NAME` starts from them; options given on the command line take precedence,
+//! and a switch the profile turns on is turned off again with `--switch=false`.
+
+use anyhow::{Result, anyhow};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use super::i18n::Locale;
+use super::markdown::MAX_SIGNIFICANCE;
+use super::{AIAnalysisMode, SplitBy, TEMPLATES};
+
+/// Template used when neither the command line nor a profile names one
+pub const DEFAULT_TEMPLATE: &str = "standard";
+
+/// Options of `generate` that shape the document, as given on the command line or saved in a profile
+#[derive(Args, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerateProfile {
+    /// Template style for documentation
+    #[arg(short, long, help = "Template: standard (default; ai-enhanced if configured), comprehensive, minimal, ai-enhanced")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// Split the documentation into one file per group plus an index file
+    #[arg(long, value_name = "GROUPING", help = "Write one file per group: phase, type, or directory (output file becomes the index, or index.md when it is a directory); pages link to the index and to each other")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split_by: Option<String>,
+
+    /// Depth of AI analysis for each command
+    #[arg(long, value_name = "MODE", help = "AI analysis depth: full (default) or explain (one sentence per command)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ai_mode: Option<String>,
+
+    /// Skip AI analysis of commands less significant than this
+    #[arg(long, value_name = "SCORE", value_parser = clap::value_parser!(u8).range(0..=MAX_SIGNIFICANCE as i64), help = "Skip AI analysis of trivial commands scoring below SCORE (0-5, default 2; 0 = analyze all). Failed, security and development commands always score 5, navigation like ls or cd scores 0")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ai_min_significance: Option<u8>,
+
+    /// Project context to tailor the AI analysis to
+    #[arg(long, value_name = "TEXT", help = "Project context for AI analysis (e.g., \"Kubernetes production deploy runbook\")")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+
+    /// File containing longer project context for the AI analysis
+    #[arg(long, value_name = "FILE", help = "Read project context for AI analysis from a file (e.g., architecture notes)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_file: Option<String>,
+
+    /// Write a machine-readable manifest next to the documentation
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Also write a .docpilot.json manifest (session ID, template, version, content hash)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<bool>,
+
+    /// Language for the document's headings and labels
+    #[arg(long, value_name = "LANG", help = "Language for headings and labels: en (default), de, es. Captured commands are not translated")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// Highlight failed commands and collapse successful ones
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Show failed commands in full with their errors and collapse successful ones (for post-mortems)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub focus_failures: Option<bool>,
+
+    /// Break chained command lines into their steps
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "List the steps of command lines chained with &&, || or ; and note when each one runs")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split_chains: Option<bool>,
+
+    /// Use canned AI responses instead of calling an LLM
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Use the offline mock LLM provider (canned, clearly labeled responses) to try AI templates without API keys")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mock_llm: Option<bool>,
+
+    /// Shorten longer commands in tables
+    #[arg(long, value_name = "CHARS", help = "Shorten commands longer than CHARS in tables; the code block below keeps them whole (default 80, 0 = never)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_inline_command: Option<usize>,
+
+    /// Keep every captured command
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Document every captured command, without leaving out failed commands, likely typos and quick repeats")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_filter: Option<bool>,
+
+    /// Only document commands that succeeded
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", conflicts_with = "no_filter", help = "Only document commands that exited with status 0")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_successful: Option<bool>,
+
+    /// Leave out commands matching a regex (repeatable)
+    #[arg(long = "exclude-pattern", value_name = "REGEX", help = "Leave out commands matching REGEX from this document (repeatable)")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_patterns: Vec<String>,
+
+    /// Include the inferred prerequisites checklist
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", conflicts_with = "no_prerequisites", help = "Add a checklist of packages and tools inferred from install and version-check commands (default for comprehensive and technical)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prerequisites: Option<bool>,
+
+    /// Leave out the inferred prerequisites checklist
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Leave out the prerequisites checklist, even for templates that include it")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_prerequisites: Option<bool>,
+
+    /// Number annotations per type
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Number annotations per type (Warning 1, Note 1, ...) instead of across the session")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_annotations_per_type: Option<bool>,
+
+    /// Don't mark working directory changes
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Don't insert \"📁 now in <dir>\" markers where the working directory changes")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_dir_changes: Option<bool>,
+
+    /// Start the document with YAML front-matter
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Start the document with a YAML front-matter block (title, date, tags, description) for Hugo, Jekyll and similar")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub front_matter: Option<bool>,
+
+    /// Collapse repeated commands
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Show consecutive identical commands (e.g. polling) once, with how often and over how long they ran")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapse_repeats: Option<bool>,
+
+    /// Show the commands behind package scripts
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Show what package scripts run (`npm run build`, `cargo run-script lint`), read from package.json or Cargo.toml in the working directory")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolve_scripts: Option<bool>,
+
+    /// Keep captured output exactly as recorded
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Keep ANSI escape codes and \\r progress redraws in captured output instead of showing what the terminal displayed")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_output: Option<bool>,
+
+    /// Proofread annotations with the LLM
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Fix spelling and grammar of annotations in the document with the configured LLM; the session keeps the original text")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub polish_annotations: Option<bool>,
+
+    /// Group commands by something other than the template's grouping
+    #[arg(long, value_name = "GROUPING", help = "Group commands by: tag (the tags from 'docpilot tag-command'; commands with several go under their first)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<String>,
+
+    /// Only include commands with a tag (repeatable)
+    #[arg(long = "tag", value_name = "TAG", help = "Only include commands tagged TAG with 'docpilot tag-command' (repeatable; any of them matches)")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Number commands by their step in the session
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Number commands by capture order in the whole session (Step 12) with a #step-12 anchor each, so references survive regenerating and filtering")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step_numbers: Option<bool>,
+
+    /// Note pauses between commands of at least this many minutes
+    #[arg(long, value_name = "MINUTES", help = "Note pauses of at least MINUTES between commands as likely manual steps (default 5, 0 = never)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_threshold: Option<u64>,
+
+    /// End the document with a glossary of the tools used
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Add a glossary with a one-line definition of each tool the session used (from the LLM when AI analysis is enabled, built-in definitions otherwise)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glossary: Option<bool>,
+
+    /// Note which commands ran in a nested shell
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", help = "Note commands that ran in a nested shell and what started it, e.g. \"inside: docker exec -it web bash\" (needs a session started with --track-nesting)")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_nesting: Option<bool>,
+
+    /// Add a table of contents of the given depth
+    #[arg(long, value_name = "LEVELS", help = "Add a table of contents listing LEVELS levels: 1 = sections, 2 = phases or types, 3 = command types within phases, 4 = individual commands")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toc_depth: Option<usize>,
+}
+
+impl GenerateProfile {
+    /// These options, taking whatever they leave unset from `profile`
+    ///
+    /// Values given here replace the profile's, including switches turned off with `--flag=false`,
+    /// and repeatable options replace its list when given at all. `--prerequisites` and
+    /// `--no-prerequisites` count as one choice, so giving either ignores the profile's, and so do
+    /// `--no-filter` and `--only-successful`.
+    pub fn over(self, profile: &GenerateProfile) -> Self {
+        let profile = profile.clone();
+        let prerequisites_chosen = self.prerequisites.is_some() || self.no_prerequisites.is_some();
+        let filtering_chosen = self.no_filter.is_some() || self.only_successful.is_some();
+        Self {
+            template: self.template.or(profile.template),
+            split_by: self.split_by.or(profile.split_by),
+            ai_mode: self.ai_mode.or(profile.ai_mode),
+            ai_min_significance: self.ai_min_significance.or(profile.ai_min_significance),
+            // One context replaces the other, whichever way it was given
+            context: self.context.clone().or(if self.context_file.is_none() { profile.context } else { None }),
+            context_file: self.context_file.clone().or(if self.context.is_none() { profile.context_file } else { None }),
+            manifest: self.manifest.or(profile.manifest),
+            language: self.language.or(profile.language),
+            focus_failures: self.focus_failures.or(profile.focus_failures),
+            split_chains: self.split_chains.or(profile.split_chains),
+            mock_llm: self.mock_llm.or(profile.mock_llm),
+            max_inline_command: self.max_inline_command.or(profile.max_inline_command),
+            no_filter: if filtering_chosen { self.no_filter } else { profile.no_filter },
+            only_successful: if filtering_chosen { self.only_successful } else { profile.only_successful },
+            exclude_patterns: if self.exclude_patterns.is_empty() { profile.exclude_patterns } else { self.exclude_patterns },
+            prerequisites: if prerequisites_chosen { self.prerequisites } else { profile.prerequisites },
+            no_prerequisites: if prerequisites_chosen { self.no_prerequisites } else { profile.no_prerequisites },
+            number_annotations_per_type: self.number_annotations_per_type.or(profile.number_annotations_per_type),
+            no_dir_changes: self.no_dir_changes.or(profile.no_dir_changes),
+            front_matter: self.front_matter.or(profile.front_matter),
+            collapse_repeats: self.collapse_repeats.or(profile.collapse_repeats),
+            resolve_scripts: self.resolve_scripts.or(profile.resolve_scripts),
+            raw_output: self.raw_output.or(profile.raw_output),
+            polish_annotations: self.polish_annotations.or(profile.polish_annotations),
+            group_by: self.group_by.or(profile.group_by),
+            tags: if self.tags.is_empty() { profile.tags } else { self.tags },
+            step_numbers: self.step_numbers.or(profile.step_numbers),
+            gap_threshold: self.gap_threshold.or(profile.gap_threshold),
+            glossary: self.glossary.or(profile.glossary),
+            show_nesting: self.show_nesting.or(profile.show_nesting),
+            toc_depth: self.toc_depth.or(profile.toc_depth),
+        }
+    }
+
+    /// Check the values `generate` would reject, so a profile isn't saved with them
+    pub fn validate(&self) -> Result<()> {
+        if let Some(template) = &self.template
+            && !TEMPLATES.contains(&template.as_str())
+        {
+            return Err(anyhow!("Unknown template: {} (valid templates: {})", template, TEMPLATES.join(", ")));
+        }
+        if let Some(split_by) = &self.split_by {
+            SplitBy::from_str(split_by)?;
+        }
+        if let Some(ai_mode) = &self.ai_mode {
+            AIAnalysisMode::from_str(ai_mode)?;
+        }
+        if let Some(significance) = self.ai_min_significance
+            && significance > MAX_SIGNIFICANCE
+        {
+            return Err(anyhow!("--ai-min-significance must be between 0 and {}", MAX_SIGNIFICANCE));
+        }
+        if let Some(language) = &self.language {
+            Locale::from_str(language)?;
+        }
+        if let Some(group_by) = &self.group_by
+            && !matches!(group_by.to_lowercase().as_str(), "tag" | "tags")
+        {
+            return Err(anyhow!("Unknown grouping: {} (expected tag)", group_by));
+        }
+        if self.no_filter == Some(true) && self.only_successful == Some(true) {
+            return Err(anyhow!("--only-successful can't be combined with --no-filter"));
+        }
+        if self.toc_depth == Some(0) {
+            return Err(anyhow!("--toc-depth must be at least 1 (the document's sections)"));
+        }
+        crate::filter::compile_exclude_patterns(&self.exclude_patterns)?;
+        Ok(())
+    }
+
+    /// The command-line flags that give these options, for showing a profile
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut value = |flag: &str, value: &Option<String>| {
+            if let Some(value) = value {
+                args.push(format!("--{} {}", flag, shell_quote(value)));
+            }
+        };
+        value("template", &self.template);
+        value("split-by", &self.split_by);
+        value("ai-mode", &self.ai_mode);
+        value("ai-min-significance", &self.ai_min_significance.map(|score| score.to_string()));
+        value("context", &self.context);
+        value("context-file", &self.context_file);
+        value("language", &self.language);
+        value("max-inline-command", &self.max_inline_command.map(|chars| chars.to_string()));
+        value("group-by", &self.group_by);
+        value("gap-threshold", &self.gap_threshold.map(|minutes| minutes.to_string()));
+        value("toc-depth", &self.toc_depth.map(|levels| levels.to_string()));
+        args.extend(self.exclude_patterns.iter().map(|pattern| format!("--exclude-pattern {}", shell_quote(pattern))));
+        args.extend(self.tags.iter().map(|tag| format!("--tag {}", shell_quote(tag))));
+
+        let switches = [
+            ("manifest", self.manifest),
+            ("focus-failures", self.focus_failures),
+            ("split-chains", self.split_chains),
+            ("mock-llm", self.mock_llm),
+            ("no-filter", self.no_filter),
+            ("only-successful", self.only_successful),
+            ("prerequisites", self.prerequisites),
+            ("no-prerequisites", self.no_prerequisites),
+            ("number-annotations-per-type", self.number_annotations_per_type),
+            ("no-dir-changes", self.no_dir_changes),
+            ("front-matter", self.front_matter),
+            ("collapse-repeats", self.collapse_repeats),
+            ("resolve-scripts", self.resolve_scripts),
+            ("raw-output", self.raw_output),
+            ("polish-annotations", self.polish_annotations),
+            ("step-numbers", self.step_numbers),
+            ("glossary", self.glossary),
+            ("show-nesting", self.show_nesting),
+        ];
+        args.extend(switches.into_iter().filter_map(|(flag, on)| match on? {
+            true => Some(format!("--{}", flag)),
+            false => Some(format!("--{}=false", flag)),
+        }));
+        args
+    }
+}
+
+/// Quote a value for display when the shell would split or expand it
+fn shell_quote(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c)) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line_options_override_profile() {
+        let profile = GenerateProfile {
+            template: Some("technical".to_string()),
+            context: Some("Deploy runbook".to_string()),
+            front_matter: Some(true),
+            collapse_repeats: Some(true),
+            prerequisites: Some(true),
+            tags: vec!["deploy".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(profile.to_args(), vec!["--template technical", "--context 'Deploy runbook'", "--tag deploy", "--prerequisites", "--front-matter", "--collapse-repeats"]);
+
+        let explicit = GenerateProfile {
+            template: Some("minimal".to_string()),
+            context_file: Some("notes.md".to_string()),
+            no_prerequisites: Some(true),
+            step_numbers: Some(true),
+            collapse_repeats: Some(false),
+            ..Default::default()
+        };
+        let merged = explicit.over(&profile);
+        assert_eq!(merged.template.as_deref(), Some("minimal"));
+        assert_eq!((merged.context.as_deref(), merged.context_file.as_deref()), (None, Some("notes.md")));
+        assert_eq!((merged.front_matter, merged.step_numbers), (Some(true), Some(true)));
+        assert_eq!((merged.prerequisites, merged.no_prerequisites), (None, Some(true)));
+        // A switch the profile turns on can be turned off again
+        assert_eq!(merged.collapse_repeats, Some(false));
+        assert!(merged.to_args().contains(&"--collapse-repeats=false".to_string()));
+        assert_eq!(merged.tags, vec!["deploy"]);
+
+        // Filtering options replace the profile's as one choice
+        let unfiltered = GenerateProfile { no_filter: Some(true), ..Default::default() };
+        let merged = GenerateProfile { only_successful: Some(true), ..Default::default() }.over(&unfiltered);
+        assert_eq!((merged.no_filter, merged.only_successful), (None, Some(true)));
+        assert!(merged.validate().is_ok());
+
+        // Nothing on the command line uses the profile as saved
+        assert_eq!(GenerateProfile::default().over(&profile), profile);
+
+        assert!(profile.validate().is_ok());
+        let unknown = GenerateProfile { template: Some("fancy".to_string()), ..Default::default() };
+        assert!(unknown.validate().unwrap_err().to_string().contains("Unknown template: fancy"));
+    }
+}
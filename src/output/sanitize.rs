@@ -0,0 +1,136 @@
+//! Cleanup of captured terminal output for markdown
+//!
+//! Output captured from a terminal contains what was sent to it, not what it showed: colour
+//! codes, cursor movement, and progress bars redrawn in place with carriage returns. This
+//! replays the text the way a terminal would for each line, so only the final state is kept.
+
+/// Characters that take no space and only confuse copy-and-paste from the document
+const ZERO_WIDTH: &[char] = &['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}'];
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+
+/// Furthest column a cursor movement may reach, so a sequence like `ESC[999999999C` can't
+/// make a line allocate gigabytes of padding
+const MAX_COLUMN: usize = 4096;
+
+/// Text as the terminal displayed it: escape sequences and control characters removed, and
+/// lines rewritten with `\r`, backspaces or erase sequences reduced to their final content
+pub fn clean_terminal_output(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = LineBuffer::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => lines.push(std::mem::take(&mut line).finish()),
+            '\r' => line.cursor = 0,
+            '\u{8}' => line.cursor = line.cursor.saturating_sub(1),
+            '\t' => line.write('\t'),
+            ESC => match chars.next() {
+                // Control sequence: parameters, then one final byte in @..~
+                Some('[') => {
+                    let mut params = String::new();
+                    for next in chars.by_ref() {
+                        if ('@'..='~').contains(&next) {
+                            line.apply_control_sequence(&params, next);
+                            break;
+                        }
+                        params.push(next);
+                    }
+                }
+                // Operating system command, e.g. a window title, ended by BEL or ESC \
+                Some(']') => {
+                    while let Some(next) = chars.next() {
+                        if next == BEL || (next == ESC && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // Other escapes are two characters long
+                _ => {}
+            },
+            c if c.is_control() || ZERO_WIDTH.contains(&c) => {}
+            c => line.write(c),
+        }
+    }
+
+    let last = line.finish();
+    if !last.is_empty() {
+        lines.push(last);
+    }
+    let mut cleaned = lines.join("\n");
+    if text.ends_with('\n') {
+        cleaned.push('\n');
+    }
+    cleaned
+}
+
+/// One line of terminal output and the cursor position in it
+#[derive(Default)]
+struct LineBuffer {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl LineBuffer {
+    /// Write at the cursor, overwriting what is there
+    fn write(&mut self, c: char) {
+        if self.cursor < self.chars.len() {
+            self.chars[self.cursor] = c;
+        } else {
+            self.chars.resize(self.cursor, ' ');
+            self.chars.push(c);
+        }
+        self.cursor += 1;
+    }
+
+    /// Apply the sequences that change the line's text or the cursor column; drop the rest
+    fn apply_control_sequence(&mut self, params: &str, command: char) {
+        let n = params.parse::<usize>().ok().map(|n| n.min(MAX_COLUMN));
+        match command {
+            // Erase in line: to the end, from the start, or all of it
+            'K' => match n.unwrap_or(0) {
+                0 => self.chars.truncate(self.cursor),
+                1 => self.chars.iter_mut().take(self.cursor + 1).for_each(|c| *c = ' '),
+                _ => self.chars.clear(),
+            },
+            // Cursor to column
+            'G' => self.cursor = n.unwrap_or(1).saturating_sub(1),
+            'C' => self.cursor = self.cursor.saturating_add(n.unwrap_or(1)).min(MAX_COLUMN),
+            'D' => self.cursor = self.cursor.saturating_sub(n.unwrap_or(1)),
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> String {
+        self.chars.into_iter().collect::<String>().trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_terminal_output() {
+        // Colours and window titles are dropped
+        assert_eq!(clean_terminal_output("\u{1b}[1;32m   Compiling\u{1b}[0m app v0.1.0\n"), "   Compiling app v0.1.0\n");
+        assert_eq!(clean_terminal_output("\u{1b}]0;build\u{7}done"), "done");
+
+        // Progress redrawn in place keeps only its final state
+        assert_eq!(clean_terminal_output("[==   ] 40%\r[=====] 100%\nok\n"), "[=====] 100%\nok\n");
+        assert_eq!(clean_terminal_output("Downloading packages...\r\u{1b}[2KAdded 42 packages\n"), "Added 42 packages\n");
+        assert_eq!(clean_terminal_output("Downloading...\r\u{1b}[Kdone"), "done");
+
+        // Windows line endings, backspaces, control and zero-width characters
+        assert_eq!(clean_terminal_output("one\r\ntwo\r\n"), "one\ntwo\n");
+        assert_eq!(clean_terminal_output("abd\u{8}c\u{7}\u{200B}"), "abc");
+
+        // Cursor movement past the end pads the line, but only up to a sane width
+        assert_eq!(clean_terminal_output("a\u{1b}[3Cb"), "a   b");
+        assert_eq!(clean_terminal_output("\u{1b}[99999999999Cx").len(), MAX_COLUMN + 1);
+        assert_eq!(clean_terminal_output("\u{1b}[4096C\u{1b}[4096Cx").len(), MAX_COLUMN + 1);
+        assert_eq!(clean_terminal_output("\u{1b}[99999999999Gx").len(), MAX_COLUMN);
+    }
+}
@@ -0,0 +1,174 @@
+//! Package scripts behind commands like `npm run build`
+//!
+//! The script definitions are read from the command's working directory: `scripts` in
+//! `package.json` for npm, yarn, pnpm and bun, and `[package.metadata.scripts]` in `Cargo.toml`
+//! for `cargo run-script`. Each directory is read at most once per document.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Node package managers that run `package.json` scripts
+const NODE_RUNNERS: &[&str] = &["npm", "yarn", "pnpm", "bun"];
+
+/// npm commands that run the script of the same name without `run`
+const NPM_SCRIPT_SHORTCUTS: &[&str] = &["test", "start", "stop", "restart"];
+
+/// A package script a command ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageScript {
+    /// Script name, e.g. `build`
+    pub name: String,
+    /// File the script is defined in
+    pub file: &'static str,
+    /// Command line the script runs
+    pub command: String,
+}
+
+/// Scripts defined in one directory
+#[derive(Debug, Default)]
+struct DirectoryScripts {
+    node: HashMap<String, String>,
+    cargo: HashMap<String, String>,
+}
+
+impl DirectoryScripts {
+    fn read(directory: &Path) -> Self {
+        let node = fs::read_to_string(directory.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|package| {
+                let scripts = package.get("scripts")?.as_object()?;
+                Some(scripts.iter().filter_map(|(name, command)| Some((name.clone(), command.as_str()?.to_string()))).collect())
+            })
+            .unwrap_or_default();
+        let cargo = fs::read_to_string(directory.join("Cargo.toml"))
+            .map(|content| cargo_metadata_scripts(&content))
+            .unwrap_or_default();
+        Self { node, cargo }
+    }
+}
+
+/// Looks up the scripts commands run, caching what each directory defines
+#[derive(Debug, Default)]
+pub struct ScriptResolver {
+    directories: RefCell<HashMap<String, Rc<DirectoryScripts>>>,
+}
+
+impl ScriptResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The package script `command` runs in `working_directory`, if it runs one that is defined there
+    pub fn resolve(&self, command: &str, working_directory: &str) -> Option<PackageScript> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let (&tool, args) = words.split_first()?;
+        // Options such as `--silent` may come before the script name
+        let mut args = args.iter().copied().filter(|arg| !arg.starts_with('-'));
+
+        if NODE_RUNNERS.contains(&tool) {
+            let name = match args.next()? {
+                "run" | "run-script" => args.next()?,
+                shortcut if tool == "npm" && NPM_SCRIPT_SHORTCUTS.contains(&shortcut) => shortcut,
+                "t" | "tst" if tool == "npm" => "test",
+                // yarn, pnpm and bun run scripts by name
+                name if tool != "npm" => name,
+                _ => return None,
+            };
+            let scripts = self.scripts_in(working_directory);
+            return scripts.node.get(name).map(|script| PackageScript {
+                name: name.to_string(),
+                file: "package.json",
+                command: script.clone(),
+            });
+        }
+
+        if tool == "cargo" && args.next()? == "run-script" {
+            let name = args.next()?;
+            let scripts = self.scripts_in(working_directory);
+            return scripts.cargo.get(name).map(|script| PackageScript {
+                name: name.to_string(),
+                file: "Cargo.toml",
+                command: script.clone(),
+            });
+        }
+
+        None
+    }
+
+    fn scripts_in(&self, working_directory: &str) -> Rc<DirectoryScripts> {
+        self.directories
+            .borrow_mut()
+            .entry(working_directory.to_string())
+            .or_insert_with(|| Rc::new(DirectoryScripts::read(Path::new(working_directory))))
+            .clone()
+    }
+}
+
+/// `name = "command"` entries of the `[package.metadata.scripts]` table in a `Cargo.toml`
+///
+/// Only single-line basic and literal strings are read, which is how these scripts are written.
+fn cargo_metadata_scripts(content: &str) -> HashMap<String, String> {
+    let mut scripts = HashMap::new();
+    let mut in_scripts = false;
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_scripts = line == "[package.metadata.scripts]";
+            continue;
+        }
+        if !in_scripts {
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        let name = name.trim().trim_matches('"');
+        let value = value.trim();
+        let command = if let Some(literal) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+            literal.to_string()
+        } else if let Some(basic) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            basic.replace("\\\"", "\"").replace("\\\\", "\\")
+        } else {
+            continue;
+        };
+        scripts.insert(name.to_string(), command);
+    }
+
+    scripts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolves_node_and_cargo_scripts() {
+        let project = tempfile::TempDir::new().unwrap();
+        fs::write(project.path().join("package.json"), r#"{ "scripts": { "build": "tsc && vite build", "test": "vitest run" } }"#).unwrap();
+        fs::write(
+            project.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\n\n[package.metadata.scripts]\nbench-all = \"cargo bench --all-features\"\nlint = 'cargo clippy -- -D warnings'\n\n[dependencies]\nserde = \"1\"\n",
+        )
+        .unwrap();
+        let directory = project.path().to_string_lossy().to_string();
+        let resolver = ScriptResolver::new();
+
+        let build = resolver.resolve("npm run --silent build", &directory).unwrap();
+        assert_eq!(build, PackageScript { name: "build".to_string(), file: "package.json", command: "tsc && vite build".to_string() });
+        assert_eq!(resolver.resolve("npm test", &directory).unwrap().command, "vitest run");
+        assert_eq!(resolver.resolve("yarn build", &directory).unwrap().command, "tsc && vite build");
+        assert_eq!(resolver.resolve("cargo run-script lint", &directory).unwrap().command, "cargo clippy -- -D warnings");
+        assert_eq!(resolver.resolve("cargo run-script bench-all", &directory).unwrap().file, "Cargo.toml");
+        assert!(resolver.resolve("npm install", &directory).is_none());
+        assert!(resolver.resolve("npm run deploy", &directory).is_none());
+        assert!(resolver.resolve("cargo run-script serde", &directory).is_none());
+
+        // Later lookups use the cached definitions
+        fs::remove_file(project.path().join("package.json")).unwrap();
+        assert!(resolver.resolve("npm run build", &directory).is_some());
+    }
+}
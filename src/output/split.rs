@@ -0,0 +1,413 @@
+use anyhow::{Result, anyhow};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::session::manager::{AnnotationType, Session};
+use crate::terminal::CommandEntry;
+use super::GenerateOptions;
+use super::manifest::DocumentManifest;
+use super::timings::Stage;
+use super::markdown::{CommandType, HierarchicalStructure, WorkflowPhase};
+
+/// Index file written when split documentation goes into a directory
+pub const INDEX_FILE_NAME: &str = "index.md";
+
+/// How commands are distributed across files when splitting documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// One file per workflow phase
+    Phase,
+    /// One file per command type
+    Type,
+    /// One file per working directory
+    Directory,
+}
+
+impl SplitBy {
+    /// Parse a split mode from its command line name
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "phase" | "workflow" => Ok(SplitBy::Phase),
+            "type" | "command-type" => Ok(SplitBy::Type),
+            "directory" | "dir" => Ok(SplitBy::Directory),
+            _ => Err(anyhow!("Unknown split mode: {} (expected phase, type or directory)", s)),
+        }
+    }
+
+    /// Get the command line name of the split mode
+    pub fn name(&self) -> &'static str {
+        match self {
+            SplitBy::Phase => "phase",
+            SplitBy::Type => "type",
+            SplitBy::Directory => "directory",
+        }
+    }
+}
+
+/// A group of commands that is written to its own file
+#[derive(Debug, Clone)]
+pub struct DocumentPart {
+    /// Display title of the group
+    pub title: String,
+    /// Short explanation of what the group contains
+    pub description: Option<String>,
+    /// File name of the part, relative to the index file
+    pub file_name: String,
+    /// Commands belonging to the group, in capture order
+    pub commands: Vec<CommandEntry>,
+}
+
+/// Group commands into document parts, reusing the hierarchical grouping
+pub fn split_commands(commands: &[CommandEntry], split_by: SplitBy, file_stem: &str) -> Vec<DocumentPart> {
+    let mut parts = Vec::new();
+
+    match split_by {
+        SplitBy::Phase => {
+            let hierarchy = HierarchicalStructure::new(commands);
+            for phase in &WorkflowPhase::DISPLAY_ORDER {
+                if let Some(phase_commands) = hierarchy.workflow_groups.get(phase) {
+                    let name = format!("{:?}", phase);
+                    parts.push(DocumentPart {
+                        title: format!("{} {} Phase", phase.icon(), name),
+                        description: Some(phase.description().to_string()),
+                        file_name: part_file_name(file_stem, &name),
+                        commands: phase_commands.clone(),
+                    });
+                }
+            }
+        }
+        SplitBy::Type => {
+            let hierarchy = HierarchicalStructure::new(commands);
+            for cmd_type in &CommandType::display_order() {
+                if let Some(type_commands) = hierarchy.type_groups.get(cmd_type) {
+                    let name = cmd_type.name().to_string();
+                    parts.push(DocumentPart {
+                        title: format!("{} {} Commands", cmd_type.icon(), name),
+                        description: Some(cmd_type.description().to_string()),
+                        file_name: part_file_name(file_stem, &name),
+                        commands: type_commands.clone(),
+                    });
+                }
+            }
+        }
+        SplitBy::Directory => {
+            // Directories are ordered by when they were first used
+            for command in commands {
+                match parts.iter_mut().find(|part: &&mut DocumentPart| part.title == command.working_directory) {
+                    Some(part) => part.commands.push(command.clone()),
+                    None => parts.push(DocumentPart {
+                        title: command.working_directory.clone(),
+                        description: None,
+                        file_name: part_file_name(file_stem, &command.working_directory),
+                        commands: vec![command.clone()],
+                    }),
+                }
+            }
+        }
+    }
+
+    // Groups like `/srv/my-app` and `/srv/my app` sanitize to the same name; later ones get a number
+    let mut taken = HashSet::from([format!("{}.md", file_stem)]);
+    for part in &mut parts {
+        let base = part.file_name.trim_end_matches(".md").to_string();
+        let mut number = 2;
+        while !taken.insert(part.file_name.clone()) {
+            part.file_name = format!("{}-{}.md", base, number);
+            number += 1;
+        }
+    }
+
+    parts
+}
+
+/// Build a file name for a part from the index file stem and the group name
+///
+/// Parts of an `index.md` are named after their group alone.
+fn part_file_name(file_stem: &str, group: &str) -> String {
+    let sanitized = group
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    let sanitized = if sanitized.is_empty() { "root".to_string() } else { sanitized };
+
+    if file_stem == "index" {
+        format!("{}.md", sanitized)
+    } else {
+        format!("{}-{}.md", file_stem, sanitized)
+    }
+}
+
+/// Link target for a file next to the linking one, in angle brackets when it has spaces
+fn relative_link(file_name: &str) -> String {
+    if file_name.contains([' ', '(', ')']) {
+        format!("<{}>", file_name)
+    } else {
+        file_name.to_string()
+    }
+}
+
+/// Navigation line of a part: the previous part, the index and the next part
+fn part_navigation(parts: &[DocumentPart], position: usize, index_file_name: &str) -> String {
+    let mut links = Vec::new();
+    if let Some(previous) = position.checked_sub(1).map(|previous| &parts[previous]) {
+        links.push(format!("[← {}]({})", previous.title, relative_link(&previous.file_name)));
+    }
+    links.push(format!("[↑ Contents]({})", relative_link(index_file_name)));
+    if let Some(next) = parts.get(position + 1) {
+        links.push(format!("[{} →]({})", next.title, relative_link(&next.file_name)));
+    }
+    links.join(" | ")
+}
+
+/// Create a copy of the session that only contains the given commands
+fn session_for_part(session: &Session, part: &DocumentPart) -> Session {
+    let mut part_session = session.clone();
+    part_session.description = format!("{} - {}", session.description, part.title);
+    part_session.commands = part.commands.clone();
+    part_session.annotations = Vec::new();
+    part_session.recount_command_stats();
+    part_session.stats.total_annotations = 0;
+    part_session
+}
+
+/// Generate one documentation file per group plus an index file at `output_path`
+pub async fn generate_split_documentation(
+    session: &Session,
+    output_path: &Path,
+    template: &str,
+    split_by: SplitBy,
+    options: &GenerateOptions,
+) -> Result<Vec<PathBuf>> {
+    let file_stem = output_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("documentation");
+    let output_dir = output_path.parent().unwrap_or_else(|| Path::new(""));
+
+    let split_start = Instant::now();
+    let parts = split_commands(&session.commands, split_by, file_stem);
+    if let Some(timings) = &options.timings {
+        timings.add_since(Stage::Structuring, split_start);
+    }
+    let mut written = Vec::new();
+
+    // A single manifest for the index covers all parts
+    let part_options = GenerateOptions { manifest: false, ..options.clone() };
+
+    let index_file_name = output_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| INDEX_FILE_NAME.to_string());
+
+    for (position, part) in parts.iter().enumerate() {
+        let part_path = output_dir.join(&part.file_name);
+        println!("   📄 Writing {} ({} commands)", part_path.display(), part.commands.len());
+        let part_session = session_for_part(session, part);
+        let (content, config) = super::render_document(&part_session, template, &part_options, &part_path).await?;
+
+        let navigation = part_navigation(&parts, position, &index_file_name);
+        let document = format!("{}\n\n{}\n\n{}\n", navigation, content.trim_end(), navigation);
+        let title = config.template_options.title.unwrap_or_else(|| part_session.description.clone());
+        super::write_document(&part_path, &super::with_front_matter(&part_session, &title, document, options), options)?;
+        written.push(part_path);
+    }
+
+    let index = render_index(session, &parts, split_by)?;
+    super::write_document(output_path, &super::with_front_matter(session, &session.description, index, options), options)?;
+
+    if options.manifest {
+        let manifest_path = DocumentManifest::for_document(session, output_path, template)?
+            .with_parts(&written)
+            .write(output_path)?;
+        println!("🧾 Manifest written to {}", manifest_path.display());
+    }
+
+    written.push(output_path.to_path_buf());
+
+    Ok(written)
+}
+
+/// Render the index file that links every part
+fn render_index(session: &Session, parts: &[DocumentPart], split_by: SplitBy) -> Result<String> {
+    let mut content = String::new();
+
+    writeln!(content, "# {}", session.description)?;
+    writeln!(content)?;
+    writeln!(content, "**Session ID:** `{}`", session.id)?;
+    writeln!(content, "**Total Commands:** {}", session.commands.len())?;
+    writeln!(content, "**Split By:** {}", split_by.name())?;
+    writeln!(content)?;
+
+    writeln!(content, "## Contents")?;
+    writeln!(content)?;
+
+    if parts.is_empty() {
+        writeln!(content, "*No commands were captured during this session.*")?;
+        writeln!(content)?;
+    }
+
+    for part in parts {
+        let successful = part.commands.iter().filter(|cmd| cmd.exit_code == Some(0)).count();
+        let failed = part.commands.iter()
+            .filter(|cmd| cmd.exit_code.is_some() && cmd.exit_code != Some(0))
+            .count();
+        let noun = if part.commands.len() == 1 { "command" } else { "commands" };
+
+        writeln!(content, "- [{}]({}) - {} {} ({} successful, {} failed)",
+                part.title, relative_link(&part.file_name), part.commands.len(), noun, successful, failed)?;
+        if let Some(description) = &part.description {
+            writeln!(content, "  - *{}*", description)?;
+        }
+    }
+    writeln!(content)?;
+
+    if !session.annotations.is_empty() {
+        writeln!(content, "## Annotations")?;
+        writeln!(content)?;
+        for annotation in &session.annotations {
+            let type_emoji = match annotation.annotation_type {
+                AnnotationType::Note => "📝",
+                AnnotationType::Explanation => "💡",
+                AnnotationType::Warning => "⚠️",
+                AnnotationType::Milestone => "🎯",
+            };
+            writeln!(content, "- {} {}", type_emoji, annotation.text)?;
+        }
+        writeln!(content)?;
+    }
+
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_command(command: &str, directory: &str, exit_code: i32) -> CommandEntry {
+        CommandEntry {
+            command: command.to_string(),
+            exit_code: Some(exit_code),
+            working_directory: directory.to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn create_test_session() -> Session {
+        let mut session = Session::new("Split Test".to_string(), None).unwrap();
+        session.add_command(create_command("git clone https://example.com/repo.git", "/home/user", 0));
+        session.add_command(create_command("cargo build", "/home/user/repo", 0));
+        session.add_command(create_command("cargo test", "/home/user/repo", 1));
+        session.add_command(create_command("ls -la", "/home/user", 0));
+        session
+    }
+
+    #[test]
+    fn test_split_mode_parsing() {
+        assert_eq!(SplitBy::from_str("phase").unwrap(), SplitBy::Phase);
+        assert_eq!(SplitBy::from_str("Type").unwrap(), SplitBy::Type);
+        assert_eq!(SplitBy::from_str("dir").unwrap(), SplitBy::Directory);
+        assert!(SplitBy::from_str("weekday").is_err());
+    }
+
+    #[test]
+    fn test_split_by_directory_keeps_first_use_order() {
+        let session = create_test_session();
+        let parts = split_commands(&session.commands, SplitBy::Directory, "guide");
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].title, "/home/user");
+        assert_eq!(parts[0].file_name, "guide-home-user.md");
+        assert_eq!(parts[0].commands.len(), 2);
+        assert_eq!(parts[1].file_name, "guide-home-user-repo.md");
+    }
+
+    #[test]
+    fn test_split_by_phase_follows_workflow_order() {
+        let session = create_test_session();
+        let parts = split_commands(&session.commands, SplitBy::Phase, "guide");
+        let files: Vec<_> = parts.iter().map(|part| part.file_name.as_str()).collect();
+
+        assert_eq!(files, vec!["guide-setup.md", "guide-build.md", "guide-testing.md", "guide-other.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_generate_split_documentation_writes_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("guide.md");
+        let session = create_test_session();
+
+        let written = generate_split_documentation(&session, &output_path, "minimal", SplitBy::Type, &GenerateOptions::default())
+            .await
+            .expect("Split generation should succeed");
+
+        assert_eq!(written.last(), Some(&output_path));
+        let index = std::fs::read_to_string(&output_path).unwrap();
+        assert!(index.contains("[📁 FileSystem Commands](guide-filesystem.md) - 1 command (1 successful, 0 failed)"));
+        assert!(index.contains("(guide-development.md) - 2 commands (1 successful, 1 failed)"));
+
+        let part = std::fs::read_to_string(temp_dir.path().join("guide-development.md")).unwrap();
+        assert!(part.contains("cargo build"));
+        assert!(!part.contains("git clone"));
+    }
+
+    #[test]
+    fn test_colliding_part_names_are_numbered() {
+        let commands = vec![
+            create_command("make", "/srv/my-app", 0),
+            create_command("make", "/srv/my app", 0),
+            create_command("make", "/srv/my_app", 0),
+            create_command("ls", "/index", 0),
+        ];
+        let files: Vec<_> = split_commands(&commands, SplitBy::Directory, "guide").into_iter().map(|part| part.file_name).collect();
+        assert_eq!(files, vec!["guide-srv-my-app.md", "guide-srv-my-app-2.md", "guide-srv-my-app-3.md", "guide-index.md"]);
+
+        // Parts of an index.md are named after their group and never replace the index
+        let files: Vec<_> = split_commands(&commands, SplitBy::Directory, "index").into_iter().map(|part| part.file_name).collect();
+        assert_eq!(files, vec!["srv-my-app.md", "srv-my-app-2.md", "srv-my-app-3.md", "index-2.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_parts_link_to_index_and_neighbours() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("my guide.md");
+
+        generate_split_documentation(&create_test_session(), &output_path, "minimal", SplitBy::Phase, &GenerateOptions::default())
+            .await
+            .expect("Split generation should succeed");
+
+        let index = std::fs::read_to_string(&output_path).unwrap();
+        assert!(index.contains("(<my guide-setup.md>)"));
+        let setup = std::fs::read_to_string(temp_dir.path().join("my guide-setup.md")).unwrap();
+        let navigation = "[↑ Contents](<my guide.md>) | [🏗️ Build Phase →](<my guide-build.md>)";
+        assert!(setup.starts_with(&format!("{}\n\n# ", navigation)));
+        assert!(setup.ends_with(&format!("\n\n{}\n", navigation)));
+        let build = std::fs::read_to_string(temp_dir.path().join("my guide-build.md")).unwrap();
+        assert!(build.starts_with("[← 🔧 Setup Phase](<my guide-setup.md>) | [↑ Contents](<my guide.md>) | [🧪 Testing Phase →](<my guide-testing.md>)"));
+    }
+
+    #[tokio::test]
+    async fn test_split_manifest_lists_parts() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("guide.md");
+        let options = GenerateOptions { manifest: true, ..GenerateOptions::default() };
+
+        generate_split_documentation(&create_test_session(), &output_path, "minimal", SplitBy::Directory, &options)
+            .await
+            .expect("Split generation should succeed");
+
+        let manifest: DocumentManifest = serde_json::from_str(
+            &std::fs::read_to_string(temp_dir.path().join("guide.docpilot.json")).unwrap()
+        ).unwrap();
+        assert_eq!(manifest.document, "guide.md");
+        assert_eq!(manifest.parts, vec!["guide-home-user.md", "guide-home-user-repo.md"]);
+        assert!(!temp_dir.path().join("guide-home-user.docpilot.json").exists());
+    }
+}
@@ -0,0 +1,123 @@
+//! Per-stage timing of documentation generation, reported by `generate --timings`
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A stage of documentation generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    LoadSession,
+    Filtering,
+    Structuring,
+    AiAnalysis,
+    Markdown,
+    FileIo,
+}
+
+impl Stage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::LoadSession => "Loading session",
+            Stage::Filtering => "Filtering",
+            Stage::Structuring => "Hierarchical structuring",
+            Stage::AiAnalysis => "AI analysis",
+            Stage::Markdown => "Markdown writing",
+            Stage::FileIo => "File IO",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct StageTotal {
+    elapsed: Duration,
+    calls: usize,
+}
+
+/// Time spent in each stage, shared between clones so split parts add to one report
+#[derive(Debug, Clone, Default)]
+pub struct StageTimings {
+    stages: Arc<Mutex<BTreeMap<Stage, StageTotal>>>,
+}
+
+impl StageTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one run of `stage` that took `elapsed`
+    pub fn add(&self, stage: Stage, elapsed: Duration) {
+        let mut stages = self.stages.lock().unwrap();
+        let total = stages.entry(stage).or_default();
+        total.elapsed += elapsed;
+        total.calls += 1;
+    }
+
+    /// Add the time since `start` to `stage`
+    pub fn add_since(&self, stage: Stage, start: Instant) {
+        self.add(stage, start.elapsed());
+    }
+
+    /// Time recorded across all stages
+    pub fn total(&self) -> Duration {
+        self.stages.lock().unwrap().values().map(|total| total.elapsed).sum()
+    }
+
+    /// Table of the recorded stages in pipeline order, with their share of the total
+    pub fn report(&self) -> String {
+        let stages = self.stages.lock().unwrap();
+        let total: Duration = stages.values().map(|total| total.elapsed).sum();
+
+        let mut report = String::new();
+        let _ = writeln!(report, "⏱️  Generation timings:");
+        for (stage, stage_total) in stages.iter() {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                stage_total.elapsed.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            let calls = if stage_total.calls > 1 { format!(" ({} runs)", stage_total.calls) } else { String::new() };
+            let _ = writeln!(
+                report,
+                "   {:<26}{:>10}  {:>5.1}%{}",
+                stage.name(), format_elapsed(stage_total.elapsed), share, calls
+            );
+        }
+        let _ = writeln!(report, "   {:<26}{:>10}", "Total", format_elapsed(total));
+        report
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    if elapsed >= Duration::from_secs(1) {
+        format!("{:.2} s", elapsed.as_secs_f64())
+    } else {
+        format!("{:.1} ms", elapsed.as_secs_f64() * 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_orders_stages_and_counts_runs() {
+        let timings = StageTimings::new();
+        let shared = timings.clone();
+        shared.add(Stage::FileIo, Duration::from_millis(5));
+        timings.add(Stage::AiAnalysis, Duration::from_millis(1500));
+        timings.add(Stage::AiAnalysis, Duration::from_millis(1500));
+        timings.add(Stage::Markdown, Duration::from_millis(995));
+
+        assert_eq!(timings.total(), Duration::from_millis(4000));
+
+        let report = timings.report();
+        let ai = report.find("AI analysis").unwrap();
+        assert!(ai < report.find("Markdown writing").unwrap());
+        assert!(report.find("Markdown writing").unwrap() < report.find("File IO").unwrap());
+        assert!(report.contains("3.00 s   75.0% (2 runs)"));
+        assert!(report.contains("5.0 ms"));
+        assert!(!report.contains("Filtering"));
+    }
+}
@@ -0,0 +1,42 @@
+use super::manager::SessionState;
+
+/// Errors from session operations that callers need to tell apart
+///
+/// These are returned inside `anyhow::Error`; use `downcast_ref::<SessionError>()` to branch on them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionError {
+    /// There is no current session to operate on
+    NoActiveSession,
+    /// A session is already running, so a new one cannot be started
+    AlreadyActive,
+    /// The session's state doesn't allow the requested change, see `SessionState::can_transition_to`
+    InvalidTransition { from: SessionState, to: SessionState },
+    /// No stored session has this ID
+    NotFound(String),
+    /// The current session has no annotation with this ID
+    AnnotationNotFound(String),
+}
+
+impl std::fmt::Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionError::NoActiveSession => write!(f, "No active session"),
+            SessionError::AlreadyActive => write!(f, "A session is already active. Stop the current session first."),
+            SessionError::InvalidTransition { from, to } => {
+                let state = match from {
+                    SessionState::Active => "active".to_string(),
+                    SessionState::Paused => "paused".to_string(),
+                    SessionState::Stopped => "already stopped".to_string(),
+                    SessionState::Error(message) => format!("in Error state ({})", message),
+                };
+                write!(f, "Cannot {}: session is {}", to.transition_verb(), state)
+            }
+            SessionError::NotFound(session_id) => write!(f, "Session not found: {}", session_id),
+            SessionError::AnnotationNotFound(annotation_id) => {
+                write!(f, "No annotation with ID {} in the current session", annotation_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
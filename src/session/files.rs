@@ -0,0 +1,263 @@
+//! Files changed on disk during a session
+//!
+//! `start --track-files` records the files matching the given globs when the session starts and
+//! compares them with the same files when it stops. Files are compared by content hash, or by size
+//! and modification time when they are too large to hash, so touching a file doesn't count as a change.
+//! The starting snapshot is kept in a file next to the session rather than in it, so a large tree
+//! doesn't bloat every save of the session.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Most files recorded per snapshot, so a pattern like `**/*` in a large tree stays cheap
+pub const MAX_TRACKED_FILES: usize = 10_000;
+
+/// Larger files are compared by size and modification time only
+const MAX_HASHED_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// What a tracked file looked like at one point
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FileState {
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+    /// Content hash, for files up to 16 MB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl FileState {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let sha256 = if metadata.len() <= MAX_HASHED_FILE_SIZE {
+            let digest = Sha256::digest(fs::read(path).ok()?);
+            Some(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+        } else {
+            None
+        };
+        Some(FileState {
+            size: metadata.len(),
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            sha256,
+        })
+    }
+
+    fn differs_from(&self, other: &FileState) -> bool {
+        match (&self.sha256, &other.sha256) {
+            (Some(hash), Some(other_hash)) => hash != other_hash,
+            _ => self.size != other.size || self.modified != other.modified,
+        }
+    }
+}
+
+/// How a tracked file changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// A tracked file that changed during the session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FileChange {
+    /// Path relative to the session's working directory, or absolute outside it
+    pub path: String,
+    pub kind: FileChangeKind,
+}
+
+/// The tracked files at one point
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// State of each file, keyed by path relative to the session's working directory where possible
+    pub files: BTreeMap<String, FileState>,
+    /// More files matched than `MAX_TRACKED_FILES`, so `files` is missing some of them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+}
+
+impl Snapshot {
+    fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read file snapshot {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Files tracked for a session started with `--track-files`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FileTracking {
+    /// Glob patterns; relative ones are relative to the session's working directory
+    pub patterns: Vec<String>,
+    /// Changes between start and stop, in path order; `None` until the session stops
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<FileChange>>,
+}
+
+impl FileTracking {
+    /// Record the files matching `patterns` below `root` in `snapshot_file`
+    pub fn start(patterns: Vec<String>, root: &Path, snapshot_file: &Path) -> Result<(Self, Snapshot)> {
+        let snapshot = snapshot(&patterns, root)?;
+        snapshot.save(snapshot_file)?;
+        Ok((FileTracking { patterns, changes: None }, snapshot))
+    }
+
+    /// Compare the files matching the patterns now with the snapshot from the start, then
+    /// remove the snapshot file
+    pub fn finish(&mut self, root: &Path, snapshot_file: &Path) -> Result<()> {
+        if self.changes.is_some() {
+            return Ok(());
+        }
+        let before = Snapshot::load(snapshot_file)?;
+        let current = snapshot(&self.patterns, root)?;
+        self.changes = Some(diff(&before, &current));
+        fs::remove_file(snapshot_file)?;
+        Ok(())
+    }
+}
+
+/// State of the files matching `patterns`, keyed by path relative to `root` where possible
+///
+/// Hidden files and directories only match patterns that name them, so `**/*` skips `.git`.
+/// Stops at `MAX_TRACKED_FILES`, marking the snapshot partial.
+pub fn snapshot(patterns: &[String], root: &Path) -> Result<Snapshot> {
+    let options = glob::MatchOptions { require_literal_leading_dot: true, ..Default::default() };
+    let mut files = BTreeMap::new();
+
+    for pattern in patterns {
+        let full_pattern = if Path::new(pattern).is_absolute() {
+            pattern.clone()
+        } else {
+            format!("{}/{}", glob::Pattern::escape(&root.to_string_lossy()), pattern)
+        };
+        let paths = glob::glob_with(&full_pattern, options)
+            .map_err(|e| anyhow!("Invalid file pattern '{}': {}", pattern, e))?;
+
+        for path in paths.flatten().filter(|path| path.is_file()) {
+            let key = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().to_string();
+            if files.len() >= MAX_TRACKED_FILES && !files.contains_key(&key) {
+                return Ok(Snapshot { files, partial: true });
+            }
+            if !files.contains_key(&key)
+                && let Some(state) = FileState::read(&path)
+            {
+                files.insert(key, state);
+            }
+        }
+    }
+
+    Ok(Snapshot { files, partial: false })
+}
+
+/// Files created, modified or deleted between two snapshots, in path order
+///
+/// A file missing from a partial snapshot may just be past the cap, so files are only reported
+/// created when `before` is complete and deleted when `after` is.
+pub fn diff(before: &Snapshot, after: &Snapshot) -> Vec<FileChange> {
+    let mut changes: Vec<FileChange> = after
+        .files
+        .iter()
+        .filter_map(|(path, state)| {
+            let kind = match before.files.get(path) {
+                None if before.partial => return None,
+                None => FileChangeKind::Created,
+                Some(previous) if previous.differs_from(state) => FileChangeKind::Modified,
+                Some(_) => return None,
+            };
+            Some(FileChange { path: path.clone(), kind })
+        })
+        .chain(
+            before
+                .files
+                .keys()
+                .filter(|path| !after.partial && !after.files.contains_key(*path))
+                .map(|path| FileChange { path: path.clone(), kind: FileChangeKind::Deleted }),
+        )
+        .collect();
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_tracking_records_changes() {
+        let root = tempfile::TempDir::new().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write("config/app.yml", "port: 80\n");
+        write("config/db.yml", "host: localhost\n");
+        write("config/unchanged.yml", "debug: false\n");
+        write("notes.txt", "not tracked\n");
+        write("config/.secret.yml", "hidden\n");
+
+        let snapshot_dir = tempfile::TempDir::new().unwrap();
+        let snapshot_file = snapshot_dir.path().join("session.files");
+        let patterns = vec!["config/**/*.yml".to_string()];
+        let (mut tracking, snapshot) = FileTracking::start(patterns, root.path(), &snapshot_file).unwrap();
+        assert_eq!(snapshot.files.keys().collect::<Vec<_>>(), vec!["config/app.yml", "config/db.yml", "config/unchanged.yml"]);
+        assert!(!snapshot.partial);
+        assert!(snapshot_file.exists());
+        // The snapshot stays out of the session
+        assert!(!serde_json::to_string(&tracking).unwrap().contains("unchanged.yml"));
+
+        write("config/app.yml", "port: 8080\n");
+        fs::remove_file(root.path().join("config/db.yml")).unwrap();
+        write("config/nested/cache.yml", "size: 10\n");
+        write("config/unchanged.yml", "debug: false\n");
+        write("notes.txt", "changed but not tracked\n");
+
+        tracking.finish(root.path(), &snapshot_file).unwrap();
+        let change = |path: &str, kind| FileChange { path: path.to_string(), kind };
+        assert_eq!(tracking.changes.unwrap(), vec![
+            change("config/app.yml", FileChangeKind::Modified),
+            change("config/db.yml", FileChangeKind::Deleted),
+            change("config/nested/cache.yml", FileChangeKind::Created),
+        ]);
+        assert!(!snapshot_file.exists());
+
+        assert!(FileTracking::start(vec!["config/[".to_string()], root.path(), &snapshot_file).is_err());
+    }
+
+    #[test]
+    fn test_partial_snapshots_only_report_what_they_saw() {
+        let state = |sha256: &str| FileState { size: 1, modified: None, sha256: Some(sha256.to_string()) };
+        let snapshot = |files: &[(&str, &str)], partial| Snapshot {
+            files: files.iter().map(|(path, sha256)| (path.to_string(), state(sha256))).collect(),
+            partial,
+        };
+        let before = snapshot(&[("a", "1"), ("b", "1")], true);
+        let after = snapshot(&[("a", "2"), ("c", "1")], true);
+        let change = |path: &str, kind| FileChange { path: path.to_string(), kind };
+
+        // Neither `b` nor `c` is reported: either may just be past the cap
+        assert_eq!(diff(&before, &after), vec![change("a", FileChangeKind::Modified)]);
+
+        let complete = |snapshot: Snapshot| Snapshot { partial: false, ..snapshot };
+        assert_eq!(diff(&complete(before.clone()), &after), vec![
+            change("a", FileChangeKind::Modified),
+            change("c", FileChangeKind::Created),
+        ]);
+        assert_eq!(diff(&before, &complete(after)), vec![
+            change("a", FileChangeKind::Modified),
+            change("b", FileChangeKind::Deleted),
+        ]);
+    }
+}
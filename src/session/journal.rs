@@ -0,0 +1,119 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::manager::{Annotation, Session, SessionState};
+use crate::terminal::CommandEntry;
+
+/// A single change recorded in a session journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEntry {
+    /// A command was captured at the given position in `Session::commands`
+    CommandAdded { position: usize, command: Box<CommandEntry> },
+    /// An annotation was added at the given position in `Session::annotations`
+    AnnotationAdded { position: usize, annotation: Annotation },
+    /// The session moved to a new state
+    StateChanged { state: SessionState, timestamp: DateTime<Utc> },
+    /// The backgrounded command at the given position in `Session::commands` finished
+    BackgroundJobFinished { position: usize, exit_code: i32 },
+}
+
+/// Append-only log of changes made since the session file was last written.
+///
+/// Appending one line per change is cheap and cannot corrupt data that is
+/// already on disk. The consolidated session JSON is rebuilt periodically, at
+/// which point the journal is truncated; after a crash the remaining entries
+/// are replayed on top of the last consolidated session.
+#[derive(Debug, Clone)]
+pub struct SessionJournal {
+    path: PathBuf,
+}
+
+impl SessionJournal {
+    /// Journal for the given session inside the sessions directory
+    pub fn for_session(sessions_dir: &Path, session_id: &str) -> Self {
+        Self {
+            path: sessions_dir.join(format!("{}.journal", session_id)),
+        }
+    }
+
+    /// Append an entry to the journal
+    pub fn append(&self, entry: &JournalEntry) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let line = serde_json::to_string(entry)?;
+        writeln!(file, "{}", line)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Read all readable entries, ignoring a torn final line from an interrupted write
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        let entries = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        Ok(entries)
+    }
+
+    /// Apply journal entries that are missing from the session, returning how many were applied
+    pub fn replay(&self, session: &mut Session) -> Result<usize> {
+        let mut applied = 0;
+
+        for entry in self.entries()? {
+            match entry {
+                JournalEntry::CommandAdded { position, command } => {
+                    // Entries at earlier positions are already part of the consolidated
+                    // file; a gap means the base file is older than the journal
+                    if position == session.commands.len() {
+                        session.add_command(*command);
+                        applied += 1;
+                    }
+                }
+                JournalEntry::AnnotationAdded { position, annotation } => {
+                    if position == session.annotations.len() {
+                        session.restore_annotation(annotation);
+                        applied += 1;
+                    }
+                }
+                JournalEntry::StateChanged { state, timestamp } => {
+                    if session.state != state {
+                        if state.is_stopped() {
+                            session.stopped_at = Some(timestamp);
+                        }
+                        session.state = state;
+                        session.updated_at = timestamp;
+                        applied += 1;
+                    }
+                }
+                JournalEntry::BackgroundJobFinished { position, exit_code } => {
+                    if session.finish_background_command(position, exit_code) {
+                        applied += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(applied)
+    }
+
+    /// Remove all entries once they have been consolidated into the session file
+    pub fn clear(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
@@ -1,16 +1,21 @@
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 use crate::terminal::{CommandEntry, TerminalMonitor};
+use super::error::SessionError;
+use super::files::FileTracking;
+use super::journal::{JournalEntry, SessionJournal};
+use super::spill::OutputSpill;
 
 /// Represents the current state of a documentation session
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub enum SessionState {
     /// Session is actively monitoring and capturing commands
     Active,
@@ -38,10 +43,42 @@ impl SessionState {
     pub fn is_error(&self) -> bool {
         matches!(self, SessionState::Error(_))
     }
+
+    /// Check whether a session in this state may move to `next`
+    ///
+    /// Active and paused sessions can switch between each other, stop, or fail. Stopped
+    /// and failed sessions are final.
+    pub fn can_transition_to(&self, next: &SessionState) -> bool {
+        matches!(
+            (self, next),
+            (SessionState::Active, SessionState::Paused)
+                | (SessionState::Paused, SessionState::Active)
+                | (SessionState::Active | SessionState::Paused, SessionState::Stopped | SessionState::Error(_))
+        )
+    }
+
+    /// What moving to this state is called in error messages, e.g. "resume" for `Active`
+    pub fn transition_verb(&self) -> &'static str {
+        match self {
+            SessionState::Active => "resume",
+            SessionState::Paused => "pause",
+            SessionState::Stopped => "stop",
+            SessionState::Error(_) => "mark as failed",
+        }
+    }
+
+    /// Error unless a session in this state may move to `next`
+    fn check_transition(&self, next: &SessionState) -> Result<()> {
+        if self.can_transition_to(next) {
+            Ok(())
+        } else {
+            Err(SessionError::InvalidTransition { from: self.clone(), to: next.clone() }.into())
+        }
+    }
 }
 
 /// Manual annotation added by the user during a session
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Annotation {
     pub id: String,
     pub text: String,
@@ -49,20 +86,25 @@ pub struct Annotation {
     pub annotation_type: AnnotationType,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
 pub enum AnnotationType {
     /// General note or comment
+    #[serde(alias = "Note")]
     Note,
     /// Explanation of what's happening
+    #[serde(alias = "Explanation")]
     Explanation,
     /// Warning or important information
+    #[serde(alias = "Warning")]
     Warning,
     /// Section divider or milestone
+    #[serde(alias = "Milestone")]
     Milestone,
 }
 
 /// Events that occur during a session for audit trail
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionEvent {
     pub id: String,
     pub event_type: SessionEventType,
@@ -70,20 +112,22 @@ pub struct SessionEvent {
     pub details: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum SessionEventType {
     SessionStarted,
     SessionPaused,
     SessionResumed,
     SessionStopped,
     AnnotationAdded,
+    AnnotationEdited,
+    AnnotationRemoved,
     CommandCaptured,
     ErrorOccurred,
     ConfigurationChanged,
 }
 
 /// Main session data structure containing all session information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Session {
     /// Unique identifier for the session
     pub id: String,
@@ -114,7 +158,7 @@ pub struct Session {
 }
 
 /// Metadata and configuration for a session
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionMetadata {
     /// Working directory when session was started
     pub working_directory: PathBuf,
@@ -132,10 +176,63 @@ pub struct SessionMetadata {
     pub llm_provider: Option<String>,
     /// Session-specific settings
     pub settings: HashMap<String, String>,
+    /// Terminal the session was started from, e.g. `/dev/pts/3`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tty: Option<String>,
+    /// Process ID of the monitor capturing commands for this session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor_pid: Option<u32>,
+    /// Allowlisted environment variables when the session started, without secrets
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environment: BTreeMap<String, String>,
+    /// Files watched for changes with `start --track-files`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_tracking: Option<FileTracking>,
+    /// Pinned with `docpilot pin`, so bulk cleanup leaves the session alone
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+}
+
+/// Point up to which a session was documented, so `generate --incremental` can pick up from there
+///
+/// Kept in its own file beside the session, so a monitor still recording the session can't
+/// overwrite it with its own copy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationMarker {
+    /// Step number of the last command that was documented, `None` when the session had no commands yet
+    pub last_step: Option<usize>,
+    /// IDs of the annotations that were documented
+    pub annotation_ids: Vec<String>,
+    /// When documentation was generated
+    pub generated_at: DateTime<Utc>,
 }
 
+/// Session setting that makes the shell hooks record alias and function expansions
+pub const EXPAND_ALIASES_SETTING: &str = "expand_aliases";
+
+/// Session setting that restricts capture to commands run inside a directory
+pub const ONLY_DIR_SETTING: &str = "only_dir";
+
+/// Session setting for live warnings about destructive commands: `warn`, or `annotate` to also add a warning annotation
+pub const WARN_DANGEROUS_SETTING: &str = "warn_dangerous";
+
+/// Session setting naming the directory whose daily log the session is appended to when it stops
+pub const DAILY_LOG_SETTING: &str = "daily_log";
+
+/// Session setting with the number of seconds after the start during which captured commands are dropped
+pub const CAPTURE_DELAY_SETTING: &str = "capture_delay";
+
+/// Session setting that records shell nesting, holding the shell level (`$SHLVL`) the session was started from
+pub const TRACK_NESTING_SETTING: &str = "track_nesting";
+
+/// Session setting with the terminal width (`$COLUMNS`) the session was started in
+pub const TERMINAL_COLUMNS_SETTING: &str = "columns";
+
+/// Session setting with the terminal height (`$LINES`) the session was started in
+pub const TERMINAL_LINES_SETTING: &str = "lines";
+
 /// Statistics about session activity
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionStats {
     /// Total number of commands captured
     pub total_commands: usize,
@@ -143,6 +240,9 @@ pub struct SessionStats {
     pub successful_commands: usize,
     /// Number of failed commands (non-zero exit code)
     pub failed_commands: usize,
+    /// Number of failed commands that were terminated by a signal (exit code 128+N)
+    #[serde(default)]
+    pub terminated_commands: usize,
     /// Number of annotations added
     pub total_annotations: usize,
     /// Total session duration in seconds
@@ -168,6 +268,12 @@ impl Session {
             .or_else(|_| std::env::var("USERNAME"))
             .ok();
 
+        // Capture keeps going with the default allowlist when filter.json is broken
+        let environment_allowlist = crate::filter::FilterConfig::load()
+            .map(|config| config.environment_variables)
+            .unwrap_or_default();
+        let environment = crate::filter::environment_snapshot(&environment_allowlist, std::env::vars());
+
         let metadata = SessionMetadata {
             working_directory,
             shell_type: "unknown".to_string(), // Will be updated when monitor is attached
@@ -177,12 +283,18 @@ impl Session {
             tags: Vec::new(),
             llm_provider: None,
             settings: HashMap::new(),
+            tty: crate::terminal::PlatformUtils::controlling_tty(),
+            monitor_pid: None,
+            environment,
+            file_tracking: None,
+            pinned: false,
         };
 
         let stats = SessionStats {
             total_commands: 0,
             successful_commands: 0,
             failed_commands: 0,
+            terminated_commands: 0,
             total_annotations: 0,
             duration_seconds: None,
             pause_resume_count: 0,
@@ -219,8 +331,83 @@ impl Session {
         self.updated_at = Utc::now();
     }
 
-    /// Add a command to the session
-    pub fn add_command(&mut self, command: CommandEntry) {
+    /// Whether aliases and shell functions are resolved when commands are captured
+    pub fn expands_aliases(&self) -> bool {
+        self.metadata.settings.get(EXPAND_ALIASES_SETTING).is_some_and(|value| value == "true")
+    }
+
+    /// Directory that capture is restricted to, if any
+    pub fn only_dir(&self) -> Option<PathBuf> {
+        self.metadata.settings.get(ONLY_DIR_SETTING).map(PathBuf::from)
+    }
+
+    /// Directory of the daily log this session is appended to when it stops, if any
+    pub fn daily_log_dir(&self) -> Option<PathBuf> {
+        self.metadata.settings.get(DAILY_LOG_SETTING).map(PathBuf::from)
+    }
+
+    /// Seconds after the start during which captured commands were dropped, if a delay was set
+    pub fn capture_delay(&self) -> Option<u64> {
+        self.metadata.settings.get(CAPTURE_DELAY_SETTING).and_then(|value| value.parse().ok()).filter(|delay| *delay > 0)
+    }
+
+    /// Shell level nesting is measured from, when shell nesting is recorded
+    pub fn nesting_base(&self) -> Option<u32> {
+        self.metadata.settings.get(TRACK_NESTING_SETTING).and_then(|value| value.parse().ok())
+    }
+
+    /// Width of the terminal the session was started in, when it was known
+    pub fn terminal_columns(&self) -> Option<u32> {
+        self.metadata.settings.get(TERMINAL_COLUMNS_SETTING).and_then(|value| value.parse().ok()).filter(|columns| *columns > 0)
+    }
+
+    /// Height of the terminal the session was started in, when it was known
+    pub fn terminal_lines(&self) -> Option<u32> {
+        self.metadata.settings.get(TERMINAL_LINES_SETTING).and_then(|value| value.parse().ok()).filter(|lines| *lines > 0)
+    }
+
+    /// Whether the monitor warns when a destructive command is captured
+    pub fn warns_dangerous(&self) -> bool {
+        self.metadata.settings.get(WARN_DANGEROUS_SETTING).is_some_and(|value| value == "warn" || value == "annotate")
+    }
+
+    /// Whether those warnings are also recorded as warning annotations
+    pub fn annotates_dangerous(&self) -> bool {
+        self.metadata.settings.get(WARN_DANGEROUS_SETTING).is_some_and(|value| value == "annotate")
+    }
+
+    /// Marker for documentation generated from the session as it is now
+    pub fn generation_marker(&self) -> GenerationMarker {
+        GenerationMarker {
+            last_step: self.commands.len().checked_sub(1).map(|last| self.step_at(last)),
+            annotation_ids: self.annotations.iter().map(|annotation| annotation.id.clone()).collect(),
+            generated_at: Utc::now(),
+        }
+    }
+
+    /// Copy of the session with only the commands and annotations added since `marker`
+    ///
+    /// Commands with a step after the last documented one are new; steps are never reused, so this
+    /// holds when commands were edited or removed since. Without a marker the session is returned whole.
+    pub fn since_last_generate(&self, marker: Option<&GenerationMarker>) -> Session {
+        let mut session = self.clone();
+        if let Some(marker) = marker {
+            if let Some(last_step) = marker.last_step {
+                session.commands = (0..self.commands.len())
+                    .filter(|&index| self.step_at(index) > last_step)
+                    .map(|index| self.commands[index].clone())
+                    .collect();
+            }
+            session.annotations.retain(|annotation| !marker.annotation_ids.contains(&annotation.id));
+            session.recount_command_stats();
+            session.stats.total_annotations = session.annotations.len();
+        }
+        session
+    }
+
+    /// Add a command to the session, numbering it as the next step
+    pub fn add_command(&mut self, mut command: CommandEntry) {
+        command.step = Some(self.next_step());
         self.commands.push(command.clone());
         self.stats.total_commands += 1;
         
@@ -230,6 +417,9 @@ impl Session {
                 self.stats.successful_commands += 1;
             } else {
                 self.stats.failed_commands += 1;
+                if command.termination_signal().is_some() {
+                    self.stats.terminated_commands += 1;
+                }
             }
         }
 
@@ -244,6 +434,128 @@ impl Session {
         self.updated_at = Utc::now();
     }
 
+    /// Step number of the command at `index`; commands captured before steps were recorded count by position
+    fn step_at(&self, index: usize) -> usize {
+        self.commands[index].step.unwrap_or(index + 1)
+    }
+
+    /// Step number the next captured command gets
+    ///
+    /// Steps only grow in capture order (merging numbers them again from 1), so the last command
+    /// has the highest one and the list doesn't need to be scanned on every capture.
+    fn next_step(&self) -> usize {
+        self.commands.len().checked_sub(1).map_or(0, |last| self.step_at(last)) + 1
+    }
+
+    /// Give commands recorded without a step number the one they have been shown with, their position
+    pub fn number_steps(&mut self) {
+        for (index, command) in self.commands.iter_mut().enumerate() {
+            command.step.get_or_insert(index + 1);
+        }
+    }
+
+    /// Add or remove a tag on the command with step `number`
+    ///
+    /// Returns false when there was nothing to change (the tag was already there, or missing).
+    pub fn set_command_tag(&mut self, number: usize, tag: &str, tagged: bool) -> Result<bool> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(anyhow!("Tag cannot be empty"));
+        }
+        let last = self.next_step() - 1;
+        let index = (0..self.commands.len())
+            .find(|&index| self.step_at(index) == number)
+            .ok_or_else(|| anyhow!("No command #{} in this session (its steps go up to {})", number, last))?;
+        let command = &mut self.commands[index];
+
+        let present = command.tags.iter().any(|existing| existing == tag);
+        if present == tagged {
+            return Ok(false);
+        }
+        if tagged {
+            command.tags.push(tag.to_string());
+        } else {
+            command.tags.retain(|existing| existing != tag);
+        }
+        self.updated_at = Utc::now();
+        Ok(true)
+    }
+
+    /// Add or remove one of the session's own tags
+    ///
+    /// Returns false when there was nothing to change (the tag was already there, or missing).
+    pub fn set_tag(&mut self, tag: &str, tagged: bool) -> Result<bool> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(anyhow!("Tag cannot be empty"));
+        }
+
+        let present = self.has_tag(tag);
+        if present == tagged {
+            return Ok(false);
+        }
+        if tagged {
+            self.metadata.tags.push(tag.to_string());
+        } else {
+            self.metadata.tags.retain(|existing| existing != tag);
+        }
+        self.updated_at = Utc::now();
+        Ok(true)
+    }
+
+    /// Whether the session is tagged with `tag`
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.metadata.tags.iter().any(|existing| existing == tag.trim())
+    }
+
+    /// Recompute the command counts in the stats after `commands` was changed directly
+    pub fn recount_command_stats(&mut self) {
+        self.stats.total_commands = self.commands.len();
+        self.stats.successful_commands = self.commands.iter().filter(|cmd| cmd.exit_code == Some(0)).count();
+        self.stats.failed_commands = self.commands.iter()
+            .filter(|cmd| cmd.exit_code.is_some() && cmd.exit_code != Some(0))
+            .count();
+        self.stats.terminated_commands = self.commands.iter()
+            .filter(|cmd| cmd.termination_signal().is_some())
+            .count();
+    }
+
+    /// Count commands without an exit code whose output suggests success or failure
+    ///
+    /// Returns `(likely successful, likely failed)`; these are not part of the exact stats.
+    pub fn inferred_outcome_counts(&self) -> (usize, usize) {
+        let filter = crate::filter::CommandFilter::new();
+        self.commands.iter().fold((0, 0), |(succeeded, failed), command| match filter.infer_success(command) {
+            Some(true) => (succeeded + 1, failed),
+            Some(false) => (succeeded, failed + 1),
+            None => (succeeded, failed),
+        })
+    }
+
+    /// Record the exit code of a backgrounded command once its job finishes
+    ///
+    /// Returns false if the command at `position` is not a backgrounded command still waiting for its exit code.
+    pub fn finish_background_command(&mut self, position: usize, exit_code: i32) -> bool {
+        let Some(command) = self.commands.get_mut(position) else {
+            return false;
+        };
+        if !command.backgrounded || command.exit_code.is_some() {
+            return false;
+        }
+
+        command.exit_code = Some(exit_code);
+        if exit_code == 0 {
+            self.stats.successful_commands += 1;
+        } else {
+            self.stats.failed_commands += 1;
+            if command.termination_signal().is_some() {
+                self.stats.terminated_commands += 1;
+            }
+        }
+        self.updated_at = Utc::now();
+        true
+    }
+
     /// Add an annotation to the session
     pub fn add_annotation(&mut self, text: String, annotation_type: AnnotationType) -> String {
         let annotation = Annotation {
@@ -253,6 +565,14 @@ impl Session {
             annotation_type,
         };
 
+        let annotation_id = annotation.id.clone();
+        self.restore_annotation(annotation);
+
+        annotation_id
+    }
+
+    /// Add an existing annotation (e.g. replayed from a journal) keeping its id and timestamp
+    pub fn restore_annotation(&mut self, annotation: Annotation) {
         let annotation_id = annotation.id.clone();
         self.annotations.push(annotation);
         self.stats.total_annotations += 1;
@@ -266,76 +586,97 @@ impl Session {
         };
         self.events.push(event);
         self.updated_at = Utc::now();
+    }
 
-        annotation_id
+    /// Replace the text of the annotation with the given ID
+    pub fn update_annotation(&mut self, annotation_id: &str, text: String) -> Result<()> {
+        let annotation = self.annotations.iter_mut()
+            .find(|annotation| annotation.id == annotation_id)
+            .ok_or_else(|| SessionError::AnnotationNotFound(annotation_id.to_string()))?;
+        annotation.text = text;
+
+        self.events.push(SessionEvent {
+            id: Uuid::new_v4().to_string(),
+            event_type: SessionEventType::AnnotationEdited,
+            timestamp: Utc::now(),
+            details: Some(format!("Annotation edited: {}", annotation_id)),
+        });
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /// Remove the annotation with the given ID, returning it
+    pub fn remove_annotation(&mut self, annotation_id: &str) -> Result<Annotation> {
+        let position = self.annotations.iter()
+            .position(|annotation| annotation.id == annotation_id)
+            .ok_or_else(|| SessionError::AnnotationNotFound(annotation_id.to_string()))?;
+        let annotation = self.annotations.remove(position);
+        self.stats.total_annotations = self.stats.total_annotations.saturating_sub(1);
+
+        self.events.push(SessionEvent {
+            id: Uuid::new_v4().to_string(),
+            event_type: SessionEventType::AnnotationRemoved,
+            timestamp: Utc::now(),
+            details: Some(format!("Annotation deleted: {}", annotation_id)),
+        });
+        self.updated_at = Utc::now();
+        Ok(annotation)
     }
 
     /// Pause the session
     pub fn pause(&mut self) -> Result<()> {
-        match self.state {
-            SessionState::Active => {
-                self.state = SessionState::Paused;
-                self.stats.pause_resume_count += 1;
-                
-                let event = SessionEvent {
-                    id: Uuid::new_v4().to_string(),
-                    event_type: SessionEventType::SessionPaused,
-                    timestamp: Utc::now(),
-                    details: None,
-                };
-                self.events.push(event);
-                self.updated_at = Utc::now();
-                Ok(())
-            }
-            _ => Err(anyhow!("Cannot pause session in state: {:?}", self.state)),
-        }
+        self.state.check_transition(&SessionState::Paused)?;
+        self.state = SessionState::Paused;
+        self.stats.pause_resume_count += 1;
+
+        let event = SessionEvent {
+            id: Uuid::new_v4().to_string(),
+            event_type: SessionEventType::SessionPaused,
+            timestamp: Utc::now(),
+            details: None,
+        };
+        self.events.push(event);
+        self.updated_at = Utc::now();
+        Ok(())
     }
 
     /// Resume the session
     pub fn resume(&mut self) -> Result<()> {
-        match self.state {
-            SessionState::Paused => {
-                self.state = SessionState::Active;
-                
-                let event = SessionEvent {
-                    id: Uuid::new_v4().to_string(),
-                    event_type: SessionEventType::SessionResumed,
-                    timestamp: Utc::now(),
-                    details: None,
-                };
-                self.events.push(event);
-                self.updated_at = Utc::now();
-                Ok(())
-            }
-            _ => Err(anyhow!("Cannot resume session in state: {:?}", self.state)),
-        }
+        self.state.check_transition(&SessionState::Active)?;
+        self.state = SessionState::Active;
+
+        let event = SessionEvent {
+            id: Uuid::new_v4().to_string(),
+            event_type: SessionEventType::SessionResumed,
+            timestamp: Utc::now(),
+            details: None,
+        };
+        self.events.push(event);
+        self.updated_at = Utc::now();
+        Ok(())
     }
 
     /// Stop the session
     pub fn stop(&mut self) -> Result<()> {
-        match self.state {
-            SessionState::Active | SessionState::Paused => {
-                self.state = SessionState::Stopped;
-                self.stopped_at = Some(Utc::now());
-                
-                // Calculate duration
-                if let Some(started_at) = self.started_at {
-                    let duration = Utc::now().signed_duration_since(started_at);
-                    self.stats.duration_seconds = Some(duration.num_seconds() as u64);
-                }
-                
-                let event = SessionEvent {
-                    id: Uuid::new_v4().to_string(),
-                    event_type: SessionEventType::SessionStopped,
-                    timestamp: Utc::now(),
-                    details: Some(format!("Session completed with {} commands", self.stats.total_commands)),
-                };
-                self.events.push(event);
-                self.updated_at = Utc::now();
-                Ok(())
-            }
-            _ => Err(anyhow!("Cannot stop session in state: {:?}", self.state)),
+        self.state.check_transition(&SessionState::Stopped)?;
+        self.state = SessionState::Stopped;
+        self.stopped_at = Some(Utc::now());
+
+        // Calculate duration
+        if let Some(started_at) = self.started_at {
+            let duration = Utc::now().signed_duration_since(started_at);
+            self.stats.duration_seconds = Some(duration.num_seconds() as u64);
         }
+
+        let event = SessionEvent {
+            id: Uuid::new_v4().to_string(),
+            event_type: SessionEventType::SessionStopped,
+            timestamp: Utc::now(),
+            details: Some(format!("Session completed with {} commands", self.stats.total_commands)),
+        };
+        self.events.push(event);
+        self.updated_at = Utc::now();
+        Ok(())
     }
 
     /// Set session to error state
@@ -367,6 +708,105 @@ impl Session {
     pub fn can_modify(&self) -> bool {
         matches!(self.state, SessionState::Active | SessionState::Paused)
     }
+
+    /// Combine sessions into a new, stopped session with a fresh ID
+    ///
+    /// Commands and annotations are interleaved by timestamp and commands are numbered
+    /// again from 1. The merged session spans from the earliest start to the latest stop;
+    /// its duration counts time covered by overlapping sessions only once.
+    pub fn merge(sessions: &[Session]) -> Result<Session> {
+        if sessions.len() < 2 {
+            return Err(anyhow!("Merging needs at least two sessions"));
+        }
+        if let Some((index, session)) = sessions.iter().enumerate()
+            .find(|(index, session)| sessions[..*index].iter().any(|earlier| earlier.id == session.id))
+        {
+            return Err(anyhow!("Session '{}' is listed more than once (position {})", session.id, index + 1));
+        }
+
+        let description = sessions.iter().map(|session| session.description.as_str()).collect::<Vec<_>>().join(" + ");
+        let mut merged = Session::new(description, None)?;
+
+        let mut commands: Vec<CommandEntry> = sessions.iter().flat_map(|session| session.commands.iter().cloned()).collect();
+        commands.sort_by_key(|command| command.timestamp);
+        for command in &mut commands {
+            command.step = None;
+        }
+        merged.commands = commands;
+        merged.number_steps();
+
+        let mut annotations: Vec<Annotation> = Vec::new();
+        for annotation in sessions.iter().flat_map(|session| &session.annotations) {
+            if !annotations.iter().any(|existing| existing.id == annotation.id) {
+                annotations.push(annotation.clone());
+            }
+        }
+        annotations.sort_by_key(|annotation| annotation.timestamp);
+        merged.annotations = annotations;
+
+        // Each session falls back to its first and last activity when it has no start or stop time
+        let mut spans: Vec<(DateTime<Utc>, DateTime<Utc>)> = sessions.iter().map(|session| {
+            let start = session.started_at.unwrap_or(session.created_at);
+            let end = session.stopped_at.unwrap_or(session.updated_at).max(start);
+            (start, end)
+        }).collect();
+        spans.sort();
+        merged.started_at = spans.first().map(|(start, _)| *start);
+        merged.stopped_at = spans.iter().map(|(_, end)| *end).max();
+
+        let mut covered = 0;
+        let mut current: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+        for (start, end) in spans {
+            current = match current {
+                Some((current_start, current_end)) if start <= current_end => Some((current_start, current_end.max(end))),
+                Some((current_start, current_end)) => {
+                    covered += (current_end - current_start).num_seconds();
+                    Some((start, end))
+                }
+                None => Some((start, end)),
+            };
+        }
+        if let Some((start, end)) = current {
+            covered += (end - start).num_seconds();
+        }
+
+        merged.stats = SessionStats {
+            total_commands: sessions.iter().map(|session| session.stats.total_commands).sum(),
+            successful_commands: sessions.iter().map(|session| session.stats.successful_commands).sum(),
+            failed_commands: sessions.iter().map(|session| session.stats.failed_commands).sum(),
+            terminated_commands: sessions.iter().map(|session| session.stats.terminated_commands).sum(),
+            total_annotations: merged.annotations.len(),
+            duration_seconds: Some(covered.max(0) as u64),
+            pause_resume_count: sessions.iter().map(|session| session.stats.pause_resume_count).sum(),
+        };
+
+        // Settings, environment and the like come from the session that started first
+        let first = sessions.iter().min_by_key(|session| session.started_at.unwrap_or(session.created_at))
+            .expect("at least two sessions");
+        merged.metadata = SessionMetadata {
+            tags: Vec::new(),
+            monitor_pid: None,
+            pinned: false,
+            ..first.metadata.clone()
+        };
+        for tag in sessions.iter().flat_map(|session| &session.metadata.tags) {
+            if !merged.metadata.tags.contains(tag) {
+                merged.metadata.tags.push(tag.clone());
+            }
+        }
+
+        merged.state = SessionState::Stopped;
+        merged.events.push(SessionEvent {
+            id: Uuid::new_v4().to_string(),
+            event_type: SessionEventType::SessionStopped,
+            timestamp: merged.created_at,
+            details: Some(format!(
+                "Merged from sessions: {}",
+                sessions.iter().map(|session| session.id.as_str()).collect::<Vec<_>>().join(", ")
+            )),
+        });
+        Ok(merged)
+    }
 }
 
 /// Session manager handles multiple sessions and persistence
@@ -385,6 +825,12 @@ pub struct SessionManager {
     last_auto_save: Option<SystemTime>,
     /// Maximum number of backups to keep per session
     max_backups: usize,
+    /// Number of captured changes not yet written to disk
+    pending_changes: usize,
+    /// Number of unsaved changes that forces a save regardless of the interval
+    max_pending_changes: usize,
+    /// Where output too large for the session file goes, if anywhere
+    output_spill: Option<OutputSpill>,
 }
 
 impl SessionManager {
@@ -400,12 +846,20 @@ impl SessionManager {
             sessions_dir,
             backups_dir,
             session_cache: HashMap::new(),
-            auto_save_interval: 30, // Auto-save every 30 seconds
+            auto_save_interval: 5, // Save captured commands at most every 5 seconds
             last_auto_save: None,
             max_backups: 5, // Keep 5 backups per session
+            pending_changes: 0,
+            max_pending_changes: 20, // ...or after 20 unsaved commands
+            output_spill: None,
         })
     }
 
+    /// Move output larger than the spill threshold of commands captured from now on to sidecar files
+    pub fn set_output_spill(&mut self, spill: Option<OutputSpill>) {
+        self.output_spill = spill;
+    }
+
     /// Get the directory where sessions are stored
     pub fn get_sessions_directory() -> Result<PathBuf> {
         let home = std::env::var("HOME")
@@ -427,7 +881,7 @@ impl SessionManager {
     /// Start a new session
     pub fn start_session(&mut self, description: String, output_file: Option<PathBuf>) -> Result<String> {
         if self.current_session.is_some() {
-            return Err(anyhow!("A session is already active. Stop the current session first."));
+            return Err(SessionError::AlreadyActive.into());
         }
 
         let session = Session::new(description, output_file)?;
@@ -455,8 +909,19 @@ impl SessionManager {
 
     /// Stop the current session
     pub fn stop_session(&mut self) -> Result<Option<Session>> {
+        // Check first, so a session that can't be stopped stays current
+        if let Some(session) = &self.current_session {
+            session.state.check_transition(&SessionState::Stopped)?;
+        }
         if let Some(mut session) = self.current_session.take() {
             session.stop()?;
+            let snapshot_file = self.file_snapshot_path(&session.id);
+            if let Some(tracking) = &mut session.metadata.file_tracking
+                && let Err(e) = tracking.finish(&session.metadata.working_directory, &snapshot_file)
+            {
+                eprintln!("Warning: Failed to compare tracked files: {}", e);
+            }
+            self.record_state_change(&session);
             self.save_session(&session)?;
             Ok(Some(session))
         } else {
@@ -470,10 +935,11 @@ impl SessionManager {
             session.pause()?;
             // Clone the session to avoid borrowing issues
             let session_clone = session.clone();
+            self.record_state_change(&session_clone);
             self.save_session(&session_clone)?;
             Ok(())
         } else {
-            Err(anyhow!("No active session to pause"))
+            Err(SessionError::NoActiveSession.into())
         }
     }
 
@@ -483,10 +949,11 @@ impl SessionManager {
             session.resume()?;
             // Clone the session to avoid borrowing issues
             let session_clone = session.clone();
+            self.record_state_change(&session_clone);
             self.save_session(&session_clone)?;
             Ok(())
         } else {
-            Err(anyhow!("No active session to resume"))
+            Err(SessionError::NoActiveSession.into())
         }
     }
 
@@ -496,28 +963,92 @@ impl SessionManager {
             let annotation_id = session.add_annotation(text, annotation_type);
             // Clone the session to avoid borrowing issues
             let session_clone = session.clone();
+            if let Some(annotation) = session_clone.annotations.last() {
+                self.journal(&session_clone.id).append(&JournalEntry::AnnotationAdded {
+                    position: session_clone.annotations.len() - 1,
+                    annotation: annotation.clone(),
+                })?;
+            }
             self.save_session(&session_clone)?;
             Ok(annotation_id)
         } else {
-            Err(anyhow!("No active session for annotation"))
+            Err(SessionError::NoActiveSession.into())
         }
     }
 
+    /// Replace the text of an annotation in the current session and save it
+    pub fn update_annotation(&mut self, annotation_id: &str, text: String) -> Result<()> {
+        let session = self.current_session.as_mut().ok_or(SessionError::NoActiveSession)?;
+        session.update_annotation(annotation_id, text)?;
+        let session_clone = session.clone();
+        self.save_session(&session_clone)
+    }
+
+    /// Remove an annotation from the current session and save it, returning the removed annotation
+    pub fn remove_annotation(&mut self, annotation_id: &str) -> Result<Annotation> {
+        let session = self.current_session.as_mut().ok_or(SessionError::NoActiveSession)?;
+        let annotation = session.remove_annotation(annotation_id)?;
+        let session_clone = session.clone();
+        self.save_session(&session_clone)?;
+        Ok(annotation)
+    }
+
     /// Add command to current session
     pub fn add_command(&mut self, command: CommandEntry) -> Result<()> {
         if let Some(session) = &mut self.current_session {
             if session.state.is_active() {
                 session.add_command(command);
-                // Clone the session to avoid borrowing issues
-                let session_clone = session.clone();
-                self.save_session(&session_clone)?;
+                let session_id = session.id.clone();
+                let position = session.commands.len() - 1;
+
+                // Spilled once the command has its step, which names the sidecar file
+                if let Some(spill) = &self.output_spill
+                    && let Err(e) = spill.spill(&session_id, &mut session.commands[position])
+                {
+                    eprintln!("⚠️  Warning: Could not move large output to {}: {}", spill.directory.display(), e);
+                }
+                let entry = JournalEntry::CommandAdded {
+                    position,
+                    command: Box::new(session.commands[position].clone()),
+                };
+
+                // The journal append is cheap and survives a crash before the next save
+                self.journal(&session_id).append(&entry)?;
+                self.pending_changes += 1;
+
+                // Throttle writes: busy sessions would otherwise rewrite the whole
+                // session file for every captured command
+                if self.pending_changes >= self.max_pending_changes || self.should_auto_save() {
+                    self.flush()?;
+                }
             }
             Ok(())
         } else {
-            Err(anyhow!("No active session for command"))
+            Err(SessionError::NoActiveSession.into())
         }
     }
 
+    /// Record the exit code of a finished background job on the latest matching backgrounded command
+    ///
+    /// Returns false when no captured command is waiting for this job's exit code.
+    pub fn complete_background_command(&mut self, command: &str, exit_code: i32) -> Result<bool> {
+        let Some(session) = &mut self.current_session else {
+            return Err(SessionError::NoActiveSession.into());
+        };
+        let position = session.commands.iter().rposition(|entry| {
+            entry.backgrounded && entry.exit_code.is_none() && entry.command == command
+        });
+        let Some(position) = position else {
+            return Ok(false);
+        };
+
+        session.finish_background_command(position, exit_code);
+        let session_id = session.id.clone();
+        self.journal(&session_id).append(&JournalEntry::BackgroundJobFinished { position, exit_code })?;
+        self.pending_changes += 1;
+        Ok(true)
+    }
+
     /// Get current session
     pub fn get_current_session(&self) -> Option<&Session> {
         self.current_session.as_ref()
@@ -533,6 +1064,18 @@ impl SessionManager {
         self.current_session = Some(session);
     }
 
+    /// Read the current session again from its file, dropping the cached copy
+    ///
+    /// Used after another process, such as a background monitor, may have saved it.
+    pub fn reload_current_session(&mut self) -> Result<()> {
+        let Some(session_id) = self.current_session.as_ref().map(|session| session.id.clone()) else {
+            return Ok(());
+        };
+        self.session_cache.remove(&session_id);
+        self.current_session = Some(self.load_session(&session_id)?);
+        Ok(())
+    }
+
     /// Clear the current session (used for interactive session handling)
     pub fn clear_current_session(&mut self) {
         self.current_session = None;
@@ -548,11 +1091,15 @@ impl SessionManager {
         // Load from file
         let session_file = self.sessions_dir.join(format!("{}.json", session_id));
         if !session_file.exists() {
-            return Err(anyhow!("Session not found: {}", session_id));
+            return Err(SessionError::NotFound(session_id.to_string()).into());
         }
 
         let content = fs::read_to_string(&session_file)?;
-        let session: Session = serde_json::from_str(&content)?;
+        let mut session: Session = serde_json::from_str(&content)?;
+        
+        // Apply changes recorded after the last consolidated save
+        self.journal(session_id).replay(&mut session)?;
+        session.number_steps();
         
         // Add to cache
         self.session_cache.insert(session_id.to_string(), session.clone());
@@ -560,6 +1107,22 @@ impl SessionManager {
         Ok(session)
     }
 
+    /// Journal of changes not yet consolidated into the session file
+    fn journal(&self, session_id: &str) -> SessionJournal {
+        SessionJournal::for_session(&self.sessions_dir, session_id)
+    }
+
+    /// Record a state transition in the journal before the session file is rewritten
+    fn record_state_change(&self, session: &Session) {
+        let entry = JournalEntry::StateChanged {
+            state: session.state.clone(),
+            timestamp: session.updated_at,
+        };
+        if let Err(e) = self.journal(&session.id).append(&entry) {
+            eprintln!("Warning: Failed to write session journal: {}", e);
+        }
+    }
+
     /// Save a session to disk with backup support
     pub fn save_session(&mut self, session: &Session) -> Result<()> {
         let session_file = self.sessions_dir.join(format!("{}.json", session.id));
@@ -577,15 +1140,119 @@ impl SessionManager {
         // Atomic rename to final location
         fs::rename(&temp_file, &session_file)?;
         
+        // Everything in the journal is now part of the session file
+        self.journal(&session.id).clear()?;
+        
         // Update cache
         self.session_cache.insert(session.id.clone(), session.clone());
         
         // Update auto-save timestamp
         self.last_auto_save = Some(SystemTime::now());
+        if self.current_session.as_ref().is_some_and(|current| current.id == session.id) {
+            self.pending_changes = 0;
+        }
         
         Ok(())
     }
 
+    /// File recording how much of a session has been documented
+    fn generation_marker_path(&self, session_id: &str) -> PathBuf {
+        self.sessions_dir.join(format!("{}.generated", session_id))
+    }
+
+    /// File holding the tracked files as they were when a `--track-files` session started
+    pub fn file_snapshot_path(&self, session_id: &str) -> PathBuf {
+        self.sessions_dir.join(format!("{}.files", session_id))
+    }
+
+    /// How much of a session the last `generate` documented, if it was ever documented
+    pub fn last_generation(&self, session_id: &str) -> Result<Option<GenerationMarker>> {
+        let path = self.generation_marker_path(session_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Remember how much of a session has been documented
+    pub fn record_generation(&mut self, session_id: &str, marker: GenerationMarker) -> Result<()> {
+        fs::write(self.generation_marker_path(session_id), serde_json::to_string_pretty(&marker)?)?;
+        Ok(())
+    }
+
+    /// Apply a change to a stored session and save it if `change` reports that it did anything
+    ///
+    /// The in-memory copy is used when it is the session being changed, so changes still
+    /// waiting in the journal are not lost when the file is rewritten.
+    fn update_session<F>(&mut self, session_id: &str, change: F) -> Result<bool>
+    where
+        F: FnOnce(&mut Session) -> Result<bool>,
+    {
+        let mut session = match self.current_session.as_ref().filter(|current| current.id == session_id) {
+            Some(current) => current.clone(),
+            None => self.load_session(session_id)?,
+        };
+        if !change(&mut session)? {
+            return Ok(false);
+        }
+        self.save_session(&session)?;
+        if let Some(current) = self.current_session.as_mut().filter(|current| current.id == session_id) {
+            *current = session;
+        }
+        Ok(true)
+    }
+
+    /// Add or remove a tag on a command of a stored session, see `Session::set_command_tag`
+    pub fn set_command_tag(&mut self, session_id: &str, number: usize, tag: &str, tagged: bool) -> Result<bool> {
+        self.update_session(session_id, |session| session.set_command_tag(number, tag, tagged))
+    }
+
+    /// Add or remove a tag on a stored session, returning whether that changed anything
+    pub fn set_session_tag(&mut self, session_id: &str, tag: &str, tagged: bool) -> Result<bool> {
+        self.update_session(session_id, |session| session.set_tag(tag, tagged))
+    }
+
+    /// Pin or unpin a stored session, returning whether that changed anything
+    pub fn set_pinned(&mut self, session_id: &str, pinned: bool) -> Result<bool> {
+        self.update_session(session_id, |session| {
+            if session.metadata.pinned == pinned {
+                return Ok(false);
+            }
+            session.metadata.pinned = pinned;
+            Ok(true)
+        })
+    }
+
+    /// Save hand-edited session JSON in place of a stored session
+    ///
+    /// The text must deserialize as a session (the format `docpilot schema` describes) with
+    /// the same ID and consistent timestamps. Stats are recounted from the edited commands and
+    /// annotations, and the previous file is backed up before it is replaced.
+    pub fn save_edited_session(&mut self, session_id: &str, json: &str) -> Result<Session> {
+        let mut session: Session = serde_json::from_str(json)
+            .map_err(|e| anyhow!("Edited session is not valid: {}", e))?;
+
+        if session.id != session_id {
+            return Err(anyhow!("The session ID cannot be changed (expected {}, found {})", session_id, session.id));
+        }
+        if session.description.trim().is_empty() {
+            return Err(anyhow!("The session description cannot be empty"));
+        }
+        if let (Some(started_at), Some(stopped_at)) = (session.started_at, session.stopped_at)
+            && stopped_at < started_at
+        {
+            return Err(anyhow!("stopped_at ({}) is before started_at ({})", stopped_at, started_at));
+        }
+
+        session.number_steps();
+        session.recount_command_stats();
+        session.stats.total_annotations = session.annotations.len();
+        session.updated_at = Utc::now();
+        self.save_session(&session)?;
+        Ok(session)
+    }
+
     /// Create a backup of an existing session
     fn create_backup(&self, session_id: &str) -> Result<()> {
         let session_file = self.sessions_dir.join(format!("{}.json", session_id));
@@ -665,6 +1332,23 @@ impl SessionManager {
         Ok(sessions)
     }
 
+    /// List session IDs with the most recently modified session file first
+    ///
+    /// Sessions whose file can't be inspected are left out.
+    pub fn list_sessions_sorted(&self) -> Result<Vec<String>> {
+        let mut session_with_times: Vec<_> = self.list_sessions()?
+            .into_iter()
+            .filter_map(|session_id| {
+                let session_file = self.sessions_dir.join(format!("{}.json", session_id));
+                let modified = fs::metadata(&session_file).ok()?.modified().ok()?;
+                Some((session_id, modified))
+            })
+            .collect();
+
+        session_with_times.sort_by(|a, b| b.1.cmp(&a.1));
+        Ok(session_with_times.into_iter().map(|(session_id, _)| session_id).collect())
+    }
+
     /// Delete a session
     pub fn delete_session(&mut self, session_id: &str) -> Result<()> {
         let session_file = self.sessions_dir.join(format!("{}.json", session_id));
@@ -672,6 +1356,15 @@ impl SessionManager {
         if session_file.exists() {
             fs::remove_file(&session_file)?;
         }
+        self.journal(session_id).clear()?;
+        let generation_marker = self.generation_marker_path(session_id);
+        if generation_marker.exists() {
+            fs::remove_file(&generation_marker)?;
+        }
+        let file_snapshot = self.file_snapshot_path(session_id);
+        if file_snapshot.exists() {
+            fs::remove_file(&file_snapshot)?;
+        }
         
         // Remove from cache
         self.session_cache.remove(session_id);
@@ -686,6 +1379,17 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Find the most recently updated active or paused session without recovery or warnings
+    ///
+    /// Meant for quick, quiet checks such as a shell prompt; sessions that fail to load are skipped.
+    pub fn find_active_session(&mut self) -> Option<Session> {
+        self.list_sessions().ok()?
+            .iter()
+            .filter_map(|session_id| self.load_session(session_id).ok())
+            .filter(|session| session.state.is_active() || session.state.is_paused())
+            .max_by_key(|session| session.updated_at)
+    }
+
     /// Recover from an interrupted session with enhanced error handling
     pub fn recover_session(&mut self) -> Result<Option<String>> {
         let sessions = self.list_sessions()?;
@@ -775,8 +1479,9 @@ impl SessionManager {
             match fs::read_to_string(&backup_path) {
                 Ok(content) => {
                     match serde_json::from_str::<Session>(&content) {
-                        Ok(session) => {
+                        Ok(mut session) => {
                             eprintln!("Successfully recovered session from backup: {}", backup_path.display());
+                            let _ = self.journal(session_id).replay(&mut session);
                             return Ok(session);
                         }
                         Err(e) => {
@@ -873,6 +1578,23 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Save the current session if it has unsaved changes
+    pub fn flush(&mut self) -> Result<bool> {
+        if self.pending_changes == 0 {
+            return Ok(false);
+        }
+        self.force_save()?;
+        Ok(true)
+    }
+
+    /// Save unsaved changes once the auto-save interval has elapsed
+    pub fn flush_if_due(&mut self) -> Result<bool> {
+        if self.pending_changes > 0 && self.should_auto_save() {
+            return self.flush();
+        }
+        Ok(false)
+    }
+
     /// Get session backup information
     pub fn get_backup_info(&self, session_id: &str) -> Result<Vec<(PathBuf, SystemTime)>> {
         let mut backups = Vec::new();
@@ -917,10 +1639,10 @@ impl SessionManager {
                     if let Ok(metadata) = entry.metadata() {
                         if let Ok(modified) = metadata.modified() {
                             if modified < cutoff_time {
-                                // Check if this is a stopped session before deleting
+                                // Only stopped sessions that weren't pinned are deleted
                                 if let Ok(content) = fs::read_to_string(&path) {
                                     if let Ok(session) = serde_json::from_str::<Session>(&content) {
-                                        if session.state.is_stopped() {
+                                        if session.state.is_stopped() && !session.metadata.pinned {
                                             if let Err(e) = fs::remove_file(&path) {
                                                 eprintln!("Warning: Failed to remove old session {}: {}", path.display(), e);
                                             } else {
@@ -973,8 +1695,23 @@ impl SessionManager {
                 
                 if path.extension().and_then(|s| s.to_str()) == Some("json") {
                     stats.session_count += 1;
-                    if let Ok(metadata) = entry.metadata() {
-                        stats.total_size += metadata.len();
+                    let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                    stats.total_size += size;
+                    stats.session_size += size;
+
+                    // Only the ID and creation time are needed, so don't read the commands
+                    let Some(times) = fs::read_to_string(&path).ok()
+                        .and_then(|content| serde_json::from_str::<SessionTimes>(&content).ok()) else {
+                        continue;
+                    };
+                    if stats.largest_session.as_ref().is_none_or(|(_, largest)| size > *largest) {
+                        stats.largest_session = Some((times.id.clone(), size));
+                    }
+                    if stats.oldest_session.as_ref().is_none_or(|(_, oldest)| times.created_at < *oldest) {
+                        stats.oldest_session = Some((times.id.clone(), times.created_at));
+                    }
+                    if stats.newest_session.as_ref().is_none_or(|(_, newest)| times.created_at > *newest) {
+                        stats.newest_session = Some((times.id, times.created_at));
                     }
                 }
             }
@@ -1003,7 +1740,7 @@ impl SessionManager {
     pub fn export_session(&self, session_id: &str, export_path: &Path) -> Result<()> {
         let session_file = self.sessions_dir.join(format!("{}.json", session_id));
         if !session_file.exists() {
-            return Err(anyhow!("Session not found: {}", session_id));
+            return Err(SessionError::NotFound(session_id.to_string()).into());
         }
         
         // Create parent directories if they don't exist
@@ -1043,6 +1780,21 @@ pub struct StorageStats {
     pub backup_count: usize,
     pub total_size: u64,
     pub backup_size: u64,
+    /// Combined size of the session files
+    pub session_size: u64,
+    /// ID and creation time of the session created first
+    pub oldest_session: Option<(String, DateTime<Utc>)>,
+    /// ID and creation time of the session created last
+    pub newest_session: Option<(String, DateTime<Utc>)>,
+    /// ID and file size of the biggest session, e.g. one a forgotten monitor kept recording
+    pub largest_session: Option<(String, u64)>,
+}
+
+/// The fields of a session file that storage statistics need
+#[derive(Deserialize)]
+struct SessionTimes {
+    id: String,
+    created_at: DateTime<Utc>,
 }
 
 impl Default for SessionManager {
@@ -1071,11 +1823,97 @@ mod tests {
             auto_save_interval: 30,
             last_auto_save: None,
             max_backups: 5,
+            pending_changes: 0,
+            max_pending_changes: 20,
+            output_spill: None,
         };
         
         (manager, temp_dir)
     }
 
+    #[test]
+    fn test_save_edited_session_validates_before_replacing() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+        let session = Session::new("Original".to_string(), None).unwrap();
+        manager.save_session(&session).unwrap();
+
+        assert!(manager.save_edited_session(&session.id, "{ not json").is_err());
+        let mut renamed = serde_json::to_value(&session).unwrap();
+        renamed["id"] = serde_json::Value::String("other".to_string());
+        assert!(manager.save_edited_session(&session.id, &renamed.to_string()).is_err());
+        manager.session_cache.clear();
+        assert_eq!(manager.load_session(&session.id).unwrap().description, "Original");
+
+        let mut edited = session.clone();
+        edited.description = "Fixed description".to_string();
+        edited.stats.total_commands = 42;
+        let saved = manager.save_edited_session(&session.id, &serde_json::to_string(&edited).unwrap()).unwrap();
+        assert_eq!(saved.stats.total_commands, 0);
+        manager.session_cache.clear();
+        assert_eq!(manager.load_session(&session.id).unwrap().description, "Fixed description");
+    }
+
+    #[test]
+    fn test_incremental_generation_marker() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+        let session_id = manager.start_session("Incremental".to_string(), None).unwrap();
+        let command = |command: &str| CommandEntry {
+            command: command.to_string(),
+            exit_code: Some(0),
+            working_directory: "/tmp".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        };
+
+        manager.add_command(command("git clone repo")).unwrap();
+        manager.add_annotation("Cloned".to_string(), AnnotationType::Note).unwrap();
+        let session = manager.get_current_session().unwrap().clone();
+        assert_eq!(session.since_last_generate(None).commands.len(), 1);
+
+        // Recorded by `generate` in its own process while the monitor keeps recording
+        let mut generate = SessionManager {
+            current_session: None,
+            sessions_dir: manager.sessions_dir.clone(),
+            backups_dir: manager.backups_dir.clone(),
+            session_cache: HashMap::new(),
+            auto_save_interval: 30,
+            last_auto_save: None,
+            max_backups: 5,
+            pending_changes: 0,
+            max_pending_changes: 20,
+            output_spill: None,
+        };
+        generate.record_generation(&session_id, session.generation_marker()).unwrap();
+        manager.add_command(command("cargo build")).unwrap();
+        manager.add_command(command("cargo test")).unwrap();
+        manager.force_save().unwrap();
+
+        let marker = generate.last_generation(&session_id).unwrap();
+        let since = manager.get_current_session().unwrap().since_last_generate(marker.as_ref());
+        let commands: Vec<&str> = since.commands.iter().map(|c| c.command.as_str()).collect();
+        assert_eq!(commands, vec!["cargo build", "cargo test"]);
+        assert!(since.annotations.is_empty());
+        assert_eq!(since.stats.total_commands, 2);
+        assert_eq!(since.stats.total_annotations, 0);
+
+        // Removing an earlier command doesn't move the marker
+        let mut edited = manager.get_current_session().unwrap().clone();
+        edited.commands.remove(0);
+        let since = edited.since_last_generate(marker.as_ref());
+        assert_eq!(since.commands.len(), 2);
+
+        // Nor does a later command with the same text in the same clock tick as the documented one
+        let mut repeated = command("git clone repo");
+        repeated.timestamp = session.commands[0].timestamp;
+        edited.add_command(repeated);
+        let since = edited.since_last_generate(marker.as_ref());
+        assert_eq!(since.commands.len(), 3);
+        assert_eq!(since.commands[2].step, Some(4));
+
+        manager.delete_session(&session_id).unwrap();
+        assert!(generate.last_generation(&session_id).unwrap().is_none());
+    }
+
     #[test]
     fn test_session_creation() {
         let session = Session::new(
@@ -1146,6 +1984,234 @@ mod tests {
         assert!(stopped.state.is_stopped());
     }
 
+    #[test]
+    fn test_session_errors_are_typed() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+
+        let error = manager.pause_session().unwrap_err();
+        assert_eq!(error.downcast_ref::<SessionError>(), Some(&SessionError::NoActiveSession));
+
+        manager.start_session("Test session".to_string(), None).unwrap();
+        let error = manager.resume_session().unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<SessionError>(),
+            Some(&SessionError::InvalidTransition { from: SessionState::Active, to: SessionState::Active })
+        );
+        assert_eq!(error.to_string(), "Cannot resume: session is active");
+
+        let error = manager.start_session("Another session".to_string(), None).unwrap_err();
+        assert_eq!(error.downcast_ref::<SessionError>(), Some(&SessionError::AlreadyActive));
+
+        let error = manager.load_session("missing").unwrap_err();
+        assert_eq!(error.downcast_ref::<SessionError>(), Some(&SessionError::NotFound("missing".to_string())));
+    }
+
+    #[test]
+    fn test_invalid_state_transitions_are_rejected() {
+        let failed = SessionState::Error("shell hook failed".to_string());
+        let states = [SessionState::Active, SessionState::Paused, SessionState::Stopped, failed.clone()];
+        let allowed = [
+            (SessionState::Active, SessionState::Paused),
+            (SessionState::Active, SessionState::Stopped),
+            (SessionState::Active, failed.clone()),
+            (SessionState::Paused, SessionState::Active),
+            (SessionState::Paused, SessionState::Stopped),
+            (SessionState::Paused, failed.clone()),
+        ];
+        for from in &states {
+            for to in &states {
+                let expected = allowed.iter().any(|(a, b)| a == from && b == to);
+                assert_eq!(from.can_transition_to(to), expected, "{:?} -> {:?}", from, to);
+            }
+        }
+
+        // pause, resume and stop reject every state they can't leave, and leave it unchanged
+        let attempts: [(fn(&mut Session) -> Result<()>, SessionState); 3] = [
+            (Session::pause, SessionState::Paused),
+            (Session::resume, SessionState::Active),
+            (Session::stop, SessionState::Stopped),
+        ];
+        for from in &states {
+            for (transition, to) in &attempts {
+                if from.can_transition_to(to) {
+                    continue;
+                }
+                let mut session = Session::new("Test session".to_string(), None).unwrap();
+                session.state = from.clone();
+                let error = transition(&mut session).unwrap_err();
+                assert_eq!(
+                    error.downcast_ref::<SessionError>(),
+                    Some(&SessionError::InvalidTransition { from: from.clone(), to: to.clone() })
+                );
+                assert_eq!(&session.state, from);
+            }
+        }
+
+        let mut session = Session::new("Test session".to_string(), None).unwrap();
+        session.state = failed;
+        assert_eq!(session.resume().unwrap_err().to_string(), "Cannot resume: session is in Error state (shell hook failed)");
+        assert_eq!(session.pause().unwrap_err().to_string(), "Cannot pause: session is in Error state (shell hook failed)");
+        session.state = SessionState::Stopped;
+        assert_eq!(session.stop().unwrap_err().to_string(), "Cannot stop: session is already stopped");
+        session.state = SessionState::Paused;
+        assert_eq!(session.pause().unwrap_err().to_string(), "Cannot pause: session is paused");
+    }
+
+    #[test]
+    fn test_stop_session_keeps_a_session_it_cannot_stop() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+        manager.start_session("Test session".to_string(), None).unwrap();
+        manager.current_session.as_mut().unwrap().state = SessionState::Error("capture failed".to_string());
+
+        assert!(manager.stop_session().is_err());
+        assert!(manager.get_current_session().is_some());
+    }
+
+    #[test]
+    fn test_set_command_tag() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+        let session_id = manager.start_session("Test session".to_string(), None).unwrap();
+        for command in ["psql -f schema.sql", "git status"] {
+            manager.add_command(CommandEntry {
+                command: command.to_string(),
+                exit_code: Some(0),
+                working_directory: "/tmp".to_string(),
+                shell: "bash".to_string(),
+                ..Default::default()
+            }).unwrap();
+        }
+
+        assert!(manager.set_command_tag(&session_id, 1, " database ", true).unwrap());
+        assert!(!manager.set_command_tag(&session_id, 1, "database", true).unwrap());
+        assert!(manager.set_command_tag(&session_id, 1, "schema", true).unwrap());
+        assert_eq!(manager.get_current_session().unwrap().commands[0].tags, vec!["database", "schema"]);
+
+        assert!(manager.set_command_tag(&session_id, 1, "database", false).unwrap());
+        assert!(!manager.set_command_tag(&session_id, 2, "database", false).unwrap());
+        assert!(manager.set_command_tag(&session_id, 3, "database", true).is_err());
+        assert!(manager.set_command_tag(&session_id, 0, "database", true).is_err());
+        assert!(manager.set_command_tag(&session_id, 2, "  ", true).is_err());
+
+        // Tags are saved with the session, including commands that were only in the journal
+        manager.session_cache.clear();
+        let stored = manager.load_session(&session_id).unwrap();
+        assert_eq!(stored.commands.len(), 2);
+        assert_eq!(stored.commands[0].tags, vec!["schema"]);
+    }
+
+    #[test]
+    fn test_set_session_tag() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+        let session_id = manager.start_session("Test session".to_string(), None).unwrap();
+
+        assert!(manager.set_session_tag(&session_id, " billing ", true).unwrap());
+        assert!(!manager.set_session_tag(&session_id, "billing", true).unwrap());
+        assert!(manager.set_session_tag(&session_id, "database", true).unwrap());
+        assert!(manager.set_session_tag(&session_id, "", true).is_err());
+        assert_eq!(manager.get_current_session().unwrap().metadata.tags, vec!["billing", "database"]);
+
+        assert!(manager.set_session_tag(&session_id, "billing", false).unwrap());
+        assert!(!manager.set_session_tag(&session_id, "billing", false).unwrap());
+
+        manager.session_cache.clear();
+        let stored = manager.load_session(&session_id).unwrap();
+        assert!(stored.has_tag("database"));
+        assert!(!stored.has_tag("billing"));
+    }
+
+    #[test]
+    fn test_command_steps_survive_removed_commands() {
+        let command = |text: &str| CommandEntry {
+            command: text.to_string(),
+            exit_code: Some(0),
+            working_directory: "/tmp".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        };
+
+        // Sessions recorded before steps existed are numbered by position
+        let mut session = Session::new("Steps".to_string(), None).unwrap();
+        session.commands = vec![command("make"), command("make test")];
+        session.add_command(command("make install"));
+        assert_eq!(session.commands[2].step, Some(3));
+        session.number_steps();
+        let steps: Vec<_> = session.commands.iter().map(|cmd| cmd.step).collect();
+        assert_eq!(steps, vec![Some(1), Some(2), Some(3)]);
+
+        // Removing a command leaves the others' steps alone, and new ones continue after the last
+        session.commands.remove(1);
+        session.add_command(command("make clean"));
+        let steps: Vec<_> = session.commands.iter().map(|cmd| cmd.step).collect();
+        assert_eq!(steps, vec![Some(1), Some(3), Some(4)]);
+
+        assert!(session.set_command_tag(3, "install", true).unwrap());
+        assert_eq!(session.commands[1].tags, vec!["install"]);
+        assert!(session.set_command_tag(2, "install", true).is_err());
+    }
+
+    #[test]
+    fn test_merge_sessions() {
+        let start = Utc::now() - chrono::Duration::hours(3);
+        let at = |minutes: i64| start + chrono::Duration::minutes(minutes);
+        let command = |text: &str, minutes: i64, exit_code: i32| CommandEntry {
+            command: text.to_string(),
+            timestamp: at(minutes),
+            exit_code: Some(exit_code),
+            working_directory: "/tmp".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        };
+
+        let mut first = Session::new("Day one".to_string(), None).unwrap();
+        first.add_command(command("git clone repo", 0, 0));
+        first.add_command(command("make", 50, 2));
+        first.add_annotation("Setting up".to_string(), AnnotationType::Note);
+        first.annotations[0].timestamp = at(10);
+        (first.started_at, first.stopped_at) = (Some(at(0)), Some(at(60)));
+
+        // Overlaps the first session by half an hour
+        let mut second = Session::new("Day two".to_string(), None).unwrap();
+        second.add_command(command("make install", 40, 0));
+        second.metadata.tags.push("build".to_string());
+        (second.started_at, second.stopped_at) = (Some(at(30)), Some(at(90)));
+
+        let merged = Session::merge(&[second.clone(), first.clone()]).unwrap();
+        assert_ne!(merged.id, first.id);
+        assert_eq!(merged.description, "Day two + Day one");
+        assert_eq!(merged.state, SessionState::Stopped);
+        let commands: Vec<_> = merged.commands.iter().map(|cmd| (cmd.command.as_str(), cmd.step)).collect();
+        assert_eq!(commands, vec![("git clone repo", Some(1)), ("make install", Some(2)), ("make", Some(3))]);
+        assert_eq!(merged.annotations.len(), 1);
+        assert_eq!((merged.started_at, merged.stopped_at), (Some(at(0)), Some(at(90))));
+        assert_eq!(merged.stats.total_commands, 3);
+        assert_eq!(merged.stats.successful_commands, 2);
+        assert_eq!(merged.stats.failed_commands, 1);
+        assert_eq!(merged.stats.total_annotations, 1);
+        assert_eq!(merged.stats.duration_seconds, Some(90 * 60));
+        assert_eq!(merged.metadata.tags, vec!["build"]);
+
+        assert!(Session::merge(std::slice::from_ref(&first)).is_err());
+        assert!(Session::merge(&[first.clone(), first]).is_err());
+    }
+
+    #[test]
+    fn test_find_active_session_skips_unreadable_sessions() {
+        let (mut manager, temp_dir) = create_test_session_manager();
+        assert!(manager.find_active_session().is_none());
+
+        let session_id = manager.start_session("Prompt status".to_string(), None).unwrap();
+        manager.force_save().unwrap();
+        std::fs::write(temp_dir.path().join("sessions").join("broken.json"), "{ not json").unwrap();
+
+        let mut fresh = SessionManager { current_session: None, session_cache: HashMap::new(), ..manager };
+        assert_eq!(fresh.find_active_session().map(|session| session.id), Some(session_id));
+
+        fresh.current_session = fresh.find_active_session();
+        fresh.stop_session().unwrap();
+        fresh.session_cache.clear();
+        assert!(fresh.find_active_session().is_none());
+    }
+
     #[test]
     fn test_backup_and_recovery() {
         let (mut manager, _temp_dir) = create_test_session_manager();
@@ -1212,6 +2278,93 @@ mod tests {
         assert!(!manager.should_auto_save());
     }
 
+    #[test]
+    fn test_command_save_throttling() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+        manager.max_pending_changes = 3;
+
+        let session_id = manager.start_session(
+            "Throttling test".to_string(),
+            None
+        ).expect("Failed to start session");
+        let session_file = manager.sessions_dir.join(format!("{}.json", session_id));
+        let saved_command_count = |path: &PathBuf| {
+            let content = std::fs::read_to_string(path).expect("Failed to read session file");
+            let session: Session = serde_json::from_str(&content).expect("Failed to parse session file");
+            session.commands.len()
+        };
+
+        let command = |text: &str| crate::terminal::CommandEntry {
+            command: text.to_string(),
+            working_directory: "/tmp".to_string(),
+            exit_code: Some(0),
+            shell: "bash".to_string(),
+            ..Default::default()
+        };
+
+        // Commands below the threshold stay in memory until the interval elapses
+        manager.add_command(command("ls")).expect("Failed to add command");
+        manager.add_command(command("pwd")).expect("Failed to add command");
+        assert_eq!(manager.pending_changes, 2);
+        assert_eq!(saved_command_count(&session_file), 0);
+        assert!(!manager.flush_if_due().expect("Failed to check pending changes"));
+
+        // Reaching the threshold forces a save
+        manager.add_command(command("whoami")).expect("Failed to add command");
+        assert_eq!(manager.pending_changes, 0);
+        assert_eq!(saved_command_count(&session_file), 3);
+
+        // An explicit flush writes whatever is pending and is a no-op otherwise
+        manager.add_command(command("date")).expect("Failed to add command");
+        assert!(manager.flush().expect("Failed to flush"));
+        assert!(!manager.flush().expect("Failed to flush"));
+        assert_eq!(saved_command_count(&session_file), 4);
+    }
+
+    #[test]
+    fn test_journal_replay_after_interrupted_capture() {
+        let (mut manager, temp_dir) = create_test_session_manager();
+
+        let session_id = manager.start_session(
+            "Journal test".to_string(),
+            None
+        ).expect("Failed to start session");
+        manager.add_annotation("Before the crash".to_string(), AnnotationType::Note)
+            .expect("Failed to add annotation");
+
+        for text in ["cargo build", "cargo test"] {
+            manager.add_command(crate::terminal::CommandEntry {
+                command: text.to_string(),
+                working_directory: "/tmp".to_string(),
+                exit_code: Some(0),
+                shell: "bash".to_string(),
+                ..Default::default()
+            }).expect("Failed to add command");
+        }
+
+        // Simulate a crash: the throttled commands only exist in the journal
+        let journal = SessionJournal::for_session(&manager.sessions_dir, &session_id);
+        assert_eq!(journal.entries().expect("Failed to read journal").len(), 2);
+        drop(manager);
+
+        let (mut recovered, _other_dir) = create_test_session_manager();
+        recovered.sessions_dir = temp_dir.path().join("sessions");
+        recovered.backups_dir = temp_dir.path().join("backups");
+
+        let session = recovered.load_session(&session_id).expect("Failed to load session");
+        assert_eq!(session.commands.len(), 2);
+        assert_eq!(session.stats.total_commands, 2);
+        assert_eq!(session.annotations.len(), 1);
+
+        // Consolidating the session clears the journal without duplicating entries
+        recovered.set_current_session(session);
+        recovered.stop_session().expect("Failed to stop session");
+        assert!(journal.entries().expect("Failed to read journal").is_empty());
+        let session = recovered.load_session(&session_id).expect("Failed to reload session");
+        assert_eq!(session.commands.len(), 2);
+        assert!(session.state.is_stopped());
+    }
+
     #[test]
     fn test_session_validation() {
         let (manager, _temp_dir) = create_test_session_manager();
@@ -1236,6 +2389,24 @@ mod tests {
         assert!(!manager.validate_session(&invalid_session));
     }
 
+    #[test]
+    fn test_list_sessions_sorted_by_modification_time() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+        let now = SystemTime::now();
+        let mut ids = Vec::new();
+        for (description, age_secs) in [("Older", 600), ("Newest", 10), ("Oldest", 3600)] {
+            let session = Session::new(description.to_string(), None).unwrap();
+            manager.save_session(&session).unwrap();
+            let file = fs::File::options().write(true)
+                .open(manager.sessions_dir.join(format!("{}.json", session.id))).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(age_secs)).unwrap();
+            ids.push(session.id);
+        }
+
+        let sorted = manager.list_sessions_sorted().unwrap();
+        assert_eq!(sorted, vec![ids[1].clone(), ids[0].clone(), ids[2].clone()]);
+    }
+
     #[test]
     fn test_storage_stats() {
         let (mut manager, _temp_dir) = create_test_session_manager();
@@ -1246,7 +2417,7 @@ mod tests {
         assert_eq!(stats.backup_count, 0);
 
         // Create a session
-        let _session_id = manager.start_session(
+        let session_id = manager.start_session(
             "Test session".to_string(),
             None
         ).expect("Failed to start session");
@@ -1255,6 +2426,19 @@ mod tests {
         let stats = manager.get_storage_stats().expect("Failed to get storage stats");
         assert_eq!(stats.session_count, 1);
         assert!(stats.total_size > 0);
+        assert_eq!(stats.session_size, stats.total_size);
+
+        // Oldest and newest go by creation time, not file order
+        let mut older = Session::new("Older session".to_string(), None).unwrap();
+        older.created_at = Utc::now() - chrono::Duration::days(30);
+        manager.save_session(&older).unwrap();
+        std::fs::write(manager.sessions_dir.join("broken.json"), "{ not json").unwrap();
+
+        let stats = manager.get_storage_stats().expect("Failed to get storage stats");
+        assert_eq!(stats.session_count, 3);
+        assert_eq!(stats.oldest_session.unwrap(), (older.id.clone(), older.created_at));
+        assert_eq!(stats.newest_session.unwrap().0, session_id);
+        assert_ne!(stats.largest_session.unwrap().0, "broken");
     }
 
     #[test]
@@ -1347,6 +2531,34 @@ mod tests {
         assert!(matches!(session.annotations[3].annotation_type, AnnotationType::Milestone));
     }
 
+    #[test]
+    fn test_edit_and_remove_annotations() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+        assert!(matches!(
+            manager.remove_annotation("missing").unwrap_err().downcast_ref::<SessionError>(),
+            Some(SessionError::NoActiveSession)
+        ));
+
+        let session_id = manager.start_session("Annotation edits".to_string(), None).unwrap();
+        let typo_id = manager.add_annotation("Conifguring nginx".to_string(), AnnotationType::Note).unwrap();
+        let warning_id = manager.add_annotation("Restart required".to_string(), AnnotationType::Warning).unwrap();
+
+        manager.update_annotation(&typo_id, "Configuring nginx".to_string()).unwrap();
+        let removed = manager.remove_annotation(&warning_id).unwrap();
+        assert_eq!(removed.text, "Restart required");
+        assert_eq!(
+            manager.update_annotation(&warning_id, "Gone".to_string()).unwrap_err().downcast_ref::<SessionError>(),
+            Some(&SessionError::AnnotationNotFound(warning_id.clone()))
+        );
+
+        // Both changes were persisted
+        manager.session_cache.clear();
+        let session = manager.load_session(&session_id).unwrap();
+        assert_eq!(session.annotations.len(), 1);
+        assert_eq!(session.annotations[0].text, "Configuring nginx");
+        assert_eq!(session.stats.total_annotations, 1);
+    }
+
     #[test]
     fn test_command_tracking_and_stats() {
         let (mut manager, _temp_dir) = create_test_session_manager();
@@ -1360,32 +2572,27 @@ mod tests {
         // Create test commands with different exit codes
         let successful_command = crate::terminal::CommandEntry {
             command: "ls -la".to_string(),
-            timestamp: chrono::Utc::now(),
             working_directory: "/tmp".to_string(),
             exit_code: Some(0),
             output: Some("file1\nfile2".to_string()),
-            error: None,
             shell: "bash".to_string(),
+            ..Default::default()
         };
 
         let failed_command = crate::terminal::CommandEntry {
             command: "cat nonexistent.txt".to_string(),
-            timestamp: chrono::Utc::now(),
             working_directory: "/tmp".to_string(),
             exit_code: Some(1),
-            output: None,
             error: Some("No such file or directory".to_string()),
             shell: "bash".to_string(),
+            ..Default::default()
         };
 
         let pending_command = crate::terminal::CommandEntry {
             command: "sleep 10".to_string(),
-            timestamp: chrono::Utc::now(),
             working_directory: "/tmp".to_string(),
-            exit_code: None, // Still running
-            output: None,
-            error: None,
             shell: "bash".to_string(),
+            ..Default::default()
         };
 
         // Add commands to session
@@ -1705,6 +2912,29 @@ mod tests {
         assert!(stats.total_size > 0);
     }
 
+    #[test]
+    fn test_pinned_sessions_survive_cleanup() {
+        let (mut manager, _temp_dir) = create_test_session_manager();
+        let reference_id = manager.start_session("Reference procedure".to_string(), None).unwrap();
+        manager.stop_session().unwrap();
+        let scratch_id = manager.start_session("Scratch".to_string(), None).unwrap();
+        manager.stop_session().unwrap();
+
+        assert!(manager.set_pinned(&reference_id, true).unwrap());
+        assert!(!manager.set_pinned(&reference_id, true).unwrap());
+        assert!(manager.load_session(&reference_id).unwrap().metadata.pinned);
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        manager.cleanup_old_data(0).unwrap();
+        assert_eq!(manager.list_sessions().unwrap(), vec![reference_id.clone()]);
+        assert!(!manager.list_sessions().unwrap().contains(&scratch_id));
+
+        assert!(manager.set_pinned(&reference_id, false).unwrap());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        manager.cleanup_old_data(0).unwrap();
+        assert!(manager.list_sessions().unwrap().is_empty());
+    }
+
     #[test]
     fn test_session_cache_management() {
         let (mut manager, _temp_dir) = create_test_session_manager();
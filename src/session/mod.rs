@@ -1,3 +1,9 @@
 pub mod manager;
+pub mod journal;
+pub mod error;
+pub mod schema;
+pub mod files;
+pub mod spill;
 
-pub use manager::{SessionManager, Session, SessionState, SessionEvent, Annotation, AnnotationType, StorageStats};
\ No newline at end of file
+pub use error::SessionError;
+pub use manager::{SessionManager, Session, SessionState, SessionEvent, Annotation, AnnotationType, StorageStats};
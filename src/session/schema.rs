@@ -0,0 +1,106 @@
+//! JSON Schema documents for DocPilot's on-disk formats
+//!
+//! The schemas are derived from the serde types, so they change together with the formats.
+
+use anyhow::{Result, anyhow};
+use schemars::schema_for;
+use serde_json::Value;
+
+use super::manager::Session;
+use crate::output::manifest::{DocumentManifest, MANIFEST_SCHEMA_VERSION};
+
+/// Format a JSON Schema can be generated for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchemaTarget {
+    /// Session files in `~/.docpilot/sessions/`
+    #[default]
+    Session,
+    /// `.docpilot.json` manifests written by `generate --manifest`
+    Manifest,
+    /// Sessions written by `export --format json`
+    Export,
+}
+
+impl SchemaTarget {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "session" => Ok(SchemaTarget::Session),
+            "manifest" => Ok(SchemaTarget::Manifest),
+            "export" => Ok(SchemaTarget::Export),
+            _ => Err(anyhow!("Unknown schema: {} (expected session, manifest or export)", s)),
+        }
+    }
+}
+
+/// Build the JSON Schema describing a format
+pub fn json_schema(target: SchemaTarget) -> Value {
+    let schema = match target {
+        SchemaTarget::Session => schema_for!(Session),
+        SchemaTarget::Manifest => {
+            let mut schema = schema_for!(DocumentManifest);
+            schema.schema.metadata().description = Some(format!("DocPilot document manifest, format version {}", MANIFEST_SCHEMA_VERSION));
+            schema
+        }
+        // The export is the session as stored, so it shares the session schema
+        SchemaTarget::Export => {
+            let mut schema = schema_for!(Session);
+            schema.schema.metadata().description = Some("DocPilot session exported with 'docpilot export --format json'".to_string());
+            schema
+        }
+    };
+
+    let mut schema = serde_json::to_value(schema).unwrap_or(Value::Null);
+    // Record which release the schema describes, since session files carry no version of their own
+    if let Value::Object(object) = &mut schema {
+        object.insert("x-docpilot-version".to_string(), Value::String(env!("CARGO_PKG_VERSION").to_string()));
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::CommandEntry;
+
+    #[test]
+    fn test_session_schema_covers_serialized_fields() {
+        let mut session = Session::new("Schema".to_string(), None).unwrap();
+        session.commands.push(CommandEntry {
+            command: "sleep 10 &".to_string(),
+            working_directory: "/tmp".to_string(),
+            shell: "bash".to_string(),
+            backgrounded: true,
+            ..Default::default()
+        });
+
+        let schema = json_schema(SchemaTarget::Session);
+        let serialized = serde_json::to_value(&session).unwrap();
+        for key in serialized.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "session field {} missing from schema", key);
+        }
+        for key in serialized["commands"][0].as_object().unwrap().keys() {
+            assert!(schema["definitions"]["CommandEntry"]["properties"].get(key).is_some(), "command field {} missing from schema", key);
+        }
+    }
+
+    #[test]
+    fn test_schema_target_parsing() {
+        assert_eq!(SchemaTarget::from_str("Manifest").unwrap(), SchemaTarget::Manifest);
+        assert_eq!(SchemaTarget::from_str("export").unwrap(), SchemaTarget::Export);
+        assert!(SchemaTarget::from_str("html").is_err());
+        assert!(json_schema(SchemaTarget::Manifest)["description"].as_str().unwrap().ends_with("format version 1"));
+    }
+
+    #[test]
+    fn test_export_schema_describes_json_export() {
+        let mut session = Session::new("Export".to_string(), None).unwrap();
+        session.add_annotation("Check the logs".to_string(), crate::session::manager::AnnotationType::Note);
+        let exported: Value = serde_json::from_str(&crate::output::json::session_json(&session).unwrap()).unwrap();
+
+        let schema = json_schema(SchemaTarget::Export);
+        assert!(schema["description"].as_str().unwrap().contains("export --format json"));
+        for key in exported.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "exported field {} missing from schema", key);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Very large command output kept in sidecar files instead of the session
+//!
+//! Output above the configured size is written to `session-<id>-cmd-<step>.log` in the sidecar
+//! directory; the command keeps a short preview, its original size and the path of the file, so the
+//! session JSON stays small and documents can link to the full log.
+
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::terminal::CommandEntry;
+
+/// Output kept inline as a preview when the rest goes to a sidecar file
+pub const SPILL_PREVIEW_BYTES: usize = 4 * 1024;
+
+/// Where and from which size command output is moved out of the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputSpill {
+    /// Output larger than this many bytes is spilled
+    pub threshold: u64,
+    /// Directory the sidecar files are written to
+    pub directory: PathBuf,
+}
+
+impl OutputSpill {
+    /// Default sidecar directory, `~/.docpilot/logs`
+    pub fn default_directory() -> Result<PathBuf> {
+        let sessions_dir = super::SessionManager::get_sessions_directory()?;
+        Ok(sessions_dir.with_file_name("logs"))
+    }
+
+    /// Move the output of `command` to its sidecar file if it is larger than the threshold
+    ///
+    /// Returns the sidecar path when the output was spilled.
+    pub fn spill(&self, session_id: &str, command: &mut CommandEntry) -> Result<Option<PathBuf>> {
+        let Some(output) = command.output.as_mut() else {
+            return Ok(None);
+        };
+        if output.len() as u64 <= self.threshold {
+            return Ok(None);
+        }
+
+        let step = command.step.unwrap_or_default();
+        let path = self.directory.join(sidecar_file_name(session_id, step));
+        fs::create_dir_all(&self.directory)?;
+        fs::write(&path, output.as_bytes())?;
+
+        command.output_size.get_or_insert(output.len() as u64);
+        output.truncate(output.floor_char_boundary(SPILL_PREVIEW_BYTES));
+        command.output_log = Some(path.to_string_lossy().to_string());
+        Ok(Some(path))
+    }
+}
+
+/// File name of the sidecar holding the output of a session's command
+pub fn sidecar_file_name(session_id: &str, step: usize) -> String {
+    format!("session-{}-cmd-{}.log", session_id, step)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_output_moves_to_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill = OutputSpill { threshold: 10_000, directory: dir.path().join("logs") };
+        let mut command = CommandEntry {
+            command: "cat build.log".to_string(),
+            exit_code: Some(0),
+            working_directory: "/tmp".to_string(),
+            shell: "bash".to_string(),
+            output: Some("ok\n".to_string()),
+            step: Some(7),
+            ..Default::default()
+        };
+        assert_eq!(spill.spill("abc", &mut command).unwrap(), None);
+        assert_eq!(command.output.as_deref(), Some("ok\n"));
+
+        let full = "é".repeat(20_000);
+        command.output = Some(full.clone());
+        let path = spill.spill("abc", &mut command).unwrap().unwrap();
+        assert_eq!(path, dir.path().join("logs").join("session-abc-cmd-7.log"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), full);
+        assert_eq!(command.output.as_deref(), Some("é".repeat(SPILL_PREVIEW_BYTES / 2).as_str()));
+        assert_eq!(command.output_size, Some(40_000));
+        assert_eq!(command.output_log, Some(path.to_string_lossy().to_string()));
+    }
+}
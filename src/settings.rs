@@ -0,0 +1,90 @@
+//! General settings
+//!
+//! Preferences that aren't about LLM providers or filtering, loaded from `settings.json` in the
+//! DocPilot config directory.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::output::profile::GenerateProfile;
+use crate::session::spill::OutputSpill;
+
+/// Settings changed with `docpilot config` that apply to every session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Settings {
+    /// Overwrite existing documentation files without asking
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub always_overwrite: bool,
+    /// Background monitors allowed to run at once; the built-in default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_monitors: Option<usize>,
+    /// Captured output larger than this many bytes goes to a sidecar file instead of the session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill_output_over: Option<u64>,
+    /// Directory for those sidecar files; `~/.docpilot/logs` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill_dir: Option<PathBuf>,
+    /// Saved `generate` options, used with `generate --profile NAME`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, GenerateProfile>,
+}
+
+/// DocPilot's config directory: `$XDG_CONFIG_HOME/docpilot`, or `~/.config/docpilot`
+pub fn config_dir() -> Result<PathBuf> {
+    let config_dir = if let Ok(xdg_config) = env::var("XDG_CONFIG_HOME") {
+        PathBuf::from(xdg_config)
+    } else if let Ok(home) = env::var("HOME") {
+        PathBuf::from(home).join(".config")
+    } else {
+        return Err(anyhow!("Cannot determine config directory"));
+    };
+
+    Ok(config_dir.join("docpilot"))
+}
+
+impl Settings {
+    /// Load the settings, falling back to defaults when no file exists
+    pub fn load() -> Result<Self> {
+        let path = Self::file_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content).map_err(|e| anyhow!("Invalid settings {}: {}", path.display(), e))
+    }
+
+    /// Background monitors allowed to run at once
+    pub fn max_monitors(&self) -> usize {
+        self.max_monitors.unwrap_or(crate::terminal::registry::DEFAULT_MAX_MONITORS)
+    }
+
+    /// Where large captured output is spilled to, or `None` when it stays in the session
+    pub fn output_spill(&self) -> Option<OutputSpill> {
+        let threshold = self.spill_output_over?;
+        let directory = match &self.spill_dir {
+            Some(dir) => dir.clone(),
+            None => OutputSpill::default_directory().ok()?,
+        };
+        Some(OutputSpill { threshold, directory })
+    }
+
+    /// Write the settings, creating the config directory if needed
+    pub fn save(&self) -> Result<()> {
+        let path = Self::file_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Get the settings file path
+    pub fn file_path() -> Result<PathBuf> {
+        Ok(config_dir()?.join("settings.json"))
+    }
+}
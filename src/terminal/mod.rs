@@ -1,5 +1,7 @@
 pub mod monitor;
 pub mod platform;
+pub mod registry;
+pub mod remote;
 
 #[cfg(test)]
 #[path = "monitor.test.rs"]
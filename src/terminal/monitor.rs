@@ -1,15 +1,142 @@
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::io::Write;
 
 use super::platform::{Platform, PlatformUtils};
+use crate::filter::FilterConfig;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Exit code field written by preexec hooks before the command has finished
+const COMMAND_START_MARKER: &str = "start";
+
+/// Exit code field of the line hooks write before a completion line when the command used an alias or function
+const EXPANSION_MARKER: &str = "expansion";
+
+/// Exit code field of the line hooks write before a completion line with the shell's nesting level and parent command
+const NESTING_MARKER: &str = "nesting";
+
+/// Exit code field prefix of the line hooks write when a backgrounded job finishes, e.g. `job:0`
+const JOB_MARKER: &str = "job:";
+
+/// zsh precmd addition logging the exit status of backgrounded jobs once they finish
+const ZSH_JOB_HOOK: &str = r#"
+    # Remember jobs started with a trailing & and log their exit status once they finish
+    if [[ -n "$!" && "$!" != "$DOCPILOT_LAST_JOB_PID" ]]; then
+        DOCPILOT_LAST_JOB_PID="$!"
+        DOCPILOT_JOBS+=("$!|$docpilot_command")
+    fi
+    local docpilot_running=() docpilot_job docpilot_status
+    for docpilot_job in "${DOCPILOT_JOBS[@]}"; do
+        if kill -0 "${docpilot_job%%|*}" 2>/dev/null; then
+            docpilot_running+=("$docpilot_job")
+        else
+            wait "${docpilot_job%%|*}" 2>/dev/null
+            docpilot_status=$?
+            echo "$(date -Iseconds)|$(pwd)|job:$docpilot_status|${docpilot_job#*|}" >> "$(docpilot_get_active_log)" 2>/dev/null || true
+        fi
+    done
+    DOCPILOT_JOBS=("${docpilot_running[@]}")"#;
+
+/// bash PROMPT_COMMAND addition logging the exit status of backgrounded jobs once they finish
+const BASH_JOB_HOOK: &str = r#"
+    # Remember jobs started with a trailing & and log their exit status once they finish
+    if [[ -n "$!" && "$!" != "$DOCPILOT_LAST_JOB_PID" ]]; then
+        DOCPILOT_LAST_JOB_PID="$!"
+        DOCPILOT_JOBS+=("$!|$last_command")
+    fi
+    local docpilot_running=() docpilot_job docpilot_status
+    for docpilot_job in "${DOCPILOT_JOBS[@]}"; do
+        if kill -0 "${docpilot_job%%|*}" 2>/dev/null; then
+            docpilot_running+=("$docpilot_job")
+        else
+            wait "${docpilot_job%%|*}" 2>/dev/null
+            docpilot_status=$?
+            echo "$(date -Iseconds)|$(pwd)|job:$docpilot_status|${docpilot_job#*|}" >> "$(docpilot_get_active_log)" 2>/dev/null || true
+        fi
+    done
+    DOCPILOT_JOBS=("${docpilot_running[@]}")"#;
+
+/// fish postexec addition logging the exit status of a backgrounded job once it finishes
+const FISH_JOB_HOOK: &str = r#"
+    # Log the exit status of a job started with a trailing & once it finishes
+    if test -n "$last_pid"; and test "$last_pid" != "$docpilot_last_job_pid"
+        set -g docpilot_last_job_pid $last_pid
+        set -g docpilot_job_$last_pid "$argv"
+        function docpilot_job_exit_$last_pid --on-process-exit $last_pid
+            set -l docpilot_job docpilot_job_$argv[2]
+            echo (date -Iseconds)"|"(pwd)"|job:"$argv[3]"|"$$docpilot_job >> (docpilot_get_active_log)
+            set -e $docpilot_job
+            functions -e docpilot_job_exit_$argv[2]
+        end
+    end"#;
+
+/// zsh preexec addition keeping the alias-expanded command line
+const ZSH_EXPANSION_PREEXEC: &str = r#"
+    # Keep zsh's alias-expanded form of the command
+    DOCPILOT_EXPANDED_CMD="$3""#;
+
+/// zsh precmd addition logging what an alias or shell function runs
+const ZSH_EXPANSION_PRECMD: &str = r#"
+        # Record what an alias or shell function actually runs
+        local docpilot_word="${DOCPILOT_CURRENT_CMD%% *}"
+        local docpilot_expansion=""
+        if [[ -n "$DOCPILOT_EXPANDED_CMD" && "$DOCPILOT_EXPANDED_CMD" != "$DOCPILOT_CURRENT_CMD" ]]; then
+            docpilot_expansion="$DOCPILOT_EXPANDED_CMD"
+        elif [[ "$(whence -w -- "$docpilot_word")" == *": function" ]]; then
+            docpilot_expansion="$(functions -- "$docpilot_word")"
+        fi
+        if [[ -n "$docpilot_expansion" ]]; then
+            echo "$(date -Iseconds)|$(pwd)|expansion|$(print -r -- "$docpilot_expansion" | awk 'BEGIN { ORS = "\\n" } { print }')" >> "$log_file" 2>/dev/null || true
+        fi"#;
+
+/// bash PROMPT_COMMAND addition logging what an alias or shell function runs
+const BASH_EXPANSION_HOOK: &str = r#"
+        # Record what an alias or shell function actually runs
+        local docpilot_word="${last_command%% *}"
+        local docpilot_expansion=""
+        case "$(type -t "$docpilot_word")" in
+            alias) docpilot_expansion="$(alias "$docpilot_word" | sed "s/^alias [^=]*='//; s/'$//")${last_command#"$docpilot_word"}" ;;
+            function) docpilot_expansion="$(declare -f "$docpilot_word")" ;;
+        esac
+        if [[ -n "$docpilot_expansion" ]]; then
+            echo "$(date -Iseconds)|$(pwd)|expansion|$(printf '%s\n' "$docpilot_expansion" | awk 'BEGIN { ORS = "\\n" } { print }')" >> "$(docpilot_get_active_log)" 2>/dev/null || true
+        fi"#;
+
+/// fish postexec addition logging what a user-defined function (fish aliases included) runs
+const FISH_EXPANSION_HOOK: &str = r#"
+    # Record what a user-defined function (fish aliases included) actually runs
+    set -l docpilot_word (string split -m 1 ' ' -- "$argv")[1]
+    if functions -q -- $docpilot_word; and not string match -q -- "$__fish_data_dir/*" (functions --details -- $docpilot_word)
+        echo (date -Iseconds)"|"(pwd)"|expansion|"(functions -- $docpilot_word | string match -v -r '^# Defined' | string join '\n') >> $log_file
+    end"#;
+
+/// zsh precmd addition logging how deeply the shell is nested and what started it
+const ZSH_NESTING_HOOK: &str = r#"
+        # Record the shell level and the command this shell runs inside
+        echo "$(date -Iseconds)|$(pwd)|nesting|${SHLVL:-1}|$(ps -o args= -p $PPID 2>/dev/null)" >> "$log_file" 2>/dev/null || true"#;
+
+/// bash PROMPT_COMMAND addition logging how deeply the shell is nested and what started it
+const BASH_NESTING_HOOK: &str = r#"
+        # Record the shell level and the command this shell runs inside
+        echo "$(date -Iseconds)|$(pwd)|nesting|${SHLVL:-1}|$(ps -o args= -p $PPID 2>/dev/null)" >> "$(docpilot_get_active_log)" 2>/dev/null || true"#;
+
+/// fish postexec addition logging how deeply the shell is nested and what started it
+const FISH_NESTING_HOOK: &str = r#"
+    # Record the shell level and the command this shell runs inside
+    echo (date -Iseconds)"|"(pwd)"|nesting|"$SHLVL"|"(ps -o args= -p (ps -o ppid= -p $fish_pid | string trim) 2>/dev/null) >> $log_file"#;
+
+/// Commands that are never captured when run without arguments
+const DEFAULT_IGNORED_COMMANDS: &[&str] = &[
+    "ls", "ll", "pwd", "cd", "clear", "exit", "logout", "history", "jobs", "bg", "fg",
+    "alias", "unalias", "type", "which", "whereis",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CommandEntry {
     pub command: String,
     pub timestamp: DateTime<Utc>,
@@ -18,6 +145,190 @@ pub struct CommandEntry {
     pub shell: String,
     pub output: Option<String>,
     pub error: Option<String>,
+    /// What an alias or shell function in the typed command actually runs, when alias expansion is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expanded_command: Option<String>,
+    /// Run in the background with a trailing `&`; the exit code arrives when the job finishes
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub backgrounded: bool,
+    /// Tags added with `docpilot tag-command`, for grouping and filtering where the classifier falls short
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Capture number in its session, counting from 1; unlike the command's position it stays
+    /// the same when other commands are filtered out or deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<usize>,
+    /// Byte length of the output when it was captured, kept so documents can say how much of it they show
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_size: Option<u64>,
+    /// Sidecar file with the full output when it was too large to keep in the session; `output` is then a preview
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_log: Option<String>,
+    /// Shell nesting of the command when nesting tracking is enabled; only set for commands run in a nested shell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nesting: Option<CommandNesting>,
+    /// Time from the command starting to the next prompt, when the shell hooks mark command starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+impl Default for CommandEntry {
+    /// A command captured now, with no output, exit code or location, for filling in the rest
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            timestamp: Utc::now(),
+            exit_code: None,
+            working_directory: String::new(),
+            shell: String::new(),
+            output: None,
+            error: None,
+            expanded_command: None,
+            backgrounded: false,
+            tags: Vec::new(),
+            step: None,
+            output_size: None,
+            output_log: None,
+            nesting: None,
+            duration_ms: None,
+        }
+    }
+}
+
+/// Where a command ran relative to the shell the session was started from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CommandNesting {
+    /// Shells between the session's shell and the one the command ran in, 1 for a direct subshell
+    pub depth: u32,
+    /// Command line of the process that started that shell, e.g. `docker exec -it web bash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+/// Operator joining a step of a chained command line to the step before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainOperator {
+    /// `&&`: runs only if the previous step succeeded
+    And,
+    /// `||`: runs only if the previous step failed
+    Or,
+    /// `;`: always runs after the previous step
+    Sequence,
+}
+
+/// One logical step of a command line chained with `&&`, `||` or `;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainStep {
+    pub command: String,
+    /// How this step is joined to the previous one; `None` for the first step
+    pub operator: Option<ChainOperator>,
+}
+
+impl CommandEntry {
+    /// Split a command line into the steps chained with `&&`, `||` and `;`
+    ///
+    /// Operators inside quotes, subshells and `$(...)` are left alone, as are pipes,
+    /// `&` and `;;`. A line without chaining yields a single step.
+    pub fn chain_steps(command: &str) -> Vec<ChainStep> {
+        let mut steps = Vec::new();
+        let mut current = String::new();
+        let mut operator = None;
+        let mut quote: Option<char> = None;
+        let mut depth = 0usize;
+        let mut chars = command.chars().peekable();
+
+        let push_step = |current: &mut String, operator: Option<ChainOperator>, steps: &mut Vec<ChainStep>| {
+            let step = current.trim();
+            if !step.is_empty() {
+                steps.push(ChainStep { command: step.to_string(), operator });
+            }
+            current.clear();
+        };
+
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some('\''), '\'') => quote = None,
+                (Some('\''), _) => {}
+                (_, '\\') => {
+                    current.push(c);
+                    if let Some(escaped) = chars.next() {
+                        current.push(escaped);
+                    }
+                    continue;
+                }
+                (Some('"'), '"') => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(c),
+                (None, '(') => depth += 1,
+                (None, ')') => depth = depth.saturating_sub(1),
+                (None, '&' | '|') if depth == 0 && chars.peek() == Some(&c) => {
+                    chars.next();
+                    push_step(&mut current, operator, &mut steps);
+                    operator = Some(if c == '&' { ChainOperator::And } else { ChainOperator::Or });
+                    continue;
+                }
+                (None, ';') if depth == 0 => {
+                    if chars.peek() == Some(&';') {
+                        current.push(c);
+                        current.push(chars.next().unwrap_or(';'));
+                        continue;
+                    }
+                    push_step(&mut current, operator, &mut steps);
+                    operator = Some(ChainOperator::Sequence);
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        push_step(&mut current, operator, &mut steps);
+
+        if steps.is_empty() {
+            steps.push(ChainStep { command: command.trim().to_string(), operator: None });
+        }
+        steps
+    }
+
+    /// Check whether a command line ends by sending the command to the background
+    pub fn is_backgrounded_command(command: &str) -> bool {
+        let command = command.trim_end();
+        command.ends_with('&') && !command.ends_with("&&") && !command.ends_with("\\&")
+    }
+
+    /// Signal number that terminated the command, using the shell's 128+N exit status convention
+    pub fn termination_signal(&self) -> Option<i32> {
+        match self.exit_code {
+            Some(code) if (129..=128 + 64).contains(&code) => Some(code - 128),
+            _ => None,
+        }
+    }
+
+    /// Describe a signal termination, e.g. "terminated by SIGKILL"
+    pub fn termination_description(&self) -> Option<String> {
+        self.termination_signal().map(|signal| match signal_name(signal) {
+            Some(name) => format!("terminated by {}", name),
+            None => format!("terminated by signal {}", signal),
+        })
+    }
+}
+
+/// Name of a signal whose number is the same on Linux and macOS
+fn signal_name(signal: i32) -> Option<&'static str> {
+    match signal {
+        1 => Some("SIGHUP"),
+        2 => Some("SIGINT"),
+        3 => Some("SIGQUIT"),
+        4 => Some("SIGILL"),
+        5 => Some("SIGTRAP"),
+        6 => Some("SIGABRT"),
+        8 => Some("SIGFPE"),
+        9 => Some("SIGKILL"),
+        11 => Some("SIGSEGV"),
+        13 => Some("SIGPIPE"),
+        14 => Some("SIGALRM"),
+        15 => Some("SIGTERM"),
+        _ => None,
+    }
 }
 
 #[derive(Debug)]
@@ -32,6 +343,29 @@ pub struct TerminalMonitor {
     command_log_path: PathBuf,
     /// Last known size of the command log file
     last_log_size: u64,
+    /// Command the shell hooks reported as started but not yet finished
+    running_command: Option<CommandEntry>,
+    /// Additional commands the user chose not to capture, from the filter configuration
+    ignored_commands: Vec<String>,
+    /// Whether the hooks resolve aliases and shell functions (adds work to every prompt)
+    expand_aliases: bool,
+    /// Expansion logged by the hooks for the command about to complete
+    pending_expansion: Option<String>,
+    /// Only commands run inside this directory are captured
+    only_dir: Option<PathBuf>,
+    /// Commands captured within this many seconds of the session start are dropped
+    capture_delay: u64,
+    /// Shell level of the session's shell when nesting is tracked; hooks then log each shell's level
+    nesting_base: Option<u32>,
+    /// Nesting logged by the hooks for the command about to complete
+    pending_nesting: Option<CommandNesting>,
+    /// Backgrounded commands that finished since the last call to `take_completed_jobs`
+    completed_jobs: Vec<CommandEntry>,
+}
+
+/// Log file the shell hooks of a session write captured commands to
+pub fn command_log_path(session_id: &str) -> PathBuf {
+    env::temp_dir().join(format!("docpilot_commands_{}.log", session_id))
 }
 
 #[derive(Debug, Clone)]
@@ -39,23 +373,30 @@ pub enum ShellType {
     Bash,
     Zsh,
     Fish,
+    PowerShell,
     Unknown(String),
 }
 
 impl ShellType {
     pub fn detect() -> Self {
-        if let Ok(shell) = env::var("SHELL") {
-            if shell.contains("bash") {
-                ShellType::Bash
-            } else if shell.contains("zsh") {
-                ShellType::Zsh
-            } else if shell.contains("fish") {
-                ShellType::Fish
-            } else {
-                ShellType::Unknown(shell)
-            }
+        match PlatformUtils::current_shell() {
+            Some(shell) => Self::from_name(&shell),
+            None => ShellType::Unknown("unknown".to_string()),
+        }
+    }
+
+    /// Shell type for a shell's name or path, e.g. `/usr/bin/fish`
+    pub fn from_name(shell: &str) -> Self {
+        if shell.contains("bash") {
+            ShellType::Bash
+        } else if shell.contains("zsh") {
+            ShellType::Zsh
+        } else if shell.contains("fish") {
+            ShellType::Fish
+        } else if shell.contains("pwsh") || shell.contains("powershell") {
+            ShellType::PowerShell
         } else {
-            ShellType::Unknown("unknown".to_string())
+            ShellType::Unknown(shell.to_string())
         }
     }
 
@@ -64,6 +405,7 @@ impl ShellType {
             ShellType::Bash => "bash",
             ShellType::Zsh => "zsh",
             ShellType::Fish => "fish",
+            ShellType::PowerShell => "powershell",
             ShellType::Unknown(name) => name,
         }
     }
@@ -78,9 +420,16 @@ impl TerminalMonitor {
             return Err(anyhow!("Unsupported platform: {}", platform.name()));
         }
 
-        // Create command log file path
-        let mut log_path = env::temp_dir();
-        log_path.push(format!("docpilot_commands_{}.log", session_id));
+        let log_path = command_log_path(&session_id);
+
+        // A broken filter configuration should not stop capture, only the user's additions are lost
+        let ignored_commands = match FilterConfig::load() {
+            Ok(config) => config.ignore_commands,
+            Err(e) => {
+                eprintln!("⚠️  Ignoring filter configuration: {}", e);
+                Vec::new()
+            }
+        };
 
         Ok(Self {
             session_id,
@@ -91,9 +440,39 @@ impl TerminalMonitor {
             session_start_time: Utc::now(),
             command_log_path: log_path,
             last_log_size: 0,
+            running_command: None,
+            ignored_commands,
+            expand_aliases: false,
+            pending_expansion: None,
+            only_dir: None,
+            capture_delay: 0,
+            nesting_base: None,
+            pending_nesting: None,
+            completed_jobs: Vec::new(),
         })
     }
 
+    /// Resolve aliases and shell functions at capture time, must be set before monitoring starts
+    pub fn set_expand_aliases(&mut self, expand_aliases: bool) {
+        self.expand_aliases = expand_aliases;
+    }
+
+    /// Record how deeply each command's shell is nested below the shell at level `base`,
+    /// must be set before monitoring starts
+    pub fn set_nesting_base(&mut self, base: Option<u32>) {
+        self.nesting_base = base;
+    }
+
+    /// Only capture commands whose working directory is inside `only_dir`
+    pub fn set_only_dir(&mut self, only_dir: Option<PathBuf>) {
+        self.only_dir = only_dir;
+    }
+
+    /// Drop commands captured within `seconds` of the session start, so setup noise stays out
+    pub fn set_capture_delay(&mut self, seconds: u64) {
+        self.capture_delay = seconds;
+    }
+
     /// Set the session start time (used for background processes)
     pub fn set_session_start_time(&mut self, start_time: DateTime<Utc>) {
         self.session_start_time = start_time;
@@ -165,6 +544,16 @@ impl TerminalMonitor {
         self.commands.push(command);
     }
 
+    /// Take the backgrounded commands that finished, each carrying its final exit code
+    pub fn take_completed_jobs(&mut self) -> Vec<CommandEntry> {
+        std::mem::take(&mut self.completed_jobs)
+    }
+
+    /// Get the command that has started but not completed yet, if any
+    pub fn running_command(&self) -> Option<&CommandEntry> {
+        self.running_command.as_ref()
+    }
+
     /// Check for new commands using hybrid approach (shell integration + process monitoring)
     pub async fn check_for_new_commands(&mut self) -> Result<Vec<CommandEntry>> {
         if !self.monitoring {
@@ -243,10 +632,57 @@ impl TerminalMonitor {
                     };
                     
                     for line in new_content.lines() {
-                        if let Some(command_entry) = self.parse_log_line(line) {
+                        // Start markers only track the in-flight command, the completion line records it
+                        if Self::is_start_marker(line) {
+                            if let Some(command_entry) = self.parse_log_line(line)
+                                && command_entry.timestamp >= self.session_start_time
+                                && self.should_capture(&command_entry) {
+                                self.running_command = Some(command_entry);
+                            }
+                            continue;
+                        }
+
+                        // Expansion lines describe the completion line that follows them
+                        if Self::is_expansion_marker(line) {
+                            self.pending_expansion = line.splitn(4, '|').nth(3).map(Self::decode_expansion);
+                            continue;
+                        }
+
+                        // Nesting lines describe the shell of the completion line that follows them
+                        if Self::is_nesting_marker(line) {
+                            self.pending_nesting = line.splitn(4, '|').nth(3).and_then(|nesting| self.parse_nesting(nesting));
+                            continue;
+                        }
+
+                        // Job lines carry the exit code of a command captured earlier with a trailing &
+                        if Self::is_job_marker(line) {
+                            let unmarked = line.splitn(4, '|')
+                                .enumerate()
+                                .map(|(index, field)| if index == 2 { field.trim_start_matches(JOB_MARKER) } else { field })
+                                .collect::<Vec<_>>()
+                                .join("|");
+                            if let Some(job) = self.parse_log_line(&unmarked) {
+                                self.complete_job(job);
+                            }
+                            continue;
+                        }
+
+                        if let Some(mut command_entry) = self.parse_log_line(line) {
+                            if let Some(running) = self.running_command.take_if(|running| running.command == command_entry.command) {
+                                command_entry.duration_ms = (command_entry.timestamp - running.timestamp).num_milliseconds().try_into().ok();
+                            }
+                            command_entry.expanded_command = self.pending_expansion.take().filter(|expansion| !expansion.is_empty());
+                            command_entry.nesting = self.pending_nesting.take();
+
+                            // The shell reports 0 as soon as a job is backgrounded, its real status comes later
+                            if CommandEntry::is_backgrounded_command(&command_entry.command) {
+                                command_entry.backgrounded = true;
+                                command_entry.exit_code = None;
+                            }
+
                             // Only include commands after session start time
                             if command_entry.timestamp >= self.session_start_time {
-                                if !self.should_ignore_command(&command_entry.command) {
+                                if self.should_capture(&command_entry) {
                                     // Check for duplicates
                                     if !self.commands.iter().any(|c|
                                         c.command == command_entry.command &&
@@ -279,7 +715,7 @@ impl TerminalMonitor {
                     for line in content.lines() {
                         if let Some(command_entry) = self.parse_zsh_history_line(line) {
                             if command_entry.timestamp >= self.session_start_time {
-                                if !self.should_ignore_command(&command_entry.command) {
+                                if self.should_capture(&command_entry) {
                                     // Check for duplicates
                                     if !self.commands.iter().any(|c| c.command == command_entry.command) {
                                         new_commands.push(command_entry.clone());
@@ -307,7 +743,7 @@ impl TerminalMonitor {
                     for line in content.lines() {
                         if let Some(command_entry) = self.parse_bash_history_line(line) {
                             if command_entry.timestamp >= self.session_start_time {
-                                if !self.should_ignore_command(&command_entry.command) {
+                                if self.should_capture(&command_entry) {
                                     // Check for duplicates
                                     if !self.commands.iter().any(|c| c.command == command_entry.command) {
                                         new_commands.push(command_entry.clone());
@@ -335,7 +771,7 @@ impl TerminalMonitor {
                     for line in content.lines() {
                         if let Some(command_entry) = self.parse_fish_history_line(line) {
                             if command_entry.timestamp >= self.session_start_time {
-                                if !self.should_ignore_command(&command_entry.command) {
+                                if self.should_capture(&command_entry) {
                                     // Check for duplicates
                                     if !self.commands.iter().any(|c| c.command == command_entry.command) {
                                         new_commands.push(command_entry.clone());
@@ -379,6 +815,14 @@ impl TerminalMonitor {
                             shell: "zsh".to_string(),
                             output: None,
                             error: None,
+                            expanded_command: None,
+                            backgrounded: false,
+                            tags: Vec::new(),
+                            step: None,
+                            output_size: None,
+                            output_log: None,
+                            nesting: None,
+                            duration_ms: None,
                         });
                     }
                 }
@@ -403,6 +847,14 @@ impl TerminalMonitor {
                 shell: "bash".to_string(),
                 output: None,
                 error: None,
+                expanded_command: None,
+                backgrounded: false,
+                tags: Vec::new(),
+                step: None,
+                output_size: None,
+                output_log: None,
+                nesting: None,
+                duration_ms: None,
             })
         } else {
             None
@@ -425,6 +877,14 @@ impl TerminalMonitor {
                 shell: "fish".to_string(),
                 output: None,
                 error: None,
+                expanded_command: None,
+                backgrounded: false,
+                tags: Vec::new(),
+                step: None,
+                output_size: None,
+                output_log: None,
+                nesting: None,
+                duration_ms: None,
             })
         } else {
             None
@@ -438,7 +898,7 @@ impl TerminalMonitor {
         // Get current processes that look like user commands
         if let Ok(processes) = self.get_recent_user_processes() {
             for process in processes {
-                if !self.should_ignore_command(&process.command) {
+                if self.should_capture(&process) {
                     // Check if we already have this command to avoid duplicates
                     if !self.commands.iter().any(|c|
                         c.command == process.command &&
@@ -506,6 +966,14 @@ impl TerminalMonitor {
             shell: self.shell_type.name().to_string(),
             output: None,
             error: None,
+            expanded_command: None,
+            backgrounded: false,
+            tags: Vec::new(),
+            step: None,
+            output_size: None,
+            output_log: None,
+            nesting: None,
+            duration_ms: None,
         })
     }
 
@@ -590,6 +1058,7 @@ impl TerminalMonitor {
             ShellType::Zsh => self.setup_automatic_zsh_integration(),
             ShellType::Bash => self.setup_automatic_bash_integration(),
             ShellType::Fish => self.setup_automatic_fish_integration(),
+            ShellType::PowerShell => self.setup_powershell_integration(),
             ShellType::Unknown(_) => {
                 println!("⚠️  Automatic shell integration not available for your shell");
                 println!("   Please manually set up command logging following the instructions above");
@@ -615,6 +1084,10 @@ impl TerminalMonitor {
 # Global variable to store the current command
 DOCPILOT_CURRENT_CMD=""
 
+# Backgrounded jobs started before the hooks were loaded are not tracked
+DOCPILOT_LAST_JOB_PID="$!"
+DOCPILOT_JOBS=()
+
 # Function to get the current active session log file
 docpilot_get_active_log() {{
     local docpilot_dir="$HOME/.docpilot"
@@ -634,32 +1107,35 @@ docpilot_get_active_log() {{
 # Define our command logging functions
 preexec() {{
     # Store the command for precmd to use
-    DOCPILOT_CURRENT_CMD="$1"
-    # Also log immediately for safety
+    DOCPILOT_CURRENT_CMD="$1"{expansion_preexec}
+    # Mark the command as started so long-running commands can be reported
     local log_file=$(docpilot_get_active_log)
-    echo "$(date -Iseconds)|$(pwd)|0|$1" >> "$log_file" 2>/dev/null || true
+    echo "$(date -Iseconds)|$(pwd)|start|$1" >> "$log_file" 2>/dev/null || true
 }}
 
 precmd() {{
+    # Capture the exit code before anything else overwrites it
+    local exit_code=$?
+    local docpilot_command="$DOCPILOT_CURRENT_CMD"
     # Log the complete command with exit code (only if we have a command)
     if [[ -n "$DOCPILOT_CURRENT_CMD" ]]; then
-        local log_file=$(docpilot_get_active_log)
-        echo "$(date -Iseconds)|$(pwd)|$?|$DOCPILOT_CURRENT_CMD" >> "$log_file" 2>/dev/null || true
+        local log_file=$(docpilot_get_active_log){expansion_precmd}
+        echo "$(date -Iseconds)|$(pwd)|$exit_code|$DOCPILOT_CURRENT_CMD" >> "$log_file" 2>/dev/null || true
         DOCPILOT_CURRENT_CMD=""
-    fi
+    fi{jobs}
 }}
 
 # Function to cleanup when DocPilot session ends
 docpilot_cleanup() {{
     unset -f preexec precmd docpilot_get_active_log
-    unset DOCPILOT_CURRENT_CMD
+    unset DOCPILOT_CURRENT_CMD DOCPILOT_EXPANDED_CMD DOCPILOT_LAST_JOB_PID DOCPILOT_JOBS
     unset -f docpilot_cleanup
 }}
 
 # Test that hooks are working
 local log_file=$(docpilot_get_active_log)
 echo "DocPilot shell hooks loaded at $(date -Iseconds)" >> "$log_file" 2>/dev/null || true
-"#, log_path);
+"#, log_path, expansion_preexec = self.expansion_hook(ZSH_EXPANSION_PREEXEC), expansion_precmd = self.completion_hooks(ZSH_EXPANSION_PRECMD, ZSH_NESTING_HOOK), jobs = ZSH_JOB_HOOK);
 
         fs::write(&hooks_file, hooks_content)?;
         
@@ -682,12 +1158,24 @@ echo "DocPilot shell hooks loaded at $(date -Iseconds)" >> "$log_file" 2>/dev/nu
         Ok(())
     }
 
+    /// Hook snippet to insert when alias expansion is enabled, empty otherwise
+    fn expansion_hook(&self, hook: &'static str) -> &'static str {
+        if self.expand_aliases { hook } else { "" }
+    }
+
+    /// Opt-in snippets logged just before a completion line: alias expansion and shell nesting
+    fn completion_hooks(&self, expansion_hook: &'static str, nesting_hook: &'static str) -> String {
+        let nesting_hook = if self.nesting_base.is_some() { nesting_hook } else { "" };
+        format!("{}{}", self.expansion_hook(expansion_hook), nesting_hook)
+    }
+
     /// Get shell hooks content for direct evaluation (auto-sourcing)
     pub fn get_shell_hooks_content(&self) -> Result<String> {
         match self.shell_type {
             ShellType::Zsh => self.get_zsh_hooks_content(),
             ShellType::Bash => self.get_bash_hooks_content(),
             ShellType::Fish => self.get_fish_hooks_content(),
+            ShellType::PowerShell => self.get_powershell_hooks_content(),
             ShellType::Unknown(_) => {
                 Err(anyhow!("Automatic shell integration not available for your shell"))
             }
@@ -704,6 +1192,10 @@ echo "DocPilot shell hooks loaded at $(date -Iseconds)" >> "$log_file" 2>/dev/nu
 # Global variable to store the current command
 DOCPILOT_CURRENT_CMD=""
 
+# Backgrounded jobs started before the hooks were loaded are not tracked
+DOCPILOT_LAST_JOB_PID="$!"
+DOCPILOT_JOBS=()
+
 # Function to get the current active session log file
 docpilot_get_active_log() {{
     local docpilot_dir="$HOME/.docpilot"
@@ -723,25 +1215,29 @@ docpilot_get_active_log() {{
 # Define our command logging functions
 preexec() {{
     # Store the command for precmd to use
-    DOCPILOT_CURRENT_CMD="$1"
-    # Also log immediately for safety
+    DOCPILOT_CURRENT_CMD="$1"{expansion_preexec}
+    # Mark the command as started so long-running commands can be reported
     local log_file=$(docpilot_get_active_log)
-    echo "$(date -Iseconds)|$(pwd)|0|$1" >> "$log_file" 2>/dev/null || true
+    echo "$(date -Iseconds)|$(pwd)|start|$1" >> "$log_file" 2>/dev/null || true
 }}
 
 precmd() {{
+    # Capture the exit code before anything else overwrites it
+    local exit_code=$?
+    local docpilot_command="$DOCPILOT_CURRENT_CMD"
     # Log the complete command with exit code (only if we have a command)
     if [[ -n "$DOCPILOT_CURRENT_CMD" ]]; then
-        local log_file=$(docpilot_get_active_log)
-        echo "$(date -Iseconds)|$(pwd)|$?|$DOCPILOT_CURRENT_CMD" >> "$log_file" 2>/dev/null || true
+        local log_file=$(docpilot_get_active_log){expansion_precmd}
+        echo "$(date -Iseconds)|$(pwd)|$exit_code|$DOCPILOT_CURRENT_CMD" >> "$log_file" 2>/dev/null || true
         DOCPILOT_CURRENT_CMD=""
-    fi
+    fi{jobs}
 }}
 
 # Test that hooks are working
 local log_file=$(docpilot_get_active_log)
 echo "DocPilot shell hooks loaded at $(date -Iseconds)" >> "$log_file" 2>/dev/null || true"#,
-            self.session_id, log_path))
+            self.session_id, log_path,
+            expansion_preexec = self.expansion_hook(ZSH_EXPANSION_PREEXEC), expansion_precmd = self.completion_hooks(ZSH_EXPANSION_PRECMD, ZSH_NESTING_HOOK), jobs = ZSH_JOB_HOOK))
     }
 
     /// Get bash hooks content for direct evaluation
@@ -754,6 +1250,10 @@ echo "DocPilot shell hooks loaded at $(date -Iseconds)" >> "$log_file" 2>/dev/nu
 # Store original PROMPT_COMMAND if it exists
 DOCPILOT_ORIGINAL_PROMPT_COMMAND="$PROMPT_COMMAND"
 
+# Backgrounded jobs started before the hooks were loaded are not tracked
+DOCPILOT_LAST_JOB_PID="$!"
+DOCPILOT_JOBS=()
+
 # Function to get the current active session log file
 docpilot_get_active_log() {{
     local docpilot_dir="$HOME/.docpilot"
@@ -770,12 +1270,23 @@ docpilot_get_active_log() {{
     echo "{}"
 }}
 
+# Log the last command with its raw exit status (128+N when it was killed by signal N)
+docpilot_prompt_command() {{
+    local exit_code=$?
+    local last_command=$(history 1 | sed 's/^[ ]*[0-9]*[ ]*//')
+    if [[ -n "$last_command" ]]; then{expansion}
+        echo "$(date -Iseconds)|$(pwd)|$exit_code|$last_command" >> "$(docpilot_get_active_log)" 2>/dev/null || true
+    fi{jobs}
+    return $exit_code
+}}
+
 # Set up command logging
-export PROMPT_COMMAND="echo \\"$(date -Iseconds)|$(pwd)|\$?|$(history 1 | sed 's/^[ ]*[0-9]*[ ]*//')\\" >> $(docpilot_get_active_log); $DOCPILOT_ORIGINAL_PROMPT_COMMAND"
+export PROMPT_COMMAND="docpilot_prompt_command; $DOCPILOT_ORIGINAL_PROMPT_COMMAND"
 
 # Test that hooks are working
 echo "DocPilot shell hooks loaded at $(date -Iseconds)" >> $(docpilot_get_active_log) 2>/dev/null || true"#,
-            self.session_id, log_path))
+            self.session_id, log_path,
+            expansion = self.completion_hooks(BASH_EXPANSION_HOOK, BASH_NESTING_HOOK), jobs = BASH_JOB_HOOK))
     }
 
     /// Get fish hooks content for direct evaluation
@@ -801,22 +1312,115 @@ function docpilot_get_active_log
     echo "{}"
 end
 
+# Backgrounded jobs started before the hooks were loaded are not tracked
+set -g docpilot_last_job_pid $last_pid
+
 function docpilot_log_command --on-event fish_preexec
+    string length -q -- (string trim -- $argv); or return
     set log_file (docpilot_get_active_log)
-    echo (date -Iseconds)"|"(pwd)"|0|"$argv >> $log_file
+    echo (date -Iseconds)"|"(pwd)"|start|"$argv >> $log_file
 end
 
 function docpilot_log_exit --on-event fish_postexec
-    set log_file (docpilot_get_active_log)
-    echo (date -Iseconds)"|"(pwd)"|"$status"|" >> $log_file
+    set exit_status $status
+    # Older fish releases fire the events for blank lines too; skip them like bash and zsh do
+    string length -q -- (string trim -- $argv); or return
+    set log_file (docpilot_get_active_log){expansion}
+    echo (date -Iseconds)"|"(pwd)"|"$exit_status"|"$argv >> $log_file{jobs}
 end
 
 # Test that hooks are working
 set log_file (docpilot_get_active_log)
 echo "DocPilot shell hooks loaded at "(date -Iseconds) >> $log_file 2>/dev/null || true"#,
+            self.session_id, log_path,
+            expansion = self.completion_hooks(FISH_EXPANSION_HOOK, FISH_NESTING_HOOK), jobs = FISH_JOB_HOOK))
+    }
+
+    /// Get PowerShell hooks content for dot-sourcing
+    ///
+    /// PowerShell has no preexec event, so the prompt function is wrapped instead and logs the
+    /// newest history entry with its exit status. `$LASTEXITCODE` only changes when a native
+    /// program runs, so a failed cmdlet is logged as 1. Alias expansion, nesting and
+    /// backgrounded job hooks are not available for PowerShell.
+    fn get_powershell_hooks_content(&self) -> Result<String> {
+        let log_path = self.command_log_path.display();
+
+        Ok(format!(r#"# DocPilot dynamic shell hooks for session {}
+# These hooks capture terminal commands for documentation
+
+# Function to get the current active session log file
+function global:Get-DocPilotActiveLog {{
+    $docpilotDir = Join-Path $HOME ".docpilot"
+    # Find the most recent active session file by modification time
+    $latestSessionFile = Get-ChildItem -Path $docpilotDir -Filter "active_session_*" -ErrorAction SilentlyContinue |
+        Sort-Object LastWriteTime -Descending | Select-Object -First 1
+    if ($latestSessionFile) {{
+        $sessionId = $latestSessionFile.Name -replace '^active_session_', ''
+        return Join-Path ([System.IO.Path]::GetTempPath()) "docpilot_commands_$sessionId.log"
+    }}
+    # Fallback to current session if no active session found
+    return "{}"
+}}
+
+# Keep the existing prompt so it still renders after each command is logged
+if (-not $global:DocPilotOriginalPrompt) {{
+    $global:DocPilotOriginalPrompt = $function:prompt
+}}
+# Commands run before the hooks were loaded are not logged
+$global:DocPilotLastHistoryId = (Get-History -Count 1).Id
+
+function global:prompt {{
+    # Read the exit status before anything else overwrites it
+    $docpilotSucceeded = $?
+    $docpilotExitCode = $global:LASTEXITCODE
+    $lastCommand = Get-History -Count 1
+    if ($lastCommand -and $lastCommand.Id -ne $global:DocPilotLastHistoryId) {{
+        $global:DocPilotLastHistoryId = $lastCommand.Id
+        $exitCode = if ($docpilotSucceeded) {{ 0 }} elseif ($docpilotExitCode) {{ $docpilotExitCode }} else {{ 1 }}
+        $timestamp = Get-Date -Format "yyyy-MM-ddTHH:mm:sszzz"
+        $line = "$timestamp|$((Get-Location).Path)|$exitCode|$($lastCommand.CommandLine)"
+        Add-Content -Path (Get-DocPilotActiveLog) -Value $line -ErrorAction SilentlyContinue
+    }}
+    $global:LASTEXITCODE = $docpilotExitCode
+    & $global:DocPilotOriginalPrompt
+}}
+
+# Function to cleanup when DocPilot session ends
+function global:Remove-DocPilotHooks {{
+    if ($global:DocPilotOriginalPrompt) {{
+        Set-Item -Path function:global:prompt -Value $global:DocPilotOriginalPrompt
+    }}
+    Remove-Variable -Name DocPilotOriginalPrompt, DocPilotLastHistoryId -Scope Global -ErrorAction SilentlyContinue
+    Remove-Item -Path function:global:Get-DocPilotActiveLog, function:global:Remove-DocPilotHooks -ErrorAction SilentlyContinue
+}}
+
+# Test that hooks are working
+Add-Content -Path (Get-DocPilotActiveLog) -Value "DocPilot shell hooks loaded at $(Get-Date -Format o)" -ErrorAction SilentlyContinue"#,
             self.session_id, log_path))
     }
 
+    /// Set up PowerShell integration by writing hooks that the profile or the user dot-sources
+    fn setup_powershell_integration(&self) -> Result<()> {
+        let hooks_dir = dirs::home_dir()
+            .ok_or_else(|| anyhow!("Could not find home directory"))?
+            .join(".docpilot");
+
+        fs::create_dir_all(&hooks_dir)?;
+        let hooks_file = hooks_dir.join("powershell_hooks.ps1");
+        fs::write(&hooks_file, self.get_powershell_hooks_content()?)?;
+
+        // Create a session marker file that the hooks use to find the active log
+        let session_marker = hooks_dir.join(format!("active_session_{}", self.session_id));
+        fs::write(&session_marker, &self.session_id)?;
+
+        println!("✅ PowerShell hooks written to {}", hooks_file.display());
+        println!("   ⚡ Current session: Run this to start capturing commands:");
+        println!("      . \"{}\"", hooks_file.display());
+        println!("   🔧 Future sessions: Add the same line to your $PROFILE");
+
+        Ok(())
+    }
+
     /// Inject hooks into the current zsh session automatically
     fn inject_zsh_hooks_into_current_session(&self, hooks_file: &std::path::PathBuf) -> Result<()> {
         let hook_content = self.get_zsh_hooks_content()?;
@@ -847,6 +1451,9 @@ echo "DocPilot shell hooks loaded at "(date -Iseconds) >> $log_file 2>/dev/null
                 println!("✅ Using Fish history monitoring");
                 println!("📁 Monitoring: ~/.local/share/fish/fish_history");
             }
+            ShellType::PowerShell => {
+                println!("⚠️  PowerShell history is not monitored, commands are captured by the prompt hook");
+            }
             ShellType::Unknown(_) => {
                 println!("⚠️  Unknown shell, using generic monitoring");
             }
@@ -1177,6 +1784,10 @@ fi
 # Store original PROMPT_COMMAND if it exists
 DOCPILOT_ORIGINAL_PROMPT_COMMAND="$PROMPT_COMMAND"
 
+# Backgrounded jobs started before the hooks were loaded are not tracked
+DOCPILOT_LAST_JOB_PID="$!"
+DOCPILOT_JOBS=()
+
 # Function to get the current active session log file
 docpilot_get_active_log() {{
     local docpilot_dir="$HOME/.docpilot"
@@ -1193,19 +1804,29 @@ docpilot_get_active_log() {{
     echo "{}"
 }}
 
+# Log the last command with its raw exit status (128+N when it was killed by signal N)
+docpilot_prompt_command() {{
+    local exit_code=$?
+    local last_command=$(history 1 | sed 's/^[ ]*[0-9]*[ ]*//')
+    if [[ -n "$last_command" ]]; then{expansion}
+        echo "$(date -Iseconds)|$(pwd)|$exit_code|$last_command" >> "$(docpilot_get_active_log)" 2>/dev/null || true
+    fi{jobs}
+    return $exit_code
+}}
+
 # Set up command logging
-export PROMPT_COMMAND="echo \\"$(date -Iseconds)|$(pwd)|\$?|$(history 1 | sed 's/^[ ]*[0-9]*[ ]*//')\\" >> $(docpilot_get_active_log); $DOCPILOT_ORIGINAL_PROMPT_COMMAND"
+export PROMPT_COMMAND="docpilot_prompt_command; $DOCPILOT_ORIGINAL_PROMPT_COMMAND"
 
 # Function to restore original PROMPT_COMMAND when DocPilot session ends
 docpilot_cleanup() {{
     export PROMPT_COMMAND="$DOCPILOT_ORIGINAL_PROMPT_COMMAND"
-    unset DOCPILOT_ORIGINAL_PROMPT_COMMAND
-    unset -f docpilot_cleanup docpilot_get_active_log
+    unset DOCPILOT_ORIGINAL_PROMPT_COMMAND DOCPILOT_LAST_JOB_PID DOCPILOT_JOBS
+    unset -f docpilot_cleanup docpilot_get_active_log docpilot_prompt_command
 }}
 
 # Test that hooks are working
 echo "DocPilot shell hooks loaded at $(date -Iseconds)" >> $(docpilot_get_active_log) 2>/dev/null || true
-"#, log_path);
+"#, log_path, expansion = self.completion_hooks(BASH_EXPANSION_HOOK, BASH_NESTING_HOOK), jobs = BASH_JOB_HOOK);
 
         fs::write(&hooks_file, hooks_content)?;
         
@@ -1300,14 +1921,21 @@ function docpilot_get_active_log
     echo "{}"
 end
 
+# Backgrounded jobs started before the hooks were loaded are not tracked
+set -g docpilot_last_job_pid $last_pid
+
 function docpilot_log_command --on-event fish_preexec
+    string length -q -- (string trim -- $argv); or return
     set log_file (docpilot_get_active_log)
-    echo (date -Iseconds)"|"(pwd)"|0|"$argv >> $log_file
+    echo (date -Iseconds)"|"(pwd)"|start|"$argv >> $log_file
 end
 
 function docpilot_log_exit --on-event fish_postexec
-    set log_file (docpilot_get_active_log)
-    echo (date -Iseconds)"|"(pwd)"|"$status"|" >> $log_file
+    set exit_status $status
+    # Older fish releases fire the events for blank lines too; skip them like bash and zsh do
+    string length -q -- (string trim -- $argv); or return
+    set log_file (docpilot_get_active_log){expansion}
+    echo (date -Iseconds)"|"(pwd)"|"$exit_status"|"$argv >> $log_file{jobs}
 end
 
 function docpilot_cleanup
@@ -1320,7 +1948,7 @@ end
 # Test that hooks are working
 set log_file (docpilot_get_active_log)
 echo "DocPilot shell hooks loaded at "(date -Iseconds) >> $log_file 2>/dev/null || true
-"#, log_path);
+"#, log_path, expansion = self.completion_hooks(FISH_EXPANSION_HOOK, FISH_NESTING_HOOK), jobs = FISH_JOB_HOOK);
 
         fs::write(&hooks_file, hooks_content)?;
         
@@ -1399,8 +2027,9 @@ end
         let zsh_hooks = hooks_dir.join("zsh_hooks.zsh");
         let bash_hooks = hooks_dir.join("bash_hooks.bash");
         let fish_hooks = hooks_dir.join("fish_hooks.fish");
+        let powershell_hooks = hooks_dir.join("powershell_hooks.ps1");
         
-        for hook_file in [zsh_hooks, bash_hooks, fish_hooks] {
+        for hook_file in [zsh_hooks, bash_hooks, fish_hooks, powershell_hooks] {
             if hook_file.exists() {
                 let _ = fs::remove_file(&hook_file);
             }
@@ -1424,7 +2053,7 @@ end
                 let _ = fs::remove_file(temp_dir.join(format!("docpilot_inject_{}.fish", self.session_id)));
                 self.remove_startup_integration_fish()?;
             }
-            ShellType::Unknown(_) => {}
+            ShellType::PowerShell | ShellType::Unknown(_) => {}
         }
         
         println!("🧹 Cleaned up automatic shell integration");
@@ -1560,6 +2189,55 @@ end
         Ok(())
     }
 
+    /// Check whether a log line was written by a preexec hook when a command started
+    fn is_start_marker(line: &str) -> bool {
+        line.split('|').nth(2) == Some(COMMAND_START_MARKER)
+    }
+
+    /// Check whether a log line holds the alias or function expansion of the next command
+    fn is_expansion_marker(line: &str) -> bool {
+        line.split('|').nth(2) == Some(EXPANSION_MARKER)
+    }
+
+    /// Check whether a log line holds the nesting of the next command's shell
+    fn is_nesting_marker(line: &str) -> bool {
+        line.split('|').nth(2) == Some(NESTING_MARKER)
+    }
+
+    /// Turn the `<shell level>|<parent command>` a nesting line logs into the command's nesting,
+    /// `None` when the command ran in the session's own shell
+    fn parse_nesting(&self, raw: &str) -> Option<CommandNesting> {
+        let base = self.nesting_base?;
+        let (level, parent) = raw.split_once('|').unwrap_or((raw, ""));
+        let depth = level.trim().parse::<u32>().ok()?.checked_sub(base).filter(|depth| *depth > 0)?;
+        let parent = parent.trim();
+        Some(CommandNesting { depth, parent: (!parent.is_empty()).then(|| parent.to_string()) })
+    }
+
+    /// Check whether a log line reports the exit status of a finished background job
+    fn is_job_marker(line: &str) -> bool {
+        line.split('|').nth(2).is_some_and(|field| field.starts_with(JOB_MARKER))
+    }
+
+    /// Record the exit code of a finished background job on the command that started it
+    fn complete_job(&mut self, job: CommandEntry) {
+        let Some(exit_code) = job.exit_code else {
+            return;
+        };
+        let started = self.commands.iter_mut().rev().find(|command| {
+            command.backgrounded && command.exit_code.is_none() && command.command == job.command
+        });
+        if let Some(started) = started {
+            started.exit_code = Some(exit_code);
+            self.completed_jobs.push(job);
+        }
+    }
+
+    /// Restore a multi-line expansion that the hooks logged with `\n` line separators
+    fn decode_expansion(raw: &str) -> String {
+        raw.trim().trim_end_matches("\\n").replace("\\n", "\n").trim().to_string()
+    }
+
     /// Parse a line from the shell integration log file
     fn parse_log_line(&self, line: &str) -> Option<CommandEntry> {
         let parts: Vec<&str> = line.splitn(4, '|').collect();
@@ -1593,9 +2271,43 @@ end
             shell: self.shell_type.name().to_string(),
             output: None,
             error: None,
+            expanded_command: None,
+            backgrounded: false,
+            tags: Vec::new(),
+            step: None,
+            output_size: None,
+            output_log: None,
+            nesting: None,
+            duration_ms: None,
         })
     }
 
+    /// Determine if a captured command should be recorded in the session
+    fn should_capture(&self, command: &CommandEntry) -> bool {
+        !self.should_ignore_command(&command.command)
+            && self.is_in_capture_directory(&command.working_directory)
+            && self.is_after_capture_delay(command.timestamp)
+    }
+
+    /// Check a command's time against the `--capture-delay` window at the start of the session
+    fn is_after_capture_delay(&self, timestamp: DateTime<Utc>) -> bool {
+        // A delay too large to represent ends after any command could run
+        i64::try_from(self.capture_delay).ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|delay| self.session_start_time.checked_add_signed(delay))
+            .is_some_and(|end| timestamp >= end)
+    }
+
+    /// Check a working directory against the `--only-dir` restriction, following symlinks
+    fn is_in_capture_directory(&self, working_directory: &str) -> bool {
+        let Some(only_dir) = &self.only_dir else {
+            return true;
+        };
+        let working_directory = Path::new(working_directory);
+        working_directory.starts_with(only_dir)
+            || fs::canonicalize(working_directory).is_ok_and(|resolved| resolved.starts_with(only_dir))
+    }
+
     /// Determine if a command should be ignored (much simpler now)
     pub(crate) fn should_ignore_command(&self, command: &str) -> bool {
         let command = command.trim();
@@ -1610,30 +2322,57 @@ end
             return true;
         }
         
-        // Filter out our own docpilot processes
-        if command.contains("docpilot") {
+        // DocPilot never documents its own invocations, regardless of configuration
+        if Self::is_docpilot_invocation(command) {
             return true;
         }
         
-        // Filter out shell built-ins that don't provide useful documentation context
-        let boring_commands = [
-            "clear", "exit", "logout", "history", "jobs", "bg", "fg",
-            "alias", "unalias", "type", "which", "whereis"
-        ];
-        
+        // Filter out navigation and shell built-ins that don't provide useful documentation context
         let cmd_parts: Vec<&str> = command.split_whitespace().collect();
-        if let Some(first_word) = cmd_parts.first() {
-            if boring_commands.contains(first_word) && cmd_parts.len() == 1 {
-                return true;
-            }
+        if cmd_parts.len() == 1 && DEFAULT_IGNORED_COMMANDS.contains(&cmd_parts[0]) {
+            return true;
         }
         
+        self.ignored_commands.iter().any(|ignored| Self::matches_ignored_command(&cmd_parts, ignored))
+    }
+
+    /// Check if a command runs DocPilot itself, by name or by path to the running binary
+    fn is_docpilot_invocation(command: &str) -> bool {
+        // Skip environment assignments and wrappers to find the program that actually runs
+        let wrappers = ["sudo", "env", "command", "exec", "time", "nohup"];
+        let program = command
+            .split_whitespace()
+            .find(|token| !token.contains('=') && !wrappers.contains(token) && !token.starts_with('-'));
+        let Some(program) = program else {
+            return false;
+        };
+
+        let program_path = std::path::Path::new(program.trim_matches(|c| c == '"' || c == '\''));
+        let file_name = program_path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        if file_name == "docpilot" || file_name == "docpilot.exe" {
+            return true;
+        }
+
+        // The binary may have been renamed or symlinked, so also compare against the running executable
+        if program_path.components().count() > 1
+            && let (Ok(program), Ok(current)) = (fs::canonicalize(program_path), env::current_exe())
+        {
+            return fs::canonicalize(current).map(|current| current == program).unwrap_or(false);
+        }
+
         false
     }
 
+    /// Check if a command is an ignore-list entry, alone or followed by more arguments
+    fn matches_ignored_command(cmd_parts: &[&str], ignored: &str) -> bool {
+        let ignored_parts: Vec<&str> = ignored.split_whitespace().collect();
+        !ignored_parts.is_empty() && cmd_parts.starts_with(&ignored_parts)
+    }
+
     /// Execute a command and capture its output (for testing purposes)
     pub async fn execute_and_capture(&mut self, command: &str) -> Result<CommandEntry> {
         let start_time = Utc::now();
+        let started = std::time::Instant::now();
         
         let output = Command::new("sh")
             .arg("-c")
@@ -1656,6 +2395,14 @@ end
             } else {
                 Some(String::from_utf8_lossy(&output.stderr).to_string())
             },
+            expanded_command: None,
+            backgrounded: false,
+            tags: Vec::new(),
+            step: None,
+            output_size: Some(output.stdout.len() as u64),
+            output_log: None,
+            nesting: None,
+            duration_ms: Some(started.elapsed().as_millis() as u64),
         };
 
         self.add_command(entry.clone());
@@ -1697,6 +2444,14 @@ end
             shell: self.shell_type.name().to_string(),
             output: None,
             error: None,
+            expanded_command: None,
+            backgrounded: false,
+            tags: Vec::new(),
+            step: None,
+            output_size: None,
+            output_log: None,
+            nesting: None,
+            duration_ms: None,
         };
         
         self.add_command(entry);
@@ -1739,6 +2494,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_user_ignore_list_matches_command_prefix() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test".to_string()) {
+            monitor.ignored_commands = vec!["git status".to_string(), "kubectl get".to_string()];
+
+            assert!(monitor.should_ignore_command("git status"));
+            assert!(monitor.should_ignore_command("git  status -s"));
+            assert!(monitor.should_ignore_command("kubectl get pods"));
+            assert!(!monitor.should_ignore_command("git statusx"));
+            assert!(!monitor.should_ignore_command("git commit -m 'status'"));
+            assert!(monitor.should_ignore_command("pwd"));
+        }
+    }
+
+    #[test]
+    fn test_only_dir_restricts_capture_to_subtree() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test".to_string()) {
+            monitor.set_only_dir(Some(PathBuf::from("/home/user/project")));
+
+            assert!(monitor.is_in_capture_directory("/home/user/project"));
+            assert!(monitor.is_in_capture_directory("/home/user/project/src"));
+            assert!(!monitor.is_in_capture_directory("/home/user/project-old"));
+            assert!(!monitor.is_in_capture_directory("/home/user"));
+
+            monitor.set_only_dir(None);
+            assert!(monitor.is_in_capture_directory("/home/user"));
+        }
+    }
+
+    #[test]
+    fn test_capture_delay_drops_commands_at_session_start() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test".to_string()) {
+            let start = Utc::now() - chrono::Duration::minutes(5);
+            monitor.set_session_start_time(start);
+            assert!(monitor.is_after_capture_delay(start));
+
+            monitor.set_capture_delay(30);
+            assert!(!monitor.is_after_capture_delay(start));
+            assert!(!monitor.is_after_capture_delay(start + chrono::Duration::seconds(29)));
+            assert!(monitor.is_after_capture_delay(start + chrono::Duration::seconds(30)));
+            assert!(monitor.is_after_capture_delay(start + chrono::Duration::minutes(2)));
+
+            monitor.set_capture_delay(u64::MAX);
+            assert!(!monitor.is_after_capture_delay(start + chrono::Duration::days(365)));
+        }
+    }
+
     #[test]
     fn test_log_parsing() {
         if let Ok(monitor) = TerminalMonitor::new("test".to_string()) {
@@ -1766,4 +2568,211 @@ mod tests {
             let _ = fs::remove_file(&monitor.command_log_path);
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_start_marker_tracks_running_command() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test-running-marker".to_string()) {
+            monitor.set_session_start_time(Utc::now() - chrono::Duration::minutes(5));
+            let started = (Utc::now() - chrono::Duration::minutes(2)).to_rfc3339();
+            fs::write(&monitor.command_log_path, format!("{}|/home/user|start|cargo build\n", started)).unwrap();
+
+            let captured = monitor.check_shell_integration_commands().await.unwrap();
+            assert!(captured.is_empty());
+            assert_eq!(monitor.running_command().map(|c| c.command.as_str()), Some("cargo build"));
+
+            monitor.log_command_to_file("cargo build", 0).unwrap();
+            let captured = monitor.check_shell_integration_commands().await.unwrap();
+            assert_eq!(captured.len(), 1);
+            assert_eq!(captured[0].exit_code, Some(0));
+            assert!(captured[0].duration_ms.is_some_and(|ms| ms >= 120_000));
+            assert!(monitor.running_command().is_none());
+
+            let _ = fs::remove_file(&monitor.command_log_path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_docpilot_commands_are_never_captured() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test-self-capture".to_string()) {
+            monitor.set_session_start_time(Utc::now() - chrono::Duration::minutes(5));
+            let at = (Utc::now() - chrono::Duration::minutes(1)).to_rfc3339();
+            let log = [
+                "docpilot status",
+                "/usr/local/bin/docpilot note \"deployed\"",
+                "DOCPILOT_DEBUG=1 docpilot annotate \"checked logs\"",
+                "cat docpilot-notes.md",
+            ]
+            .iter()
+            .map(|command| format!("{}|/home/user|0|{}\n", at, command))
+            .collect::<String>();
+            fs::write(&monitor.command_log_path, log).unwrap();
+
+            let captured = monitor.check_shell_integration_commands().await.unwrap();
+            let commands: Vec<_> = captured.iter().map(|c| c.command.as_str()).collect();
+            assert_eq!(commands, vec!["cat docpilot-notes.md"]);
+
+            let _ = fs::remove_file(&monitor.command_log_path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expansion_line_attaches_to_next_command() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test-alias-expansion".to_string()) {
+            monitor.set_session_start_time(Utc::now() - chrono::Duration::minutes(5));
+            let at = (Utc::now() - chrono::Duration::minutes(1)).to_rfc3339();
+            let log = format!(
+                "{at}|/srv|expansion|deploy () {{\\n    git pull && make release\\n}}\\n\n{at}|/srv|0|deploy --prod\n{at}|/srv|0|make test\n"
+            );
+            fs::write(&monitor.command_log_path, log).unwrap();
+
+            let captured = monitor.check_shell_integration_commands().await.unwrap();
+            assert_eq!(captured.len(), 2);
+            assert_eq!(captured[0].command, "deploy --prod");
+            assert_eq!(captured[0].expanded_command.as_deref(), Some("deploy () {\n    git pull && make release\n}"));
+            assert_eq!(captured[1].expanded_command, None);
+
+            let _ = fs::remove_file(&monitor.command_log_path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backgrounded_command_gets_exit_code_when_job_finishes() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test-background-jobs".to_string()) {
+            monitor.set_session_start_time(Utc::now() - chrono::Duration::minutes(5));
+            let at = (Utc::now() - chrono::Duration::minutes(1)).to_rfc3339();
+            let launched = format!("{at}|/srv|0|./long-task.sh &\n{at}|/srv|0|make && make install\n");
+            fs::write(&monitor.command_log_path, &launched).unwrap();
+
+            let captured = monitor.check_shell_integration_commands().await.unwrap();
+            assert_eq!(captured.len(), 2);
+            assert!(captured[0].backgrounded);
+            assert_eq!(captured[0].exit_code, None);
+            assert!(!captured[1].backgrounded);
+            assert!(monitor.take_completed_jobs().is_empty());
+
+            fs::write(&monitor.command_log_path, format!("{launched}{at}|/srv|job:2|./long-task.sh &\n")).unwrap();
+            assert!(monitor.check_shell_integration_commands().await.unwrap().is_empty());
+
+            let completed = monitor.take_completed_jobs();
+            assert_eq!(completed.len(), 1);
+            assert_eq!(completed[0].command, "./long-task.sh &");
+            assert_eq!(completed[0].exit_code, Some(2));
+            assert_eq!(monitor.get_commands()[0].exit_code, Some(2));
+
+            let _ = fs::remove_file(&monitor.command_log_path);
+        }
+    }
+
+    #[test]
+    fn test_expansion_hooks_are_opt_in() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test-alias-hooks".to_string()) {
+            monitor.shell_type = ShellType::Bash;
+            assert!(!monitor.get_shell_hooks_content().unwrap().contains("|expansion|"));
+
+            monitor.set_expand_aliases(true);
+            assert!(monitor.get_shell_hooks_content().unwrap().contains("|expansion|"));
+        }
+    }
+
+    #[test]
+    fn test_fish_hooks_use_event_functions() {
+        assert!(matches!(ShellType::from_name("/usr/local/bin/fish"), ShellType::Fish));
+        if let Ok(mut monitor) = TerminalMonitor::new("test-fish-hooks".to_string()) {
+            monitor.shell_type = ShellType::Fish;
+            let hooks = monitor.get_shell_hooks_content().unwrap();
+            assert!(hooks.contains("function docpilot_log_command --on-event fish_preexec"));
+            assert!(hooks.contains("function docpilot_log_exit --on-event fish_postexec"));
+            assert!(!hooks.contains("PROMPT_COMMAND"));
+            // Same `timestamp|directory|exit code|command` lines as bash and zsh
+            assert!(hooks.contains(r#"echo (date -Iseconds)"|"(pwd)"|"$exit_status"|"$argv >> $log_file"#));
+        }
+    }
+
+    #[test]
+    fn test_powershell_hooks_wrap_the_prompt() {
+        assert!(matches!(ShellType::from_name("pwsh"), ShellType::PowerShell));
+        assert!(matches!(ShellType::from_name("powershell"), ShellType::PowerShell));
+        if let Ok(mut monitor) = TerminalMonitor::new("test-powershell-hooks".to_string()) {
+            monitor.shell_type = ShellType::PowerShell;
+            let hooks = monitor.get_shell_hooks_content().unwrap();
+            assert!(hooks.contains("function global:prompt {"));
+            assert!(hooks.contains("$global:LASTEXITCODE"));
+            assert!(hooks.contains(r#""$timestamp|$((Get-Location).Path)|$exitCode|$($lastCommand.CommandLine)""#));
+            assert!(hooks.contains(&monitor.command_log_path.display().to_string()));
+        }
+    }
+
+    #[test]
+    fn test_powershell_log_line_parses_like_other_shells() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test-powershell-log".to_string()) {
+            monitor.shell_type = ShellType::PowerShell;
+            let entry = monitor
+                .parse_log_line(r"2026-10-17T09:15:02+02:00|C:\Users\dev\project|3|cargo build --release")
+                .unwrap();
+            assert_eq!(entry.command, "cargo build --release");
+            assert_eq!(entry.exit_code, Some(3));
+            assert_eq!(entry.working_directory, r"C:\Users\dev\project");
+            assert_eq!(entry.shell, "powershell");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nesting_line_attaches_to_next_command() {
+        if let Ok(mut monitor) = TerminalMonitor::new("test-nesting".to_string()) {
+            monitor.shell_type = ShellType::Bash;
+            assert!(!monitor.get_shell_hooks_content().unwrap().contains("|nesting|"));
+            monitor.set_nesting_base(Some(2));
+            assert!(monitor.get_shell_hooks_content().unwrap().contains("|nesting|"));
+
+            monitor.set_session_start_time(Utc::now() - chrono::Duration::minutes(5));
+            let at = (Utc::now() - chrono::Duration::minutes(1)).to_rfc3339();
+            let log = format!(
+                "{at}|/srv|nesting|2|/usr/bin/tmux\n{at}|/srv|0|make shell\n{at}|/app|nesting|3|docker exec -it web bash\n{at}|/app|0|rails db:migrate\n{at}|/srv|0|make test\n"
+            );
+            fs::write(&monitor.command_log_path, log).unwrap();
+
+            let captured = monitor.check_shell_integration_commands().await.unwrap();
+            assert_eq!(captured.len(), 3);
+            assert_eq!(captured[0].nesting, None);
+            assert_eq!(captured[1].nesting, Some(CommandNesting { depth: 1, parent: Some("docker exec -it web bash".to_string()) }));
+            assert_eq!(captured[2].nesting, None);
+
+            let _ = fs::remove_file(&monitor.command_log_path);
+        }
+    }
+
+    #[test]
+    fn test_termination_signal_from_exit_code() {
+        let entry = |exit_code| CommandEntry {
+            command: "make test".to_string(),
+            exit_code,
+            working_directory: "/tmp".to_string(),
+            shell: "bash".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(entry(Some(137)).termination_signal(), Some(9));
+        assert_eq!(entry(Some(137)).termination_description().as_deref(), Some("terminated by SIGKILL"));
+        assert_eq!(entry(Some(130)).termination_description().as_deref(), Some("terminated by SIGINT"));
+        assert_eq!(entry(Some(162)).termination_description().as_deref(), Some("terminated by signal 34"));
+        assert_eq!(entry(Some(1)).termination_signal(), None);
+        assert_eq!(entry(Some(128)).termination_signal(), None);
+        assert_eq!(entry(None).termination_signal(), None);
+    }
+
+    #[test]
+    fn test_chain_steps_split_on_chaining_operators() {
+        let steps = CommandEntry::chain_steps("make && make install || echo 'build failed; see log' ; cd ..");
+        let commands: Vec<&str> = steps.iter().map(|step| step.command.as_str()).collect();
+        let operators: Vec<_> = steps.iter().map(|step| step.operator).collect();
+        assert_eq!(commands, ["make", "make install", "echo 'build failed; see log'", "cd .."]);
+        assert_eq!(operators, [None, Some(ChainOperator::And), Some(ChainOperator::Or), Some(ChainOperator::Sequence)]);
+
+        // Pipes, backgrounding, subshells and escaped operators stay within one step
+        assert_eq!(CommandEntry::chain_steps("ps aux | grep node").len(), 1);
+        assert_eq!(CommandEntry::chain_steps("./server.sh &").len(), 1);
+        assert_eq!(CommandEntry::chain_steps("(cd web && npm test)").len(), 1);
+        assert_eq!(CommandEntry::chain_steps("echo \\&\\& \"a || b\"").len(), 1);
+        assert_eq!(CommandEntry::chain_steps("case $x in a) echo a;; esac").len(), 1);
+    }
+}
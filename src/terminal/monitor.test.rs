@@ -12,35 +12,20 @@ mod tests {
             ShellType::Bash => assert_eq!(shell.name(), "bash"),
             ShellType::Zsh => assert_eq!(shell.name(), "zsh"),
             ShellType::Fish => assert_eq!(shell.name(), "fish"),
+            ShellType::PowerShell => assert_eq!(shell.name(), "powershell"),
             ShellType::Unknown(name) => assert!(!name.is_empty()),
         }
     }
 
-    #[test]
-    fn test_shell_history_file_paths() {
-        let shells = vec![
-            ShellType::Bash,
-            ShellType::Zsh,
-            ShellType::Fish,
-        ];
-
-        for shell in shells {
-            if let Some(history_path) = shell.history_file() {
-                assert!(history_path.to_string_lossy().contains("history"));
-            }
-        }
-    }
-
     #[test]
     fn test_command_entry_creation() {
         let entry = CommandEntry {
             command: "ls -la".to_string(),
-            timestamp: Utc::now(),
             exit_code: Some(0),
             working_directory: "/home/user".to_string(),
             shell: "bash".to_string(),
             output: Some("file1\nfile2".to_string()),
-            error: None,
+            ..Default::default()
         };
 
         assert_eq!(entry.command, "ls -la");
@@ -59,7 +44,7 @@ mod tests {
                 assert_eq!(monitor.session_id, "test-session");
                 assert!(!monitor.is_monitoring());
                 assert_eq!(monitor.get_commands().len(), 0);
-                assert!(matches!(monitor.platform, Platform::Linux | Platform::MacOS));
+                assert!(matches!(monitor.platform, Platform::Linux | Platform::MacOS | Platform::Windows));
             }
             Err(_) => {
                 // Expected on unsupported platforms
@@ -72,20 +57,14 @@ mod tests {
     #[test]
     fn test_command_filtering_logic() {
         if let Ok(monitor) = TerminalMonitor::new("test".to_string()) {
-            // Commands that should be ignored
+            // Commands that should be ignored: bare navigation and shell built-ins
             let ignore_commands = vec![
                 "ls",
                 "pwd",
-                "cd /home",
+                "cd",
                 "clear",
                 "history",
                 "exit",
-                "echo hello",
-                "cat file.txt",
-                "less file.txt",
-                "more file.txt",
-                "head file.txt",
-                "tail file.txt",
             ];
 
             for cmd in ignore_commands {
@@ -103,6 +82,8 @@ mod tests {
                 "make install",
                 "curl -X POST https://api.example.com",
                 "ssh user@server",
+                "cd /home",
+                "cat file.txt",
             ];
 
             for cmd in keep_commands {
@@ -112,86 +93,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_bash_history_parsing() {
-        if let Ok(monitor) = TerminalMonitor::new("test".to_string()) {
-            // Test bash history line parsing
-            let bash_lines = vec![
-                "ls -la",
-                "cd /home/user",
-                "git status",
-                "",  // empty line should be ignored
-                "   ",  // whitespace only should be ignored
-            ];
-
-            let mut valid_commands = 0;
-            for line in bash_lines {
-                if let Some(entry) = monitor.parse_history_line(line) {
-                    assert!(!entry.command.is_empty());
-                    assert_eq!(entry.shell, monitor.shell_type.name());
-                    valid_commands += 1;
-                }
-            }
-
-            // Should have parsed some valid commands (exact number depends on filtering)
-            assert!(valid_commands > 0);
-        }
-    }
-
-    #[test]
-    fn test_zsh_history_parsing() {
-        if let Ok(mut monitor) = TerminalMonitor::new("test".to_string()) {
-            // Simulate zsh shell
-            monitor.shell_type = ShellType::Zsh;
-
-            // Test zsh history format: ": timestamp:duration;command"
-            let zsh_lines = vec![
-                ": 1640995200:0;git status",
-                ": 1640995201:5;cargo build",
-                "regular command without timestamp",
-                ": 1640995202:0;ls",  // should be filtered out
-            ];
-
-            let mut valid_commands = 0;
-            for line in zsh_lines {
-                if let Some(entry) = monitor.parse_history_line(line) {
-                    assert!(!entry.command.is_empty());
-                    assert_eq!(entry.shell, "zsh");
-                    valid_commands += 1;
-                }
-            }
-
-            assert!(valid_commands > 0);
-        }
-    }
-
-    #[test]
-    fn test_fish_history_parsing() {
-        if let Ok(mut monitor) = TerminalMonitor::new("test".to_string()) {
-            // Simulate fish shell
-            monitor.shell_type = ShellType::Fish;
-
-            // Test fish history format
-            let fish_lines = vec![
-                "- cmd: git status",
-                "- cmd: cargo test",
-                "- when: 1640995200",  // metadata line, should be ignored
-                "- cmd: ls",  // should be filtered out
-            ];
-
-            let mut valid_commands = 0;
-            for line in fish_lines {
-                if let Some(entry) = monitor.parse_history_line(line) {
-                    assert!(!entry.command.is_empty());
-                    assert_eq!(entry.shell, "fish");
-                    valid_commands += 1;
-                }
-            }
-
-            assert!(valid_commands > 0);
-        }
-    }
-
     #[test]
     fn test_monitor_state_management() {
         if let Ok(mut monitor) = TerminalMonitor::new("test".to_string()) {
@@ -221,14 +122,12 @@ mod tests {
 
             let entry = CommandEntry {
                 command: "test command".to_string(),
-                timestamp: Utc::now(),
                 exit_code: Some(0),
                 working_directory: env::current_dir()
                     .map(|p| p.to_string_lossy().to_string())
                     .unwrap_or_else(|_| "unknown".to_string()),
                 shell: "bash".to_string(),
-                output: None,
-                error: None,
+                ..Default::default()
             };
 
             monitor.add_command(entry);
@@ -283,14 +182,12 @@ mod tests {
             // Create a test command entry
             let entry = CommandEntry {
                 command: "pwd".to_string(),
-                timestamp: Utc::now(),
                 exit_code: Some(0),
                 working_directory: env::current_dir()
                     .map(|p| p.to_string_lossy().to_string())
                     .unwrap_or_else(|_| "unknown".to_string()),
                 shell: monitor.shell_type.name().to_string(),
-                output: None,
-                error: None,
+                ..Default::default()
             };
 
             assert!(!entry.working_directory.is_empty());
@@ -305,12 +202,10 @@ mod tests {
             
             let entry = CommandEntry {
                 command: "test".to_string(),
-                timestamp: Utc::now(),
                 exit_code: Some(0),
                 working_directory: "/test".to_string(),
                 shell: monitor.shell_type.name().to_string(),
-                output: None,
-                error: None,
+                ..Default::default()
             };
             
             let after = Utc::now();
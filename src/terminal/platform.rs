@@ -7,6 +7,7 @@ use std::process::Command;
 pub enum Platform {
     Linux,
     MacOS,
+    Windows,
     Unknown(String),
 }
 
@@ -15,6 +16,7 @@ impl Platform {
         match env::consts::OS {
             "linux" => Platform::Linux,
             "macos" => Platform::MacOS,
+            "windows" => Platform::Windows,
             other => Platform::Unknown(other.to_string()),
         }
     }
@@ -23,6 +25,7 @@ impl Platform {
         match self {
             Platform::Linux => "linux",
             Platform::MacOS => "macos",
+            Platform::Windows => "windows",
             Platform::Unknown(name) => name,
         }
     }
@@ -32,6 +35,7 @@ impl Platform {
         match self {
             Platform::Linux => "bash",
             Platform::MacOS => "zsh", // macOS default since Catalina
+            Platform::Windows => "powershell",
             Platform::Unknown(_) => "sh",
         }
     }
@@ -54,6 +58,12 @@ impl Platform {
                 "Kitty.app",
                 "Hyper.app",
             ],
+            Platform::Windows => vec![
+                "WindowsTerminal.exe",
+                "pwsh.exe",
+                "powershell.exe",
+                "conhost.exe",
+            ],
             Platform::Unknown(_) => vec!["xterm"],
         }
     }
@@ -63,13 +73,14 @@ impl Platform {
         match self {
             Platform::Linux => ("ps", vec!["aux"]),
             Platform::MacOS => ("ps", vec!["aux"]),
+            Platform::Windows => ("tasklist", vec![]),
             Platform::Unknown(_) => ("ps", vec!["aux"]),
         }
     }
 
     /// Get platform-specific shell configuration files
     pub fn shell_config_files(&self) -> Vec<PathBuf> {
-        let home = match env::var("HOME") {
+        let home = match env::var("HOME").or_else(|_| env::var("USERPROFILE")) {
             Ok(h) => PathBuf::from(h),
             Err(_) => return vec![],
         };
@@ -97,6 +108,12 @@ impl Platform {
                     PathBuf::from("/etc/zshrc"),
                 ]);
             }
+            Platform::Windows => {
+                configs.extend(vec![
+                    home.join("Documents/PowerShell/Microsoft.PowerShell_profile.ps1"),
+                    home.join("Documents/WindowsPowerShell/Microsoft.PowerShell_profile.ps1"),
+                ]);
+            }
             Platform::Unknown(_) => {}
         }
 
@@ -130,6 +147,11 @@ impl Platform {
                 println!("Initializing real-time monitoring for macOS");
                 Ok(())
             }
+            Platform::Windows => {
+                // Commands come from the PowerShell prompt hook, which needs no extra access
+                println!("Initializing real-time monitoring for Windows");
+                Ok(())
+            }
             Platform::Unknown(os) => {
                 Err(anyhow!("Unsupported platform: {}", os))
             }
@@ -174,6 +196,13 @@ impl Platform {
                 
                 Ok(None)
             }
+            Platform::Windows => {
+                if let Ok(session) = env::var("WT_SESSION") {
+                    return Ok(Some(format!("WindowsTerminal:{}", session)));
+                }
+
+                Ok(env::var("TERM_PROGRAM").ok())
+            }
             Platform::Unknown(_) => Ok(None),
         }
     }
@@ -183,6 +212,7 @@ impl Platform {
         match self {
             Platform::Linux => "Package manager (apt, yum, pacman) or cargo install",
             Platform::MacOS => "Homebrew: brew install docpilot, or cargo install",
+            Platform::Windows => "cargo install",
             Platform::Unknown(_) => "cargo install",
         }
     }
@@ -190,7 +220,7 @@ impl Platform {
     /// Check if the platform supports advanced terminal features
     pub fn supports_advanced_monitoring(&self) -> bool {
         match self {
-            Platform::Linux | Platform::MacOS => true,
+            Platform::Linux | Platform::MacOS | Platform::Windows => true,
             Platform::Unknown(_) => false,
         }
     }
@@ -229,10 +259,92 @@ impl PlatformUtils {
     }
 
 
+    /// Name of the user's interactive shell, e.g. `fish`
+    ///
+    /// `FISH_VERSION` is checked before `SHELL`, so fish is recognised when it isn't the login
+    /// shell. `PSModulePath` is inherited by every shell started from PowerShell, and on Windows
+    /// is set system-wide, so it only names PowerShell when `SHELL` is unset.
+    pub fn current_shell() -> Option<String> {
+        Self::shell_name(
+            env::var("FISH_VERSION").ok().as_deref(),
+            env::var("PSModulePath").ok().as_deref(),
+            env::var("SHELL").ok().as_deref(),
+        )
+    }
+
+    /// Shell named by the values of `FISH_VERSION`, `PSModulePath` and `SHELL`
+    fn shell_name(fish_version: Option<&str>, ps_module_path: Option<&str>, shell: Option<&str>) -> Option<String> {
+        if fish_version.is_some_and(|version| !version.is_empty()) {
+            return Some("fish".to_string());
+        }
+        let Some(shell) = shell.filter(|shell| !shell.is_empty()) else {
+            return ps_module_path.filter(|path| !path.is_empty()).map(|_| "powershell".to_string());
+        };
+        let name = std::path::Path::new(shell).file_name().map(|name| name.to_string_lossy().into_owned());
+        Some(name.unwrap_or_else(|| shell.to_string()))
+    }
+
     /// Check if running in a supported environment
     pub fn is_supported_environment() -> bool {
         let platform = Platform::detect();
-        matches!(platform, Platform::Linux | Platform::MacOS)
+        matches!(platform, Platform::Linux | Platform::MacOS | Platform::Windows)
+    }
+
+    /// Device path of the terminal this process runs in, e.g. `/dev/pts/3`
+    #[cfg(unix)]
+    pub fn controlling_tty() -> Option<String> {
+        [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO].into_iter().find_map(|fd| {
+            // SAFETY: ttyname returns null or a NUL-terminated static buffer, copied out before the next call
+            let name = unsafe { libc::ttyname(fd) };
+            if name.is_null() {
+                return None;
+            }
+            let name = unsafe { std::ffi::CStr::from_ptr(name) };
+            Some(name.to_string_lossy().into_owned())
+        })
+    }
+
+    /// Device path of the terminal this process runs in (not available on this platform)
+    #[cfg(not(unix))]
+    pub fn controlling_tty() -> Option<String> {
+        None
+    }
+
+    /// Check whether a process is still running, or `None` when that can't be determined here
+    #[cfg(unix)]
+    pub fn is_process_running(pid: u32) -> Option<bool> {
+        let pid = libc::pid_t::try_from(pid).ok()?;
+        // SAFETY: signal 0 only checks that the process exists and may be signalled
+        if unsafe { libc::kill(pid, 0) } == 0 {
+            return Some(true);
+        }
+        // EPERM means the process exists but belongs to another user
+        Some(std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM))
+    }
+
+    /// Check whether a process is still running (not available on this platform)
+    #[cfg(not(unix))]
+    pub fn is_process_running(_pid: u32) -> Option<bool> {
+        None
+    }
+
+    /// Check whether a PID still belongs to a DocPilot process, not another one that reused it
+    ///
+    /// On Linux the process's program name is compared with ours; elsewhere any running
+    /// process is assumed to be DocPilot.
+    pub fn is_docpilot_process(pid: u32) -> Option<bool> {
+        if !Self::is_process_running(pid)? {
+            return Some(false);
+        }
+        #[cfg(target_os = "linux")]
+        if let Ok(cmdline) = std::fs::read(format!("/proc/{}/cmdline", pid))
+            && let Ok(exe) = env::current_exe()
+        {
+            let program = cmdline.split(|&b| b == 0).next().unwrap_or_default();
+            let program = std::path::Path::new(std::str::from_utf8(program).unwrap_or_default());
+            return Some(program.file_name().is_some() && program.file_name() == exe.file_name());
+        }
+        Some(true)
     }
 }
 
@@ -240,6 +352,14 @@ impl PlatformUtils {
 mod tests {
     use super::*;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_process_liveness() {
+        assert_eq!(PlatformUtils::is_process_running(std::process::id()), Some(true));
+        assert_eq!(PlatformUtils::is_process_running(u32::MAX), None);
+        assert_eq!(PlatformUtils::is_docpilot_process(std::process::id()), Some(true));
+    }
+
     #[test]
     fn test_platform_detection() {
         let platform = Platform::detect();
@@ -255,6 +375,24 @@ mod tests {
         assert!(PlatformUtils::is_supported_environment());
     }
 
+    #[test]
+    fn test_shell_name_prefers_fish_version() {
+        assert_eq!(PlatformUtils::shell_name(Some("3.7.1"), None, Some("/bin/bash")).as_deref(), Some("fish"));
+        assert_eq!(PlatformUtils::shell_name(None, None, Some("/usr/local/bin/fish")).as_deref(), Some("fish"));
+        assert_eq!(PlatformUtils::shell_name(Some(""), None, Some("/bin/zsh")).as_deref(), Some("zsh"));
+        assert_eq!(PlatformUtils::shell_name(None, None, None), None);
+    }
+
+    #[test]
+    fn test_shell_name_detects_powershell_from_module_path() {
+        let module_path = Some(r"C:\Program Files\PowerShell\7\Modules");
+        assert_eq!(PlatformUtils::shell_name(None, module_path, None).as_deref(), Some("powershell"));
+        // A shell started from PowerShell inherits PSModulePath but sets SHELL
+        assert_eq!(PlatformUtils::shell_name(None, module_path, Some("/bin/bash")).as_deref(), Some("bash"));
+        assert_eq!(PlatformUtils::shell_name(None, module_path, Some("")).as_deref(), Some("powershell"));
+        assert_eq!(PlatformUtils::shell_name(None, Some(""), Some("/bin/zsh")).as_deref(), Some("zsh"));
+    }
+
     #[test]
     fn test_shell_config_files() {
         let platform = Platform::detect();
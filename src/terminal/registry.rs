@@ -0,0 +1,186 @@
+//! Registry of running background monitors
+//!
+//! Each monitor is recorded with its session and terminal in `monitors.json` in the
+//! DocPilot home directory, so a second monitor on the same terminal can be refused and
+//! `stop`/`status` can pick a specific one.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::platform::PlatformUtils;
+
+const REGISTRY_FILE: &str = "monitors.json";
+
+/// Single-PID file written by earlier versions
+const LEGACY_PID_FILE: &str = "monitor.pid";
+
+/// Monitors allowed to run at once unless configured otherwise
+pub const DEFAULT_MAX_MONITORS: usize = 1;
+
+/// A running monitor process and what it captures
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorRecord {
+    pub pid: u32,
+    /// Session the monitor captures into; empty for monitors imported from `monitor.pid`
+    pub session_id: String,
+    /// Terminal the monitor was started from
+    pub tty: Option<String>,
+    pub started_at: DateTime<Utc>,
+}
+
+/// Monitors recorded in the registry file
+#[derive(Debug)]
+pub struct MonitorRegistry {
+    path: PathBuf,
+    monitors: Vec<MonitorRecord>,
+}
+
+impl MonitorRegistry {
+    /// Open the registry in `~/.docpilot`
+    pub fn open_default() -> Result<Self> {
+        let home_dir = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        Self::open(&PathBuf::from(home_dir).join(".docpilot"))
+    }
+
+    /// Open the registry in `dir`, importing a `monitor.pid` left by an earlier version
+    pub fn open(dir: &Path) -> Result<Self> {
+        let path = dir.join(REGISTRY_FILE);
+        let mut monitors: Vec<MonitorRecord> = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow!("Invalid monitor registry {}: {}", path.display(), e))?
+        } else {
+            Vec::new()
+        };
+
+        let legacy = dir.join(LEGACY_PID_FILE);
+        let Ok(legacy_pid) = fs::read_to_string(&legacy) else {
+            return Ok(Self { path, monitors });
+        };
+        if let Ok(pid) = legacy_pid.trim().parse::<u32>()
+            && !monitors.iter().any(|m| m.pid == pid)
+        {
+            monitors.push(MonitorRecord { pid, session_id: String::new(), tty: None, started_at: Utc::now() });
+        }
+        let registry = Self { path, monitors };
+        registry.save()?;
+        let _ = fs::remove_file(&legacy);
+
+        Ok(registry)
+    }
+
+    /// Write the registry back to disk
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.monitors)?)?;
+        Ok(())
+    }
+
+    /// Forget monitors whose process has exited or whose PID was reused; returns how many were removed
+    pub fn prune(&mut self) -> usize {
+        let before = self.monitors.len();
+        self.monitors.retain(|m| PlatformUtils::is_docpilot_process(m.pid) != Some(false));
+        before - self.monitors.len()
+    }
+
+    /// All recorded monitors
+    pub fn monitors(&self) -> &[MonitorRecord] {
+        &self.monitors
+    }
+
+    /// Find a monitor by PID or session ID
+    pub fn find(&self, pid_or_session: &str) -> Option<&MonitorRecord> {
+        self.monitors.iter().find(|m| m.pid.to_string() == pid_or_session || m.session_id == pid_or_session)
+    }
+
+    /// Monitors capturing into a session
+    pub fn for_session(&self, session_id: &str) -> Vec<MonitorRecord> {
+        self.monitors.iter().filter(|m| m.session_id == session_id).cloned().collect()
+    }
+
+    /// Check that another monitor may start on `tty`
+    ///
+    /// Two monitors on one terminal would install competing shell hooks, so that is always
+    /// refused; otherwise at most `max_monitors` may run at once.
+    pub fn check_can_start(&self, tty: Option<&str>, max_monitors: usize) -> Result<()> {
+        if let Some(tty) = tty
+            && let Some(existing) = self.monitors.iter().find(|m| m.tty.as_deref() == Some(tty))
+        {
+            return Err(anyhow!(
+                "A monitor is already running on {} (PID {}, session {})",
+                tty, existing.pid, describe_session(existing)
+            ));
+        }
+        if self.monitors.len() >= max_monitors {
+            return Err(anyhow!(
+                "{} monitor(s) already running, the configured maximum is {}",
+                self.monitors.len(), max_monitors
+            ));
+        }
+        Ok(())
+    }
+
+    /// Record a newly started monitor
+    pub fn register(&mut self, record: MonitorRecord) {
+        self.monitors.retain(|m| m.pid != record.pid);
+        self.monitors.push(record);
+    }
+
+    /// Remove a monitor, returning its record if it was registered
+    pub fn unregister(&mut self, pid: u32) -> Option<MonitorRecord> {
+        let position = self.monitors.iter().position(|m| m.pid == pid)?;
+        Some(self.monitors.remove(position))
+    }
+}
+
+/// Session ID of a monitor for messages, or a placeholder for imported records
+pub fn describe_session(record: &MonitorRecord) -> &str {
+    if record.session_id.is_empty() { "unknown" } else { &record.session_id }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(pid: u32, session_id: &str, tty: Option<&str>) -> MonitorRecord {
+        MonitorRecord { pid, session_id: session_id.to_string(), tty: tty.map(String::from), started_at: Utc::now() }
+    }
+
+    #[test]
+    fn test_registry_refuses_second_monitor_on_same_tty() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut registry = MonitorRegistry::open(dir.path()).unwrap();
+        registry.register(record(std::process::id(), "session-a", Some("/dev/pts/3")));
+        registry.save().unwrap();
+
+        let registry = MonitorRegistry::open(dir.path()).unwrap();
+        let error = registry.check_can_start(Some("/dev/pts/3"), 4).unwrap_err();
+        assert!(error.to_string().contains("already running on /dev/pts/3"));
+        assert!(registry.check_can_start(Some("/dev/pts/4"), 4).is_ok());
+        assert!(registry.check_can_start(Some("/dev/pts/4"), 1).is_err());
+        assert_eq!(registry.find("session-a").map(|m| m.pid), Some(std::process::id()));
+    }
+
+    #[test]
+    fn test_registry_imports_legacy_pid_file_and_prunes_exited() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(LEGACY_PID_FILE), format!("{}\n", std::process::id())).unwrap();
+
+        let mut registry = MonitorRegistry::open(dir.path()).unwrap();
+        assert!(!dir.path().join(LEGACY_PID_FILE).exists());
+        assert_eq!(MonitorRegistry::open(dir.path()).unwrap().monitors(), registry.monitors());
+        assert_eq!(registry.monitors().len(), 1);
+        assert_eq!(describe_session(&registry.monitors()[0]), "unknown");
+
+        // A PID that can't belong to a running process
+        registry.register(record(i32::MAX as u32, "gone", None));
+        assert_eq!(registry.prune(), 1);
+        assert!(registry.unregister(std::process::id()).is_some());
+        assert!(registry.monitors().is_empty());
+    }
+}
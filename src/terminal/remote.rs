@@ -0,0 +1,208 @@
+//! Capture of commands run on a remote host over SSH
+//!
+//! `docpilot ssh` copies a bash hook script to the remote host, starts an interactive shell
+//! that sources it, and when the connection closes copies the remote command log back into
+//! the local session's log, with each working directory prefixed by the host. All SSH calls
+//! share one control connection, so the user authenticates once.
+
+use anyhow::{Result, anyhow};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+
+use super::monitor::command_log_path;
+
+/// ssh options that take a value, so their argument is not mistaken for the destination
+const SSH_OPTIONS_WITH_VALUE: &str = "BbcDEeFIiJLlmOoPpQRSWw";
+
+/// An SSH connection whose remote commands are added to a local session
+pub struct SshCapture {
+    session_id: String,
+    /// Arguments for `ssh`: options and the destination, without a remote command
+    ssh_args: Vec<String>,
+    host: String,
+    control_path: PathBuf,
+    remote_log: String,
+    remote_hooks: String,
+}
+
+impl SshCapture {
+    pub fn new(session_id: &str, ssh_args: Vec<String>) -> Result<Self> {
+        let host = destination_host(&ssh_args)
+            .ok_or_else(|| anyhow!("No destination host found in the ssh arguments"))?;
+        let tag = format!("{}_{}", session_id, std::process::id());
+        Ok(Self {
+            session_id: session_id.to_string(),
+            ssh_args,
+            host,
+            control_path: std::env::temp_dir().join(format!("docpilot_ssh_{}.sock", tag)),
+            remote_log: format!("/tmp/docpilot_remote_{}.log", tag),
+            remote_hooks: format!("/tmp/docpilot_remote_{}.sh", tag),
+        })
+    }
+
+    /// Host the remote commands are tagged with
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Install the hooks, run the interactive shell, then relay what it logged
+    ///
+    /// Returns the exit status of the interactive session and how many log lines were relayed.
+    pub fn run(&self) -> Result<(ExitStatus, usize)> {
+        self.install_hooks()?;
+        let status = self.ssh(&["-t"], Some(&format!("exec bash --rcfile {} -i", self.remote_hooks)))
+            .status()
+            .map_err(|e| anyhow!("Failed to run ssh: {}", e))?;
+        let relayed = self.relay_commands();
+        self.close();
+        Ok((status, relayed?))
+    }
+
+    /// Copy the hook script to the remote host through the control connection
+    fn install_hooks(&self) -> Result<()> {
+        let mut child = self.ssh(&["-o", "ControlPersist=300"], Some(&format!("cat > {}", self.remote_hooks)))
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("Failed to run ssh: {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(remote_hooks_script(&self.remote_log).as_bytes())?;
+        }
+        if !child.wait()?.success() {
+            return Err(anyhow!("Could not install the DocPilot hooks on {}", self.host));
+        }
+        Ok(())
+    }
+
+    /// Fetch the remote log, remove the remote files and append the commands to the local log
+    fn relay_commands(&self) -> Result<usize> {
+        let output = self.ssh(&[], Some(&format!("cat {log} 2>/dev/null; rm -f {log} {hooks}", log = self.remote_log, hooks = self.remote_hooks)))
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| anyhow!("Failed to fetch the remote command log: {}", e))?;
+        let lines = tag_remote_log(&String::from_utf8_lossy(&output.stdout), &self.host);
+        if lines.is_empty() {
+            return Ok(0);
+        }
+
+        append_to_log(&command_log_path(&self.session_id), &lines)?;
+        Ok(lines.len())
+    }
+
+    /// Shut down the shared control connection
+    fn close(&self) {
+        let _ = self.ssh(&["-O", "exit"], None).stdout(Stdio::null()).stderr(Stdio::null()).status();
+        let _ = fs::remove_file(&self.control_path);
+    }
+
+    /// `ssh` with the user's arguments, going through the shared control connection
+    fn ssh(&self, extra_args: &[&str], remote_command: Option<&str>) -> Command {
+        let mut command = Command::new("ssh");
+        command
+            .arg("-o").arg("ControlMaster=auto")
+            .arg("-o").arg(format!("ControlPath={}", self.control_path.display()))
+            .args(extra_args)
+            .args(&self.ssh_args);
+        if let Some(remote_command) = remote_command {
+            command.arg(remote_command);
+        }
+        command
+    }
+}
+
+/// Destination host of an ssh command line, e.g. `web1` for `-p 2222 deploy@web1`
+pub fn destination_host(ssh_args: &[String]) -> Option<String> {
+    let mut args = ssh_args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(flags) = arg.strip_prefix('-') {
+            // A value-taking option consumes the next argument unless the value is attached
+            if let Some(position) = flags.find(|flag| SSH_OPTIONS_WITH_VALUE.contains(flag))
+                && position == flags.len() - 1
+            {
+                args.next();
+            }
+            continue;
+        }
+        let destination = arg.strip_prefix("ssh://").unwrap_or(arg);
+        let host = destination.rsplit('@').next().unwrap_or(destination);
+        let host = host.split([':', '/']).next().unwrap_or(host);
+        return (!host.is_empty()).then(|| host.to_string());
+    }
+    None
+}
+
+/// Rewrite remote log lines so each working directory reads `host:/path`
+///
+/// Lines that aren't command records, such as the "hooks loaded" notice, are dropped.
+pub fn tag_remote_log(content: &str, host: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '|');
+            let (timestamp, directory, status, command) = (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+            Some(format!("{}|{}:{}|{}|{}", timestamp, host, directory, status, command))
+        })
+        .collect()
+}
+
+fn append_to_log(path: &Path, lines: &[String]) -> Result<()> {
+    let mut log = OpenOptions::new().create(true).append(true).open(path)?;
+    for line in lines {
+        writeln!(log, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// bash rcfile for the remote shell: the user's own `.bashrc`, then DocPilot's logging hook
+pub fn remote_hooks_script(remote_log: &str) -> String {
+    format!(r#"# DocPilot hooks for a remote SSH session, removed when the session ends
+[[ -f ~/.bashrc ]] && source ~/.bashrc
+
+DOCPILOT_REMOTE_LOG="{remote_log}"
+DOCPILOT_ORIGINAL_PROMPT_COMMAND="$PROMPT_COMMAND"
+DOCPILOT_LAST_HISTORY=""
+
+docpilot_prompt_command() {{
+    local exit_code=$?
+    local last_history=$(history 1)
+    local last_command=$(printf '%s' "$last_history" | sed 's/^[ ]*[0-9]*[ ]*//')
+    # An empty prompt leaves the previous history entry in place
+    if [[ -n "$last_command" && "$last_history" != "$DOCPILOT_LAST_HISTORY" ]]; then
+        echo "$(date -Iseconds)|$(pwd)|$exit_code|$last_command" >> "$DOCPILOT_REMOTE_LOG" 2>/dev/null || true
+    fi
+    DOCPILOT_LAST_HISTORY="$last_history"
+    return $exit_code
+}}
+
+DOCPILOT_LAST_HISTORY=$(history 1)
+PROMPT_COMMAND="docpilot_prompt_command; $DOCPILOT_ORIGINAL_PROMPT_COMMAND"
+"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_destination_host_skips_option_values() {
+        assert_eq!(destination_host(&args(&["web1"])), Some("web1".to_string()));
+        assert_eq!(destination_host(&args(&["-p", "2222", "-i", "~/.ssh/deploy", "deploy@web1"])), Some("web1".to_string()));
+        assert_eq!(destination_host(&args(&["-A", "-p2222", "ssh://ops@db.internal:2200"])), Some("db.internal".to_string()));
+        assert_eq!(destination_host(&args(&["-o", "StrictHostKeyChecking=no"])), None);
+    }
+
+    #[test]
+    fn test_tag_remote_log_prefixes_directory_with_host() {
+        let log = "2026-10-16T10:00:00+00:00|/var/www|0|systemctl restart nginx\nnot a record\n2026-10-16T10:00:05+00:00|/etc|1|cat a|b\n";
+        let lines = tag_remote_log(log, "web1");
+        assert_eq!(lines, vec![
+            "2026-10-16T10:00:00+00:00|web1:/var/www|0|systemctl restart nginx".to_string(),
+            "2026-10-16T10:00:05+00:00|web1:/etc|1|cat a|b".to_string(),
+        ]);
+    }
+}